@@ -1,6 +1,7 @@
 use std::ffi::OsString;
 
-use clap::{Parser, Subcommand};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use git_clone::commands::{self, ResetMode};
 
 /// Contains the commands passed to the program
 #[derive(Parser, Debug)]
@@ -39,7 +40,27 @@ pub enum Command {
         debug: bool,
     },
     /// Shows working tree status
-    Status,
+    Status {
+        /// Shows every file in a single line, with a two letter code for its status
+        #[arg(short, long)]
+        short: bool,
+        /// Shows a stable format meant for scripts, `v1` (the default) or `v2`
+        #[arg(
+            long,
+            value_name = "VERSION",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "v1"
+        )]
+        porcelain: Option<PorcelainVersion>,
+        /// Shows the branch in the short and porcelain formats
+        #[arg(short, long)]
+        branch: bool,
+        /// Terminates entries with NUL instead of new lines, and does not quote paths. Implies
+        /// `--porcelain` if no other format is given
+        #[arg(short = 'z')]
+        null_terminated: bool,
+    },
 
     /// Creates a new commit object representing the current index
     Commit {
@@ -52,10 +73,171 @@ pub enum Command {
         /// Reference or commit hash
         reference: String,
     },
+    /// Lists, creates, deletes or renames branches
+    Branch {
+        /// Name of the branch to create, delete or rename
+        name: Option<String>,
+        /// Commit the new branch will point to (HEAD by default), or the new name when renaming
+        start_point: Option<String>,
+        /// Deletes the branch, only if it has been merged into HEAD
+        #[arg(short, long, group = "action")]
+        delete: bool,
+        /// Deletes the branch, even if it has not been merged
+        #[arg(short = 'D', group = "action")]
+        force_delete: bool,
+        /// Renames a branch, or the current branch if only one name is given
+        #[arg(short = 'm', long = "move", group = "action")]
+        rename: bool,
+    },
+    /// Joins the history of another commit into the current branch
+    Merge {
+        /// Branch or revision to merge
+        #[arg(required_unless_present = "abort")]
+        revision: Option<String>,
+        /// Stops the merge in progress, going back to the state before it started
+        #[arg(long, conflicts_with = "revision")]
+        abort: bool,
+    },
+    /// Applies the changes introduced by existing commits on top of the current one
+    CherryPick {
+        #[command(flatten)]
+        sequence: SequencerArgs,
+    },
+    /// Creates commits undoing the changes introduced by existing commits
+    Revert {
+        #[command(flatten)]
+        sequence: SequencerArgs,
+    },
+    /// Replays the commits of the current branch on top of another commit
+    Rebase {
+        /// Branch or revision to replay the commits onto
+        #[arg(required_unless_present_any = ["resume", "skip", "abort"])]
+        upstream: Option<String>,
+        /// Opens the list of commits in the editor first, to reorder, reword, edit, squash or
+        /// drop them
+        #[arg(short, long, conflicts_with_all = ["resume", "skip", "abort"])]
+        interactive: bool,
+        /// Concludes the commit that stopped once its conflicts are resolved and added, and
+        /// replays the rest
+        #[arg(long = "continue", conflicts_with_all = ["upstream", "skip", "abort"])]
+        resume: bool,
+        /// Skips the commit that stopped and replays the rest
+        #[arg(long, conflicts_with_all = ["upstream", "abort"])]
+        skip: bool,
+        /// Goes back to the branch and commit before the rebase started
+        #[arg(long, conflicts_with = "upstream")]
+        abort: bool,
+    },
+    /// Finds the best common ancestors of commits
+    MergeBase {
+        /// Shows every best common ancestor instead of only one
+        #[arg(long)]
+        all: bool,
+        /// Checks whether the first commit is an ancestor of the second one, exiting with 0 if
+        /// it is and with 1 if it is not
+        #[arg(long, conflicts_with = "all")]
+        is_ancestor: bool,
+        /// Commits to find the ancestors of, the first one is compared against all the others
+        #[arg(required = true, num_args = 2..)]
+        commits: Vec<String>,
+    },
+    /// Lists, creates or deletes tags
+    Tag {
+        /// Name of the tag to create or delete
+        name: Option<String>,
+        /// Commit the new tag will point to, HEAD by default
+        commit: Option<String>,
+        /// Creates an annotated tag, which needs a message
+        #[arg(short, long, requires = "message")]
+        annotate: bool,
+        /// Message of an annotated tag, implies `-a`
+        #[arg(short, long)]
+        message: Option<String>,
+        /// Deletes the tag
+        #[arg(short, long, conflicts_with_all = ["commit", "annotate", "message"])]
+        delete: bool,
+    },
+    /// Shows the commit history
+    Log {
+        /// Revision to start from, HEAD by default
+        revision: Option<String>,
+        /// Limits the number of commits shown
+        #[arg(short = 'n', long = "max-count")]
+        max_count: Option<usize>,
+        /// Shows every commit in a single line
+        #[arg(long)]
+        oneline: bool,
+        /// Format string, supports %H, %h, %an, %ae, %ad and %s
+        #[arg(long, conflicts_with = "oneline")]
+        format: Option<String>,
+        /// Only shows commits that modify these paths
+        #[arg(last = true)]
+        paths: Vec<OsString>,
+    },
+    /// Shows the changes between the working tree and the index, the index and the last commit,
+    /// or two commits
+    Diff {
+        /// Compares the index with the last commit
+        #[arg(long, alias = "staged", conflicts_with = "commits")]
+        cached: bool,
+        /// Number of unchanged lines shown around every change
+        #[arg(short = 'U', long = "unified", default_value_t = 3)]
+        context: usize,
+        /// Two commits to compare
+        #[arg(num_args = 2)]
+        commits: Vec<String>,
+    },
+    /// Packs every reachable object into a single pack
+    Repack,
+    /// Packs every reachable object and removes the loose objects that were packed
+    Gc,
+    /// Gets, sets or lists configuration values
+    Config {
+        /// Uses the global config file instead of the repository one
+        #[arg(long)]
+        global: bool,
+        /// Lists every value with its name
+        #[arg(short, long, group = "action", conflicts_with_all = ["name", "value"])]
+        list: bool,
+        /// Shows every value of a multi-valued key
+        #[arg(long, group = "action", requires = "name", conflicts_with = "value")]
+        get_all: bool,
+        /// Adds a new value without replacing the existing ones
+        #[arg(long, group = "action", requires = "value")]
+        add: bool,
+        /// Removes the value of the key
+        #[arg(long, group = "action", requires = "name", conflicts_with = "value")]
+        unset: bool,
+        /// Checks values are of this type, and shows them in their canonical form
+        #[arg(long = "type")]
+        value_type: Option<ConfigType>,
+        /// Name of the key, as `section.key` or `section.subsection.key`
+        name: Option<String>,
+        /// New value of the key, it is shown if not given
+        value: Option<String>,
+    },
+    /// Shows which paths are ignored and the rule that ignores them
+    CheckIgnore {
+        /// Shows the file, line and pattern deciding every path, including negated patterns
+        #[arg(short, long)]
+        verbose: bool,
+        /// Paths to check
+        #[arg(required = true)]
+        paths: Vec<OsString>,
+    },
     /// Shows the object file with the specified hash
     CatFile {
-        /// Hash of the file to show
-        hash: String,
+        /// Object to show, as a hash or any other revision
+        object: String,
+    },
+    /// Shows the hash of the objects the given revisions point to
+    RevParse {
+        /// Shows abbreviated hashes
+        #[arg(long)]
+        short: bool,
+        /// Revisions to resolve, like `HEAD~2`, `main^2`, `v1.0^{tree}` or `HEAD:src/main.rs`
+        #[arg(required = true)]
+        revisions: Vec<String>,
     },
 }
 
@@ -68,10 +250,78 @@ pub enum ResetCommand {
     },
     /// Reset to a previous commit
     Commit {
-        /// Reset all files and working tree
-        #[arg(long)]
-        hard: bool,
-        /// Commit hash to reset to
-        commit_hash: String,
+        #[command(flatten)]
+        mode: ResetModeArgs,
+        /// Commit to reset to, as a hash or any other revision
+        commit: String,
     },
 }
+
+/// Flags that select how much of the repository a reset affects, only one can be set at a time.
+#[derive(ClapArgs, Debug)]
+#[group(multiple = false)]
+pub struct ResetModeArgs {
+    /// Only move the current branch
+    #[arg(long)]
+    soft: bool,
+    /// Move the current branch and reset the index (default)
+    #[arg(long)]
+    mixed: bool,
+    /// Move the current branch and reset both the index and working tree
+    #[arg(long)]
+    hard: bool,
+}
+
+impl ResetModeArgs {
+    pub fn mode(&self) -> ResetMode {
+        if self.soft {
+            ResetMode::Soft
+        } else if self.hard {
+            ResetMode::Hard
+        } else {
+            ResetMode::Mixed
+        }
+    }
+}
+
+/// Commits to apply, or what to do with the cherry-pick or revert that stopped because of
+/// conflicts. Only one can be set at a time.
+#[derive(ClapArgs, Debug)]
+#[group(required = true, multiple = false)]
+pub struct SequencerArgs {
+    /// Commits to apply, in order
+    pub commits: Vec<String>,
+    /// Concludes the commit that stopped once its conflicts are resolved and added, and applies
+    /// the rest
+    #[arg(long = "continue")]
+    pub resume: bool,
+    /// Skips the commit that stopped and applies the rest
+    #[arg(long)]
+    pub skip: bool,
+    /// Goes back to the state before the first commit was applied
+    #[arg(long)]
+    pub abort: bool,
+}
+
+/// Versions of the porcelain status format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PorcelainVersion {
+    V1,
+    V2,
+}
+
+/// The types config values can be read or written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigType {
+    Bool,
+    Int,
+}
+
+impl From<ConfigType> for commands::ConfigType {
+    fn from(value: ConfigType) -> Self {
+        match value {
+            ConfigType::Bool => commands::ConfigType::Bool,
+            ConfigType::Int => commands::ConfigType::Int,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use git_clone::Repository;
+use git_clone::commands;
+use git_clone::fs::{self, Head};
+
+/// Performs the different branch operations depending on the flags passed:
+/// - No flags and no name: lists every branch, marking the current one or the detached HEAD.
+/// - `name` (and optionally `start_point`): creates a new branch.
+/// - `delete` or `force_delete`: deletes the branch called `name`.
+/// - `rename`: renames `name` to `start_point`, or the current branch to `name`.
+pub fn branch(
+    repo: &Repository,
+    name: Option<&str>,
+    start_point: Option<&str>,
+    delete: bool,
+    force_delete: bool,
+    rename: bool,
+) -> Result<String> {
+    if delete || force_delete {
+        let name = name.context("branch name required")?;
+        let hash = commands::delete_branch(repo, name, force_delete)?;
+        return Ok(format!(
+            "Deleted branch {} (was {}).\n",
+            name,
+            &hash.to_string()[..7]
+        ));
+    }
+
+    if rename {
+        let (old, new) = match (name, start_point) {
+            (Some(old), Some(new)) => (Some(old), new),
+            (Some(new), None) => (None, new),
+            _ => bail!("branch name required"),
+        };
+        let old = commands::rename_branch(repo, old, new)?;
+        return Ok(format!("Renamed branch '{}' to '{}'\n", old, new));
+    }
+
+    match name {
+        Some(name) => {
+            let hash = commands::create_branch(repo, name, start_point)?;
+            Ok(format!(
+                "Created branch '{}' at {}\n",
+                name,
+                &hash.to_string()[..7]
+            ))
+        }
+        None => {
+            let mut formatted = String::new();
+            if let Head::Detached(hash) = fs::read_head(repo)? {
+                let detached = format!("(HEAD detached at {})", &hash.to_string()[..7]);
+                formatted.push_str(&format!("* {}\n", detached.green()));
+            }
+            for b in commands::list_branches(repo)? {
+                if b.current {
+                    formatted.push_str(&format!("* {}\n", b.name.green()));
+                } else {
+                    formatted.push_str(&format!("  {}\n", b.name));
+                }
+            }
+            Ok(formatted)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::ffi::OsString;
+
+use anyhow::Result;
+use git_clone::Repository;
+use git_clone::commands::{self, IgnoreMatch};
+
+use super::ExitStatus;
+
+/// Returns the ignored paths, one per line. Like git, it fails with exit status 1 when none of
+/// the paths is shown.
+///
+/// With `verbose`, every path matching a pattern is shown along with the file, line and pattern
+/// that decided it as `source:line:pattern<TAB>path`, including the ones that are not ignored.
+pub fn check_ignore(repo: &Repository, paths: &[OsString], verbose: bool) -> Result<String> {
+    let formatted = format_matches(&commands::check_ignore(repo, paths)?, verbose);
+    if formatted.is_empty() {
+        return Err(ExitStatus(1).into());
+    }
+    Ok(formatted)
+}
+
+fn format_matches(matches: &[IgnoreMatch], verbose: bool) -> String {
+    let mut formatted = String::new();
+    for m in matches {
+        let path = m.path.to_string_lossy();
+        if verbose {
+            formatted.push_str(&format!(
+                "{}:{}:{}\t{}\n",
+                m.pattern.source.to_string_lossy(),
+                m.pattern.line_number,
+                m.pattern.text,
+                path
+            ));
+        } else if m.is_ignored() {
+            formatted.push_str(&format!("{}\n", path));
+        }
+    }
+    formatted
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use git_clone::gitignore::Pattern;
+
+    use super::*;
+
+    fn create_match(path: &str, line: &str, line_number: usize) -> IgnoreMatch {
+        IgnoreMatch {
+            path: path.into(),
+            pattern: Pattern::parse(line, Path::new(""), Path::new(".gitignore"), line_number)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    pub fn test_format_matches() {
+        let matches = [
+            create_match("build/out.o", "*.o", 1),
+            create_match("keep.o", "!keep.o", 2),
+        ];
+        assert_eq!(format_matches(&matches, false), "build/out.o\n");
+        assert_eq!(
+            format_matches(&matches, true),
+            ".gitignore:1:*.o\tbuild/out.o\n.gitignore:2:!keep.o\tkeep.o\n"
+        );
+        assert_eq!(format_matches(&matches[1..], false), "");
+        assert_eq!(format_matches(&[], true), "");
+    }
+}
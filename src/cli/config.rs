@@ -0,0 +1,62 @@
+use anyhow::Result;
+use git_clone::Repository;
+use git_clone::commands::{self, ConfigAction, ConfigOutput};
+
+use crate::args::Command;
+
+/// Runs the config command, showing the values it reads one per line and the listed ones as
+/// `name=value`, or just `name` for keys without a value.
+pub fn config(repo: Option<&Repository>, command: &Command) -> Result<String> {
+    let Command::Config {
+        global,
+        list,
+        get_all,
+        add,
+        unset,
+        value_type,
+        name,
+        value,
+    } = command
+    else {
+        unreachable!("only called with config commands")
+    };
+
+    let action = if *list {
+        ConfigAction::List
+    } else if *get_all {
+        ConfigAction::GetAll
+    } else if *add {
+        ConfigAction::Add
+    } else if *unset {
+        ConfigAction::Unset
+    } else {
+        ConfigAction::GetOrSet
+    };
+    let output = commands::config(
+        repo,
+        *global,
+        action,
+        value_type.map(Into::into),
+        name.as_deref(),
+        value.as_deref(),
+    )?;
+
+    let mut formatted = String::new();
+    match output {
+        ConfigOutput::Values(values) => {
+            for v in values {
+                formatted.push_str(&format!("{}\n", v));
+            }
+        }
+        ConfigOutput::Entries(entries) => {
+            for entry in entries {
+                match &entry.value {
+                    Some(value) => formatted.push_str(&format!("{}={}\n", entry.name, value)),
+                    None => formatted.push_str(&format!("{}\n", entry.name)),
+                }
+            }
+        }
+        ConfigOutput::Updated => (),
+    }
+    Ok(formatted)
+}
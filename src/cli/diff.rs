@@ -0,0 +1,144 @@
+use anyhow::Result;
+use colored::Colorize;
+use git_clone::Repository;
+use git_clone::commands::{self, DiffContent, FileDiff, FileVersion};
+
+/// Length of the abbreviated hashes shown in the `index` line.
+const SHORT_HASH_LEN: usize = 7;
+const NULL_SHORT_HASH: &str = "0000000";
+const DEV_NULL: &str = "/dev/null";
+
+/// Shows the changes between two versions of the repository as colored unified diffs (see
+/// `commands::diff`).
+pub fn diff(repo: &Repository, cached: bool, context: usize, commits: &[String]) -> Result<String> {
+    Ok(commands::diff(repo, cached, context, commits)?
+        .iter()
+        .map(format_file_diff)
+        .collect())
+}
+
+/// Returns the `diff --git` header and the hunks for a single file.
+fn format_file_diff(diff: &FileDiff) -> String {
+    let path_str = diff.path.to_string_lossy();
+    let short = |f: Option<&FileVersion>| match f {
+        Some(f) => f.hash.to_string()[..SHORT_HASH_LEN].to_string(),
+        None => NULL_SHORT_HASH.to_string(),
+    };
+    let (old, new) = (diff.old.as_ref(), diff.new.as_ref());
+
+    let mut header = format!("diff --git a/{} b/{}\n", path_str, path_str);
+    match (old, new) {
+        (None, Some(n)) => header.push_str(&format!(
+            "new file mode {}\nindex {}..{}\n",
+            n.mode,
+            short(old),
+            short(new)
+        )),
+        (Some(o), None) => header.push_str(&format!(
+            "deleted file mode {}\nindex {}..{}\n",
+            o.mode,
+            short(old),
+            short(new)
+        )),
+        (Some(o), Some(n)) => {
+            if o.mode != n.mode {
+                header.push_str(&format!("old mode {}\nnew mode {}\n", o.mode, n.mode));
+            }
+            if diff.content == DiffContent::Unchanged {
+                // only the mode changed, there are no hunks to show
+                return header.bold().to_string();
+            }
+            header.push_str(&format!("index {}..{}", short(old), short(new)));
+            if o.mode == n.mode {
+                header.push_str(&format!(" {}", o.mode));
+            }
+            header.push('\n');
+        }
+        (None, None) => return String::new(),
+    }
+
+    let old_name = match old {
+        Some(_) => format!("a/{}", path_str),
+        None => DEV_NULL.to_string(),
+    };
+    let new_name = match new {
+        Some(_) => format!("b/{}", path_str),
+        None => DEV_NULL.to_string(),
+    };
+
+    let hunks = match &diff.content {
+        DiffContent::Text(hunks) => hunks,
+        _ => {
+            header.push_str(&format!(
+                "Binary files {} and {} differ\n",
+                old_name, new_name
+            ));
+            return header.bold().to_string();
+        }
+    };
+
+    header.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+
+    let mut formatted = header.bold().to_string();
+    for line in hunks.lines() {
+        let colored = if line.starts_with("@@") {
+            line.cyan()
+        } else if line.starts_with('+') {
+            line.green()
+        } else if line.starts_with('-') {
+            line.red()
+        } else {
+            line.normal()
+        };
+        formatted.push_str(&format!("{}\n", colored));
+    }
+
+    formatted
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use git_clone::Hash;
+
+    use super::*;
+
+    const TEST_HASH: &str = "980a72fb0cd5a4985c44cba8a407e79db7e83e32";
+
+    fn version(mode: u32) -> Option<FileVersion> {
+        Some(FileVersion {
+            mode,
+            hash: Hash::from_str(TEST_HASH).unwrap(),
+        })
+    }
+
+    #[test]
+    pub fn test_format_mode_change() {
+        colored::control::set_override(false);
+
+        let diff = FileDiff {
+            path: PathBuf::from("script"),
+            old: version(100644),
+            new: version(100755),
+            content: DiffContent::Unchanged,
+        };
+        assert_eq!(
+            format_file_diff(&diff),
+            "diff --git a/script b/script\nold mode 100644\nnew mode 100755\n"
+        );
+
+        let diff = FileDiff {
+            content: DiffContent::Text("@@ -1 +1 @@\n-a\n+b\n".to_string()),
+            ..diff
+        };
+        assert_eq!(
+            format_file_diff(&diff),
+            "diff --git a/script b/script\nold mode 100644\nnew mode 100755\nindex 980a72f..980a72f\n\
+             --- a/script\n+++ b/script\n@@ -1 +1 @@\n-a\n+b\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::ffi::OsString;
+
+use anyhow::Result;
+use colored::Colorize;
+use git_clone::Repository;
+use git_clone::commands::{self, LogEntry};
+
+/// Length of the abbreviated hashes shown in merge parents.
+const SHORT_HASH_LEN: usize = 7;
+
+/// Shows the history of commits reachable from `revision` (or HEAD), most recent first.
+///
+/// - `max_count` limits the amount of commits shown.
+/// - `oneline` shows every commit as its abbreviated hash and subject.
+/// - `format` replaces the default layout with a custom one (see `format_entry`).
+/// - `paths`, if not empty, only keeps the commits that changed something inside of them.
+pub fn log(
+    repo: &Repository,
+    revision: Option<&str>,
+    max_count: Option<usize>,
+    oneline: bool,
+    format: Option<&str>,
+    paths: &[OsString],
+) -> Result<String> {
+    let format = format.map(|f| {
+        f.strip_prefix("format:")
+            .or(f.strip_prefix("tformat:"))
+            .unwrap_or(f)
+    });
+
+    let formatted: Vec<String> = commands::log(repo, revision, max_count, paths)?
+        .iter()
+        .map(|entry| match format {
+            Some(f) => format!("{}\n", format_entry(entry, f)),
+            None if oneline => format!("{} {}\n", entry.short_hash().yellow(), entry.subject()),
+            None => format_medium(entry),
+        })
+        .collect();
+
+    Ok(formatted.join(if oneline || format.is_some() {
+        ""
+    } else {
+        "\n"
+    }))
+}
+
+/// Formats a commit the way `git log` does by default: hash, merge parents, author, date and the
+/// message indented.
+fn format_medium(entry: &LogEntry) -> String {
+    let mut s = format!("{}\n", format!("commit {}", entry.hash).yellow());
+
+    if entry.parents.len() > 1 {
+        let parents: Vec<String> = entry
+            .parents
+            .iter()
+            .map(|p| p.to_string()[..SHORT_HASH_LEN].to_string())
+            .collect();
+        s.push_str(&format!("Merge: {}\n", parents.join(" ")));
+    }
+
+    s.push_str(&format!("Author: {}\n", entry.author.identifier));
+    s.push_str(&format!("Date:   {}\n\n", entry.author.format_date()));
+    for line in entry.message.lines() {
+        s.push_str(&format!("    {}\n", line));
+    }
+
+    s
+}
+
+/// Replaces the placeholders in `format` with the data of the commit:
+/// - `%H`: hash.
+/// - `%h`: abbreviated hash.
+/// - `%an`: author name.
+/// - `%ae`: author email.
+/// - `%ad`: author date.
+/// - `%s`: subject (first line of the message).
+/// - `%n`: new line.
+/// - `%%`: a literal `%`.
+///
+/// Unknown placeholders are left as they are.
+fn format_entry(entry: &LogEntry, format: &str) -> String {
+    let mut s = String::with_capacity(format.len());
+    let mut rest = format;
+
+    while let Some(position) = rest.find('%') {
+        s.push_str(&rest[..position]);
+        rest = &rest[position + 1..];
+
+        let (replacement, consumed) = if rest.starts_with("an") {
+            (entry.author.name().to_string(), 2)
+        } else if rest.starts_with("ae") {
+            (entry.author.email().to_string(), 2)
+        } else if rest.starts_with("ad") {
+            (entry.author.format_date(), 2)
+        } else if rest.starts_with('H') {
+            (entry.hash.to_string(), 1)
+        } else if rest.starts_with('h') {
+            (entry.short_hash(), 1)
+        } else if rest.starts_with('s') {
+            (entry.subject().to_string(), 1)
+        } else if rest.starts_with('n') {
+            ("\n".to_string(), 1)
+        } else if rest.starts_with('%') {
+            ("%".to_string(), 1)
+        } else {
+            ("%".to_string(), 0)
+        };
+
+        s.push_str(&replacement);
+        rest = &rest[consumed..];
+    }
+    s.push_str(rest);
+
+    s
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::str::FromStr;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use time::UtcOffset;
+
+    use git_clone::Hash;
+    use git_clone::object::commit::{CommitUser, CommitUserKind};
+
+    use super::*;
+
+    const TEST_HASH: &str = "980a72fb0cd5a4985c44cba8a407e79db7e83e32";
+
+    fn create_test_entry() -> LogEntry {
+        let user = |kind| CommitUser {
+            kind,
+            identifier: "John Doe <john@example.com>".to_string(),
+            timestamp: UNIX_EPOCH + Duration::from_secs(1640995200),
+            timezone: UtcOffset::UTC,
+        };
+        LogEntry {
+            hash: Hash::from_str(TEST_HASH).unwrap(),
+            tree: Hash::from_str(TEST_HASH).unwrap(),
+            parents: Rc::new([]),
+            author: user(CommitUserKind::Author),
+            committer: user(CommitUserKind::Committer),
+            message: "Subject line\n\nBody".into(),
+        }
+    }
+
+    #[test]
+    pub fn test_format_entry_placeholders() {
+        let entry = create_test_entry();
+        assert_eq!(
+            format_entry(&entry, "%h %an <%ae> %s"),
+            "980a72f John Doe <john@example.com> Subject line"
+        );
+        assert_eq!(format_entry(&entry, "%H"), TEST_HASH);
+        assert_eq!(format_entry(&entry, "%ad"), "Sat Jan 1 00:00:00 2022 +0000");
+    }
+
+    #[test]
+    pub fn test_format_entry_escapes() {
+        let entry = create_test_entry();
+        assert_eq!(format_entry(&entry, "100%% %x%n"), "100% %x\n");
+        assert_eq!(format_entry(&entry, "trailing %"), "trailing %");
+    }
+}
@@ -0,0 +1,334 @@
+mod branch;
+mod check_ignore;
+mod config;
+mod diff;
+mod log;
+mod rebase;
+mod reset;
+mod sequencer;
+mod status;
+mod tag;
+
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use git_clone::Repository;
+use git_clone::commands::{self, CheckoutOutcome, InitOutcome, MergeOutcome};
+
+use crate::args::{Command, PorcelainVersion};
+use crate::cli::sequencer::SequencerCommand;
+
+/// Error of the commands that report their result only through the exit code, like
+/// `merge-base --is-ancestor`. Nothing is printed for it, the process exits with the code.
+#[derive(Debug)]
+pub struct ExitStatus(pub i32);
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+/// Calls the corresponding library function to perform every command variant, formatting its
+/// result.
+///
+/// # Return
+///
+/// The success message
+///
+/// # Errors
+///
+/// This function will fail if any of the executed commands return an error.
+pub fn execute_command(command: &Command) -> Result<String> {
+    if let Command::Init { folder_name } = command {
+        // Only command that can be executed without a repository already existing, it does not
+        // look for one in the parent directories either
+        return init(folder_name.as_deref());
+    }
+
+    // if no repository is found, the current directory is used as if it had one
+    let cwd = env::current_dir().context("could not get current directory")?;
+    let repo = Repository::discover(&cwd).unwrap_or_else(|| Repository::in_dir(&cwd));
+    if !repo.exists() {
+        if let Command::Config { global: true, .. } = command {
+            // global values do not need a repository either
+            return config::config(None, command);
+        }
+        return Ok("Folder is not a git repository".into());
+    }
+    let repo = &repo;
+
+    match command {
+        Command::Init { .. } => unreachable!("init is run before looking for a repository"),
+        Command::Add { files } => {
+            let summary = commands::add(repo, files)?;
+            if summary.matched == 0 {
+                Ok("There were no files to add\n".into())
+            } else {
+                Ok("Added files successfully\n".into())
+            }
+        }
+        Command::Reset { command } => reset::reset(repo, command.as_ref()),
+        Command::Status {
+            short,
+            porcelain,
+            branch,
+            null_terminated,
+        } => {
+            let format = match (porcelain, short, null_terminated) {
+                (Some(version), _, _) => status::StatusFormat::Porcelain(*version),
+                (None, true, _) => status::StatusFormat::Short,
+                (None, false, true) => status::StatusFormat::Porcelain(PorcelainVersion::V1),
+                (None, false, false) => status::StatusFormat::Long,
+            };
+            status::status(repo, format, *branch, *null_terminated)
+        }
+        Command::Commit { message } => {
+            commands::commit(repo, message)?;
+            Ok("Commited changes successfully\n".into())
+        }
+        Command::Checkout { reference } => match commands::checkout(repo, reference)? {
+            CheckoutOutcome::AlreadyOn => Ok(format!("Already on '{}'\n", reference)),
+            CheckoutOutcome::Switched => Ok(format!("Switched to branch '{}'\n", reference)),
+            CheckoutOutcome::Detached(hash) => {
+                let entry = commands::LogEntry::read(repo, hash)?;
+                Ok(format!(
+                    "HEAD is now at {} {}\n",
+                    entry.short_hash(),
+                    entry.subject()
+                ))
+            }
+        },
+        Command::LsFiles { debug } => ls_files(repo, *debug),
+        Command::CatFile { object } => Ok(format!("{}", commands::cat_file(repo, object)?)),
+        Command::RevParse { short, revisions } => {
+            let mut output = String::new();
+            for hash in commands::rev_parse(repo, revisions)? {
+                let hash = hash.to_string();
+                if *short {
+                    output.push_str(&hash[..7]);
+                } else {
+                    output.push_str(&hash);
+                }
+                output.push('\n');
+            }
+            Ok(output)
+        }
+        Command::CheckIgnore { verbose, paths } => {
+            check_ignore::check_ignore(repo, paths, *verbose)
+        }
+        Command::Log {
+            revision,
+            max_count,
+            oneline,
+            format,
+            paths,
+        } => log::log(
+            repo,
+            revision.as_deref(),
+            *max_count,
+            *oneline,
+            format.as_deref(),
+            paths,
+        ),
+        Command::Diff {
+            cached,
+            context,
+            commits,
+        } => diff::diff(repo, *cached, *context, commits),
+        Command::Repack => Ok(match commands::repack(repo)? {
+            Some(summary) => format!(
+                "Packed {} objects into {}\n{}",
+                summary.packed,
+                summary.pack_path.to_string_lossy(),
+                format_loosened(&summary)
+            ),
+            None => "Nothing to pack\n".into(),
+        }),
+        Command::Gc => Ok(match commands::gc(repo)? {
+            Some(summary) => format!(
+                "Packed {} objects, removed {} loose objects\n{}",
+                summary.packed,
+                summary.pruned,
+                format_loosened(&summary)
+            ),
+            None => "Nothing to pack\n".into(),
+        }),
+        Command::Config { .. } => config::config(Some(repo), command),
+        Command::Merge { revision, abort } => merge(repo, revision.as_deref(), *abort),
+        Command::CherryPick { sequence } => {
+            sequencer::sequencer(repo, SequencerCommand::CherryPick, sequence)
+        }
+        Command::Revert { sequence } => {
+            sequencer::sequencer(repo, SequencerCommand::Revert, sequence)
+        }
+        Command::Rebase {
+            upstream,
+            interactive,
+            resume,
+            skip,
+            abort,
+        } => rebase::rebase(
+            repo,
+            upstream.as_deref(),
+            *interactive,
+            *resume,
+            *skip,
+            *abort,
+        ),
+        Command::MergeBase {
+            all,
+            is_ancestor,
+            commits,
+        } => merge_base(repo, commits, *all, *is_ancestor),
+        Command::Tag {
+            name,
+            commit,
+            message,
+            delete,
+            ..
+        } => tag::tag(
+            repo,
+            name.as_deref(),
+            commit.as_deref(),
+            message.as_deref(),
+            *delete,
+        ),
+        Command::Branch {
+            name,
+            start_point,
+            delete,
+            force_delete,
+            rename,
+        } => branch::branch(
+            repo,
+            name.as_deref(),
+            start_point.as_deref(),
+            *delete,
+            *force_delete,
+            *rename,
+        ),
+    }
+}
+
+/// Creates a new repository in the current directory, or inside `folder_name` if one is
+/// provided.
+fn init(folder_name: Option<&OsStr>) -> Result<String> {
+    // the repository goes in the current directory even if it is inside of another one, unless
+    // GIT_DIR says otherwise
+    let cwd = env::current_dir().context("could not get current directory")?;
+    let repo = match folder_name {
+        Some(name) => Repository::in_dir(&cwd.join(name)),
+        None if env::var_os("GIT_DIR").is_some() => {
+            Repository::discover(&cwd).unwrap_or_else(|| Repository::in_dir(&cwd))
+        }
+        None => Repository::in_dir(&cwd),
+    };
+
+    match commands::init(&repo)? {
+        InitOutcome::Created => Ok("Created new git repository\n".into()),
+        InitOutcome::AlreadyExists => Ok("The directory is already a git repository\n".into()),
+    }
+}
+
+/// Merges `revision` into the current branch, or aborts the merge in progress with `abort`.
+fn merge(repo: &Repository, revision: Option<&str>, abort: bool) -> Result<String> {
+    let Some(revision) = revision.filter(|_| !abort) else {
+        commands::merge_abort(repo)?;
+        return Ok("Merge aborted\n".into());
+    };
+
+    Ok(match commands::merge(repo, revision)? {
+        MergeOutcome::UpToDate => "Already up to date.\n".into(),
+        MergeOutcome::FastForward { from, to } => format!(
+            "Updating {}..{}\nFast-forward\n",
+            &from.to_string()[..7],
+            &to.to_string()[..7]
+        ),
+        MergeOutcome::Merged(hash) => {
+            format!("Merge made, created commit {}\n", &hash.to_string()[..7])
+        }
+        MergeOutcome::Conflicts(paths) => {
+            let mut formatted = String::new();
+            for path in paths {
+                formatted.push_str(&format!(
+                    "CONFLICT: Merge conflict in {}\n",
+                    path.to_string_lossy()
+                ));
+            }
+            formatted
+                .push_str("Automatic merge failed; fix conflicts and then commit the result.\n");
+            formatted
+        }
+    })
+}
+
+/// Returns the best common ancestor of the commits, or all of them with `all`. With
+/// `is_ancestor`, returns nothing if the first commit is an ancestor of the second one.
+///
+/// Both fail with an exit status of 1 when there is no ancestor.
+fn merge_base(
+    repo: &Repository,
+    commits: &[String],
+    all: bool,
+    is_ancestor: bool,
+) -> Result<String> {
+    if is_ancestor {
+        let [ancestor, descendant] = commits else {
+            bail!("--is-ancestor takes exactly two commits")
+        };
+        if commands::is_ancestor(repo, ancestor, descendant)? {
+            return Ok(String::new());
+        }
+        return Err(ExitStatus(1).into());
+    }
+
+    let bases = commands::merge_base(repo, commits)?;
+    if bases.is_empty() {
+        return Err(ExitStatus(1).into());
+    }
+    let count = if all { bases.len() } else { 1 };
+    Ok(bases
+        .iter()
+        .take(count)
+        .map(|hash| format!("{}\n", hash))
+        .collect())
+}
+
+/// Returns the line telling how many unreachable objects were kept as loose objects, or nothing
+/// if there were none.
+fn format_loosened(summary: &commands::PackSummary) -> String {
+    match summary.loosened {
+        0 => String::new(),
+        n => format!("Kept {} unreachable objects as loose objects\n", n),
+    }
+}
+
+fn ls_files(repo: &Repository, debug: bool) -> Result<String> {
+    let index = commands::ls_files(repo)?;
+
+    if index.entries().count() == 0 {
+        return Ok("There were no files to list\n".into());
+    }
+
+    let mut formatted = String::new();
+    for e in index.entries() {
+        if debug {
+            formatted.push_str(format!("{e:?}").as_str());
+            formatted.push('\n');
+        } else {
+            formatted.push_str(format!("{e}").as_str());
+        }
+        formatted.push('\n');
+    }
+
+    if debug {
+        formatted.pop();
+    } // removing extra new line
+
+    Ok(formatted)
+}
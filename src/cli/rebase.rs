@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use git_clone::commands::{self, LogEntry, RebaseOutcome};
+use git_clone::{Repository, fs};
+
+/// Rebases the current branch onto `upstream`, or continues, skips or aborts the rebase that
+/// stopped, describing where it ended up.
+pub fn rebase(
+    repo: &Repository,
+    upstream: Option<&str>,
+    interactive: bool,
+    resume: bool,
+    skip: bool,
+    abort: bool,
+) -> Result<String> {
+    let outcome = if abort {
+        commands::rebase_abort(repo)?;
+        return Ok("Rebase aborted\n".into());
+    } else if resume {
+        commands::rebase_continue(repo)?
+    } else if skip {
+        commands::rebase_skip(repo)?
+    } else {
+        let upstream = upstream.context("missing upstream to rebase onto")?;
+        commands::rebase(repo, upstream, interactive)?
+    };
+
+    let branch = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    Ok(match outcome {
+        RebaseOutcome::UpToDate => format!(
+            "Current branch {} is up to date.\n",
+            branch.as_deref().unwrap_or("HEAD")
+        ),
+        RebaseOutcome::FastForward(hash) => format!(
+            "Fast-forwarded {} to {}.\n",
+            branch.as_deref().unwrap_or("HEAD"),
+            &hash.to_string()[..7]
+        ),
+        RebaseOutcome::Done(_) => match branch {
+            Some(branch) => format!("Successfully rebased and updated refs/heads/{}.\n", branch),
+            None => "Successfully rebased and updated detached HEAD.\n".into(),
+        },
+        RebaseOutcome::Conflicts { commit, paths } => {
+            let mut formatted = String::new();
+            for path in paths {
+                formatted.push_str(&format!(
+                    "CONFLICT: Merge conflict in {}\n",
+                    path.to_string_lossy()
+                ));
+            }
+            let entry = LogEntry::read(repo, commit)?;
+            formatted.push_str(&format!(
+                "could not apply {}... {}\nfix conflicts and add them, then run \"rebase --continue\", \"rebase --skip\" to skip this commit, or \"rebase --abort\" to go back\n",
+                entry.short_hash(),
+                entry.subject()
+            ));
+            formatted
+        }
+        RebaseOutcome::Edit(commit) => {
+            let entry = LogEntry::read(repo, commit)?;
+            format!(
+                "Stopped at {}... {}\nadd the changes to amend it with, then run \"rebase --continue\"\n",
+                entry.short_hash(),
+                entry.subject()
+            )
+        }
+        RebaseOutcome::ExecFailed(command) => format!(
+            "Execution failed: {}\nfix the problem, then run \"rebase --continue\"\n",
+            command
+        ),
+    })
+}
@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+use git_clone::commands::{self, LogEntry};
+use git_clone::{Repository, revision};
+
+use crate::args::ResetCommand;
+
+/// Unstages the given files, resets to a commit, or unstages everything if there is no
+/// subcommand.
+pub fn reset(repo: &Repository, command: Option<&ResetCommand>) -> Result<String> {
+    match command {
+        Some(ResetCommand::Files { files }) => {
+            let reset = commands::reset_files(repo, files)?;
+            Ok(format!("Reset {} file(s)\n", reset.len()))
+        }
+        Some(ResetCommand::Commit { mode, commit }) => {
+            let hash =
+                revision::resolve_commit(repo, commit).context("could not resolve commit")?;
+
+            Ok(format_head(&commands::reset_to_commit(
+                repo,
+                mode.mode(),
+                hash,
+            )?))
+        }
+        // the reset command, without arguments, resets to the previous commit
+        None => match commands::reset_head(repo)? {
+            Some(commit) => Ok(format_head(&commit)),
+            None => Ok("cleaned index file\n".into()),
+        },
+    }
+}
+
+fn format_head(commit: &LogEntry) -> String {
+    format!(
+        "HEAD is now at {} {}\n",
+        commit.short_hash(),
+        commit.subject()
+    )
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use git_clone::commands::{self, LogEntry, SequencerOutcome};
+use git_clone::{Repository, fs};
+
+use crate::args::SequencerArgs;
+
+/// The commands that apply commits through the sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequencerCommand {
+    CherryPick,
+    Revert,
+}
+
+impl SequencerCommand {
+    fn name(self) -> &'static str {
+        match self {
+            SequencerCommand::CherryPick => "cherry-pick",
+            SequencerCommand::Revert => "revert",
+        }
+    }
+}
+
+/// Cherry-picks or reverts the given commits, or continues, skips or aborts the run that stopped
+/// because of conflicts. Every new commit is shown as `[branch hash] subject`.
+pub fn sequencer(
+    repo: &Repository,
+    command: SequencerCommand,
+    args: &SequencerArgs,
+) -> Result<String> {
+    let outcome = if args.abort {
+        commands::sequencer_abort(repo)?;
+        return Ok(match command {
+            SequencerCommand::CherryPick => "Cherry-pick aborted\n".into(),
+            SequencerCommand::Revert => "Revert aborted\n".into(),
+        });
+    } else if args.resume {
+        commands::sequencer_continue(repo)?
+    } else if args.skip {
+        commands::sequencer_skip(repo)?
+    } else {
+        match command {
+            SequencerCommand::CherryPick => commands::cherry_pick(repo, &args.commits)?,
+            SequencerCommand::Revert => commands::revert(repo, &args.commits)?,
+        }
+    };
+
+    let branch = fs::get_current_branch_name(repo)
+        .context("could not get current branch name")?
+        .unwrap_or_else(|| "detached HEAD".to_string());
+    let applied = match &outcome {
+        SequencerOutcome::Done(applied) => applied,
+        SequencerOutcome::Conflicts { applied, .. } => applied,
+    };
+
+    let mut formatted = String::new();
+    for hash in applied {
+        let entry = LogEntry::read(repo, hash.clone())?;
+        formatted.push_str(&format!(
+            "[{} {}] {}\n",
+            branch,
+            entry.short_hash(),
+            entry.subject()
+        ));
+    }
+
+    if let SequencerOutcome::Conflicts { commit, paths, .. } = outcome {
+        for path in paths {
+            formatted.push_str(&format!(
+                "CONFLICT: Merge conflict in {}\n",
+                path.to_string_lossy()
+            ));
+        }
+        let entry = LogEntry::read(repo, commit)?;
+        let verb = match command {
+            SequencerCommand::CherryPick => "apply",
+            SequencerCommand::Revert => "revert",
+        };
+        formatted.push_str(&format!(
+            "could not {} {}... {}\nfix conflicts and add them, then run \"{} --continue\", or \"{} --skip\" to skip this commit\n",
+            verb,
+            entry.short_hash(),
+            entry.subject(),
+            command.name(),
+            command.name()
+        ));
+    }
+
+    Ok(formatted)
+}
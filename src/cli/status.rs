@@ -0,0 +1,627 @@
+use std::path::Path;
+
+use anyhow::Result;
+use colored::Colorize;
+use git_clone::commands::{
+    self, FileVersion, FileWithStatus, StageStatus, Status, StatusReport, UpstreamStatus,
+};
+use git_clone::repository::Operation;
+use git_clone::{Hash, Repository};
+
+use crate::args::PorcelainVersion;
+
+/// Hash shown for the sides a file is missing from in the porcelain v2 format.
+const NULL_HASH: &str = "0000000000000000000000000000000000000000";
+/// Rename score of moved files, which always have the same contents in both paths.
+const RENAME_SCORE: &str = "R100";
+/// Submodule state field of the porcelain v2 format, none of the files are submodules.
+const NOT_SUBMODULE: &str = "N...";
+
+/// The layouts status can be shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFormat {
+    /// Changes grouped by whether they are staged, meant for people
+    Long,
+    /// A two letter code and the path of every file, colored
+    Short,
+    /// Like `Short` but never colored for v1, or with the modes and hashes of every side for v2
+    Porcelain(PorcelainVersion),
+}
+
+/// Returns the status of the repository in the given format.
+///
+/// `branch` adds the current branch as a header to the short and porcelain formats, and
+/// `null_terminated` ends their entries with NUL instead of new lines, leaving paths unquoted.
+pub fn status(
+    repo: &Repository,
+    format: StatusFormat,
+    branch: bool,
+    null_terminated: bool,
+) -> Result<String> {
+    let report = commands::status(repo)?;
+    let terminator = if null_terminated { '\0' } else { '\n' };
+    Ok(match format {
+        StatusFormat::Long => format_long(report),
+        StatusFormat::Short => format_short(&report, branch, terminator, !null_terminated),
+        StatusFormat::Porcelain(PorcelainVersion::V1) => {
+            format_short(&report, branch, terminator, false)
+        }
+        StatusFormat::Porcelain(PorcelainVersion::V2) => {
+            format_porcelain_v2(&report, branch, terminator)
+        }
+    })
+}
+
+/// Returns the changes of every file, grouped by whether they are staged.
+fn format_long(report: StatusReport) -> String {
+    let mut header = match (&report.branch, &report.operation, &report.head) {
+        (Some(branch), _, _) => format!("On branch {}\n", branch),
+        (None, Some(Operation::Rebase { .. }), _) => String::new(),
+        (None, _, Some(head)) => format!("HEAD detached at {}\n", &head.to_string()[..7]),
+        (None, _, None) => "HEAD detached\n".to_string(),
+    };
+    if let Some(upstream) = &report.upstream {
+        header.push_str(&format_tracking(upstream));
+    }
+    if let Some(operation) = &report.operation {
+        header.push_str(&format_operation(operation, report.branch.is_none()));
+    }
+
+    if report.head.is_none() {
+        header.push_str("\nNo commits yet\n");
+    }
+
+    let files: Vec<FileWithStatus> = report
+        .files
+        .into_iter()
+        .filter(|fws| fws.status != Status::Unchanged)
+        .collect();
+    if files.is_empty() {
+        return format!("{}\nThere is nothing to commit, all clean!\n", header);
+    }
+
+    let mut commit = String::new();
+    let mut unmerged = String::new();
+    let mut notcommit = String::new();
+    let mut untracked = String::new();
+    let mut status_str: String;
+    let mut path_str: String;
+    for s in files {
+        path_str = s.path.to_string_lossy().to_string();
+
+        if s.stage_status == StageStatus::Untracked {
+            untracked.push_str(&format!("\t{}\n", path_str));
+            continue;
+        }
+
+        status_str = match &s.status {
+            Status::New => format!("\tnew file:\t{}\n", path_str),
+            Status::Moved { previous } => {
+                format!("\tmoved:\t{} -> {}\n", previous.to_string_lossy(), path_str)
+            }
+            Status::Deleted => format!("\tdeleted:\t{}\n", path_str),
+            Status::Modified => format!("\tmodified:\t{}\n", path_str),
+            Status::Unchanged => continue,
+            Status::Unmerged(_) => {
+                let description = match s.short_code() {
+                    ['U', 'U'] => "both modified",
+                    ['A', 'A'] => "both added",
+                    ['D', 'U'] => "deleted by us",
+                    ['U', 'D'] => "deleted by them",
+                    ['A', 'U'] => "added by us",
+                    ['U', 'A'] => "added by them",
+                    _ => "both deleted",
+                };
+                unmerged.push_str(&format!("\t{}:\t{}\n", description, path_str));
+                continue;
+            }
+        };
+
+        match s.stage_status {
+            StageStatus::Commit => commit.push_str(&status_str),
+            StageStatus::NotCommit => notcommit.push_str(&status_str),
+            StageStatus::Untracked => untracked.push_str(&status_str),
+        };
+    }
+
+    if !commit.is_empty() {
+        header = format!("{}\nChanges staged for commit:\n{}", header, commit.green());
+    }
+    if !unmerged.is_empty() {
+        header = format!("{}\nUnmerged paths:\n{}", header, unmerged.red());
+    }
+    if !notcommit.is_empty() {
+        header = format!("{}\nNot staged for commit:\n{}", header, notcommit.red());
+    }
+    if !untracked.is_empty() {
+        header = format!("{}\nUntracked files:\n{}", header, untracked.red());
+    }
+
+    header
+}
+
+/// Returns how the current branch compares to its upstream, in one or two lines.
+fn format_tracking(upstream: &UpstreamStatus) -> String {
+    let commits = |n: usize| if n == 1 { "commit" } else { "commits" };
+    match upstream.ahead_behind {
+        None => format!(
+            "Your branch is based on '{}', but the upstream is gone.\n",
+            upstream.name
+        ),
+        Some((0, 0)) => format!("Your branch is up to date with '{}'.\n", upstream.name),
+        Some((ahead, 0)) => format!(
+            "Your branch is ahead of '{}' by {} {}.\n",
+            upstream.name,
+            ahead,
+            commits(ahead)
+        ),
+        Some((0, behind)) => format!(
+            "Your branch is behind '{}' by {} {}, and can be fast-forwarded.\n",
+            upstream.name,
+            behind,
+            commits(behind)
+        ),
+        Some((ahead, behind)) => format!(
+            "Your branch and '{}' have diverged,\nand have {} and {} different commits each, respectively.\n",
+            upstream.name, ahead, behind
+        ),
+    }
+}
+
+/// Returns the lines describing the operation waiting to be continued. Rebases replace the
+/// detached HEAD line when HEAD is `detached`.
+fn format_operation(operation: &Operation, detached: bool) -> String {
+    let short = |hash: &Hash| hash.to_string()[..7].to_string();
+    match operation {
+        Operation::Merge => "\nYou are in the middle of a merge.\n".to_string(),
+        Operation::Rebase {
+            branch,
+            onto,
+            interactive,
+        } => {
+            let onto = onto.as_ref().map(short).unwrap_or_default();
+            let mut formatted = String::new();
+            if detached {
+                let kind = if *interactive {
+                    "interactive rebase"
+                } else {
+                    "rebase"
+                };
+                formatted.push_str(&format!("{} in progress; onto {}\n", kind, onto));
+            }
+            match branch {
+                Some(branch) => formatted.push_str(&format!(
+                    "\nYou are currently rebasing branch '{}' on '{}'.\n",
+                    branch, onto
+                )),
+                None => formatted.push_str("\nYou are currently rebasing.\n"),
+            }
+            formatted
+        }
+        Operation::CherryPick(hash) => {
+            format!(
+                "\nYou are currently cherry-picking commit {}.\n",
+                short(hash)
+            )
+        }
+        Operation::Revert(hash) => {
+            format!("\nYou are currently reverting commit {}.\n", short(hash))
+        }
+    }
+}
+
+/// Returns every file as `XY path`, or `XY previous -> path` for moved files (see
+/// `FileWithStatus::short_code`). With NUL terminators, moved files are `XY path\0previous`.
+fn format_short(report: &StatusReport, branch: bool, terminator: char, colored: bool) -> String {
+    let mut formatted = String::new();
+    if branch {
+        formatted.push_str(&format_short_branch(report, colored));
+        formatted.push(terminator);
+    }
+
+    let quoted = terminator == '\n';
+    for fws in sorted_files(report) {
+        let [x, y] = fws.short_code();
+        if colored && fws.stage_status == StageStatus::Untracked {
+            formatted.push_str(&"??".red().to_string());
+        } else if colored {
+            formatted.push_str(&format!("{}{}", x.to_string().green(), y.to_string().red()));
+        } else {
+            formatted.push_str(&format!("{}{}", x, y));
+        }
+        formatted.push(' ');
+
+        match &fws.status {
+            Status::Moved { previous } if quoted => formatted.push_str(&format!(
+                "{} -> {}",
+                format_path(previous, quoted, true),
+                format_path(&fws.path, quoted, true)
+            )),
+            Status::Moved { previous } => formatted.push_str(&format!(
+                "{}{}{}",
+                format_path(&fws.path, quoted, true),
+                terminator,
+                format_path(previous, quoted, true)
+            )),
+            _ => formatted.push_str(&format_path(&fws.path, quoted, true)),
+        }
+        formatted.push(terminator);
+    }
+
+    formatted
+}
+
+/// Returns the branch header of the short format: `## branch...upstream [ahead A, behind B]`, or
+/// `## HEAD (no branch)` when HEAD is detached.
+fn format_short_branch(report: &StatusReport, colored: bool) -> String {
+    let color = |text: &str, red: bool| match (colored, red) {
+        (false, _) => text.to_string(),
+        (true, false) => text.green().to_string(),
+        (true, true) => text.red().to_string(),
+    };
+
+    let Some(branch) = &report.branch else {
+        return format!("## {}", color("HEAD (no branch)", true));
+    };
+    if report.head.is_none() {
+        return format!("## No commits yet on {}", color(branch, false));
+    }
+
+    let mut formatted = format!("## {}", color(branch, false));
+    if let Some(upstream) = &report.upstream {
+        formatted.push_str(&format!("...{}", color(&upstream.name, true)));
+        match upstream.ahead_behind {
+            None => formatted.push_str(" [gone]"),
+            Some((0, 0)) => {}
+            Some((ahead, behind)) => {
+                let mut counts = Vec::new();
+                if ahead > 0 {
+                    counts.push(format!("ahead {}", color(&ahead.to_string(), false)));
+                }
+                if behind > 0 {
+                    counts.push(format!("behind {}", color(&behind.to_string(), true)));
+                }
+                formatted.push_str(&format!(" [{}]", counts.join(", ")));
+            }
+        }
+    }
+    formatted
+}
+
+/// Returns every file in the porcelain v2 format:
+/// - `1 XY sub mH mI mW hH hI path` for changed files.
+/// - `2 XY sub mH mI mW hH hI score path<TAB>previous` for moved files.
+/// - `u XY sub m1 m2 m3 mW h1 h2 h3 path` for files with conflicts.
+/// - `? path` for untracked files.
+///
+/// The modes and hashes are the ones of the file in the last commit (H), the index (I) and the
+/// working tree (W), and unchanged sides of the code are a `.`. Files with conflicts have the
+/// ones of each stage of the index instead: the base (1), ours (2) and theirs (3).
+fn format_porcelain_v2(report: &StatusReport, branch: bool, terminator: char) -> String {
+    let mut formatted = String::new();
+    if branch {
+        let oid = match &report.head {
+            Some(hash) => hash.to_string(),
+            None => "(initial)".to_string(),
+        };
+        let head = report.branch.as_deref().unwrap_or("(detached)");
+        formatted.push_str(&format!("# branch.oid {}{}", oid, terminator));
+        formatted.push_str(&format!("# branch.head {}{}", head, terminator));
+        if let Some(upstream) = &report.upstream {
+            formatted.push_str(&format!(
+                "# branch.upstream {}{}",
+                upstream.name, terminator
+            ));
+            if let Some((ahead, behind)) = upstream.ahead_behind {
+                formatted.push_str(&format!("# branch.ab +{} -{}{}", ahead, behind, terminator));
+            }
+        }
+    }
+
+    let quoted = terminator == '\n';
+    let mode = |mode: Option<u32>| format!("{:06}", mode.unwrap_or_default());
+    let hash = |version: Option<&FileVersion>| match version {
+        Some(v) => v.hash.to_string(),
+        None => NULL_HASH.to_string(),
+    };
+    // like git, unmerged entries come after the ordinary ones and before the untracked ones
+    let mut files = sorted_files(report);
+    files.sort_by_key(|fws| match &fws.status {
+        _ if fws.stage_status == StageStatus::Untracked => 2,
+        Status::Unmerged(_) => 1,
+        _ => 0,
+    });
+    for fws in files {
+        if fws.stage_status == StageStatus::Untracked {
+            formatted.push_str(&format!(
+                "? {}{}",
+                format_path(&fws.path, quoted, false),
+                terminator
+            ));
+            continue;
+        }
+
+        if let Status::Unmerged(conflict) = &fws.status {
+            let [x, y] = fws.short_code();
+            let stages = [&conflict.base, &conflict.ours, &conflict.theirs];
+            formatted.push_str(&format!(
+                "u {}{} {} {} {} {} {}{}",
+                x,
+                y,
+                NOT_SUBMODULE,
+                stages.map(|v| mode(v.as_ref().map(|v| v.mode))).join(" "),
+                mode(fws.worktree_mode),
+                stages.map(|v| hash(v.as_ref())).join(" "),
+                format_path(&fws.path, quoted, false),
+                terminator
+            ));
+            continue;
+        }
+
+        let [x, y] = fws.short_code().map(|c| if c == ' ' { '.' } else { c });
+        let fields = format!(
+            "{}{} {} {} {} {} {} {}",
+            x,
+            y,
+            NOT_SUBMODULE,
+            mode(fws.head.as_ref().map(|v| v.mode)),
+            mode(fws.index.as_ref().map(|v| v.mode)),
+            mode(fws.worktree_mode),
+            hash(fws.head.as_ref()),
+            hash(fws.index.as_ref()),
+        );
+
+        match &fws.status {
+            Status::Moved { previous } => formatted.push_str(&format!(
+                "2 {} {} {}{}{}",
+                fields,
+                RENAME_SCORE,
+                format_path(&fws.path, quoted, false),
+                if quoted { '\t' } else { '\0' },
+                format_path(previous, quoted, false)
+            )),
+            _ => formatted.push_str(&format!(
+                "1 {} {}",
+                fields,
+                format_path(&fws.path, quoted, false)
+            )),
+        }
+        formatted.push(terminator);
+    }
+
+    formatted
+}
+
+/// Returns the files of the report sorted by path, with the untracked ones at the end.
+fn sorted_files(report: &StatusReport) -> Vec<&FileWithStatus> {
+    let mut files: Vec<&FileWithStatus> = report.files.iter().collect();
+    files.sort_by(|a, b| {
+        let untracked = |f: &FileWithStatus| f.stage_status == StageStatus::Untracked;
+        untracked(a)
+            .cmp(&untracked(b))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    files
+}
+
+fn format_path(path: &Path, quoted: bool, quote_spaces: bool) -> String {
+    if quoted {
+        quote_path(path, quote_spaces)
+    } else {
+        path.to_string_lossy().to_string()
+    }
+}
+
+/// Returns `path` between double quotes with its special characters escaped the way git does, or
+/// as it is if it does not have any. Bytes outside of ASCII are escaped as octal numbers.
+///
+/// With `quote_spaces`, paths with spaces are quoted too, since they would be ambiguous in the
+/// `previous -> path` form of the short format.
+fn quote_path(path: &Path, quote_spaces: bool) -> String {
+    fn needs_escaping(b: u8) -> bool {
+        !(0x20..0x7f).contains(&b) || b == b'"' || b == b'\\'
+    }
+    let needs_quoting = |b: u8| needs_escaping(b) || (quote_spaces && b == b' ');
+
+    let bytes = path.as_os_str().as_encoded_bytes();
+    if !bytes.iter().any(|b| needs_quoting(*b)) {
+        return path.to_string_lossy().to_string();
+    }
+
+    let mut quoted = String::from('"');
+    for b in bytes {
+        match *b {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            0x07 => quoted.push_str("\\a"),
+            0x08 => quoted.push_str("\\b"),
+            0x0b => quoted.push_str("\\v"),
+            0x0c => quoted.push_str("\\f"),
+            b if needs_escaping(b) => quoted.push_str(&format!("\\{:03o}", b)),
+            b => quoted.push(b as char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use git_clone::commands::Conflict;
+
+    use super::*;
+
+    const TEST_HASH: &str = "980a72fb0cd5a4985c44cba8a407e79db7e83e32";
+
+    fn version(mode: u32) -> Option<FileVersion> {
+        Some(FileVersion {
+            mode,
+            hash: Hash::from_str(TEST_HASH).unwrap(),
+        })
+    }
+
+    fn create_test_report() -> StatusReport {
+        StatusReport {
+            branch: Some("main".into()),
+            head: None,
+            upstream: None,
+            operation: None,
+            files: vec![
+                FileWithStatus {
+                    path: PathBuf::from("new name"),
+                    status: Status::Moved {
+                        previous: PathBuf::from("old"),
+                    },
+                    stage_status: StageStatus::Commit,
+                    head: version(100644),
+                    index: version(100644),
+                    worktree_mode: Some(100644),
+                },
+                FileWithStatus {
+                    path: PathBuf::from("notes.txt"),
+                    status: Status::New,
+                    stage_status: StageStatus::Untracked,
+                    head: None,
+                    index: None,
+                    worktree_mode: Some(100644),
+                },
+                FileWithStatus {
+                    path: PathBuf::from("a.sh"),
+                    status: Status::New,
+                    stage_status: StageStatus::NotCommit,
+                    head: None,
+                    index: version(100755),
+                    worktree_mode: Some(100755),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    pub fn test_format_porcelain_v1() {
+        let report = create_test_report();
+        assert_eq!(
+            format_short(&report, true, '\n', false),
+            "## No commits yet on main\nAM a.sh\nR  old -> \"new name\"\n?? notes.txt\n"
+        );
+        assert_eq!(
+            format_short(&report, false, '\0', false),
+            "AM a.sh\0R  new name\0old\0?? notes.txt\0"
+        );
+    }
+
+    #[test]
+    pub fn test_format_porcelain_v2() {
+        let report = create_test_report();
+        let lines: Vec<String> = format_porcelain_v2(&report, true, '\n')
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "# branch.oid (initial)".to_string(),
+                "# branch.head main".to_string(),
+                format!(
+                    "1 AM N... 000000 100755 100755 {} {} a.sh",
+                    NULL_HASH, TEST_HASH
+                ),
+                format!(
+                    "2 R. N... 100644 100644 100644 {} {} R100 new name\told",
+                    TEST_HASH, TEST_HASH
+                ),
+                "? notes.txt".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_format_branch_headers() {
+        let mut report = create_test_report();
+        report.files.clear();
+        report.head = Some(Hash::from_str(TEST_HASH).unwrap());
+        report.upstream = Some(UpstreamStatus {
+            name: "origin/main".into(),
+            ahead_behind: Some((1, 2)),
+        });
+        assert_eq!(
+            format_short(&report, true, '\n', false),
+            "## main...origin/main [ahead 1, behind 2]\n"
+        );
+        assert_eq!(
+            format_porcelain_v2(&report, true, '\n'),
+            format!(
+                "# branch.oid {}\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +1 -2\n",
+                TEST_HASH
+            )
+        );
+
+        report.upstream = Some(UpstreamStatus {
+            name: "origin/main".into(),
+            ahead_behind: None,
+        });
+        assert_eq!(
+            format_short(&report, true, '\n', false),
+            "## main...origin/main [gone]\n"
+        );
+
+        report.branch = None;
+        report.upstream = None;
+        assert_eq!(
+            format_short(&report, true, '\n', false),
+            "## HEAD (no branch)\n"
+        );
+    }
+
+    #[test]
+    pub fn test_format_unmerged() {
+        let mut report = create_test_report();
+        report.files.push(FileWithStatus {
+            path: PathBuf::from("conflict"),
+            status: Status::Unmerged(Conflict {
+                base: version(100644),
+                ours: version(100644),
+                theirs: None,
+            }),
+            stage_status: StageStatus::NotCommit,
+            head: version(100644),
+            index: None,
+            worktree_mode: Some(100644),
+        });
+        assert_eq!(
+            format_short(&report, false, '\n', false),
+            "AM a.sh\nUD conflict\nR  old -> \"new name\"\n?? notes.txt\n"
+        );
+
+        let lines: Vec<String> = format_porcelain_v2(&report, false, '\n')
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            lines[2],
+            format!(
+                "u UD N... 100644 100644 000000 100644 {} {} {} conflict",
+                TEST_HASH, TEST_HASH, NULL_HASH
+            )
+        );
+        assert_eq!(lines[3], "? notes.txt");
+    }
+
+    #[test]
+    pub fn test_quote_path() {
+        assert_eq!(quote_path(Path::new("src/main.rs"), true), "src/main.rs");
+        assert_eq!(quote_path(Path::new("with space"), false), "with space");
+        assert_eq!(quote_path(Path::new("with space"), true), "\"with space\"");
+        assert_eq!(quote_path(Path::new("tab\there"), false), "\"tab\\there\"");
+        assert_eq!(quote_path(Path::new("quote\""), false), "\"quote\\\"\"");
+        assert_eq!(quote_path(Path::new("ñ"), false), "\"\\303\\261\"");
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::{Result, bail};
+use git_clone::Repository;
+use git_clone::commands;
+
+/// Performs the different tag operations depending on the arguments passed:
+/// - No name: lists every tag.
+/// - `name` (and optionally `commit`): creates a lightweight tag, or an annotated one if a
+///   `message` is given.
+/// - `delete`: deletes the tag called `name`.
+pub fn tag(
+    repo: &Repository,
+    name: Option<&str>,
+    commit: Option<&str>,
+    message: Option<&str>,
+    delete: bool,
+) -> Result<String> {
+    match name {
+        Some(name) if delete => {
+            let hash = commands::delete_tag(repo, name)?;
+            Ok(format!(
+                "Deleted tag '{}' (was {})\n",
+                name,
+                &hash.to_string()[..7]
+            ))
+        }
+        Some(name) => {
+            let commit = commands::create_tag(repo, name, commit, message)?;
+            Ok(format!(
+                "Created tag '{}' at {}\n",
+                name,
+                &commit.to_string()[..7]
+            ))
+        }
+        None if delete => bail!("tag name required"),
+        None => Ok(commands::list_tags(repo)?
+            .iter()
+            .map(|n| format!("{}\n", n))
+            .collect()),
+    }
+}
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use anyhow::{Context, Result};
@@ -10,32 +10,52 @@ use anyhow::{Context, Result};
 use crate::byteable::Byteable;
 use crate::hashing::Hash;
 use crate::index::IndexEntryCache;
-use crate::index::{builder::IndexBuilder, IndexEntry};
+use crate::index::{IndexEntry, builder::IndexBuilder};
 use crate::object::Object;
-use crate::Constants;
+use crate::repository::Repository;
 use crate::{fs, utils};
 
 const PATTERN_EVERY_FILE: &str = ".";
 
+/// The changes `add` made to the index.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AddSummary {
+    /// Amount of files matching the given paths, changed or not
+    pub matched: usize,
+    /// Files that were added to the index or updated in it, relative to the working tree root
+    pub updated: Vec<PathBuf>,
+    /// Files that were removed from the index since they no longer exist
+    pub removed: Vec<PathBuf>,
+}
+
 /// Fetches all files from the worktree (not in .gitignore unless explicitly added),
 /// creates blob objects for all of them, creates index entries from those objects
 /// and adds them to the index file.
-pub fn add(files: &[OsString]) -> Result<String> {
-    let root_path = Constants::working_tree_root_path();
+///
+/// Files are only read and written when their metadata changed since they were added, and the
+/// ones with the same contents as in the index are not reported as updated.
+pub fn add(repo: &Repository, files: &[OsString]) -> Result<AddSummary> {
+    let root_path = repo.working_tree();
     let mut delete_files = false;
 
+    // the directory `.` refers to, relative to the working tree root
+    let mut scope = PathBuf::new();
+
     let filtered_paths: Vec<PathBuf> = if files[0] == PATTERN_EVERY_FILE {
-        // We only delete files if we are checking every file in the working tree, that way we know
+        // We only delete files if we are checking every file in the directory, that way we know
         // if any files are missing (deleted)
         delete_files = true;
+        scope = repo
+            .relative_path(Path::new("."))
+            .context("current directory is outside of the repository")?;
 
-        let all_absolute =
-            fs::get_all_paths(&root_path).context("could not filter ignored files")?;
+        let all_absolute = fs::get_all_paths(repo).context("could not filter ignored files")?;
 
         // Returning the paths as relative to working tree root
         all_absolute
             .into_iter()
-            .map(|path| utils::path::relative_path(&path, &root_path).unwrap_or(path))
+            .map(|path| utils::path::relative_path(&path, root_path).unwrap_or(path))
+            .filter(|path| path.starts_with(&scope))
             .collect()
     } else {
         // We do not check if a file is in .gitignore if it's deliberately added
@@ -44,7 +64,7 @@ pub fn add(files: &[OsString]) -> Result<String> {
         // "normalizing" every path
         for f in files {
             filtered_paths.push(
-                utils::path::normalize_path_relative(PathBuf::from(f), &root_path)
+                utils::path::normalize_path_relative(PathBuf::from(f), root_path)
                     .context("could not normalize path")?,
             );
         }
@@ -53,18 +73,29 @@ pub fn add(files: &[OsString]) -> Result<String> {
     };
 
     if filtered_paths.is_empty() {
-        return Ok("There were no files to add\n".into());
+        return Ok(AddSummary::default());
     }
+    let mut summary = AddSummary {
+        matched: filtered_paths.len(),
+        ..AddSummary::default()
+    };
 
-    // reading all files as blob objects
-    let objects = fs::path::read_bufered(filtered_paths).context("could not get bufered files")?;
+    // reading all files as blob objects, the paths are made relative to the root again below
+    let objects =
+        fs::path::read_bufered(filtered_paths.iter().map(|p| root_path.join(p)).collect())
+            .context("could not get bufered files")?;
 
     // getting previous index to update it
-    let previous_index = fs::index::read_index_file().context("could not read index file")?;
+    let previous_index = fs::index::read_index_file(repo).context("could not read index file")?;
 
     // building a set containing hashes already in index to avoid adding a file twice
     let mut index_data: HashMap<PathBuf, (Hash, IndexEntryCache)> = HashMap::new();
+    // files with conflicts are resolved by adding them, even if they match one of their stages
+    let mut unmerged: HashSet<PathBuf> = HashSet::new();
     for ie in previous_index.entries() {
+        if ie.is_unmerged() {
+            unmerged.insert(ie.path().to_owned());
+        }
         index_data.insert(
             ie.path().to_owned(),
             (ie.object_hash(), ie.cache_data.clone()),
@@ -96,13 +127,16 @@ pub fn add(files: &[OsString]) -> Result<String> {
     let mut bytes: Rc<[u8]> = Rc::default();
     let mut hash: Hash = Hash::default();
     let mut hash_computed: bool;
-    for mut o in objects {
+    for (mut o, path) in objects.into_iter().zip(filtered_paths) {
+        o.path = path;
         hash_computed = false;
 
         if let Some((index_hash, index_cache)) = index_data.remove(&o.path) {
             // file already in index, we delete it since we won't be needing it and it would be
             // useful later (paths left at the end are deleted files)
-            if index_cache.matches_loose(&o.cache) {
+            if unmerged.contains(&o.path) {
+                index_builder.remove_index_entry_by_path(&o.path);
+            } else if index_cache.matches_loose(&o.cache) {
                 // we can assume the file is unchanged
                 continue;
             } else {
@@ -110,7 +144,7 @@ pub fn add(files: &[OsString]) -> Result<String> {
                     .context(format!("could not hash file: {:?}", o.path))?;
                 hash_computed = true;
 
-                if index_hash == hash {
+                if index_hash != hash {
                     // ...and has been modified. We remove it and add it as if it was a new file
                     index_builder.remove_index_entry_by_path(&o.path);
                 } else {
@@ -127,25 +161,28 @@ pub fn add(files: &[OsString]) -> Result<String> {
                 .context(format!("could not hash file: {:?}", o.path))?;
         }
 
-        index_entry = IndexEntry::try_from_file(&o.path, hash.clone()).context(format!(
-            "could not create index entry from file: {:?}",
-            o.path
-        ))?;
+        index_entry = IndexEntry::try_from_file(root_path, &o.path, hash.clone()).context(
+            format!("could not create index entry from file: {:?}", o.path),
+        )?;
 
-        fs::object::write_to_object_dir(&bytes, &hash).context("could not write to object dir")?;
+        fs::object::write_to_object_dir(repo, &bytes, &hash)
+            .context("could not write to object dir")?;
 
         index_builder.add_index_entry(index_entry);
+        summary.updated.push(o.path);
     }
 
     if delete_files {
-        for p in index_data.into_keys() {
+        for p in index_data.into_keys().filter(|p| p.starts_with(&scope)) {
             index_builder.remove_index_entry_by_path(&p);
+            summary.removed.push(p);
         }
+        summary.removed.sort();
     }
 
     let index = index_builder.build();
 
-    fs::index::write_index_file(index).context("could not write to index file")?;
+    fs::index::write_index_file(repo, index).context("could not write to index file")?;
 
-    Ok("Added files successfully\n".into())
+    Ok(summary)
 }
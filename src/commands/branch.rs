@@ -0,0 +1,255 @@
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::object::commit::is_ancestor;
+use crate::repository::Repository;
+use crate::{fs, revision};
+
+/// A local branch, as returned by `list_branches`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    pub name: String,
+    /// `true` if HEAD points to this branch
+    pub current: bool,
+}
+
+/// Returns every branch, sorted by name. None of them is current if HEAD is detached.
+pub fn list_branches(repo: &Repository) -> Result<Vec<Branch>> {
+    let current = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+
+    Ok(fs::get_all_branch_names(repo)
+        .context("could not get branch names")?
+        .into_iter()
+        .map(|name| Branch {
+            current: current.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Creates a branch called `name` pointing to `start_point`, or to the last commit if it is not
+/// provided.
+///
+/// # Returns
+///
+/// The hash of the commit the branch points to.
+///
+/// # Errors
+///
+/// This function will fail if the name is not valid, the branch already exists or the start
+/// point could not be resolved.
+pub fn create_branch(repo: &Repository, name: &str, start_point: Option<&str>) -> Result<Hash> {
+    check_branch_name(name)?;
+    check_branch_available(repo, name)?;
+
+    let hash = match start_point {
+        Some(reference) => revision::resolve_commit(repo, reference)?,
+        None => fs::get_last_commit_hash(repo)
+            .context("could not get last commit hash")?
+            .context("not a valid object name: there are no commits yet")?,
+    };
+
+    fs::write_branch(repo, name, &hash).context("could not write branch")?;
+
+    Ok(hash)
+}
+
+/// Deletes the branch called `name`. Unless `force` is set, the branch has to be merged into the
+/// current HEAD.
+///
+/// # Returns
+///
+/// The hash the branch pointed to.
+///
+/// # Errors
+///
+/// This function will fail if the name is not valid, or if the branch does not exist, is checked
+/// out or is not merged.
+pub fn delete_branch(repo: &Repository, name: &str, force: bool) -> Result<Hash> {
+    check_branch_name(name)?;
+    let hash = fs::read_branch(repo, name)
+        .context("could not read branch")?
+        .context(format!("branch '{}' not found", name))?;
+
+    let current = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    if current.as_deref() == Some(name) {
+        bail!("cannot delete branch '{}' since it is checked out", name)
+    }
+
+    if !force {
+        let merged =
+            match fs::get_last_commit_hash(repo).context("could not get last commit hash")? {
+                Some(head) => {
+                    is_ancestor(repo, &hash, head).context("could not check if branch is merged")?
+                }
+                None => false,
+            };
+        if !merged {
+            bail!(
+                "the branch '{}' is not fully merged, use -D to delete it anyway",
+                name
+            )
+        }
+    }
+
+    fs::delete_branch(repo, name).context("could not delete branch")?;
+
+    Ok(hash)
+}
+
+/// Renames the branch `old` to `new`, or the current branch if `old` is not given. HEAD points to
+/// the new name if the current branch was renamed.
+///
+/// # Returns
+///
+/// The previous name of the branch.
+///
+/// # Errors
+///
+/// This function will fail if any of the names is not valid, if the new one is already taken, if
+/// `old` does not exist or if it is not given while HEAD is detached.
+pub fn rename_branch(repo: &Repository, old: Option<&str>, new: &str) -> Result<String> {
+    check_branch_name(new)?;
+
+    let current = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    let current = current.as_deref();
+    let old = old
+        .or(current)
+        .context("HEAD is detached, there is no current branch to rename")?;
+    check_branch_name(old)?;
+
+    let hash = match fs::read_branch(repo, old).context("could not read branch")? {
+        Some(hash) => hash,
+        // the current branch might not have any commits yet, in that case only HEAD changes
+        None if Some(old) == current => {
+            check_branch_available(repo, new)?;
+            fs::set_current_branch(repo, new).context("could not update HEAD")?;
+            return Ok(old.to_string());
+        }
+        None => bail!("branch '{}' not found", old),
+    };
+
+    fs::delete_branch(repo, old).context("could not remove old branch")?;
+    if let Err(e) = check_branch_available(repo, new) {
+        // restoring the old branch so nothing is lost
+        fs::write_branch(repo, old, &hash).context("could not restore old branch")?;
+        return Err(e);
+    }
+    fs::write_branch(repo, new, &hash).context("could not write new branch")?;
+
+    if Some(old) == current {
+        fs::set_current_branch(repo, new).context("could not update HEAD")?;
+    }
+
+    Ok(old.to_string())
+}
+
+/// Fails if a branch called `name` already exists, or if creating it would clash with the
+/// directories of another branch (for example, `feature` and `feature/x`).
+fn check_branch_available(repo: &Repository, name: &str) -> Result<()> {
+    let path = fs::get_branch_path(repo, name);
+    if fs::read_branch(repo, name)
+        .context("could not read branch")?
+        .is_some()
+    {
+        bail!("a branch named '{}' already exists", name)
+    }
+    if path.is_dir() {
+        bail!("'{}' is used as a directory by other branches", name)
+    }
+
+    for ancestor in path.ancestors().skip(1) {
+        if ancestor == repo.heads_path() {
+            break;
+        }
+        if ancestor.is_file() {
+            bail!("'{}' clashes with an existing branch", name)
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if `name` is not a valid branch name.
+fn check_branch_name(name: &str) -> Result<()> {
+    if !fs::is_valid_ref_name(name) {
+        bail!("'{}' is not a valid branch name", name)
+    }
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::checkout;
+    use crate::commands::test_utils::{commit_files, create_repository, remove_repository};
+
+    #[test]
+    pub fn test_valid_branch_names() {
+        for name in [
+            "main",
+            "feature/x",
+            "fix-123",
+            "user/josgtg/new_thing",
+            "v1.2",
+        ] {
+            assert!(check_branch_name(name).is_ok(), "{} should be valid", name);
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_branch_names() {
+        for name in [
+            "",
+            "-main",
+            "feature/",
+            "/feature",
+            "a..b",
+            "a//b",
+            "name.lock",
+            "with space",
+            "what?",
+            "star*",
+            "@",
+            "a@{b",
+            "feature/.hidden",
+            "ends.",
+        ] {
+            assert!(
+                check_branch_name(name).is_err(),
+                "{:?} should be invalid",
+                name
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_branch_names_outside_of_heads() {
+        let repo = create_repository("branch-names");
+        let head = commit_files(&repo, &[("file", "content\n")], "first");
+        let branch = fs::get_current_branch_name(&repo).unwrap().unwrap();
+        fs::write_ref(&repo, "ORIG_HEAD", &head).unwrap();
+
+        let name = "../../ORIG_HEAD";
+        assert!(delete_branch(&repo, name, true).is_err());
+        assert!(rename_branch(&repo, Some(name), "renamed").is_err());
+        assert!(create_branch(&repo, name, None).is_err());
+        assert!(rename_branch(&repo, None, name).is_err());
+        assert!(checkout(&repo, name).is_err());
+
+        assert_eq!(
+            fs::read_ref(&repo, "ORIG_HEAD").unwrap(),
+            Some(head.clone())
+        );
+        assert_eq!(fs::read_branch(&repo, "renamed").unwrap(), None);
+        assert_eq!(
+            fs::get_current_branch_name(&repo).unwrap(),
+            Some(branch.clone())
+        );
+        assert_eq!(fs::read_branch(&repo, &branch).unwrap(), Some(head));
+
+        remove_repository(&repo);
+    }
+}
@@ -1,15 +1,12 @@
-use std::str::FromStr;
-
-use crate::fs;
-use crate::hashing::Hash;
+use crate::object::Object;
+use crate::repository::Repository;
+use crate::{fs, revision};
 
 use anyhow::{Context, Result};
 
-pub fn cat_file(hash: &str) -> Result<String> {
-    // just checking the string is valid
-    let hash = Hash::from_str(hash).context("object hash was invalid")?;
-
-    let object = fs::object::read_object(hash).context("could not read object")?;
+/// Reads the object `object` points to, which can be a hash or any other revision.
+pub fn cat_file(repo: &Repository, object: &str) -> Result<Object> {
+    let hash = revision::resolve(repo, object).context("could not resolve object")?;
 
-    Ok(format!("{}", object))
+    fs::object::read_object(repo, hash).context("could not read object")
 }
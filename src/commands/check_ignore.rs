@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::fs;
+use crate::gitignore::{Gitignore, Pattern};
+use crate::repository::Repository;
+
+/// A path matching an ignore rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreMatch {
+    /// The path as it was given
+    pub path: OsString,
+    /// The last pattern matching the path, which decides whether it is ignored
+    pub pattern: Pattern,
+}
+
+impl IgnoreMatch {
+    /// Returns `true` unless the deciding pattern is negated, which re-includes the path.
+    pub fn is_ignored(&self) -> bool {
+        !self.pattern.negated
+    }
+}
+
+/// Returns the paths matching an ignore pattern along with the file, line and pattern that
+/// decided them. This includes paths matching a negated pattern, which are not ignored.
+///
+/// Files in the index are never ignored, so they are not returned.
+///
+/// # Errors
+///
+/// This function will fail if a path is outside of the repository or if the index or any of the
+/// ignore files could not be read.
+pub fn check_ignore(repo: &Repository, paths: &[OsString]) -> Result<Vec<IgnoreMatch>> {
+    let root = repo.working_tree();
+    let mut gitignore = Gitignore::load(repo).context("could not read ignore files")?;
+
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let tracked: HashSet<PathBuf> = index.entries().map(|e| e.path().to_path_buf()).collect();
+
+    let mut matches = Vec::new();
+    for path in paths {
+        let relative = repo
+            .relative_path(Path::new(path))
+            .context(format!("{:?} is outside of the repository", path))?;
+        if tracked.contains(&relative) {
+            continue;
+        }
+
+        gitignore
+            .read_parent_files(&relative)
+            .context(format!("could not read ignore files for {:?}", path))?;
+
+        let is_dir = path.to_string_lossy().ends_with('/') || root.join(&relative).is_dir();
+        let Some(pattern) = gitignore.matching_pattern(&relative, is_dir) else {
+            continue;
+        };
+
+        matches.push(IgnoreMatch {
+            path: path.clone(),
+            pattern: pattern.clone(),
+        });
+    }
+
+    Ok(matches)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::{
+        add_files, create_repository, remove_repository, write_file,
+    };
+
+    /// Returns the path, pattern, source and line of every match, and whether it is ignored.
+    fn describe(matches: &[IgnoreMatch]) -> Vec<(String, String, String, usize, bool)> {
+        matches
+            .iter()
+            .map(|m| {
+                (
+                    m.path.to_string_lossy().into_owned(),
+                    m.pattern.text.clone(),
+                    m.pattern.source.to_string_lossy().into_owned(),
+                    m.pattern.line_number,
+                    m.is_ignored(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_check_ignore() {
+        let repo = create_repository("check-ignore");
+        write_file(
+            &repo,
+            ".gitignore",
+            "# build output\n*.o\n!keep.o\nbuild/\n",
+        );
+        write_file(&repo, "src/.gitignore", "*.tmp\n");
+        write_file(&repo, "build/out", "");
+        write_file(&repo, "tracked.o", "");
+        add_files(&repo, &["tracked.o"]);
+
+        let paths: Vec<OsString> = [
+            "main.o",
+            "keep.o",
+            "build",
+            "build/out",
+            "src/a.tmp",
+            "a.tmp",
+            "main.c",
+            "tracked.o",
+        ]
+        .iter()
+        .map(|p| repo.working_tree().join(p).into_os_string())
+        .collect();
+        let matches = check_ignore(&repo, &paths).unwrap();
+
+        let path = |p: &str| repo.working_tree().join(p).to_string_lossy().into_owned();
+        assert_eq!(
+            describe(&matches),
+            vec![
+                (path("main.o"), "*.o".into(), ".gitignore".into(), 2, true),
+                (
+                    path("keep.o"),
+                    "!keep.o".into(),
+                    ".gitignore".into(),
+                    3,
+                    false
+                ),
+                (path("build"), "build/".into(), ".gitignore".into(), 4, true),
+                (
+                    path("build/out"),
+                    "build/".into(),
+                    ".gitignore".into(),
+                    4,
+                    true
+                ),
+                (
+                    path("src/a.tmp"),
+                    "*.tmp".into(),
+                    "src/.gitignore".into(),
+                    1,
+                    true
+                ),
+            ]
+        );
+
+        let outside = std::env::temp_dir().join("outside").into_os_string();
+        assert!(check_ignore(&repo, &[outside]).is_err());
+
+        remove_repository(&repo);
+    }
+}
@@ -1,5 +1,198 @@
-use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
-pub fn checkout(reference: &str) -> Result<String> {
-    Ok(format!("HEAD is now at {reference}\n"))
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::hashing::Hash;
+use crate::index::IndexEntry;
+use crate::index::builder::IndexBuilder;
+use crate::repository::Repository;
+use crate::{fs, revision};
+
+use super::commit::log_head_move;
+use super::status::read::{read_commit_data, read_commit_entries};
+use super::status::{StageStatus, Status, get_statuses};
+
+/// The result of `checkout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckoutOutcome {
+    /// The branch was already checked out, so nothing changed
+    AlreadyOn,
+    Switched,
+    /// The reference was not a branch, so HEAD holds the commit it resolved to
+    Detached(Hash),
+}
+
+/// Switches to the branch called `reference`, updating the working tree and the index so they
+/// match the commit the branch points to, and making HEAD point to the branch.
+///
+/// If `reference` is not a branch, it is resolved as a revision and HEAD is detached at the
+/// commit it points to instead.
+///
+/// Local changes are kept as long as the files they affect are the same in both commits.
+///
+/// # Errors
+///
+/// This function will fail if:
+/// - The reference is neither a branch nor a revision pointing to a commit.
+/// - There are local changes that would be overwritten by the checkout.
+/// - Any of the objects, the index or the working tree files could not be read or written.
+pub fn checkout(repo: &Repository, reference: &str) -> Result<CheckoutOutcome> {
+    // anything that is not a valid branch name could point outside of refs/heads
+    let branch_hash = if fs::is_valid_ref_name(reference) {
+        fs::read_branch(repo, reference).context("could not read branch")?
+    } else {
+        None
+    };
+    let current_branch =
+        fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    if branch_hash.is_some() && current_branch.as_deref() == Some(reference) {
+        return Ok(CheckoutOutcome::AlreadyOn);
+    }
+
+    let target_hash = match &branch_hash {
+        Some(hash) => hash.clone(),
+        None => revision::resolve_commit(repo, reference)?,
+    };
+    let current_hash = fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
+
+    let mut target_data = HashMap::new();
+    for e in read_commit_entries(repo, target_hash.clone())
+        .context("could not read target commit entries")?
+    {
+        target_data.insert(e.path, (e.mode, e.hash));
+    }
+
+    let changed = changed_paths(repo, &target_data)?;
+    check_overwritten_changes(repo, &changed, "checkout", "switching branches")?;
+    let index_builder = update_files(repo, &changed, target_data)?;
+
+    fs::index::write_index_file(repo, index_builder.build())
+        .context("could not write index file")?;
+    match branch_hash {
+        Some(_) => fs::set_current_branch(repo, reference),
+        None => fs::detach_head(repo, &target_hash),
+    }
+    .context("could not update HEAD")?;
+
+    // the reflog is what `@{-N}` is read from, a detached HEAD is recorded as its full hash so
+    // it can go back to it
+    let from = match (current_branch, &current_hash) {
+        (Some(branch), _) => branch,
+        (None, Some(hash)) => hash.to_string(),
+        (None, None) => Constants::HEAD_FILE_NAME.to_string(),
+    };
+    let message = format!("{}{} to {}", fs::CHECKOUT_LOG_PREFIX, from, reference);
+    log_head_move(repo, current_hash.as_ref(), &target_hash, &message)?;
+
+    Ok(match branch_hash {
+        Some(_) => CheckoutOutcome::Switched,
+        None => CheckoutOutcome::Detached(target_hash),
+    })
+}
+
+/// Returns the paths whose files are not the same in the last commit and in `target`, which
+/// holds the mode (in its octal representation) and hash of every file by path.
+pub(super) fn changed_paths(
+    repo: &Repository,
+    target: &HashMap<PathBuf, (u32, Hash)>,
+) -> Result<HashSet<PathBuf>> {
+    let current_data = read_commit_data(repo)
+        .context("could not read current commit data")?
+        .unwrap_or_default();
+
+    let mut changed: HashSet<PathBuf> = HashSet::new();
+    for (path, hash) in current_data.iter() {
+        match target.get(path) {
+            Some((_, target)) if target == hash => (),
+            _ => {
+                changed.insert(path.clone());
+            }
+        }
+    }
+    for (path, (_, hash)) in target.iter() {
+        if current_data.get(path) != Some(hash) {
+            changed.insert(path.clone());
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Writes the `changed` files as they are in `target` to the working tree, removing the ones it
+/// does not have.
+///
+/// # Returns
+///
+/// The current index with the entries of the `changed` files replaced, which still has to be
+/// written. The entries of the other files are kept as they are.
+pub(super) fn update_files(
+    repo: &Repository,
+    changed: &HashSet<PathBuf>,
+    mut target: HashMap<PathBuf, (u32, Hash)>,
+) -> Result<IndexBuilder> {
+    let previous_index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let mut index_builder = IndexBuilder::new();
+    for e in previous_index.into_entries() {
+        if !changed.contains(e.path()) {
+            index_builder.add_index_entry(e);
+        }
+    }
+
+    for path in changed {
+        match target.remove(path) {
+            Some((mode, hash)) => {
+                fs::working_tree::write_blob(repo, path, mode, hash.clone())
+                    .context(format!("could not write {:?}", path))?;
+                index_builder.add_index_entry(
+                    IndexEntry::try_from_file(repo.working_tree(), path, hash)
+                        .context(format!("could not create index entry for {:?}", path))?,
+                );
+            }
+            None => fs::working_tree::remove_file(repo, path)
+                .context(format!("could not remove {:?}", path))?,
+        }
+    }
+
+    Ok(index_builder)
+}
+
+/// Fails if there is any local change (staged or not) or untracked file in one of the `changed`
+/// paths, since `command` would overwrite it. The error suggests committing before `action`.
+pub(super) fn check_overwritten_changes(
+    repo: &Repository,
+    changed: &HashSet<PathBuf>,
+    command: &str,
+    action: &str,
+) -> Result<()> {
+    let mut overwritten = Vec::new();
+    for fws in get_statuses(repo).context("could not get file statuses")? {
+        if fws.status == Status::Unchanged && fws.stage_status == StageStatus::Commit {
+            continue;
+        }
+        if let Status::Moved { previous } = &fws.status
+            && changed.contains(previous)
+        {
+            overwritten.push(previous.clone());
+        }
+        if changed.contains(&fws.path) {
+            overwritten.push(fws.path);
+        }
+    }
+
+    if overwritten.is_empty() {
+        return Ok(());
+    }
+
+    overwritten.sort();
+    let mut message = format!(
+        "your local changes to the following files would be overwritten by {}:\n",
+        command
+    );
+    for p in overwritten {
+        message.push_str(&format!("\t{}\n", p.to_string_lossy()));
+    }
+    message.push_str(&format!("commit your changes before {}", action));
+    bail!(message)
 }
@@ -1,47 +1,194 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
+use crate::Constants;
+use crate::config::Config;
 use crate::fs;
 use crate::fs::index::read_index_file;
 use crate::fs::object::write_object;
+use crate::hashing::Hash;
+use crate::index::Index;
 use crate::object::Object;
 use crate::object::commit::{CommitUser, CommitUserKind};
 use crate::object::tree::TreeBuilder;
+use crate::repository::Repository;
 
-/// Creates a commit object file, a tree from the current index contents and updates the branch
-/// HEAD points to to point at the new commit.
-pub fn commit(message: &str) -> Result<String> {
-    // Creating a tree from every file in the index
-    let mut tree_builder = TreeBuilder::new();
-    let index = read_index_file().context("could not read index file")?;
+use super::LogEntry;
 
-    for e in index.entries() {
-        tree_builder.add_object(e.mode, e.path().to_owned(), e.object_hash());
+/// Creates a commit object file, a tree from the current index contents and updates the branch
+/// HEAD points to to point at the new commit. If HEAD is detached, HEAD itself is updated.
+///
+/// While a merge is in progress, the merged commit is added as a second parent, concluding it.
+/// A cherry-pick or revert stopped by conflicts is concluded too, keeping the author of the
+/// picked commit.
+///
+/// # Returns
+///
+/// The hash of the new commit.
+///
+/// # Errors
+///
+/// This function will fail if the index has files with unresolved conflicts, if the identity of
+/// the author or committer is not set, or if any of the objects or refs could not be written.
+pub fn commit(repo: &Repository, message: &str) -> Result<Hash> {
+    let index = read_index_file(repo).context("could not read index file")?;
+    if index.has_unmerged_entries() {
+        bail!("committing is not possible because you have unmerged files, add them once resolved")
     }
 
-    let tree = tree_builder
-        .build_and_write()
-        .context("could not write tree object")?;
-
     let mut parents = Vec::new();
-    let previous_commit = fs::get_last_commit_hash().context("could not get last commit hash")?;
+    let previous_commit =
+        fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
     if let Some(h) = previous_commit {
         parents.push(h);
     }
+    let merge_head =
+        fs::read_ref(repo, Constants::MERGE_HEAD_FILE_NAME).context("could not read MERGE_HEAD")?;
+    parents.extend(merge_head);
+
+    let cherry_pick_head = fs::read_ref(repo, Constants::CHERRY_PICK_HEAD_FILE_NAME)
+        .context("could not read CHERRY_PICK_HEAD")?;
+    let author = match cherry_pick_head {
+        Some(hash) => Some(LogEntry::read(repo, hash)?.author),
+        None => None,
+    };
+
+    let kind = if parents.is_empty() {
+        " (initial)"
+    } else if parents.len() > 1 {
+        " (merge)"
+    } else {
+        ""
+    };
+    let subject = message.lines().next().unwrap_or_default();
+    let log_message = format!("commit{}: {}", kind, subject);
+
+    let tree = write_tree(repo, &index)?;
+    let commit_hash = write_commit(repo, tree, parents, message, author)?;
+
+    move_head(repo, &commit_hash, &log_message)
+        .context("could not update current branch (make it point to the new commit))")?;
+
+    clear_merge_state(repo)?;
+
+    Ok(commit_hash)
+}
+
+/// Writes a tree with every file in `index`, returning its hash.
+///
+/// # Errors
+///
+/// This function will fail if any of the tree objects could not be written.
+pub(super) fn write_tree(repo: &Repository, index: &Index) -> Result<Hash> {
+    let mut tree_builder = TreeBuilder::new();
+    for e in index.entries() {
+        tree_builder.add_object(e.mode, e.path().to_owned(), e.object_hash());
+    }
+
+    tree_builder
+        .build_and_write(repo)
+        .context("could not write tree object")
+}
+
+/// Writes a commit of `tree` with the given parents and message. The author is the configured
+/// identity unless `author` is given. No ref is updated.
+///
+/// # Returns
+///
+/// The hash of the new commit.
+///
+/// # Errors
+///
+/// This function will fail if the identity of the author or committer is not set, or if the
+/// commit could not be written.
+pub(super) fn write_commit(
+    repo: &Repository,
+    tree: Hash,
+    parents: Vec<Hash>,
+    message: &str,
+    author: Option<CommitUser>,
+) -> Result<Hash> {
+    let config = Config::load(repo).context("could not read config")?;
+    let author = match author {
+        Some(author) => author,
+        None => CommitUser::from_identity(CommitUserKind::Author, &config)?,
+    };
+    let committer = CommitUser::from_identity(CommitUserKind::Committer, &config)?;
 
     let commit = Object::Commit {
         tree,
         parents: parents.into(),
-        author: CommitUser::default(CommitUserKind::Author),
-        committer: CommitUser::default(CommitUserKind::Committer),
+        author,
+        committer,
         message: message.into(),
     };
 
-    let commit_hash = write_object(&commit).context("could not write commit file")?;
+    write_object(repo, &commit).context("could not write commit file")
+}
 
-    let current_branch =
-        fs::get_current_branch_path().context("could not get current branch path")?;
-    std::fs::write(current_branch, commit_hash.to_string().as_bytes())
-        .context("could not update current branch (make it point to the new commit))")?;
+/// Moves HEAD to `hash` like `fs::update_head` does, recording the move in the HEAD reflog with
+/// `message`.
+///
+/// # Errors
+///
+/// This function will fail if HEAD, the branch it points to or the reflog could not be read or
+/// written.
+pub(super) fn move_head(repo: &Repository, hash: &Hash, message: &str) -> Result<()> {
+    let old = fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
+    fs::update_head(repo, hash).context("could not update HEAD")?;
+    log_head_move(repo, old.as_ref(), hash, message)
+}
+
+/// Appends an entry to the HEAD reflog recording that HEAD moved from `old` to `new`, with the
+/// committer identity. Nothing is recorded if the identity is not set, it is not worth failing
+/// an operation that already moved HEAD over it.
+///
+/// # Errors
+///
+/// This function will fail if the reflog could not be written.
+pub(super) fn log_head_move(
+    repo: &Repository,
+    old: Option<&Hash>,
+    new: &Hash,
+    message: &str,
+) -> Result<()> {
+    let Ok(user) = Config::load(repo)
+        .and_then(|config| CommitUser::from_identity(CommitUserKind::Committer, &config))
+    else {
+        return Ok(());
+    };
+    fs::append_head_log(repo, old, new, &user, message).context("could not write HEAD reflog")
+}
 
-    Ok("Commited changes successfully\n".into())
+/// Returns `message` without its comment lines (the ones starting with `#`) and the blank
+/// lines around it, ending with a new line. An empty string is returned if nothing is left.
+pub(super) fn clean_message(message: &str) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    let message = lines.join("\n");
+    let message = message.trim();
+    if message.is_empty() {
+        return String::new();
+    }
+    format!("{}\n", message)
+}
+
+/// Removes the files that record a merge, cherry-pick or revert in progress.
+///
+/// # Errors
+///
+/// This function will fail if any of the files exist but could not be removed.
+pub(super) fn clear_merge_state(repo: &Repository) -> Result<()> {
+    for path in [
+        repo.merge_head_path(),
+        repo.merge_msg_path(),
+        repo.cherry_pick_head_path(),
+        repo.revert_head_path(),
+    ] {
+        if path.is_file() {
+            std::fs::remove_file(&path).context(format!("could not remove {:?}", path))?;
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,175 @@
+use std::fs;
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::{
+    Config, ConfigEntry, ConfigFile, ConfigScope, parse_bool, parse_int, split_name,
+};
+use crate::repository::Repository;
+
+/// The operations the config command can perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// Shows the last value of a key, or sets it if a value is given
+    GetOrSet,
+    /// Shows every value of a key
+    GetAll,
+    /// Adds another value to a key
+    Add,
+    /// Removes a key
+    Unset,
+    /// Shows every value as `name=value`
+    List,
+}
+
+/// The types config values can be read or written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigType {
+    Bool,
+    Int,
+}
+
+/// The result of `config`.
+#[derive(Debug, Clone)]
+pub enum ConfigOutput {
+    /// Values of the key, the last one being the one in effect
+    Values(Vec<String>),
+    /// Every value, for `ConfigAction::List`
+    Entries(Vec<ConfigEntry>),
+    /// A config file was written
+    Updated,
+}
+
+/// Performs `action` on the key called `name`.
+///
+/// With `value_type`, values are checked to be of that type and shown or written in their
+/// canonical form (`true` or `false` for booleans, without suffixes for integers).
+///
+/// Values are read from every scope, or only the global one with `global`. Changes are written to
+/// the config file of `repo`, or to the global one with `global`, keeping its comments and layout.
+/// There might be no repository when `global` is set.
+///
+/// # Errors
+///
+/// This function will fail if the key does not exist, if it is not valid or if any of the config
+/// files could not be read, parsed or written.
+pub fn config(
+    repo: Option<&Repository>,
+    global: bool,
+    action: ConfigAction,
+    value_type: Option<ConfigType>,
+    name: Option<&str>,
+    value: Option<&str>,
+) -> Result<ConfigOutput> {
+    let scope = if global {
+        ConfigScope::Global
+    } else {
+        ConfigScope::Local
+    };
+
+    if action == ConfigAction::List {
+        return list_values(repo, global);
+    }
+
+    let name = name.context("key name required")?;
+    split_name(name)?;
+    match (action, value) {
+        (ConfigAction::Unset, _) => edit_file(repo, scope, |file| {
+            if !file.unset(name)? {
+                bail!("key '{}' is not set", name)
+            }
+            Ok(())
+        }),
+        (_, Some(value)) => edit_file(repo, scope, |file| {
+            let value = &canonicalize(name, value, value_type)?;
+            if action == ConfigAction::Add {
+                file.add(name, value)
+            } else {
+                file.set(name, value)
+            }
+        }),
+        (_, None) => get_values(
+            repo,
+            global,
+            name,
+            action == ConfigAction::GetAll,
+            value_type,
+        ),
+    }
+}
+
+fn read_config(repo: Option<&Repository>, global: bool) -> Result<Config> {
+    let config = match repo {
+        Some(repo) if !global => Config::load(repo),
+        _ => Config::load_scopes(&[ConfigScope::Global], None),
+    };
+    config.context("could not read config")
+}
+
+/// Returns every value in the order they were read.
+fn list_values(repo: Option<&Repository>, global: bool) -> Result<ConfigOutput> {
+    Ok(ConfigOutput::Entries(
+        read_config(repo, global)?.entries().to_vec(),
+    ))
+}
+
+fn get_values(
+    repo: Option<&Repository>,
+    global: bool,
+    name: &str,
+    get_all: bool,
+    value_type: Option<ConfigType>,
+) -> Result<ConfigOutput> {
+    let config = read_config(repo, global)?;
+    let values: Vec<String> = if get_all {
+        config
+            .get_all(name)
+            .into_iter()
+            .map(|v| canonicalize(name, v, value_type))
+            .collect::<Result<_>>()?
+    } else {
+        let value = match value_type {
+            Some(ConfigType::Bool) => config.get_bool(name)?.map(|b| b.to_string()),
+            Some(ConfigType::Int) => config.get_int(name)?.map(|i| i.to_string()),
+            None => config.get(name).map(String::from),
+        };
+        value.into_iter().collect()
+    };
+    if values.is_empty() {
+        bail!("key '{}' is not set", name)
+    }
+
+    Ok(ConfigOutput::Values(values))
+}
+
+/// Returns `value` in the canonical form of `value_type`, or as it is if there is no type.
+fn canonicalize(name: &str, value: &str, value_type: Option<ConfigType>) -> Result<String> {
+    let context = || format!("invalid value for '{}'", name);
+    match value_type {
+        Some(ConfigType::Bool) => Ok(parse_bool(value).with_context(context)?.to_string()),
+        Some(ConfigType::Int) => Ok(parse_int(value).with_context(context)?.to_string()),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Applies `edit` to the config file of `scope`, creating the file if it does not exist.
+fn edit_file(
+    repo: Option<&Repository>,
+    scope: ConfigScope,
+    edit: impl FnOnce(&mut ConfigFile) -> Result<()>,
+) -> Result<ConfigOutput> {
+    let path = Config::write_path(scope, repo)?;
+    let mut file = if path.is_file() {
+        let content =
+            fs::read_to_string(&path).context(format!("could not read config file {:?}", path))?;
+        ConfigFile::parse(&content).context(format!("could not parse config file {:?}", path))?
+    } else {
+        ConfigFile::default()
+    };
+
+    edit(&mut file)?;
+
+    fs::write(&path, file.to_string())
+        .context(format!("could not write config file {:?}", path))?;
+    Ok(ConfigOutput::Updated)
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::index::IndexEntryCache;
+use crate::object::Object;
+use crate::repository::Repository;
+use crate::utils::nums::as_octal;
+use crate::{diff, fs, revision};
+
+use super::status::read::{read_commit_entries, read_index_data};
+
+/// A version of a file on one of the sides of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    /// Mode in its octal representation.
+    pub mode: u32,
+    pub hash: Hash,
+}
+
+/// The changes to a single file. A missing side means the file was created or deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub old: Option<FileVersion>,
+    pub new: Option<FileVersion>,
+    pub content: DiffContent,
+}
+
+/// How the contents of a file changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffContent {
+    /// The contents are the same, only the mode changed
+    Unchanged,
+    /// One of the versions is binary, so there are no lines to compare
+    Binary,
+    /// The hunks in the unified format, one `@@` header followed by its lines for every hunk
+    Text(String),
+}
+
+/// The version of a file on one of the sides of a diff, along with its contents if they were
+/// already read.
+struct DiffFile {
+    /// Mode in its octal representation.
+    mode: u32,
+    hash: Hash,
+    /// Only set for files that have already been read from the working tree.
+    data: Option<Rc<[u8]>>,
+}
+
+impl DiffFile {
+    /// Returns the contents of this file, reading the blob object if they were not loaded yet.
+    fn read_data(&self, repo: &Repository) -> Result<Rc<[u8]>> {
+        if let Some(data) = &self.data {
+            return Ok(Rc::clone(data));
+        }
+        match fs::object::read_object(repo, self.hash.clone()).context("could not read blob")? {
+            Object::Blob { data } => Ok(data),
+            _ => bail!("{} is not a blob", self.hash),
+        }
+    }
+
+    fn version(&self) -> FileVersion {
+        FileVersion {
+            mode: self.mode,
+            hash: self.hash.clone(),
+        }
+    }
+}
+
+type DiffSide = BTreeMap<PathBuf, DiffFile>;
+
+/// Returns the changes between two versions of the repository, sorted by path:
+/// - No commits: the working tree compared with the index.
+/// - `cached`: the index compared with the last commit.
+/// - Two commits: the first commit compared with the second one.
+///
+/// `context` is the number of unchanged lines shown around every change.
+///
+/// # Errors
+///
+/// This function will fail if the arguments are not valid or if any of the objects, the index or
+/// the working tree could not be read.
+pub fn diff(
+    repo: &Repository,
+    cached: bool,
+    context: usize,
+    commits: &[String],
+) -> Result<Vec<FileDiff>> {
+    let (old, new) = match (commits, cached) {
+        ([], false) => {
+            let index = read_index_side(repo).context("could not read index")?;
+            let working_tree =
+                read_working_tree_side(repo).context("could not read working tree")?;
+            (index, working_tree)
+        }
+        ([], true) => {
+            let commit = match fs::get_last_commit_hash(repo)
+                .context("could not get last commit")?
+            {
+                Some(hash) => read_commit_side(repo, hash).context("could not read last commit")?,
+                None => DiffSide::new(),
+            };
+            (
+                commit,
+                read_index_side(repo).context("could not read index")?,
+            )
+        }
+        ([old, new], false) => (
+            read_commit_side(repo, revision::resolve_commit(repo, old)?)
+                .context("could not read first commit")?,
+            read_commit_side(repo, revision::resolve_commit(repo, new)?)
+                .context("could not read second commit")?,
+        ),
+        _ => bail!("expected either no commits or two commits to compare"),
+    };
+
+    let paths: BTreeSet<&PathBuf> = old.keys().chain(new.keys()).collect();
+
+    let mut diffs = Vec::new();
+    for path in paths {
+        let old_file = old.get(path);
+        let new_file = new.get(path);
+        if let (Some(o), Some(n)) = (old_file, new_file)
+            && o.hash == n.hash
+            && o.mode == n.mode
+        {
+            continue;
+        }
+
+        diffs.push(FileDiff {
+            path: path.clone(),
+            old: old_file.map(DiffFile::version),
+            new: new_file.map(DiffFile::version),
+            content: diff_content(repo, old_file, new_file, context)
+                .context(format!("could not diff {:?}", path))?,
+        });
+    }
+
+    Ok(diffs)
+}
+
+/// Compares the contents of both versions of a file, grouping the changed lines in hunks with
+/// `context` unchanged lines around them.
+fn diff_content(
+    repo: &Repository,
+    old: Option<&DiffFile>,
+    new: Option<&DiffFile>,
+    context: usize,
+) -> Result<DiffContent> {
+    if let (Some(o), Some(n)) = (old, new)
+        && o.hash == n.hash
+    {
+        return Ok(DiffContent::Unchanged);
+    }
+
+    let old_data = match old {
+        Some(f) => f.read_data(repo)?,
+        None => Rc::default(),
+    };
+    let new_data = match new {
+        Some(f) => f.read_data(repo)?,
+        None => Rc::default(),
+    };
+
+    if old_data.contains(&0) || new_data.contains(&0) {
+        return Ok(DiffContent::Binary);
+    }
+
+    let old_lines = diff::split_lines(&old_data);
+    let new_lines = diff::split_lines(&new_data);
+    let edits = diff::diff(&old_lines, &new_lines);
+    Ok(DiffContent::Text(diff::format_hunks(
+        &old_lines,
+        &new_lines,
+        &diff::hunks(&edits, context),
+    )))
+}
+
+/// Reads every file in the tree of the commit with the given hash.
+fn read_commit_side(repo: &Repository, commit_hash: Hash) -> Result<DiffSide> {
+    Ok(read_commit_entries(repo, commit_hash)?
+        .into_iter()
+        .map(|e| {
+            (
+                e.path,
+                DiffFile {
+                    mode: e.mode,
+                    hash: e.hash,
+                    data: None,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Reads every file in the index.
+fn read_index_side(repo: &Repository) -> Result<DiffSide> {
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    Ok(index
+        .entries()
+        .map(|ie| {
+            (
+                ie.path().to_owned(),
+                DiffFile {
+                    mode: as_octal(ie.mode),
+                    hash: ie.object_hash(),
+                    data: None,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Reads the files in the working tree that are tracked by the index. Files whose metadata did
+/// not change since they were added are assumed to have the same contents as in the index.
+fn read_working_tree_side(repo: &Repository) -> Result<DiffSide> {
+    let root = repo.working_tree();
+
+    let mut side = DiffSide::new();
+    for (path, (hash, cache)) in read_index_data(repo).context("could not read index data")? {
+        let full_path = root.join(&path);
+        let metadata = match std::fs::symlink_metadata(&full_path) {
+            Ok(m) => m,
+            // deleted file
+            Err(_) => continue,
+        };
+        let mode = as_octal(metadata.mode());
+
+        let unchanged = IndexEntryCache::try_from_metadata(metadata.clone())
+            .is_ok_and(|c| c.matches_loose(&cache));
+        if unchanged {
+            side.insert(
+                path,
+                DiffFile {
+                    mode,
+                    hash,
+                    data: None,
+                },
+            );
+            continue;
+        }
+
+        let data: Rc<[u8]> = if metadata.is_symlink() {
+            std::fs::read_link(&full_path)
+                .context(format!("could not read link {:?}", full_path))?
+                .as_os_str()
+                .as_encoded_bytes()
+                .into()
+        } else {
+            std::fs::read(&full_path)
+                .context(format!("could not read {:?}", full_path))?
+                .into()
+        };
+        let hash = Object::from_bytes_new_blob(&data)
+            .hash()
+            .context("could not hash blob")?;
+
+        side.insert(
+            path,
+            DiffFile {
+                mode,
+                hash,
+                data: Some(data),
+            },
+        );
+    }
+
+    Ok(side)
+}
@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::object::Object;
+use crate::pack::{Pack, PackIndex, PackObject, build_pack};
+use crate::repository::Repository;
+use crate::utils::nums::as_octal;
+use crate::{Constants, fs};
+
+use super::{rebase, sequencer};
+
+/// Mode of the entries that point to a commit of a submodule, which is not stored in this
+/// repository.
+const GITLINK_MODE: u32 = 160000;
+
+/// Refs outside of refs/ that point to commits an operation might go back to.
+const SPECIAL_REFS: &[&str] = &[
+    Constants::ORIG_HEAD_FILE_NAME,
+    Constants::MERGE_HEAD_FILE_NAME,
+    Constants::CHERRY_PICK_HEAD_FILE_NAME,
+    Constants::REVERT_HEAD_FILE_NAME,
+    Constants::REBASE_HEAD_FILE_NAME,
+];
+
+/// What `repack` and `gc` did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackSummary {
+    /// Amount of objects in the new pack
+    pub packed: usize,
+    /// Path of the new pack file
+    pub pack_path: PathBuf,
+    /// Amount of loose objects removed, always 0 for `repack`
+    pub pruned: usize,
+    /// Amount of objects of the previous packs that were not reachable, written out as loose
+    /// objects so removing the packs does not lose them
+    pub loosened: usize,
+}
+
+/// Packs every object reachable from HEAD, the branches, the tags, the special refs like
+/// `ORIG_HEAD`, the operations in progress and the HEAD reflog, along with the files in the index,
+/// into a single pack, removing the packs that existed before. The objects of those packs that
+/// are not reachable are kept as loose objects.
+///
+/// # Returns
+///
+/// `None` if there were no reachable objects to pack.
+///
+/// # Errors
+///
+/// This function will fail if any reachable object could not be read or the pack could not be
+/// written.
+pub fn repack(repo: &Repository) -> Result<Option<PackSummary>> {
+    Ok(repack_reachable(repo)?.map(|(summary, _)| summary))
+}
+
+/// Runs `repack` and then removes the loose objects that were packed.
+///
+/// Loose objects that are not reachable are kept.
+///
+/// # Errors
+///
+/// This function will fail if repacking fails or a loose object could not be removed.
+pub fn gc(repo: &Repository) -> Result<Option<PackSummary>> {
+    let Some((mut summary, index)) = repack_reachable(repo)? else {
+        return Ok(None);
+    };
+
+    for hash in fs::object::get_all_loose_object_hashes(repo).context("could not list objects")? {
+        if index.find_offset(&hash).is_some() {
+            fs::object::remove_loose_object(repo, &hash).context("could not prune object")?;
+            summary.pruned += 1;
+        }
+    }
+
+    Ok(Some(summary))
+}
+
+/// Writes a pack with every reachable object and removes the other packs, writing out the
+/// objects of those that are not reachable as loose objects first.
+///
+/// # Returns
+///
+/// `None` if there were no reachable objects, otherwise the summary (with nothing pruned) and the
+/// index of the new pack.
+fn repack_reachable(repo: &Repository) -> Result<Option<(PackSummary, PackIndex)>> {
+    let objects = collect_reachable_objects(repo).context("could not collect reachable objects")?;
+    if objects.is_empty() {
+        return Ok(None);
+    }
+    let count = objects.len();
+
+    let previous_packs =
+        fs::pack::get_all_pack_index_paths(repo).context("could not list packs")?;
+
+    let (data, index) = build_pack(objects).context("could not build pack")?;
+    let index_path = fs::pack::write_pack(repo, &data, &index).context("could not write pack")?;
+
+    let mut loosened = 0;
+    for path in previous_packs {
+        if path != index_path {
+            loosened += loosen_unreachable(repo, &path, &index).context(format!(
+                "could not keep unreachable objects of pack {:?}",
+                path
+            ))?;
+            fs::pack::remove_pack(repo, &path)
+                .context(format!("could not remove pack {:?}", path))?;
+        }
+    }
+
+    Ok(Some((
+        PackSummary {
+            packed: count,
+            pack_path: index_path.with_extension(Constants::PACK_EXTENSION),
+            pruned: 0,
+            loosened,
+        },
+        index,
+    )))
+}
+
+/// Writes the objects of the pack with the index at `index_path` that are not in `reachable` as
+/// loose objects, unless they already are, returning how many were written.
+fn loosen_unreachable(
+    repo: &Repository,
+    index_path: &Path,
+    reachable: &PackIndex,
+) -> Result<usize> {
+    let pack = Pack::open(index_path).context("could not open pack")?;
+
+    let mut loosened = 0;
+    for (hash, _) in pack.index().entries() {
+        if reachable.find_offset(&hash).is_some()
+            || fs::object::get_loose_object_path(repo, &hash).is_file()
+        {
+            continue;
+        }
+        let (kind, data) = pack
+            .read(&hash)?
+            .context(format!("object {} not found in pack", hash))?;
+        let mut bytes = format!("{} {}\0", kind, data.len()).into_bytes();
+        bytes.extend_from_slice(&data);
+        fs::object::write_to_object_dir(repo, &bytes, &hash)
+            .context(format!("could not write object {}", hash))?;
+        loosened += 1;
+    }
+    Ok(loosened)
+}
+
+/// Returns the commits that must be kept besides the ones HEAD, the branches and the tags reach:
+/// the ones of the special refs, the operations in progress and the HEAD reflog. The ones that
+/// no longer exist are left out.
+fn extra_root_commits(repo: &Repository) -> Result<Vec<Hash>> {
+    let mut commits = Vec::new();
+    for name in SPECIAL_REFS {
+        commits.extend(fs::read_ref(repo, name).context(format!("could not read {}", name))?);
+    }
+    commits.extend(rebase::state_commits(repo).context("could not read rebase state")?);
+    commits.extend(sequencer::state_commits(repo).context("could not read sequencer state")?);
+    commits.extend(fs::read_head_log_hashes(repo).context("could not read HEAD reflog")?);
+
+    Ok(commits
+        .into_iter()
+        .filter(|hash| fs::object::read_raw_object(repo, hash).is_ok())
+        .collect())
+}
+
+/// Returns every object reachable from HEAD, any branch, any tag or any of the extra roots (see
+/// `extra_root_commits`), along with the files in the index.
+fn collect_reachable_objects(repo: &Repository) -> Result<Vec<PackObject>> {
+    let mut commits: Vec<Hash> = Vec::new();
+    if let Some(hash) = fs::get_last_commit_hash(repo).context("could not get last commit hash")? {
+        commits.push(hash);
+    }
+    for name in fs::get_all_branch_names(repo).context("could not get branch names")? {
+        if let Some(hash) = fs::read_branch(repo, &name).context("could not read branch")? {
+            commits.push(hash);
+        }
+    }
+    commits.extend(extra_root_commits(repo)?);
+
+    let mut seen: HashSet<Hash> = HashSet::new();
+    let mut objects: Vec<PackObject> = Vec::new();
+    // trees are stored along with their path, so their entries get a path too
+    let mut trees: Vec<(Hash, PathBuf)> = Vec::new();
+
+    // tags can point to any kind of object, annotated tags are followed until they reach one that
+    // is not a tag
+    let mut tags: Vec<Hash> = Vec::new();
+    for name in fs::get_all_tag_names(repo).context("could not get tag names")? {
+        if let Some(hash) = fs::read_tag(repo, &name).context("could not read tag")? {
+            tags.push(hash);
+        }
+    }
+    while let Some(hash) = tags.pop() {
+        match fs::object::read_object(repo, hash.clone()).context("could not read tagged object")? {
+            Object::Tag { object, .. } => {
+                if seen.insert(hash.clone()) {
+                    objects.push(read_pack_object(repo, hash, None)?);
+                    tags.push(object);
+                }
+            }
+            Object::Commit { .. } => commits.push(hash),
+            Object::Tree { .. } => trees.push((hash, PathBuf::new())),
+            Object::Blob { .. } => {
+                if seen.insert(hash.clone()) {
+                    objects.push(read_pack_object(repo, hash, None)?);
+                }
+            }
+        }
+    }
+
+    while let Some(hash) = commits.pop() {
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+        match fs::object::read_object(repo, hash.clone()).context("could not read commit")? {
+            Object::Commit { tree, parents, .. } => {
+                trees.push((tree, PathBuf::new()));
+                commits.extend(parents.iter().cloned());
+            }
+            _ => bail!("{} is not a commit", hash),
+        }
+        objects.push(read_pack_object(repo, hash, None)?);
+    }
+
+    while let Some((hash, path)) = trees.pop() {
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+        let entries =
+            match fs::object::read_object(repo, hash.clone()).context("could not read tree")? {
+                Object::Tree { entries } => entries,
+                _ => bail!("{} is not a tree", hash),
+            };
+        for e in entries {
+            if e.mode == GITLINK_MODE {
+                continue;
+            }
+            let entry_path = path.join(&e.path);
+            if e.is_tree() {
+                trees.push((e.hash, entry_path));
+            } else if seen.insert(e.hash.clone()) {
+                objects.push(read_pack_object(repo, e.hash, Some(entry_path))?);
+            }
+        }
+        objects.push(read_pack_object(repo, hash, Some(path))?);
+    }
+
+    // staged files are not in any commit yet, including every stage of the ones with conflicts
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    for ie in index.entries() {
+        if as_octal(ie.mode) != GITLINK_MODE && seen.insert(ie.object_hash()) {
+            objects.push(read_pack_object(
+                repo,
+                ie.object_hash(),
+                Some(ie.path().to_owned()),
+            )?);
+        }
+    }
+
+    Ok(objects)
+}
+
+fn read_pack_object(repo: &Repository, hash: Hash, path: Option<PathBuf>) -> Result<PackObject> {
+    let (kind, data) = fs::object::read_raw_object(repo, &hash)
+        .context(format!("could not read object {}", hash))?;
+    Ok(PackObject {
+        hash,
+        kind,
+        data,
+        path,
+    })
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::commands::test_utils::*;
+    use crate::commands::{LogEntry, ResetMode, reset_to_commit};
+
+    /// Returns whether the object is in a pack, which is where `gc` leaves the reachable ones.
+    fn is_packed(repo: &Repository, hash: &Hash) -> bool {
+        fs::object::read_raw_object(repo, hash).is_ok()
+            && !fs::object::get_loose_object_path(repo, hash).is_file()
+    }
+
+    #[test]
+    pub fn test_gc_keeps_extra_roots() {
+        let repo = create_repository("gc-roots");
+        let base = commit_files(&repo, &[("f", "base\n")], "base");
+        let dropped = commit_files(&repo, &[("g", "dropped\n")], "dropped");
+        let staged = fs::index::read_index_file(&repo)
+            .unwrap()
+            .entries()
+            .find(|ie| ie.path() == Path::new("g"))
+            .unwrap()
+            .object_hash();
+        gc(&repo).unwrap();
+
+        // only ORIG_HEAD, the reflog and the index reach the commit and the file now
+        reset_to_commit(&repo, ResetMode::Soft, base.clone()).unwrap();
+        assert_eq!(
+            fs::read_ref(&repo, Constants::ORIG_HEAD_FILE_NAME).unwrap(),
+            Some(dropped.clone())
+        );
+        gc(&repo).unwrap();
+        assert!(is_packed(&repo, &dropped));
+
+        std::fs::remove_file(repo.git_dir().join(Constants::ORIG_HEAD_FILE_NAME)).unwrap();
+        gc(&repo).unwrap();
+        assert!(is_packed(&repo, &dropped));
+
+        std::fs::remove_file(repo.head_log_path()).unwrap();
+        let summary = gc(&repo).unwrap().unwrap();
+        assert!(!is_packed(&repo, &dropped));
+        assert!(is_packed(&repo, &staged));
+        // the commit and its tree, the file is still staged
+        assert_eq!(summary.loosened, 2);
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_gc_keeps_reset_away_commits() {
+        let repo = create_repository("gc-reset");
+        let base = commit_files(&repo, &[("f", "base\n")], "base");
+        let dropped = commit_files(&repo, &[("g", "dropped\n")], "dropped");
+        gc(&repo).unwrap();
+
+        reset_to_commit(&repo, ResetMode::Hard, base.clone()).unwrap();
+        assert_eq!(
+            fs::read_head_log_messages(&repo).unwrap(),
+            vec![
+                "commit (initial): base".to_string(),
+                "commit: dropped".to_string(),
+                format!("reset: moving to {}", base),
+            ]
+        );
+        gc(&repo).unwrap();
+        assert!(is_packed(&repo, &dropped));
+
+        // without anything pointing to them, the objects of the old pack are kept as loose objects
+        std::fs::remove_file(repo.git_dir().join(Constants::ORIG_HEAD_FILE_NAME)).unwrap();
+        std::fs::remove_file(repo.head_log_path()).unwrap();
+        let summary = gc(&repo).unwrap().unwrap();
+        // the commit, its tree and the new file
+        assert_eq!(summary.loosened, 3);
+        let entry = LogEntry::read(&repo, dropped.clone()).unwrap();
+        assert_eq!(entry.subject(), "dropped");
+        assert!(fs::object::read_object(&repo, entry.tree).is_ok());
+
+        // once loose, they are left alone by the next runs
+        let summary = gc(&repo).unwrap().unwrap();
+        assert_eq!(summary.loosened, 0);
+        assert!(LogEntry::read(&repo, dropped).is_ok());
+
+        remove_repository(&repo);
+    }
+}
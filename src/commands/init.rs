@@ -1,46 +1,74 @@
-use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 
 use crate::Constants;
+use crate::config::{Config, ConfigFile, ConfigScope};
+use crate::repository::Repository;
 
-/// Creates a new git repository, placing it inside `folder_name` if one is provided.
+/// Values written to the config file of every new repository.
+const INITIAL_CONFIG: &[(&str, &str)] = &[
+    ("core.repositoryformatversion", "0"),
+    ("core.filemode", "true"),
+    ("core.bare", "false"),
+];
+
+/// The result of `init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitOutcome {
+    Created,
+    /// The repository folder already existed, so nothing was written
+    AlreadyExists,
+}
+
+/// Creates the repository folder of `repo`, along with the working tree if it does not exist.
+///
+/// HEAD points to the branch set in `init.defaultBranch`, or `Constants::DEFAULT_BRANCH_NAME` if
+/// it is not set.
 ///
 /// # Errors
 ///
 /// This function will fail if any of the operations related with the creation of directories and
 /// files fail.
-pub fn init(folder_name: Option<&OsStr>) -> Result<String> {
-    // building root folder
-    let repository_path = Constants::repository_path();
-    let path = match folder_name {
-        Some(name) => PathBuf::from(name).join(repository_path),
-        None => repository_path,
-    };
-
-    if fs::exists(&path).context("could not verify folder existance when initializing")? {
-        return Ok("The directory is already a git repository\n".into());
+pub fn init(repo: &Repository) -> Result<InitOutcome> {
+    let path = repo.git_dir();
+
+    if fs::exists(path).context("could not verify folder existance when initializing")? {
+        return Ok(InitOutcome::AlreadyExists);
     }
 
     // creating directory if it didn't exist
-    fs::create_dir_all(&path).context("could not create repository directory when initializing")?;
+    fs::create_dir_all(path).context("could not create repository directory when initializing")?;
 
     // creating subdirectories
     for p in [
-        Constants::objects_path(),
-        Constants::refs_path(),
-        Constants::heads_path(),
+        repo.objects_path(),
+        repo.refs_path(),
+        repo.heads_path(),
+        repo.tags_path(),
     ] {
         fs::create_dir_all(&p).context(format!(
             "could not create repository subdirectories, specifically: {p:?}"
         ))?;
     }
 
+    // only the system and global config files, the repository one does not exist yet
+    let config = Config::load_scopes(&[ConfigScope::System, ConfigScope::Global], None)
+        .context("could not read config when initializing")?;
+    let branch = config
+        .get("init.defaultBranch")
+        .unwrap_or(Constants::DEFAULT_BRANCH_NAME);
+
     // creating default head file
-    fs::write(Constants::head_path(), Constants::default_head_content())
+    crate::fs::set_current_branch(repo, branch)
         .context("could not write to HEAD when initializing")?;
 
-    Ok("Created new git repository\n".into())
+    let mut config_file = ConfigFile::default();
+    for (name, value) in INITIAL_CONFIG {
+        config_file.set(name, value)?;
+    }
+    fs::write(repo.config_path(), config_file.to_string())
+        .context("could not write config file when initializing")?;
+
+    Ok(InitOutcome::Created)
 }
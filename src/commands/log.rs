@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::object::commit::CommitUser;
+use crate::object::{self, Object};
+use crate::repository::Repository;
+use crate::{Constants, fs, revision};
+
+/// Length of the abbreviated hashes returned by `LogEntry::short_hash`.
+const SHORT_HASH_LEN: usize = 7;
+
+/// A commit read from the object directory, along with its hash.
+#[derive(Debug)]
+pub struct LogEntry {
+    pub hash: Hash,
+    pub tree: Hash,
+    pub parents: Rc<[Hash]>,
+    pub author: CommitUser,
+    pub committer: CommitUser,
+    pub message: Rc<str>,
+}
+
+impl LogEntry {
+    /// Reads the commit with the given hash, failing if the object is not a commit.
+    pub fn read(repo: &Repository, hash: Hash) -> Result<Self> {
+        match fs::object::read_object(repo, hash.clone()).context("could not read commit")? {
+            Object::Commit {
+                tree,
+                parents,
+                author,
+                committer,
+                message,
+            } => Ok(Self {
+                hash,
+                tree,
+                parents,
+                author,
+                committer,
+                message,
+            }),
+            _ => bail!("{} is not a commit", hash),
+        }
+    }
+
+    /// The hash abbreviated to its first 7 characters
+    pub fn short_hash(&self) -> String {
+        self.hash.to_string()[..SHORT_HASH_LEN].to_string()
+    }
+
+    /// The first line of the message
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+// The hash is computed from the whole commit, so two entries are the same commit if their hashes
+// are equal.
+impl PartialEq for LogEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+impl Eq for LogEntry {}
+
+/// Wraps an entry in the queue of the history walk, which is ordered by commit date only so the
+/// most recent commit is always popped first.
+struct ByCommitDate(LogEntry);
+
+impl PartialEq for ByCommitDate {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.committer.timestamp == other.0.committer.timestamp
+    }
+}
+impl Eq for ByCommitDate {}
+impl PartialOrd for ByCommitDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ByCommitDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        SystemTime::cmp(&self.0.committer.timestamp, &other.0.committer.timestamp)
+    }
+}
+
+/// Returns the history of commits reachable from `revision` (or HEAD), most recent first.
+///
+/// - `max_count` limits the amount of commits returned.
+/// - `paths`, if not empty, only keeps the commits that changed something inside of them.
+///
+/// # Errors
+///
+/// This function will fail if the revision could not be resolved or any commit or tree in the
+/// history could not be read.
+pub fn log(
+    repo: &Repository,
+    revision: Option<&str>,
+    max_count: Option<usize>,
+    paths: &[OsString],
+) -> Result<Vec<LogEntry>> {
+    let start = revision::resolve_commit(repo, revision.unwrap_or(Constants::HEAD_FILE_NAME))
+        .context("could not resolve revision")?;
+
+    let mut filters = Vec::with_capacity(paths.len());
+    for p in paths {
+        filters.push(
+            repo.relative_path(Path::new(p))
+                .context(format!("{:?} is outside of the repository", p))?,
+        );
+    }
+    // An empty path is the root of the repository, so it would not filter anything
+    if filters.iter().any(|f| f.as_os_str().is_empty()) {
+        filters.clear();
+    }
+
+    let mut tree_cache: HashMap<Hash, HashMap<PathBuf, Hash>> = HashMap::new();
+    let mut seen: HashSet<Hash> = HashSet::from([start.clone()]);
+    let mut queue = BinaryHeap::from([ByCommitDate(
+        LogEntry::read(repo, start).context("could not read commit")?,
+    )]);
+
+    let mut history: Vec<LogEntry> = Vec::new();
+    while let Some(ByCommitDate(entry)) = queue.pop() {
+        if max_count.is_some_and(|max| history.len() >= max) {
+            break;
+        }
+
+        for p in entry.parents.iter() {
+            if seen.insert(p.clone()) {
+                queue.push(ByCommitDate(
+                    LogEntry::read(repo, p.clone()).context("could not read parent commit")?,
+                ));
+            }
+        }
+
+        if !filters.is_empty()
+            && !touches_paths(repo, &entry, &filters, &mut tree_cache)
+                .context("could not compare commit with its parents")?
+        {
+            continue;
+        }
+
+        history.push(entry);
+    }
+
+    Ok(history)
+}
+
+/// Returns `true` if the files under `filters` are different from the ones in every parent of
+/// the commit (for a root commit, if there are any files under `filters`).
+fn touches_paths(
+    repo: &Repository,
+    entry: &LogEntry,
+    filters: &[PathBuf],
+    tree_cache: &mut HashMap<Hash, HashMap<PathBuf, Hash>>,
+) -> Result<bool> {
+    let entries = read_filtered_tree(repo, entry.tree.clone(), filters, tree_cache)?;
+
+    if entry.parents.is_empty() {
+        return Ok(!entries.is_empty());
+    }
+
+    for p in entry.parents.iter() {
+        let parent_tree = LogEntry::read(repo, p.clone())?.tree;
+        let parent_entries = read_filtered_tree(repo, parent_tree, filters, tree_cache)?;
+        if parent_entries == entries {
+            // The commit did not change anything compared to this parent
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Returns every file in the tree with the given hash that is inside one of the `filters`,
+/// storing the result in `tree_cache` to avoid reading the same tree twice.
+fn read_filtered_tree(
+    repo: &Repository,
+    tree: Hash,
+    filters: &[PathBuf],
+    tree_cache: &mut HashMap<Hash, HashMap<PathBuf, Hash>>,
+) -> Result<HashMap<PathBuf, Hash>> {
+    if let Some(cached) = tree_cache.get(&tree) {
+        return Ok(cached.clone());
+    }
+
+    let entries =
+        match fs::object::read_object(repo, tree.clone()).context("could not read tree")? {
+            Object::Tree { entries } => entries,
+            _ => bail!("{} is not a tree", tree),
+        };
+
+    let filtered: HashMap<PathBuf, Hash> = object::tree::get_all_tree_entries(repo, entries)
+        .context("could not get all tree entries")?
+        .into_iter()
+        .filter(|e| filters.iter().any(|f| e.path.starts_with(f)))
+        .map(|e| (e.path, e.hash))
+        .collect();
+
+    tree_cache.insert(tree, filtered.clone());
+    Ok(filtered)
+}
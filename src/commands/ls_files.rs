@@ -1,28 +1,10 @@
 use anyhow::{Context, Result};
 
 use crate::fs;
+use crate::index::Index;
+use crate::repository::Repository;
 
-pub fn ls_files(debug: bool) -> Result<String> {
-    let index = fs::index::read_index_file().context("could not read from index file")?;
-
-    if index.entries().count() == 0 {
-        return Ok("There were no files to list\n".into());
-    }
-
-    let mut formatted = String::new();
-    for e in index.entries() {
-        if debug {
-            formatted.push_str(format!("{e:?}").as_str());
-            formatted.push('\n');
-        } else {
-            formatted.push_str(format!("{e}").as_str());
-        }
-        formatted.push('\n');
-    }
-
-    if debug {
-        formatted.pop();
-    } // removing extra new line
-
-    Ok(formatted.to_string())
+/// Reads the index file, which lists every file staged for the next commit.
+pub fn ls_files(repo: &Repository) -> Result<Index> {
+    fs::index::read_index_file(repo).context("could not read from index file")
 }
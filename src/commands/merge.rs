@@ -0,0 +1,609 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::config::Config;
+use crate::fs;
+use crate::fs::object::write_object;
+use crate::hashing::Hash;
+use crate::index::{FileStage, IndexEntry};
+use crate::merge::{MergeLabels, MergeOptions, merge_blobs};
+use crate::object::Object;
+use crate::object::commit::merge_bases;
+use crate::repository::{Repository, read_operation};
+use crate::revision;
+use crate::utils::nums::from_octal;
+
+use super::checkout::{changed_paths, check_overwritten_changes, update_files};
+use super::commit::{clear_merge_state, move_head, write_commit, write_tree};
+use super::status::read::read_commit_entries;
+use super::{Conflict, FileVersion, ResetMode, StageStatus, Status, get_statuses, reset_to_commit};
+
+/// The result of `merge`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The commit was already reachable from HEAD, so nothing changed
+    UpToDate,
+    /// HEAD was an ancestor of the commit, so it was moved forward without a merge commit
+    FastForward { from: Hash, to: Hash },
+    /// Both histories were combined in this new merge commit
+    Merged(Hash),
+    /// The merge stopped because of conflicts in these files, it is concluded by committing once
+    /// they are resolved and added
+    Conflicts(Vec<PathBuf>),
+}
+
+/// Every file of a tree by path.
+pub(super) type TreeFiles = HashMap<PathBuf, FileVersion>;
+
+/// The result of merging the files of two trees against the tree of their common ancestor.
+#[derive(Debug, Default)]
+pub(super) struct TreeMerge {
+    /// Files that were merged without conflicts, by path
+    pub files: TreeFiles,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A file that was changed in different ways on both sides of a merge.
+#[derive(Debug)]
+pub(super) struct MergeConflict {
+    pub path: PathBuf,
+    pub versions: Conflict,
+    /// What is left in the working tree for the conflict to be resolved
+    pub result: ConflictResult,
+}
+
+#[derive(Debug)]
+pub(super) enum ConflictResult {
+    /// One of the versions as it is, for files that were deleted on the other side or that can
+    /// not be merged
+    Keep(FileVersion),
+    /// The merged contents with conflict markers, with the mode in its octal representation
+    Markers { mode: u32, content: Vec<u8> },
+}
+
+/// The result of merging a file changed on both sides.
+enum FileMergeResult {
+    Merged(FileVersion),
+    Conflict(ConflictResult),
+}
+
+/// Merges the commit `revision` points to into HEAD.
+///
+/// If HEAD is an ancestor of the commit, HEAD is just moved forward to it. Otherwise, the files
+/// changed on each side since their merge base are combined, and a merge commit with both
+/// commits as its parents is created. Files whose changes can not be combined are conflicts:
+/// their versions are added to the index as stages, the working tree gets the merged contents
+/// with conflict markers in the style set by `merge.conflictStyle`, and the merge waits for them
+/// to be resolved and committed.
+///
+/// When there is more than one merge base, the first one found is used.
+///
+/// # Errors
+///
+/// This function will fail if:
+/// - Another operation is in progress, there are staged changes, or HEAD has no commits.
+/// - The revision does not point to a commit, or it has no history in common with HEAD.
+/// - There are local changes to files the merge would overwrite.
+/// - Any of the objects, refs, the index or the working tree files could not be read or written.
+pub fn merge(repo: &Repository, revision: &str) -> Result<MergeOutcome> {
+    if read_operation(repo)
+        .context("could not read operation in progress")?
+        .is_some()
+    {
+        bail!("there is an operation in progress, conclude or abort it before merging")
+    }
+    // the merge commit is made from the whole index, so staged changes would end up in it
+    if has_staged_changes(repo)? {
+        bail!("cannot merge: you have staged changes, commit them first")
+    }
+
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to merge into yet")?;
+    let theirs = revision::resolve_commit(repo, revision)?;
+
+    let bases =
+        merge_bases(repo, head.clone(), theirs.clone()).context("could not find merge base")?;
+    if bases.contains(&theirs) {
+        return Ok(MergeOutcome::UpToDate);
+    }
+    if bases.contains(&head) {
+        let target = read_tree_files(repo, theirs.clone())?;
+        let merge = TreeMerge {
+            files: target,
+            conflicts: Vec::new(),
+        };
+        apply_merge(repo, merge, "merge", "merging")?;
+        fs::write_ref(repo, Constants::ORIG_HEAD_FILE_NAME, &head)
+            .context("could not write ORIG_HEAD")?;
+        let message = format!("merge {}: Fast-forward", revision);
+        move_head(repo, &theirs, &message).context("could not update current branch")?;
+        return Ok(MergeOutcome::FastForward {
+            from: head,
+            to: theirs,
+        });
+    }
+    let Some(base) = bases.into_iter().next() else {
+        bail!("refusing to merge unrelated histories")
+    };
+
+    let config = Config::load(repo).context("could not read config")?;
+    let options = MergeOptions::from_config(&config)?;
+    let base_label = base.to_string()[..7].to_string();
+    let labels = MergeLabels {
+        base: &base_label,
+        ours: Constants::HEAD_FILE_NAME,
+        theirs: revision,
+    };
+    let tree_merge = merge_trees(
+        repo,
+        &read_tree_files(repo, base)?,
+        &read_tree_files(repo, head.clone())?,
+        &read_tree_files(repo, theirs.clone())?,
+        &labels,
+        &options,
+    )?;
+    let conflicts = apply_merge(repo, tree_merge, "merge", "merging")?;
+    fs::write_ref(repo, Constants::ORIG_HEAD_FILE_NAME, &head)
+        .context("could not write ORIG_HEAD")?;
+
+    let message = merge_message(repo, revision)?;
+    if !conflicts.is_empty() {
+        fs::write_ref(repo, Constants::MERGE_HEAD_FILE_NAME, &theirs)
+            .context("could not write MERGE_HEAD")?;
+        write_merge_msg(repo, &message, &conflicts)?;
+        return Ok(MergeOutcome::Conflicts(conflicts));
+    }
+
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let tree = write_tree(repo, &index)?;
+    let commit = write_commit(repo, tree, vec![head, theirs], &message, None)?;
+    let log_message = format!("merge {}: Merge made by the three-way strategy.", revision);
+    move_head(repo, &commit, &log_message).context("could not update current branch")?;
+
+    Ok(MergeOutcome::Merged(commit))
+}
+
+/// Stops the merge in progress, going back to the last commit and discarding the changes in the
+/// index and the working tree.
+///
+/// # Errors
+///
+/// This function will fail if there is no merge in progress, or if the index or the working tree
+/// could not be reset.
+pub fn merge_abort(repo: &Repository) -> Result<()> {
+    if !repo.merge_head_path().is_file() {
+        bail!("there is no merge to abort (MERGE_HEAD missing)")
+    }
+
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to go back to")?;
+    reset_to_commit(repo, ResetMode::Hard, head).context("could not reset to last commit")?;
+
+    clear_merge_state(repo)
+}
+
+/// Returns whether the index differs from the tree of HEAD, including files with conflicts.
+pub(super) fn has_staged_changes(repo: &Repository) -> Result<bool> {
+    Ok(get_statuses(repo)
+        .context("could not get file statuses")?
+        .into_iter()
+        .any(|f| match f.status {
+            Status::Unmerged(_) => true,
+            Status::Unchanged => false,
+            _ => f.stage_status == StageStatus::Commit,
+        }))
+}
+
+/// Returns the message of the commit merging `revision`, like git does: `Merge branch 'name'`
+/// for branches and `Merge commit 'revision'` for anything else, followed by `into <branch>`
+/// unless merging into the default branch.
+fn merge_message(repo: &Repository, revision: &str) -> Result<String> {
+    let is_branch = fs::is_valid_ref_name(revision)
+        && fs::read_branch(repo, revision)
+            .context("could not read branch")?
+            .is_some();
+    let mut message = if is_branch {
+        format!("Merge branch '{}'", revision)
+    } else {
+        format!("Merge commit '{}'", revision)
+    };
+
+    let current = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    if let Some(branch) = current
+        && branch != Constants::DEFAULT_BRANCH_NAME
+    {
+        message.push_str(&format!(" into {}", branch));
+    }
+
+    Ok(message)
+}
+
+/// Writes the message for the commit that concludes an operation stopped by conflicts, listing
+/// the files with conflicts in comments.
+pub(super) fn write_merge_msg(
+    repo: &Repository,
+    message: &str,
+    conflicts: &[PathBuf],
+) -> Result<()> {
+    let mut message = format!("{}\n\n# Conflicts:\n", message.trim_end());
+    for path in conflicts {
+        message.push_str(&format!("#\t{}\n", path.to_string_lossy()));
+    }
+    std::fs::write(repo.merge_msg_path(), message).context("could not write MERGE_MSG")
+}
+
+/// Returns every file in the tree of the commit with the given hash.
+pub(super) fn read_tree_files(repo: &Repository, commit: Hash) -> Result<TreeFiles> {
+    Ok(read_commit_entries(repo, commit)
+        .context("could not read commit entries")?
+        .into_iter()
+        .map(|e| {
+            let version = FileVersion {
+                mode: e.mode,
+                hash: e.hash,
+            };
+            (e.path, version)
+        })
+        .collect())
+}
+
+/// Merges the files of `ours` and `theirs`, which both come from `base`. Files changed only on
+/// one side take that version, while the contents of files changed on both sides are merged
+/// line by line (see `merge::merge_lines`). Files whose changes could not be merged are
+/// conflicts.
+///
+/// # Errors
+///
+/// This function will fail if the contents of a file changed on both sides could not be read,
+/// or if the merged contents could not be written.
+pub(super) fn merge_trees(
+    repo: &Repository,
+    base: &TreeFiles,
+    ours: &TreeFiles,
+    theirs: &TreeFiles,
+    labels: &MergeLabels,
+    options: &MergeOptions,
+) -> Result<TreeMerge> {
+    let paths: HashSet<&PathBuf> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+
+    let mut merge = TreeMerge::default();
+    for path in paths {
+        let versions = Conflict {
+            base: base.get(path).cloned(),
+            ours: ours.get(path).cloned(),
+            theirs: theirs.get(path).cloned(),
+        };
+
+        let merged = if versions.ours == versions.theirs || versions.base == versions.theirs {
+            versions.ours
+        } else if versions.base == versions.ours {
+            versions.theirs
+        } else {
+            match merge_file(repo, &versions, labels, options)? {
+                FileMergeResult::Merged(version) => Some(version),
+                FileMergeResult::Conflict(result) => {
+                    merge.conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        versions,
+                        result,
+                    });
+                    continue;
+                }
+            }
+        };
+
+        if let Some(version) = merged {
+            merge.files.insert(path.clone(), version);
+        }
+    }
+
+    merge.conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(merge)
+}
+
+/// Merges a file changed differently on both sides. Files deleted on one side keep the version
+/// of the other one, and binary files keep our version, both as conflicts.
+fn merge_file(
+    repo: &Repository,
+    versions: &Conflict,
+    labels: &MergeLabels,
+    options: &MergeOptions,
+) -> Result<FileMergeResult> {
+    let (ours, theirs) = match (&versions.ours, &versions.theirs) {
+        (Some(ours), Some(theirs)) => (ours, theirs),
+        (Some(kept), None) | (None, Some(kept)) => {
+            return Ok(FileMergeResult::Conflict(ConflictResult::Keep(
+                kept.clone(),
+            )));
+        }
+        (None, None) => unreachable!("files deleted on both sides are never conflicts"),
+    };
+
+    let base_object = match &versions.base {
+        Some(base) => Some(read_blob(repo, base.hash.clone())?),
+        None => None,
+    };
+    let ours_object = read_blob(repo, ours.hash.clone())?;
+    let theirs_object = read_blob(repo, theirs.hash.clone())?;
+    if is_binary(&ours_object) || is_binary(&theirs_object) {
+        return Ok(FileMergeResult::Conflict(ConflictResult::Keep(
+            ours.clone(),
+        )));
+    }
+
+    let file_merge = merge_blobs(
+        base_object.as_ref(),
+        &ours_object,
+        &theirs_object,
+        labels,
+        options,
+    )?;
+
+    // the mode is merged like the contents, taking the one that changed
+    let base_mode = versions.base.as_ref().map(|b| b.mode);
+    let (mode, mode_conflict) = if ours.mode == theirs.mode || base_mode == Some(theirs.mode) {
+        (ours.mode, false)
+    } else if base_mode == Some(ours.mode) {
+        (theirs.mode, false)
+    } else {
+        (ours.mode, true)
+    };
+
+    if !file_merge.is_clean() || mode_conflict {
+        return Ok(FileMergeResult::Conflict(ConflictResult::Markers {
+            mode,
+            content: file_merge.content,
+        }));
+    }
+
+    let blob = Object::Blob {
+        data: file_merge.content.into(),
+    };
+    let hash = write_object(repo, &blob).context("could not write merged blob")?;
+    Ok(FileMergeResult::Merged(FileVersion { mode, hash }))
+}
+
+fn is_binary(blob: &Object) -> bool {
+    matches!(blob, Object::Blob { data } if data.contains(&0))
+}
+
+fn read_blob(repo: &Repository, hash: Hash) -> Result<Object> {
+    fs::object::read_object(repo, hash).context("could not read blob object")
+}
+
+/// Updates the index and the working tree from the last commit to the result of `merge`. The
+/// files with conflicts get an index entry for each of their versions instead of a normal one.
+///
+/// `command` and `action` describe the operation in the error shown when local changes would be
+/// overwritten (see `check_overwritten_changes`).
+///
+/// # Returns
+///
+/// The paths of the files with conflicts, sorted.
+///
+/// # Errors
+///
+/// This function will fail if there are local changes to any of the files the merge changes, or
+/// if the index or the working tree could not be updated.
+pub(super) fn apply_merge(
+    repo: &Repository,
+    merge: TreeMerge,
+    command: &str,
+    action: &str,
+) -> Result<Vec<PathBuf>> {
+    let target: HashMap<PathBuf, (u32, Hash)> = merge
+        .files
+        .into_iter()
+        .map(|(path, version)| (path, (version.mode, version.hash)))
+        .collect();
+
+    let mut changed = changed_paths(repo, &target)?;
+    changed.extend(merge.conflicts.iter().map(|c| c.path.clone()));
+    check_overwritten_changes(repo, &changed, command, action)?;
+
+    // the files with conflicts are not in the target, so they are removed here and written below
+    let mut index_builder = update_files(repo, &changed, target)?;
+
+    let mut conflicted = Vec::new();
+    for conflict in merge.conflicts {
+        let path = conflict.path;
+        match conflict.result {
+            ConflictResult::Keep(version) => {
+                fs::working_tree::write_blob(repo, &path, version.mode, version.hash)
+            }
+            ConflictResult::Markers { mode, content } => {
+                fs::working_tree::write_file(repo, &path, mode, &content)
+            }
+        }
+        .context(format!("could not write {:?}", path))?;
+
+        let stages = [
+            (FileStage::Base, conflict.versions.base),
+            (FileStage::Ours, conflict.versions.ours),
+            (FileStage::Theirs, conflict.versions.theirs),
+        ];
+        for (stage, version) in stages {
+            if let Some(version) = version {
+                let mut entry =
+                    IndexEntry::new(from_octal(version.mode), path.clone(), version.hash);
+                entry.set_stage(stage);
+                index_builder.add_index_entry(entry);
+            }
+        }
+        conflicted.push(path);
+    }
+
+    fs::index::write_index_file(repo, index_builder.build())
+        .context("could not write index file")?;
+
+    Ok(conflicted)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::*;
+    use crate::commands::{LogEntry, checkout, commit, create_branch};
+
+    /// Commits `base` to `f`, then `ours` on the current branch and `theirs` on a new branch
+    /// called `side`, returning the commits of both sides.
+    fn diverge(
+        repo: &Repository,
+        base: &str,
+        ours: &[(&str, &str)],
+        theirs: &[(&str, &str)],
+    ) -> (Hash, Hash) {
+        commit_files(repo, &[("f", base)], "base");
+        let branch = fs::get_current_branch_name(repo).unwrap().unwrap();
+        create_branch(repo, "side", None).unwrap();
+        checkout(repo, "side").unwrap();
+        let theirs = commit_files(repo, theirs, "theirs");
+        checkout(repo, &branch).unwrap();
+        let ours = commit_files(repo, ours, "ours");
+        (ours, theirs)
+    }
+
+    fn head(repo: &Repository) -> Hash {
+        fs::get_last_commit_hash(repo).unwrap().unwrap()
+    }
+
+    #[test]
+    pub fn test_merge_fast_forward_and_up_to_date() {
+        let repo = create_repository("merge-fast-forward");
+        let base = commit_files(&repo, &[("f", "1\n")], "base");
+        let branch = fs::get_current_branch_name(&repo).unwrap().unwrap();
+        create_branch(&repo, "side", None).unwrap();
+        checkout(&repo, "side").unwrap();
+        let side = commit_files(&repo, &[("g", "side\n")], "side");
+        checkout(&repo, &branch).unwrap();
+
+        assert_eq!(
+            merge(&repo, "side").unwrap(),
+            MergeOutcome::FastForward {
+                from: base,
+                to: side.clone()
+            }
+        );
+        assert_eq!(head(&repo), side);
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("side\n"));
+        assert_eq!(merge(&repo, "side").unwrap(), MergeOutcome::UpToDate);
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_merge_commit() {
+        let repo = create_repository("merge-commit");
+        let (ours, theirs) = diverge(
+            &repo,
+            "1\n2\n3\n",
+            &[("f", "one\n2\n3\n")],
+            &[("f", "1\n2\nthree\n"), ("g", "new\n")],
+        );
+
+        let MergeOutcome::Merged(merged) = merge(&repo, "side").unwrap() else {
+            panic!("the merge should be clean")
+        };
+        let entry = LogEntry::read(&repo, merged.clone()).unwrap();
+        assert_eq!(entry.parents.to_vec(), vec![ours, theirs]);
+        assert_eq!(head(&repo), merged);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("one\n2\nthree\n"));
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("new\n"));
+        assert!(!has_staged_changes(&repo).unwrap());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_merge_conflicts() {
+        let repo = create_repository("merge-conflicts");
+        let (ours, theirs) = diverge(
+            &repo,
+            "1\n2\n3\n",
+            &[("f", "1\nours\n3\n")],
+            &[("f", "1\ntheirs\n3\n")],
+        );
+
+        assert_eq!(
+            merge(&repo, "side").unwrap(),
+            MergeOutcome::Conflicts(vec![PathBuf::from("f")])
+        );
+        let index = fs::index::read_index_file(&repo).unwrap();
+        let stages: Vec<FileStage> = index.entries().map(|e| e.get_stage()).collect();
+        assert_eq!(
+            stages,
+            vec![FileStage::Base, FileStage::Ours, FileStage::Theirs]
+        );
+        assert_eq!(
+            read_file(&repo, "f").as_deref(),
+            Some("1\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> side\n3\n")
+        );
+        assert_eq!(
+            fs::read_ref(&repo, Constants::MERGE_HEAD_FILE_NAME).unwrap(),
+            Some(theirs.clone())
+        );
+
+        // the merge is concluded by committing, once every conflict is resolved
+        assert!(commit(&repo, "merge").is_err());
+        write_file(&repo, "f", "1\nboth\n3\n");
+        add_files(&repo, &["f"]);
+        let merged = commit(&repo, "merge").unwrap();
+        let entry = LogEntry::read(&repo, merged).unwrap();
+        assert_eq!(entry.parents.to_vec(), vec![ours, theirs]);
+        assert!(!repo.merge_head_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_merge_abort() {
+        let repo = create_repository("merge-abort");
+        let (ours, _) = diverge(
+            &repo,
+            "1\n2\n3\n",
+            &[("f", "1\nours\n3\n")],
+            &[("f", "1\ntheirs\n3\n")],
+        );
+
+        assert!(merge_abort(&repo).is_err());
+        merge(&repo, "side").unwrap();
+        merge_abort(&repo).unwrap();
+
+        assert_eq!(head(&repo), ours);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("1\nours\n3\n"));
+        assert!(
+            !fs::index::read_index_file(&repo)
+                .unwrap()
+                .has_unmerged_entries()
+        );
+        assert!(!repo.merge_head_path().exists());
+        assert!(!repo.merge_msg_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_merge_refuses_staged_changes() {
+        let repo = create_repository("merge-staged");
+        let (ours, _) = diverge(&repo, "1\n", &[("f", "ours\n")], &[("g", "theirs\n")]);
+        write_file(&repo, "u", "staged\n");
+        add_files(&repo, &["u"]);
+
+        assert!(merge(&repo, "side").is_err());
+        assert_eq!(head(&repo), ours);
+        assert_eq!(read_file(&repo, "g"), None);
+
+        remove_repository(&repo);
+    }
+}
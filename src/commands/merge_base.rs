@@ -0,0 +1,91 @@
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::object::commit;
+use crate::repository::Repository;
+use crate::revision;
+
+/// Returns the best common ancestors of the commit the first revision points to and the commits
+/// the rest point to, as if they were merged together. Criss-cross histories can have more than
+/// one, and unrelated ones have none.
+///
+/// # Errors
+///
+/// This function will fail if there are less than two revisions, if any of them does not point
+/// to a commit, or if any of the commits in the history could not be read.
+pub fn merge_base(repo: &Repository, revisions: &[String]) -> Result<Vec<Hash>> {
+    if revisions.len() < 2 {
+        bail!("merge-base needs at least two commits")
+    }
+
+    let one = revision::resolve_commit(repo, &revisions[0])?;
+    let others = revisions[1..]
+        .iter()
+        .map(|r| revision::resolve_commit(repo, r))
+        .collect::<Result<Vec<Hash>>>()?;
+    commit::merge_bases_many(repo, one, others).context("could not find merge bases")
+}
+
+/// Returns whether the commit `ancestor` points to can be reached from the one `descendant`
+/// points to. A commit is an ancestor of itself.
+///
+/// # Errors
+///
+/// This function will fail if any of the revisions does not point to a commit, or if any of the
+/// commits in the history could not be read.
+pub fn is_ancestor(repo: &Repository, ancestor: &str, descendant: &str) -> Result<bool> {
+    let ancestor = revision::resolve_commit(repo, ancestor)?;
+    let descendant = revision::resolve_commit(repo, descendant)?;
+    commit::is_ancestor(repo, &ancestor, descendant).context("could not walk the history")
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::{commit_files, create_repository, remove_repository};
+    use crate::commands::{checkout, create_branch};
+    use crate::fs;
+
+    /// Creates a base commit with one commit on top of it in `side` and another in the current
+    /// branch, returning the base, the side commit, the current branch and its commit.
+    fn setup(repo: &Repository) -> (Hash, Hash, String, Hash) {
+        let base = commit_files(repo, &[("file", "base\n")], "base");
+        let branch = fs::get_current_branch_name(repo).unwrap().unwrap();
+        create_branch(repo, "side", None).unwrap();
+        checkout(repo, "side").unwrap();
+        let side = commit_files(repo, &[("side", "side\n")], "side");
+        checkout(repo, &branch).unwrap();
+        let ours = commit_files(repo, &[("ours", "ours\n")], "ours");
+        (base, side, branch, ours)
+    }
+
+    #[test]
+    pub fn test_merge_base() {
+        let repo = create_repository("merge-base");
+        let (base, _, branch, _) = setup(&repo);
+
+        let bases = merge_base(&repo, &[branch.clone(), "side".into()]).unwrap();
+        assert_eq!(bases, vec![base]);
+        assert!(merge_base(&repo, &[branch]).is_err());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_is_ancestor() {
+        let repo = create_repository("is-ancestor");
+        let (base, side, branch, ours) = setup(&repo);
+
+        assert!(is_ancestor(&repo, &base.to_string(), &branch).unwrap());
+        assert!(is_ancestor(&repo, &base.to_string(), "side").unwrap());
+        assert!(is_ancestor(&repo, &ours.to_string(), &ours.to_string()).unwrap());
+        assert!(!is_ancestor(&repo, "side", &branch).unwrap());
+        assert!(!is_ancestor(&repo, &side.to_string(), &base.to_string()).unwrap());
+        assert!(is_ancestor(&repo, "nosuchref", &branch).is_err());
+        assert!(is_ancestor(&repo, &branch, "nosuchref").is_err());
+
+        remove_repository(&repo);
+    }
+}
@@ -1,53 +1,50 @@
+//! The operations of every command, returning their results instead of printing them.
+
 mod add;
+mod branch;
 mod cat_file;
+mod check_ignore;
 mod checkout;
 mod commit;
+mod config;
+mod diff;
+mod gc;
 mod init;
+mod log;
 mod ls_files;
+mod merge;
+mod merge_base;
+mod rebase;
 mod reset;
+mod rev_parse;
+mod sequencer;
 mod status;
+mod tag;
+#[cfg(test)]
+mod test_utils;
 
-use std::fs;
-
-use add::add;
-use cat_file::cat_file;
-use checkout::checkout;
-use commit::commit;
-use init::init;
-use ls_files::ls_files;
-use reset::reset;
-use status::status;
-
-use anyhow::Result;
-
-use crate::Constants;
-use crate::args::Command;
-
-/// Calls the corresponding function to perform every command variant.
-///
-/// # Return
-///
-/// The success message
-///
-/// # Errors
-///
-/// This function will fail if any of the executed commands return an error.
-pub fn execute_command(command: &Command) -> Result<String> {
-    if !fs::exists(Constants::repository_path())? {
-        if let Command::Init { folder_name } = command {
-            // Only command that can be executed without a repository already existing
-            return init(folder_name.as_deref());
-        }
-        return Ok("Folder is not a git repository".into());
-    }
-    match command {
-        Command::Init { folder_name } => init(folder_name.as_deref()), // always returns an "already a git repository"
-        Command::Add { files } => add(files),
-        Command::Reset { command } => reset(command.as_ref()),
-        Command::Status => status(),
-        Command::Commit { message } => commit(message.as_ref()),
-        Command::Checkout { reference } => checkout(reference),
-        Command::LsFiles { debug } => ls_files(*debug),
-        Command::CatFile { hash } => cat_file(hash),
-    }
-}
+pub use add::{AddSummary, add};
+pub use branch::{Branch, create_branch, delete_branch, list_branches, rename_branch};
+pub use cat_file::cat_file;
+pub use check_ignore::{IgnoreMatch, check_ignore};
+pub use checkout::{CheckoutOutcome, checkout};
+pub use commit::commit;
+pub use config::{ConfigAction, ConfigOutput, ConfigType, config};
+pub use diff::{DiffContent, FileDiff, FileVersion, diff};
+pub use gc::{PackSummary, gc, repack};
+pub use init::{InitOutcome, init};
+pub use log::{LogEntry, log};
+pub use ls_files::ls_files;
+pub use merge::{MergeOutcome, merge, merge_abort};
+pub use merge_base::{is_ancestor, merge_base};
+pub use rebase::{RebaseOutcome, rebase, rebase_abort, rebase_continue, rebase_skip};
+pub use reset::{ResetMode, reset_files, reset_head, reset_to_commit};
+pub use rev_parse::rev_parse;
+pub use sequencer::{
+    SequencerOutcome, cherry_pick, revert, sequencer_abort, sequencer_continue, sequencer_skip,
+};
+pub use status::{
+    Conflict, FileWithStatus, StageStatus, Status, StatusReport, UpstreamStatus, get_statuses,
+    status,
+};
+pub use tag::{create_tag, delete_tag, list_tags};
@@ -0,0 +1,1221 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::config::Config;
+use crate::fs;
+use crate::hashing::Hash;
+use crate::object::commit::{CommitUser, commits_between, is_ancestor};
+use crate::repository::{Repository, read_operation};
+use crate::revision;
+
+use super::commit::{clean_message, log_head_move, move_head, write_commit, write_tree};
+use super::reset::move_to_commit;
+use super::sequencer::{Action, Step, StepResult, apply_step};
+use super::{LogEntry, ResetMode, StageStatus, Status, get_statuses};
+
+/// Name of the file inside the rebase folder with the commit HEAD pointed to at the start.
+const ORIG_HEAD_FILE_NAME: &str = "orig-head";
+/// Name of the file inside the rebase folder with the commands left to execute.
+const TODO_FILE_NAME: &str = "git-rebase-todo";
+/// Name of the file inside the rebase folder with the commands already executed.
+const DONE_FILE_NAME: &str = "done";
+/// Name of the file inside the rebase folder with the commit that stopped because of conflicts.
+const STOPPED_FILE_NAME: &str = "stopped-sha";
+/// Name of the file inside the rebase folder with the commit to amend after stopping at `edit`.
+const AMEND_FILE_NAME: &str = "amend";
+/// Names of the files inside the rebase folder with the author, as shell variables, and the
+/// message of the commit that stopped, so git can conclude it too.
+const AUTHOR_SCRIPT_FILE_NAME: &str = "author-script";
+const MESSAGE_FILE_NAME: &str = "message";
+/// Value of the head name file when HEAD was detached at the start.
+const DETACHED_HEAD_NAME: &str = "detached HEAD";
+/// Name of the file in the repository folder where commit messages are edited.
+const COMMIT_EDITMSG_FILE_NAME: &str = "COMMIT_EDITMSG";
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Explanation of the commands added at the end of the todo file of an interactive rebase.
+const TODO_HELP: &str = "
+# Commands:
+# p, pick <commit> = use commit
+# r, reword <commit> = use commit, but edit the commit message
+# e, edit <commit> = use commit, but stop for amending
+# s, squash <commit> = use commit, but meld into previous commit
+# f, fixup <commit> = like \"squash\" but keep only the previous commit's log message
+# x, exec <command> = run command (the rest of the line) using shell
+# d, drop <commit> = remove commit
+#
+# These lines can be re-ordered; they are executed from top to bottom.
+#
+# If you remove a line here THAT COMMIT WILL BE LOST.
+#
+# However, if you remove everything, the rebase will be aborted.
+";
+/// Explanation added after a message opened in the editor.
+const MESSAGE_HELP: &str = "
+# Please enter the commit message for your changes. Lines starting
+# with '#' will be ignored, and an empty message aborts the commit.
+";
+
+/// The result of a rebase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebaseOutcome {
+    /// The upstream was already in the history of HEAD, so nothing changed
+    UpToDate,
+    /// HEAD was an ancestor of the upstream, so it was moved forward to this commit
+    FastForward(Hash),
+    /// Every command was executed, HEAD points to this last commit
+    Done(Hash),
+    /// Applying `commit` stopped because of conflicts in these files, the rebase goes on with
+    /// `rebase_continue` once they are resolved and added
+    Conflicts { commit: Hash, paths: Vec<PathBuf> },
+    /// Stopped after applying this commit, as asked by an `edit` command, so it can be amended
+    Edit(Hash),
+    /// An `exec` command failed with this command line
+    ExecFailed(String),
+}
+
+/// A line of the todo file of a rebase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Instruction {
+    Pick(Hash),
+    /// Pick and edit the message
+    Reword(Hash),
+    /// Pick and stop to amend it
+    Edit(Hash),
+    /// Pick and meld into the previous commit, editing the combined message
+    Squash(Hash),
+    /// Pick and meld into the previous commit, keeping its message
+    Fixup(Hash),
+    Drop(Hash),
+    /// Run the command line with the shell
+    Exec(String),
+}
+
+impl Instruction {
+    /// Parses a line of the todo file, returning `None` for comments and empty lines.
+    fn parse(repo: &Repository, line: &str) -> Result<Option<Self>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        if command == "x" || command == "exec" {
+            if rest.is_empty() {
+                bail!("missing command line in: {}", line)
+            }
+            return Ok(Some(Instruction::Exec(rest.to_string())));
+        }
+
+        let Some(commit) = rest.split_whitespace().next() else {
+            bail!("missing commit in: {}", line)
+        };
+        let commit = revision::resolve_commit(repo, commit)?;
+        Ok(Some(match command {
+            "p" | "pick" => Instruction::Pick(commit),
+            "r" | "reword" => Instruction::Reword(commit),
+            "e" | "edit" => Instruction::Edit(commit),
+            "s" | "squash" => Instruction::Squash(commit),
+            "f" | "fixup" => Instruction::Fixup(commit),
+            "d" | "drop" => Instruction::Drop(commit),
+            _ => bail!("invalid command '{}' in: {}", command, line),
+        }))
+    }
+
+    /// The commit the instruction uses, if any.
+    fn commit(&self) -> Option<&Hash> {
+        match self {
+            Instruction::Pick(commit)
+            | Instruction::Reword(commit)
+            | Instruction::Edit(commit)
+            | Instruction::Squash(commit)
+            | Instruction::Fixup(commit)
+            | Instruction::Drop(commit) => Some(commit),
+            Instruction::Exec(_) => None,
+        }
+    }
+}
+
+/// Replays the commits of HEAD that are not in the history of `upstream` on top of it, one by
+/// one, leaving the current branch pointing to the last one. Merge commits are left out.
+///
+/// With `interactive`, the list of commands is opened in the editor first (see `edit_file`), so
+/// commits can be reordered, reworded, edited, squashed, dropped, or commands run in between.
+///
+/// The rebase stops if a commit conflicts, at `edit` commands and when an `exec` command fails,
+/// and goes on with `rebase_continue` or `rebase_skip`, or is undone with `rebase_abort`.
+///
+/// # Errors
+///
+/// This function will fail if:
+/// - Another operation is in progress, HEAD has no commits or there are uncommitted changes.
+/// - The upstream does not point to a commit.
+/// - The edited todo file has invalid lines, has no commands or starts by squashing.
+/// - Any of the objects, refs, the index or the working tree files could not be read or written.
+pub fn rebase(repo: &Repository, upstream: &str, interactive: bool) -> Result<RebaseOutcome> {
+    rebase_with(repo, upstream, interactive, &|name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Same as `rebase`, reading environment variables through `var`.
+fn rebase_with(
+    repo: &Repository,
+    upstream: &str,
+    interactive: bool,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<RebaseOutcome> {
+    let in_progress = read_operation(repo)
+        .context("could not read operation in progress")?
+        .is_some();
+    if in_progress || repo.sequencer_path().exists() {
+        bail!("there is an operation in progress, conclude or abort it first")
+    }
+    let changed = get_statuses(repo)
+        .context("could not get file statuses")?
+        .into_iter()
+        .any(|f| f.stage_status != StageStatus::Untracked && f.status != Status::Unchanged);
+    if changed {
+        bail!("cannot rebase: you have uncommitted changes, commit them first")
+    }
+
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to rebase yet")?;
+    let onto = revision::resolve_commit(repo, upstream)?;
+
+    if !interactive {
+        if is_ancestor(repo, &onto, head.clone())? {
+            return Ok(RebaseOutcome::UpToDate);
+        }
+        if is_ancestor(repo, &head, onto.clone())? {
+            let message = format!("rebase (finish): fast-forward to {}", upstream);
+            move_to_commit(repo, ResetMode::Hard, onto.clone(), &message)
+                .context("could not move to upstream")?;
+            fs::write_ref(repo, Constants::ORIG_HEAD_FILE_NAME, &head)
+                .context("could not write ORIG_HEAD")?;
+            return Ok(RebaseOutcome::FastForward(onto));
+        }
+    }
+
+    let mut todo = String::new();
+    let commits = commits_between(repo, onto.clone(), head.clone())?;
+    for hash in commits {
+        let entry = LogEntry::read(repo, hash)?;
+        if entry.parents.len() <= 1 {
+            todo.push_str(&format!(
+                "pick {} {}\n",
+                entry.short_hash(),
+                entry.subject()
+            ));
+        }
+    }
+
+    let branch = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    let head_name = match &branch {
+        Some(branch) => format!(
+            "{}/{}/{}",
+            Constants::REFS_FOLDER_NAME,
+            Constants::HEADS_FOLDER_NAME,
+            branch
+        ),
+        None => DETACHED_HEAD_NAME.to_string(),
+    };
+    let folder = repo.rebase_merge_path();
+    std::fs::create_dir_all(&folder).context("could not create rebase folder")?;
+    std::fs::write(
+        folder.join(Constants::REBASE_HEAD_NAME_FILE_NAME),
+        format!("{}\n", head_name),
+    )
+    .context("could not write rebase head name")?;
+    write_state_hash(repo, Constants::REBASE_ONTO_FILE_NAME, &onto)?;
+    write_state_hash(repo, ORIG_HEAD_FILE_NAME, &head)?;
+    std::fs::write(folder.join(DONE_FILE_NAME), "").context("could not write rebase done")?;
+
+    if interactive {
+        std::fs::write(folder.join(Constants::REBASE_INTERACTIVE_FILE_NAME), "")
+            .context("could not mark rebase as interactive")?;
+        let short = |hash: &Hash| hash.to_string()[..7].to_string();
+        todo.push_str(&format!(
+            "\n# Rebase {}..{} onto {}\n{}",
+            short(&onto),
+            short(&head),
+            short(&onto),
+            TODO_HELP
+        ));
+        std::fs::write(folder.join(TODO_FILE_NAME), &todo).context("could not write todo")?;
+
+        if let Err(error) = validate_todo(repo, var) {
+            remove_state(repo)?;
+            return Err(error);
+        }
+    } else {
+        std::fs::write(folder.join(TODO_FILE_NAME), &todo).context("could not write todo")?;
+    }
+
+    // replaying from a detached HEAD, the branch is only moved at the end
+    fs::detach_head(repo, &head).context("could not detach HEAD")?;
+    let message = format!("rebase (start): checkout {}", upstream);
+    move_to_commit(repo, ResetMode::Hard, onto, &message).context("could not move to upstream")?;
+
+    run(repo, var)
+}
+
+/// Continues the rebase that stopped. A commit that stopped because of conflicts is concluded
+/// with the resolved files in the index, and a commit that stopped at an `edit` command is
+/// amended with the changes added to the index, before executing the rest of the commands.
+///
+/// # Errors
+///
+/// This function will fail if there is no rebase in progress, if there are files with
+/// unresolved conflicts, or if any of the commands left fail.
+pub fn rebase_continue(repo: &Repository) -> Result<RebaseOutcome> {
+    rebase_continue_with(repo, &|name| std::env::var(name).ok())
+}
+
+/// Same as `rebase_continue`, reading environment variables through `var`.
+fn rebase_continue_with(
+    repo: &Repository,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<RebaseOutcome> {
+    ensure_rebase(repo)?;
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    if index.has_unmerged_entries() {
+        bail!("you must resolve all conflicts and add them before continuing")
+    }
+
+    let head = current_commit(repo)?;
+    let tree = write_tree(repo, &index)?;
+
+    if let Some(stopped) = read_state_hash(repo, STOPPED_FILE_NAME)? {
+        clear_stop(repo)?;
+        let entry = LogEntry::read(repo, stopped)?;
+        let message = std::fs::read_to_string(repo.merge_msg_path())
+            .map(|m| clean_message(&m))
+            .ok()
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| entry.message.to_string());
+        std::fs::remove_file(repo.merge_msg_path()).ok();
+
+        // the resolved changes might already be in HEAD, then there is nothing to commit
+        if tree != head.tree {
+            let log_message = format!("rebase (continue): {}", entry.subject());
+            let commit = write_commit(repo, tree, vec![head.hash], &message, Some(entry.author))?;
+            move_head(repo, &commit, &log_message).context("could not update HEAD")?;
+
+            // the command that stopped is the last one done
+            let done = read_lines(repo, DONE_FILE_NAME)?;
+            if let Some(line) = done.last()
+                && let Some(instruction) = Instruction::parse(repo, line)?
+                && let Some(outcome) = after_pick(repo, &instruction, var)?
+            {
+                return Ok(outcome);
+            }
+        }
+    } else if let Some(amended) = read_state_hash(repo, AMEND_FILE_NAME)? {
+        clear_stop(repo)?;
+        if head.hash == amended && tree != head.tree {
+            amend_head(
+                repo,
+                "amend",
+                head.parents.to_vec(),
+                &head.message,
+                head.author,
+            )?;
+        }
+    }
+
+    run(repo, var)
+}
+
+/// Skips the commit the rebase stopped at, discarding the changes in the index and the working
+/// tree, and executes the rest of the commands.
+///
+/// # Errors
+///
+/// This function will fail if there is no rebase in progress, or if any of the commands left
+/// fail.
+pub fn rebase_skip(repo: &Repository) -> Result<RebaseOutcome> {
+    rebase_skip_with(repo, &|name| std::env::var(name).ok())
+}
+
+/// Same as `rebase_skip`, reading environment variables through `var`.
+fn rebase_skip_with(
+    repo: &Repository,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<RebaseOutcome> {
+    ensure_rebase(repo)?;
+    let head = current_commit(repo)?;
+    move_to_commit(repo, ResetMode::Hard, head.hash, "rebase (skip)")
+        .context("could not reset to HEAD")?;
+    clear_stop(repo)?;
+    std::fs::remove_file(repo.merge_msg_path()).ok();
+
+    run(repo, var)
+}
+
+/// Stops the rebase in progress, going back to the branch and commit HEAD pointed to before it
+/// started and discarding the changes in the index and the working tree.
+///
+/// # Errors
+///
+/// This function will fail if there is no rebase in progress, or if HEAD, the index or the
+/// working tree could not be reset.
+pub fn rebase_abort(repo: &Repository) -> Result<()> {
+    ensure_rebase(repo)?;
+    let orig_head = read_state_hash(repo, ORIG_HEAD_FILE_NAME)?
+        .context("the rebase does not know the original HEAD")?;
+
+    let message = format!("rebase (abort): returning to {}", read_head_name(repo)?);
+    move_to_commit(repo, ResetMode::Hard, orig_head, &message)
+        .context("could not reset to original HEAD")?;
+    if let Some(branch) = read_branch_name(repo)? {
+        fs::set_current_branch(repo, &branch).context("could not go back to the branch")?;
+    }
+
+    std::fs::remove_file(repo.merge_msg_path()).ok();
+    remove_state(repo)
+}
+
+/// Executes the commands left in the todo file, moving each one to the done file once it has run
+/// or stopped the rebase, until one of them stops it or there are none left. A command that
+/// fails stays in the todo file, so continuing the rebase runs it again.
+fn run(repo: &Repository, var: &dyn Fn(&str) -> Option<String>) -> Result<RebaseOutcome> {
+    loop {
+        let todo = read_lines(repo, TODO_FILE_NAME)?;
+        let Some(line) = todo.first() else {
+            return finish(repo);
+        };
+        let instruction = Instruction::parse(repo, line)?;
+        // every commit after onto was created by the rebase, melding into onto would rewrite
+        // a commit of the upstream
+        if let Some(Instruction::Squash(commit) | Instruction::Fixup(commit)) = &instruction
+            && current_commit(repo)?.hash == read_onto(repo)?
+        {
+            bail!(
+                "cannot meld {} into a previous commit, the rebase has not created any yet",
+                commit
+            )
+        }
+
+        let Some(instruction) = instruction else {
+            mark_done(repo)?;
+            continue;
+        };
+        let commit = match &instruction {
+            Instruction::Drop(_) => {
+                mark_done(repo)?;
+                continue;
+            }
+            Instruction::Exec(command) => {
+                let status = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .current_dir(repo.working_tree())
+                    .status()
+                    .context(format!("could not run {}", command))?;
+                mark_done(repo)?;
+                if !status.success() {
+                    return Ok(RebaseOutcome::ExecFailed(command.clone()));
+                }
+                continue;
+            }
+            Instruction::Pick(commit)
+            | Instruction::Reword(commit)
+            | Instruction::Edit(commit)
+            | Instruction::Squash(commit)
+            | Instruction::Fixup(commit) => commit.clone(),
+        };
+
+        let step = Step {
+            action: Action::Pick,
+            commit: commit.clone(),
+            subject: String::new(),
+        };
+        let result = apply_step(repo, &step, "rebase", "rebasing")?;
+        mark_done(repo)?;
+        match result {
+            StepResult::Conflicts(paths) => {
+                write_state_hash(repo, STOPPED_FILE_NAME, &commit)?;
+                let entry = LogEntry::read(repo, commit.clone())?;
+                write_author_script(repo, &entry.author)?;
+                let message_path = repo.rebase_merge_path().join(MESSAGE_FILE_NAME);
+                std::fs::write(message_path, entry.message.as_bytes())
+                    .context("could not write rebase message")?;
+                fs::write_ref(repo, Constants::REBASE_HEAD_FILE_NAME, &commit)
+                    .context("could not write REBASE_HEAD")?;
+                return Ok(RebaseOutcome::Conflicts { commit, paths });
+            }
+            // the changes are already upstream, so the commit is dropped
+            StepResult::Empty => {}
+            StepResult::Committed(_) => {
+                if let Some(outcome) = after_pick(repo, &instruction, var)? {
+                    return Ok(outcome);
+                }
+            }
+        }
+    }
+}
+
+/// Finishes what `instruction` does once its commit is applied as the new HEAD: rewording it,
+/// melding it into the previous commit, or stopping to edit it.
+fn after_pick(
+    repo: &Repository,
+    instruction: &Instruction,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<Option<RebaseOutcome>> {
+    match instruction {
+        Instruction::Reword(_) => {
+            let head = current_commit(repo)?;
+            let message = edit_message(repo, &head.message, var)?;
+            amend_head(repo, "reword", head.parents.to_vec(), &message, head.author)?;
+        }
+        Instruction::Squash(_) | Instruction::Fixup(_) => {
+            let head = current_commit(repo)?;
+            let Some(previous) = head.parents.first() else {
+                bail!(
+                    "there is no previous commit to meld {} into",
+                    head.short_hash()
+                )
+            };
+            let previous = LogEntry::read(repo, previous.clone())?;
+            let (action, message) = match instruction {
+                Instruction::Squash(_) => (
+                    "squash",
+                    edit_message(
+                        repo,
+                        &format!("{}\n\n{}", previous.message.trim_end(), head.message),
+                        var,
+                    )?,
+                ),
+                _ => ("fixup", previous.message.to_string()),
+            };
+            amend_head(
+                repo,
+                action,
+                previous.parents.to_vec(),
+                &message,
+                previous.author,
+            )?;
+        }
+        Instruction::Edit(commit) => {
+            let head = current_commit(repo)?;
+            write_state_hash(repo, AMEND_FILE_NAME, &head.hash)?;
+            return Ok(Some(RebaseOutcome::Edit(commit.clone())));
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// Moves the first command of the todo file to the done file.
+fn mark_done(repo: &Repository) -> Result<()> {
+    let mut todo = read_lines(repo, TODO_FILE_NAME)?;
+    if todo.is_empty() {
+        return Ok(());
+    }
+    let line = todo.remove(0);
+    write_lines(repo, TODO_FILE_NAME, &todo)?;
+    let mut done = read_lines(repo, DONE_FILE_NAME)?;
+    done.push(line);
+    write_lines(repo, DONE_FILE_NAME, &done)
+}
+
+/// Moves the branch being rebased to HEAD and checks it out again, removing the rebase state.
+fn finish(repo: &Repository) -> Result<RebaseOutcome> {
+    let head = current_commit(repo)?.hash;
+    if let Some(branch) = read_branch_name(repo)? {
+        let name = format!(
+            "{}/{}/{}",
+            Constants::REFS_FOLDER_NAME,
+            Constants::HEADS_FOLDER_NAME,
+            branch
+        );
+        fs::write_ref(repo, &name, &head).context("could not update rebased branch")?;
+        fs::set_current_branch(repo, &branch).context("could not check out rebased branch")?;
+        let message = format!("rebase (finish): returning to {}", name);
+        log_head_move(repo, Some(&head), &head, &message)?;
+    }
+    if let Some(orig_head) = read_state_hash(repo, ORIG_HEAD_FILE_NAME)? {
+        fs::write_ref(repo, Constants::ORIG_HEAD_FILE_NAME, &orig_head)
+            .context("could not write ORIG_HEAD")?;
+    }
+
+    remove_state(repo)?;
+    Ok(RebaseOutcome::Done(head))
+}
+
+/// Replaces HEAD with a commit of the index with the given parents, message and author. `action`
+/// is the rebase command it is recorded as in the HEAD reflog.
+fn amend_head(
+    repo: &Repository,
+    action: &str,
+    parents: Vec<Hash>,
+    message: &str,
+    author: CommitUser,
+) -> Result<Hash> {
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let tree = write_tree(repo, &index)?;
+    let commit = write_commit(repo, tree, parents, message, Some(author))?;
+    let subject = message.lines().next().unwrap_or_default();
+    let log_message = format!("rebase ({}): {}", action, subject);
+    move_head(repo, &commit, &log_message).context("could not update HEAD")?;
+    Ok(commit)
+}
+
+/// Opens the todo file in the editor and checks the result, removing the comments.
+fn validate_todo(repo: &Repository, var: &dyn Fn(&str) -> Option<String>) -> Result<()> {
+    let path = repo.rebase_merge_path().join(TODO_FILE_NAME);
+    edit_file(repo, &path, true, var)?;
+
+    let lines = read_lines(repo, TODO_FILE_NAME)?;
+    let mut instructions = Vec::new();
+    for line in lines.iter() {
+        instructions.extend(Instruction::parse(repo, line)?);
+    }
+    match instructions.first() {
+        None => bail!("nothing to do"),
+        Some(Instruction::Squash(_) | Instruction::Fixup(_)) => {
+            bail!("cannot squash or fixup without a previous commit")
+        }
+        _ => {}
+    }
+    write_lines(repo, TODO_FILE_NAME, &lines)
+}
+
+/// Opens `message` in the editor, returning it without comments once the editor exits.
+fn edit_message(
+    repo: &Repository,
+    message: &str,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<String> {
+    let path = repo.git_dir().join(COMMIT_EDITMSG_FILE_NAME);
+    std::fs::write(&path, format!("{}\n{}", message.trim_end(), MESSAGE_HELP))
+        .context("could not write message to edit")?;
+    edit_file(repo, &path, false, var)?;
+
+    let edited = std::fs::read_to_string(&path).context("could not read edited message")?;
+    let edited = clean_message(&edited);
+    if edited.is_empty() {
+        bail!("aborting commit due to empty commit message")
+    }
+    Ok(edited)
+}
+
+/// Opens the file at `path` with the editor (see `find_editor`), waiting for it to exit.
+/// Environment variables are read through `var`.
+fn edit_file(
+    repo: &Repository,
+    path: &Path,
+    sequence: bool,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<()> {
+    let config = Config::load(repo).context("could not read config")?;
+    let editor = find_editor(&config, sequence, var);
+
+    // through the shell, so the editor can have arguments
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .context(format!("could not run editor '{}'", editor))?;
+    if !status.success() {
+        bail!("there was a problem with the editor '{}'", editor)
+    }
+    Ok(())
+}
+
+/// Returns the editor like git does: the first one set in `GIT_EDITOR`, `core.editor`, `VISUAL`
+/// or `EDITOR`, or `vi`. For todo files (`sequence`), `GIT_SEQUENCE_EDITOR` and `sequence.editor`
+/// are checked before them. Environment variables are read through `var`.
+fn find_editor(config: &Config, sequence: bool, var: impl Fn(&str) -> Option<String>) -> String {
+    let env = |name: &str| var(name).filter(|v| !v.is_empty());
+    let config_value = |name: &str| config.get(name).map(String::from);
+
+    let mut editor = None;
+    if sequence {
+        editor = env("GIT_SEQUENCE_EDITOR").or_else(|| config_value("sequence.editor"));
+    }
+    editor
+        .or_else(|| env("GIT_EDITOR"))
+        .or_else(|| config_value("core.editor"))
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Returns the commits the rebase in progress started from, the ones it stopped at and the ones
+/// its commands use, or nothing if there is no rebase in progress.
+pub(super) fn state_commits(repo: &Repository) -> Result<Vec<Hash>> {
+    if !repo.rebase_merge_path().is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut commits = Vec::new();
+    for name in [
+        ORIG_HEAD_FILE_NAME,
+        Constants::REBASE_ONTO_FILE_NAME,
+        STOPPED_FILE_NAME,
+        AMEND_FILE_NAME,
+    ] {
+        commits.extend(read_state_hash(repo, name)?);
+    }
+    for name in [TODO_FILE_NAME, DONE_FILE_NAME] {
+        for line in read_lines(repo, name).unwrap_or_default() {
+            if let Ok(Some(instruction)) = Instruction::parse(repo, &line) {
+                commits.extend(instruction.commit().cloned());
+            }
+        }
+    }
+    Ok(commits)
+}
+
+fn ensure_rebase(repo: &Repository) -> Result<()> {
+    if !repo.rebase_merge_path().is_dir() {
+        bail!("there is no rebase in progress")
+    }
+    Ok(())
+}
+
+fn read_onto(repo: &Repository) -> Result<Hash> {
+    read_state_hash(repo, Constants::REBASE_ONTO_FILE_NAME)?
+        .context("the rebase does not know the commit it started onto")
+}
+
+fn current_commit(repo: &Repository) -> Result<LogEntry> {
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("HEAD has no commits")?;
+    LogEntry::read(repo, head)
+}
+
+/// Returns the full name of the branch being rebased, or `detached HEAD` if HEAD was detached at
+/// the start.
+fn read_head_name(repo: &Repository) -> Result<String> {
+    let path = repo
+        .rebase_merge_path()
+        .join(Constants::REBASE_HEAD_NAME_FILE_NAME);
+    let name = std::fs::read_to_string(path).context("could not read rebase head name")?;
+    Ok(name.trim_end().to_string())
+}
+
+/// Returns the name of the branch being rebased, or `None` if HEAD was detached at the start.
+fn read_branch_name(repo: &Repository) -> Result<Option<String>> {
+    let name = read_head_name(repo)?;
+    let prefix = format!(
+        "{}/{}/",
+        Constants::REFS_FOLDER_NAME,
+        Constants::HEADS_FOLDER_NAME
+    );
+    Ok(name.strip_prefix(&prefix).map(String::from))
+}
+
+fn state_ref_name(name: &str) -> String {
+    format!("{}/{}", Constants::REBASE_MERGE_FOLDER_NAME, name)
+}
+
+fn read_state_hash(repo: &Repository, name: &str) -> Result<Option<Hash>> {
+    fs::read_ref(repo, &state_ref_name(name))
+}
+
+fn write_state_hash(repo: &Repository, name: &str, hash: &Hash) -> Result<()> {
+    fs::write_ref(repo, &state_ref_name(name), hash)
+}
+
+/// Returns the lines of a file of the rebase folder, without comments and empty lines.
+fn read_lines(repo: &Repository, name: &str) -> Result<Vec<String>> {
+    let path = repo.rebase_merge_path().join(name);
+    let content = std::fs::read_to_string(&path).context(format!("could not read {:?}", path))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+fn write_lines(repo: &Repository, name: &str, lines: &[String]) -> Result<()> {
+    let path = repo.rebase_merge_path().join(name);
+    let content: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    std::fs::write(&path, content).context(format!("could not write {:?}", path))
+}
+
+/// Writes the author of the commit that stopped like git does:
+///
+/// `GIT_AUTHOR_NAME='{name}'`, `GIT_AUTHOR_EMAIL='{email}'` and
+/// `GIT_AUTHOR_DATE='@{timestamp} {timezone}'`, one per line.
+fn write_author_script(repo: &Repository, author: &CommitUser) -> Result<()> {
+    let header = author.to_header_line()?;
+    let mut date = header.trim_end().rsplitn(3, ' ');
+    let timezone = date.next().unwrap_or_default();
+    let timestamp = date.next().unwrap_or_default();
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+
+    let script = format!(
+        "GIT_AUTHOR_NAME={}\nGIT_AUTHOR_EMAIL={}\nGIT_AUTHOR_DATE={}\n",
+        quote(author.name()),
+        quote(author.email()),
+        quote(&format!("@{} {}", timestamp, timezone))
+    );
+    let path = repo.rebase_merge_path().join(AUTHOR_SCRIPT_FILE_NAME);
+    std::fs::write(&path, script).context(format!("could not write {:?}", path))
+}
+
+/// Removes the files that record where the rebase stopped.
+fn clear_stop(repo: &Repository) -> Result<()> {
+    for path in [
+        repo.rebase_merge_path().join(STOPPED_FILE_NAME),
+        repo.rebase_merge_path().join(AMEND_FILE_NAME),
+        repo.rebase_merge_path().join(AUTHOR_SCRIPT_FILE_NAME),
+        repo.rebase_merge_path().join(MESSAGE_FILE_NAME),
+        repo.git_dir().join(Constants::REBASE_HEAD_FILE_NAME),
+    ] {
+        if path.is_file() {
+            std::fs::remove_file(&path).context(format!("could not remove {:?}", path))?;
+        }
+    }
+    Ok(())
+}
+
+fn remove_state(repo: &Repository) -> Result<()> {
+    clear_stop(repo)?;
+    let folder = repo.rebase_merge_path();
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).context("could not remove rebase folder")?;
+    }
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::merge::read_tree_files;
+    use crate::commands::test_utils::*;
+    use crate::commands::{checkout, create_branch};
+    use crate::object::Object;
+
+    /// Commits `f`, then a commit for every `(path, content, subject)` on a new branch called
+    /// `topic` and `upstream` on a new branch called `upstream`, ending on `topic`. Returns the
+    /// commits of `topic`.
+    fn setup(repo: &Repository, topic: &[(&str, &str, &str)], upstream: (&str, &str)) -> Vec<Hash> {
+        commit_files(repo, &[("f", "1\n2\n3\n")], "base");
+        create_branch(repo, "upstream", None).unwrap();
+        checkout(repo, "upstream").unwrap();
+        commit_files(repo, &[upstream], "upstream");
+
+        create_branch(repo, "topic", Some("upstream~1")).unwrap();
+        checkout(repo, "topic").unwrap();
+        topic
+            .iter()
+            .map(|(path, content, subject)| commit_files(repo, &[(path, content)], subject))
+            .collect()
+    }
+
+    /// Makes the editor of todo files and messages run these `sed` expressions.
+    fn set_editor(repo: &Repository, name: &str, expressions: &str) {
+        let script = repo.git_dir().join(format!("{}.sh", name));
+        std::fs::write(&script, format!("sed -i {} \"$1\"\n", expressions)).unwrap();
+        set_config_value(repo, name, &format!("sh {}", script.to_string_lossy()));
+    }
+
+    fn subjects(repo: &Repository, count: usize) -> Vec<String> {
+        let mut hash = current_commit(repo).unwrap().hash;
+        let mut subjects = Vec::new();
+        for _ in 0..count {
+            let entry = LogEntry::read(repo, hash).unwrap();
+            subjects.push(entry.subject().to_string());
+            let Some(parent) = entry.parents.first() else {
+                break;
+            };
+            hash = parent.clone();
+        }
+        subjects
+    }
+
+    fn head_branch(repo: &Repository) -> Option<String> {
+        fs::get_current_branch_name(repo).unwrap()
+    }
+
+    // The commands with an empty environment, so the editors of the one running the tests are
+    // never used instead of the ones set in the config
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn rebase(repo: &Repository, upstream: &str, interactive: bool) -> Result<RebaseOutcome> {
+        rebase_with(repo, upstream, interactive, &no_env)
+    }
+
+    fn rebase_continue(repo: &Repository) -> Result<RebaseOutcome> {
+        rebase_continue_with(repo, &no_env)
+    }
+
+    fn rebase_skip(repo: &Repository) -> Result<RebaseOutcome> {
+        rebase_skip_with(repo, &no_env)
+    }
+
+    #[test]
+    pub fn test_parse_instructions() {
+        let repo = create_repository("rebase-parse");
+        let commit = commit_files(&repo, &[("f", "1\n")], "base");
+        let short = &commit.to_string()[..7];
+
+        let parse = |line: &str| Instruction::parse(&repo, line);
+        for (line, expected) in [
+            (
+                format!("pick {} base", short),
+                Instruction::Pick(commit.clone()),
+            ),
+            (format!("p {}", commit), Instruction::Pick(commit.clone())),
+            (
+                format!("r {} base", short),
+                Instruction::Reword(commit.clone()),
+            ),
+            (format!("edit {}", short), Instruction::Edit(commit.clone())),
+            (format!("s {}", short), Instruction::Squash(commit.clone())),
+            (
+                format!("fixup {}", short),
+                Instruction::Fixup(commit.clone()),
+            ),
+            (
+                format!("  d {}  ", short),
+                Instruction::Drop(commit.clone()),
+            ),
+            (
+                "x make  test".into(),
+                Instruction::Exec("make  test".into()),
+            ),
+            ("exec echo".into(), Instruction::Exec("echo".into())),
+        ] {
+            assert_eq!(parse(&line).unwrap(), Some(expected), "{}", line);
+        }
+        assert_eq!(parse("# pick it").unwrap(), None);
+        assert_eq!(parse("   ").unwrap(), None);
+        for line in [
+            format!("frob {}", short),
+            "pick".into(),
+            "exec".into(),
+            "pick nothing".into(),
+        ] {
+            assert!(parse(&line).is_err(), "{}", line);
+        }
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_find_editor() {
+        let config: Config = "[core]\n\teditor = nano\n[sequence]\n\teditor = sed\n"
+            .parse()
+            .unwrap();
+        let no_config = Config::default();
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        assert_eq!(find_editor(&config, false, env(&[])), "nano");
+        assert_eq!(find_editor(&config, true, env(&[])), "sed");
+        assert_eq!(
+            find_editor(&config, false, env(&[("GIT_EDITOR", "emacs")])),
+            "emacs"
+        );
+        assert_eq!(
+            find_editor(&config, true, env(&[("GIT_EDITOR", "emacs")])),
+            "sed"
+        );
+        assert_eq!(
+            find_editor(&config, true, env(&[("GIT_SEQUENCE_EDITOR", "cat")])),
+            "cat"
+        );
+        assert_eq!(
+            find_editor(
+                &no_config,
+                false,
+                env(&[("VISUAL", "code"), ("EDITOR", "ed")])
+            ),
+            "code"
+        );
+        assert_eq!(
+            find_editor(
+                &no_config,
+                true,
+                env(&[("GIT_EDITOR", ""), ("EDITOR", "ed")])
+            ),
+            "ed"
+        );
+        assert_eq!(find_editor(&no_config, true, env(&[])), DEFAULT_EDITOR);
+    }
+
+    #[test]
+    pub fn test_rebase_onto_upstream() {
+        let repo = create_repository("rebase-upstream");
+        let commits = setup(
+            &repo,
+            &[("a", "a\n", "add a"), ("b", "b\n", "add b")],
+            ("u", "u\n"),
+        );
+
+        let RebaseOutcome::Done(head) = rebase(&repo, "upstream", false).unwrap() else {
+            panic!("the commits should apply cleanly")
+        };
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+        assert_eq!(fs::read_branch(&repo, "topic").unwrap(), Some(head));
+        assert_eq!(subjects(&repo, 3), vec!["add b", "add a", "upstream"]);
+        assert_eq!(read_file(&repo, "u").as_deref(), Some("u\n"));
+        assert_eq!(
+            fs::read_ref(&repo, Constants::ORIG_HEAD_FILE_NAME).unwrap(),
+            commits.last().cloned()
+        );
+        assert!(!repo.rebase_merge_path().exists());
+        assert_eq!(
+            rebase(&repo, "upstream", false).unwrap(),
+            RebaseOutcome::UpToDate
+        );
+
+        checkout(&repo, "upstream").unwrap();
+        let topic = fs::read_branch(&repo, "topic").unwrap().unwrap();
+        assert_eq!(
+            rebase(&repo, "topic", false).unwrap(),
+            RebaseOutcome::FastForward(topic)
+        );
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_interactive_commands() {
+        let repo = create_repository("rebase-interactive");
+        let topic = [
+            ("a", "a\n", "add a"),
+            ("b", "b\n", "add b"),
+            ("c", "c\n", "add c"),
+            ("d", "d\n", "add d"),
+        ];
+        setup(&repo, &topic, ("u", "u\n"));
+        set_editor(
+            &repo,
+            "sequence.editor",
+            "-e 1s/^pick/reword/ -e 2s/^pick/squash/ -e 3s/^pick/drop/ \
+             -e '3a exec touch exec-ran' -e 4s/^pick/fixup/",
+        );
+        set_editor(&repo, "core.editor", "1s/^/new-/");
+
+        assert!(matches!(
+            rebase(&repo, "upstream", true).unwrap(),
+            RebaseOutcome::Done(_)
+        ));
+        let head = current_commit(&repo).unwrap();
+        assert_eq!(head.message.as_ref(), "new-new-add a\n\nadd b\n");
+        assert_eq!(subjects(&repo, 2)[1], "upstream");
+        for (path, exists) in [("a", true), ("b", true), ("c", false), ("d", true)] {
+            assert_eq!(read_file(&repo, path).is_some(), exists, "{}", path);
+        }
+        assert!(read_file(&repo, "exec-ran").is_some());
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_interactive_edit() {
+        let repo = create_repository("rebase-edit");
+        let commits = setup(
+            &repo,
+            &[("a", "a\n", "add a"), ("b", "b\n", "add b")],
+            ("u", "u\n"),
+        );
+        set_editor(&repo, "sequence.editor", "1s/^pick/edit/");
+
+        assert_eq!(
+            rebase(&repo, "upstream", true).unwrap(),
+            RebaseOutcome::Edit(commits[0].clone())
+        );
+        assert_eq!(head_branch(&repo), None);
+        assert_eq!(read_file(&repo, "b"), None);
+
+        write_file(&repo, "a", "amended\n");
+        add_files(&repo, &["a"]);
+        assert!(matches!(
+            rebase_continue(&repo).unwrap(),
+            RebaseOutcome::Done(_)
+        ));
+        assert_eq!(subjects(&repo, 3), vec!["add b", "add a", "upstream"]);
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("amended\n"));
+        let amended = current_commit(&repo).unwrap().parents[0].clone();
+        let files = read_tree_files(&repo, amended).unwrap();
+        let Object::Blob { data } =
+            fs::object::read_object(&repo, files[Path::new("a")].hash.clone()).unwrap()
+        else {
+            panic!("a should be a file")
+        };
+        assert_eq!(data.as_ref(), b"amended\n");
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_continue_after_failed_pick() {
+        let repo = create_repository("rebase-failed-pick");
+        let commits = setup(
+            &repo,
+            &[("a", "a\n", "add a"), ("b", "b\n", "add b")],
+            ("u", "u\n"),
+        );
+        set_editor(&repo, "sequence.editor", "1s/^pick/edit/");
+        assert_eq!(
+            rebase(&repo, "upstream", true).unwrap(),
+            RebaseOutcome::Edit(commits[0].clone())
+        );
+
+        // picking the next commit would overwrite the untracked file
+        write_file(&repo, "b", "local\n");
+        let error = rebase_continue(&repo).unwrap_err();
+        assert!(
+            format!("{:?}", error).contains("overwritten by rebase"),
+            "{:?}",
+            error
+        );
+        assert_eq!(subjects(&repo, 2), vec!["add a", "upstream"]);
+
+        std::fs::remove_file(repo.working_tree().join("b")).unwrap();
+        assert!(matches!(
+            rebase_continue(&repo).unwrap(),
+            RebaseOutcome::Done(_)
+        ));
+        assert_eq!(subjects(&repo, 3), vec!["add b", "add a", "upstream"]);
+        assert_eq!(read_file(&repo, "b").as_deref(), Some("b\n"));
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_continue_after_conflict() {
+        let repo = create_repository("rebase-continue");
+        let commits = setup(
+            &repo,
+            &[("f", "topic\n", "change f"), ("b", "b\n", "add b")],
+            ("f", "upstream\n"),
+        );
+
+        assert_eq!(
+            rebase(&repo, "upstream", false).unwrap(),
+            RebaseOutcome::Conflicts {
+                commit: commits[0].clone(),
+                paths: vec![PathBuf::from("f")],
+            }
+        );
+        assert_eq!(
+            fs::read_ref(&repo, Constants::REBASE_HEAD_FILE_NAME).unwrap(),
+            Some(commits[0].clone())
+        );
+        assert!(rebase_continue(&repo).is_err());
+
+        write_file(&repo, "f", "both\n");
+        add_files(&repo, &["f"]);
+        assert!(matches!(
+            rebase_continue(&repo).unwrap(),
+            RebaseOutcome::Done(_)
+        ));
+        assert_eq!(subjects(&repo, 3), vec!["add b", "change f", "upstream"]);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("both\n"));
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+        assert!(!repo.rebase_merge_path().exists());
+        assert!(
+            !repo
+                .git_dir()
+                .join(Constants::REBASE_HEAD_FILE_NAME)
+                .exists()
+        );
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_skip() {
+        let repo = create_repository("rebase-skip");
+        setup(
+            &repo,
+            &[("f", "topic\n", "change f"), ("b", "b\n", "add b")],
+            ("f", "upstream\n"),
+        );
+
+        rebase(&repo, "upstream", false).unwrap();
+        assert!(matches!(
+            rebase_skip(&repo).unwrap(),
+            RebaseOutcome::Done(_)
+        ));
+        assert_eq!(subjects(&repo, 2), vec!["add b", "upstream"]);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("upstream\n"));
+        assert!(!repo.rebase_merge_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_abort() {
+        let repo = create_repository("rebase-abort");
+        let commits = setup(
+            &repo,
+            &[("b", "b\n", "add b"), ("f", "topic\n", "change f")],
+            ("f", "upstream\n"),
+        );
+
+        assert!(rebase_abort(&repo).is_err());
+        let outcome = rebase(&repo, "upstream", false).unwrap();
+        assert!(matches!(outcome, RebaseOutcome::Conflicts { .. }));
+        assert_eq!(head_branch(&repo), None);
+
+        rebase_abort(&repo).unwrap();
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+        assert_eq!(current_commit(&repo).unwrap().hash, commits[1]);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("topic\n"));
+        assert!(
+            !fs::index::read_index_file(&repo)
+                .unwrap()
+                .has_unmerged_entries()
+        );
+        assert!(!repo.rebase_merge_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_squash_needs_created_commit() {
+        let repo = create_repository("rebase-squash-upstream");
+        setup(
+            &repo,
+            &[("a", "a\n", "add a"), ("b", "b\n", "add b")],
+            ("u", "u\n"),
+        );
+        let upstream = fs::read_branch(&repo, "upstream").unwrap().unwrap();
+        set_editor(
+            &repo,
+            "sequence.editor",
+            "-e 1s/^pick/drop/ -e 2s/^pick/squash/",
+        );
+
+        assert!(rebase(&repo, "upstream", true).is_err());
+        assert_eq!(current_commit(&repo).unwrap().hash, upstream);
+        assert_eq!(fs::read_branch(&repo, "upstream").unwrap(), Some(upstream));
+
+        rebase_abort(&repo).unwrap();
+        assert_eq!(head_branch(&repo).as_deref(), Some("topic"));
+        assert_eq!(read_file(&repo, "b").as_deref(), Some("b\n"));
+
+        remove_repository(&repo);
+    }
+}
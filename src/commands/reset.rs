@@ -1,48 +1,454 @@
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
-use crate::args::ResetCommand;
-use crate::fs;
 use crate::hashing::Hash;
-use crate::index::Index;
+use crate::index::builder::IndexBuilder;
+use crate::index::{Index, IndexEntry, IndexEntryCache};
+use crate::object::tree::TreeEntry;
+use crate::repository::Repository;
+use crate::utils::nums::from_octal;
+use crate::{Constants, fs};
 
-pub fn reset(command: Option<&ResetCommand>) -> Result<String> {
-    match command {
-        Some(cmd) => match cmd {
-            ResetCommand::Files { files } => reset_files(files),
-            ResetCommand::Commit { hard, commit_hash } => {
-                let hash = Hash::from_str(&commit_hash)
-                    .context("hash provided was not a valid hexadecimal hash string")?;
+use super::commit::move_head;
+use super::log::LogEntry;
+use super::status::read::{CommitData, read_commit_data, read_commit_entries};
 
-                reset_to_commit(*hard, hash)
-            }
-        },
+/// The different ways a reset can be performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+/// Performs a mixed reset to the last commit, which unstages every change. If there are no
+/// commits yet, the index is emptied instead.
+///
+/// # Returns
+///
+/// The last commit, or `None` if there were no commits.
+///
+/// # Errors
+///
+/// This function will fail if the last commit or the index could not be read or written.
+pub fn reset_head(repo: &Repository) -> Result<Option<LogEntry>> {
+    let last_commit = fs::get_last_commit_hash(repo).context("could not get las commit hash")?;
+    match last_commit {
+        Some(hash) => reset_to_commit(repo, ResetMode::Mixed, hash).map(Some),
         None => {
-            // the reset command, without arguments, resets to the previous commit
-            let last_commit =
-                fs::get_last_commit_hash().context("could not get las commit hash")?;
-            match last_commit {
-                Some(hash) => reset_to_commit(false, hash),
-                None => {
-                    // there is no previous commit, so we can just reset the index
-                    fs::index::write_index_file(Index::default())
-                        .context("could not write index file")?;
-
-                    Ok("cleaned index file\n".into())
-                }
+            // there is no previous commit, so we can just reset the index
+            fs::index::write_index_file(repo, Index::default())
+                .context("could not write index file")?;
+
+            Ok(None)
+        }
+    }
+}
+
+/// Restores the index entries of the given paths to the version in the last commit, removing
+/// them from the index if they were not part of it. Directories are expanded to every tracked
+/// path beneath them.
+///
+/// # Returns
+///
+/// Every path that was reset, sorted.
+///
+/// # Errors
+///
+/// This function will fail if a path is outside of the repository or does not match any tracked
+/// file, or if the index or the last commit could not be read.
+pub fn reset_files(repo: &Repository, files: &[OsString]) -> Result<Vec<PathBuf>> {
+    let commit_entries: HashMap<PathBuf, TreeEntry> =
+        match fs::get_last_commit_hash(repo).context("could not get last commit hash")? {
+            Some(hash) => read_commit_entries(repo, hash)
+                .context("could not read last commit entries")?
+                .into_iter()
+                .map(|e| (e.path.clone(), e))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+
+    // Tracked paths are the ones either in the index or in the last commit
+    let tracked: HashSet<PathBuf> = index
+        .entries()
+        .map(|ie| ie.path().to_owned())
+        .chain(commit_entries.keys().cloned())
+        .collect();
+
+    let mut to_reset: HashSet<&PathBuf> = HashSet::new();
+    for f in files {
+        let path = repo
+            .relative_path(Path::new(f))
+            .context(format!("{:?} is outside of the repository", f))?;
+
+        // an empty path represents the root, so it matches everything
+        let matched: Vec<&PathBuf> = tracked.iter().filter(|p| p.starts_with(&path)).collect();
+        if matched.is_empty() {
+            bail!("path {:?} did not match any tracked file", f)
+        }
+        to_reset.extend(matched);
+    }
+
+    let mut index_builder = IndexBuilder::from(index);
+    for path in to_reset.iter() {
+        let previous = index_builder.remove_index_entry_by_path(path);
+
+        if let Some(e) = commit_entries.get(*path) {
+            index_builder.add_index_entry(match previous {
+                Some(ie) if ie.object_hash() == e.hash => ie,
+                _ => IndexEntry::new(from_octal(e.mode), e.path.clone(), e.hash.clone()),
+            });
+        }
+    }
+
+    fs::index::write_index_file(repo, index_builder.build())
+        .context("could not write index file")?;
+
+    let mut reset: Vec<PathBuf> = to_reset.into_iter().cloned().collect();
+    reset.sort();
+    Ok(reset)
+}
+
+/// Makes the current branch point to `commit_hash`. Depending on `mode`:
+/// - `Soft`: only the branch is moved.
+/// - `Mixed`: the index is also rebuilt from the commit's tree.
+/// - `Hard`: the working tree is also rewritten to match the commit's tree.
+///
+/// The commit HEAD pointed to before is saved as `ORIG_HEAD`, and the move is recorded in the
+/// HEAD reflog.
+///
+/// # Returns
+///
+/// The commit the branch points to now.
+///
+/// # Errors
+///
+/// This function will fail if `commit_hash` is not the hash of a commit, or if the branch, index,
+/// working tree, `ORIG_HEAD` or reflog could not be updated.
+pub fn reset_to_commit(repo: &Repository, mode: ResetMode, commit_hash: Hash) -> Result<LogEntry> {
+    let previous = fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
+    let message = format!("reset: moving to {}", commit_hash);
+    let commit = move_to_commit(repo, mode, commit_hash, &message)?;
+    if let Some(previous) = previous {
+        fs::write_ref(repo, Constants::ORIG_HEAD_FILE_NAME, &previous)
+            .context("could not write ORIG_HEAD")?;
+    }
+    Ok(commit)
+}
+
+/// Resets to `commit_hash` like `reset_to_commit` does, recording the move in the HEAD reflog
+/// with `message` instead and leaving `ORIG_HEAD` alone. Used by the operations that move HEAD
+/// as one of their steps.
+pub(super) fn move_to_commit(
+    repo: &Repository,
+    mode: ResetMode,
+    commit_hash: Hash,
+    message: &str,
+) -> Result<LogEntry> {
+    let commit =
+        LogEntry::read(repo, commit_hash.clone()).context("could not read commit to reset to")?;
+
+    // Has to be read before moving the branch, a hard reset needs to know which files were tracked
+    let previous_commit_data = read_commit_data(repo)
+        .context("could not read current commit data")?
+        .unwrap_or_default();
+
+    move_head(repo, &commit_hash, message).context("could not update current branch")?;
+
+    if mode != ResetMode::Soft {
+        let entries = read_commit_entries(repo, commit_hash.clone())
+            .context("could not read commit entries")?;
+        reset_index(repo, entries, mode == ResetMode::Hard, previous_commit_data)
+            .context("could not reset index")?;
+    }
+
+    Ok(commit)
+}
+
+/// Rewrites the index so it contains exactly the given tree entries, reusing the previous index
+/// entries that already pointed to the same objects.
+///
+/// If `working_tree` is set, the files in the working tree are also updated, and the files that
+/// were tracked (in the index or `previous_commit_data`) but are not in `entries` are removed.
+fn reset_index(
+    repo: &Repository,
+    entries: Vec<TreeEntry>,
+    working_tree: bool,
+    previous_commit_data: CommitData,
+) -> Result<()> {
+    let previous_index = fs::index::read_index_file(repo).context("could not read index file")?;
+    // the versions of files with conflicts are never reused, but they are still tracked
+    let (unmerged_entries, normal_entries): (Vec<IndexEntry>, Vec<IndexEntry>) = previous_index
+        .into_entries()
+        .partition(|ie| ie.is_unmerged());
+    let mut previous_entries: HashMap<PathBuf, IndexEntry> = normal_entries
+        .into_iter()
+        .map(|ie| (ie.path().to_owned(), ie))
+        .collect();
+
+    let root = repo.working_tree();
+    let target_paths: HashSet<PathBuf> = entries.iter().map(|e| e.path.clone()).collect();
+
+    let mut index_builder = IndexBuilder::new();
+    for e in entries {
+        let previous = previous_entries
+            .remove(&e.path)
+            .filter(|ie| ie.object_hash() == e.hash);
+        let full_path = root.join(&e.path);
+
+        match previous {
+            // A hard reset can only skip files that have not been touched since they were added
+            Some(ie) if !working_tree || file_matches_cache(&full_path, &ie.cache_data) => {
+                index_builder.add_index_entry(ie);
+            }
+            _ if working_tree => {
+                fs::working_tree::write_blob(repo, &e.path, e.mode, e.hash.clone())
+                    .context(format!("could not write {:?}", e.path))?;
+                index_builder.add_index_entry(
+                    IndexEntry::try_from_file(root, &e.path, e.hash)
+                        .context(format!("could not create index entry for {:?}", e.path))?,
+                );
+            }
+            _ => index_builder.add_index_entry(IndexEntry::new(from_octal(e.mode), e.path, e.hash)),
+        }
+    }
+
+    if working_tree {
+        for path in previous_entries
+            .into_keys()
+            .chain(unmerged_entries.into_iter().map(|ie| ie.into_path()))
+            .chain(previous_commit_data.into_keys())
+        {
+            if !target_paths.contains(&path) {
+                fs::working_tree::remove_file(repo, &path)
+                    .context(format!("could not remove {:?}", path))?;
             }
         }
     }
+
+    fs::index::write_index_file(repo, index_builder.build()).context("could not write index file")
 }
 
-#[allow(unused)]
-fn reset_files(files: &[OsString]) -> Result<String> {
-    Ok("files have been reset".into())
+/// Returns `true` if the file at `path` exists and its metadata matches `cache`.
+fn file_matches_cache(path: &std::path::Path, cache: &IndexEntryCache) -> bool {
+    std::fs::symlink_metadata(path)
+        .ok()
+        .and_then(|m| IndexEntryCache::try_from_metadata(m).ok())
+        .is_some_and(|c| c.matches_loose(cache))
 }
 
-#[allow(unused)]
-fn reset_to_commit(hard: bool, commit_hash: Hash) -> Result<String> {
-    Ok(format!("reset to commit {}", commit_hash))
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::{
+        add_files, commit_files, create_repository, read_file, remove_repository, write_file,
+    };
+    use crate::object::Object;
+
+    /// Returns the path and contents of every file in the index, sorted by path.
+    fn index_files(repo: &Repository) -> Vec<(String, String)> {
+        let index = fs::index::read_index_file(repo).unwrap();
+        let mut files: Vec<(String, String)> = index
+            .entries()
+            .map(|ie| {
+                let Object::Blob { data } =
+                    fs::object::read_object(repo, ie.object_hash()).unwrap()
+                else {
+                    panic!("{:?} should be a file", ie.path())
+                };
+                (
+                    ie.path().to_string_lossy().into_owned(),
+                    String::from_utf8_lossy(&data).into_owned(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Commits `a` and `b`, then changes `a`, removes `b` and adds `c` in a second commit, and
+    /// finally leaves an unstaged change in `a` and an untracked file. Returns both commits.
+    fn setup(repo: &Repository) -> (Hash, Hash) {
+        let first = commit_files(repo, &[("a", "1\n"), ("b", "1\n")], "first");
+        std::fs::remove_file(repo.working_tree().join("b")).unwrap();
+        let mut index_builder = IndexBuilder::from(fs::index::read_index_file(repo).unwrap());
+        index_builder.remove_index_entry_by_path(Path::new("b"));
+        fs::index::write_index_file(repo, index_builder.build()).unwrap();
+        let second = commit_files(repo, &[("a", "2\n"), ("c", "2\n")], "second");
+        write_file(repo, "a", "unstaged\n");
+        write_file(repo, "untracked", "untracked\n");
+        (first, second)
+    }
+
+    fn pairs(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_reset_soft() {
+        let repo = create_repository("reset-soft");
+        let (first, _) = setup(&repo);
+
+        assert_eq!(
+            reset_to_commit(&repo, ResetMode::Soft, first.clone())
+                .unwrap()
+                .hash,
+            first
+        );
+        assert_eq!(fs::get_last_commit_hash(&repo).unwrap(), Some(first));
+        assert_eq!(index_files(&repo), pairs(&[("a", "2\n"), ("c", "2\n")]));
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("unstaged\n"));
+        assert_eq!(read_file(&repo, "b"), None);
+        assert_eq!(read_file(&repo, "c").as_deref(), Some("2\n"));
+        assert_eq!(
+            read_file(&repo, "untracked").as_deref(),
+            Some("untracked\n")
+        );
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_reset_mixed() {
+        let repo = create_repository("reset-mixed");
+        let (first, _) = setup(&repo);
+
+        assert_eq!(
+            reset_to_commit(&repo, ResetMode::Mixed, first.clone())
+                .unwrap()
+                .hash,
+            first
+        );
+        assert_eq!(fs::get_last_commit_hash(&repo).unwrap(), Some(first));
+        assert_eq!(index_files(&repo), pairs(&[("a", "1\n"), ("b", "1\n")]));
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("unstaged\n"));
+        assert_eq!(read_file(&repo, "b"), None);
+        assert_eq!(read_file(&repo, "c").as_deref(), Some("2\n"));
+        assert_eq!(
+            read_file(&repo, "untracked").as_deref(),
+            Some("untracked\n")
+        );
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_reset_hard() {
+        let repo = create_repository("reset-hard");
+        let (first, second) = setup(&repo);
+
+        assert_eq!(
+            reset_to_commit(&repo, ResetMode::Hard, first.clone())
+                .unwrap()
+                .hash,
+            first
+        );
+        assert_eq!(fs::get_last_commit_hash(&repo).unwrap(), Some(first));
+        assert_eq!(index_files(&repo), pairs(&[("a", "1\n"), ("b", "1\n")]));
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("1\n"));
+        assert_eq!(read_file(&repo, "b").as_deref(), Some("1\n"));
+        assert_eq!(read_file(&repo, "c"), None);
+        assert_eq!(
+            read_file(&repo, "untracked").as_deref(),
+            Some("untracked\n")
+        );
+
+        // moving forward again brings back the files of the later commit
+        reset_to_commit(&repo, ResetMode::Hard, second.clone()).unwrap();
+        assert_eq!(fs::get_last_commit_hash(&repo).unwrap(), Some(second));
+        assert_eq!(index_files(&repo), pairs(&[("a", "2\n"), ("c", "2\n")]));
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("2\n"));
+        assert_eq!(read_file(&repo, "b"), None);
+        assert_eq!(read_file(&repo, "c").as_deref(), Some("2\n"));
+
+        remove_repository(&repo);
+    }
+
+    /// Returns the arguments for `reset_files` as paths under the working tree.
+    fn paths(repo: &Repository, files: &[&str]) -> Vec<OsString> {
+        files
+            .iter()
+            .map(|p| repo.working_tree().join(p).into_os_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_reset_files_restores_entry_from_head() {
+        let repo = create_repository("reset-files-restore");
+        commit_files(&repo, &[("a", "1\n"), ("b", "1\n")], "first");
+        write_file(&repo, "a", "2\n");
+        write_file(&repo, "b", "2\n");
+        add_files(&repo, &["a", "b"]);
+
+        let reset = reset_files(&repo, &paths(&repo, &["a"])).unwrap();
+        assert_eq!(reset, vec![PathBuf::from("a")]);
+        assert_eq!(index_files(&repo), pairs(&[("a", "1\n"), ("b", "2\n")]));
+        // the working tree is left untouched
+        assert_eq!(read_file(&repo, "a").as_deref(), Some("2\n"));
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_reset_files_removes_path_not_in_head() {
+        let repo = create_repository("reset-files-remove");
+        commit_files(&repo, &[("a", "1\n")], "first");
+        write_file(&repo, "new", "new\n");
+        add_files(&repo, &["new"]);
+
+        let reset = reset_files(&repo, &paths(&repo, &["new"])).unwrap();
+        assert_eq!(reset, vec![PathBuf::from("new")]);
+        assert_eq!(index_files(&repo), pairs(&[("a", "1\n")]));
+        assert_eq!(read_file(&repo, "new").as_deref(), Some("new\n"));
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_reset_files_expands_directory() {
+        let repo = create_repository("reset-files-directory");
+        commit_files(
+            &repo,
+            &[("dir/a", "1\n"), ("dir/sub/b", "1\n"), ("other", "1\n")],
+            "first",
+        );
+        write_file(&repo, "dir/a", "2\n");
+        write_file(&repo, "dir/sub/b", "2\n");
+        write_file(&repo, "dir/new", "2\n");
+        write_file(&repo, "other", "2\n");
+        add_files(&repo, &["dir/a", "dir/sub/b", "dir/new", "other"]);
+
+        let reset = reset_files(&repo, &paths(&repo, &["dir"])).unwrap();
+        assert_eq!(
+            reset,
+            vec![
+                PathBuf::from("dir/a"),
+                PathBuf::from("dir/new"),
+                PathBuf::from("dir/sub/b")
+            ]
+        );
+        assert_eq!(
+            index_files(&repo),
+            pairs(&[("dir/a", "1\n"), ("dir/sub/b", "1\n"), ("other", "2\n")])
+        );
+
+        // a path matching nothing tracked is an error and leaves the index as it is
+        assert!(reset_files(&repo, &paths(&repo, &["missing"])).is_err());
+        assert_eq!(
+            index_files(&repo),
+            pairs(&[("dir/a", "1\n"), ("dir/sub/b", "1\n"), ("other", "2\n")])
+        );
+
+        remove_repository(&repo);
+    }
 }
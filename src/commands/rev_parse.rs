@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+
+use crate::hashing::Hash;
+use crate::repository::Repository;
+use crate::revision;
+
+/// Returns the hash of the object every revision points to, in the same order.
+///
+/// # Errors
+///
+/// This function will fail if any of the revisions could not be resolved.
+pub fn rev_parse(repo: &Repository, revisions: &[String]) -> Result<Vec<Hash>> {
+    let mut hashes = Vec::with_capacity(revisions.len());
+    for r in revisions {
+        hashes.push(revision::resolve(repo, r).context(format!("could not resolve '{}'", r))?);
+    }
+    Ok(hashes)
+}
@@ -0,0 +1,705 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::config::Config;
+use crate::fs;
+use crate::hashing::Hash;
+use crate::merge::{MergeLabels, MergeOptions};
+use crate::repository::{Repository, read_operation};
+use crate::revision;
+
+use super::commit::{clean_message, clear_merge_state, move_head, write_commit, write_tree};
+use super::merge::{
+    TreeFiles, apply_merge, has_staged_changes, merge_trees, read_tree_files, write_merge_msg,
+};
+use super::{LogEntry, ResetMode, commit, reset_to_commit};
+
+/// Name of the file inside the sequencer folder with the commits left to apply, one per line.
+const TODO_FILE_NAME: &str = "todo";
+/// Name of the file inside the sequencer folder with the commit HEAD pointed to at the start.
+const HEAD_FILE_NAME: &str = "head";
+/// Name of the file inside the sequencer folder that marks the first step in the todo file as
+/// stopped by conflicts, as opposed to failed before it could be applied.
+const STOPPED_FILE_NAME: &str = "stopped";
+
+const PICK_STR: &str = "pick";
+const REVERT_STR: &str = "revert";
+
+/// What the sequencer does with a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Action {
+    /// Applies the changes of the commit
+    Pick,
+    /// Applies the opposite of the changes of the commit
+    Revert,
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            PICK_STR => Action::Pick,
+            REVERT_STR => Action::Revert,
+            _ => bail!("invalid sequencer action: {}", s),
+        })
+    }
+}
+
+impl Action {
+    /// Returns the command that performs the action and what performing it is called, for the
+    /// messages about local changes it would overwrite.
+    fn command_names(self) -> (&'static str, &'static str) {
+        match self {
+            Action::Pick => ("cherry-pick", "cherry-picking"),
+            Action::Revert => ("revert", "reverting"),
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::Pick => PICK_STR,
+            Action::Revert => REVERT_STR,
+        })
+    }
+}
+
+/// A commit waiting to be applied, a line of the todo file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Step {
+    pub action: Action,
+    pub commit: Hash,
+    /// Subject of the commit, only to make the todo file readable
+    pub subject: String,
+}
+
+/// The result of cherry-picking or reverting commits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequencerOutcome {
+    /// Every commit was applied, creating these new commits in order
+    Done(Vec<Hash>),
+    /// Applying `commit` stopped because of conflicts in these files, after creating the
+    /// `applied` commits. The run goes on with `sequencer_continue` once they are resolved and
+    /// added.
+    Conflicts {
+        applied: Vec<Hash>,
+        commit: Hash,
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// The result of applying a single step.
+pub(super) enum StepResult {
+    Committed(Hash),
+    /// The changes were already in HEAD, so nothing was committed
+    Empty,
+    Conflicts(Vec<PathBuf>),
+}
+
+/// Applies the changes introduced by every commit the revisions point to on top of HEAD, in
+/// order, creating a new commit for each of them with the same message and author. Commits whose
+/// changes are already in HEAD are skipped.
+///
+/// The changes are merged with a three-way merge against the parent of the commit, so they can
+/// be applied to a different history. If they conflict, the run stops until it is continued,
+/// skipped or aborted (see `sequencer_continue`, `sequencer_skip` and `sequencer_abort`).
+///
+/// # Errors
+///
+/// This function will fail if:
+/// - Another operation is in progress, there are staged changes, or HEAD has no commits.
+/// - Any of the revisions does not point to a commit, or points to a merge commit.
+/// - There are local changes to files that any of the commits change.
+/// - Any of the objects, refs, the index or the working tree files could not be read or written.
+pub fn cherry_pick(repo: &Repository, revisions: &[String]) -> Result<SequencerOutcome> {
+    start(repo, revisions, Action::Pick)
+}
+
+/// Applies the opposite of the changes introduced by every commit the revisions point to on top
+/// of HEAD, in order, creating a new commit for each of them with a `This reverts commit`
+/// message. It works like `cherry_pick` otherwise.
+///
+/// # Errors
+///
+/// This function will fail in the same cases as `cherry_pick`.
+pub fn revert(repo: &Repository, revisions: &[String]) -> Result<SequencerOutcome> {
+    start(repo, revisions, Action::Revert)
+}
+
+/// Continues the cherry-pick or revert that stopped because of conflicts. The commit that
+/// stopped is concluded with the resolved files in the index, unless it was already committed,
+/// and the rest of the commits are applied. A commit that could not be applied at all (because
+/// of local changes, for example) is applied again.
+///
+/// # Errors
+///
+/// This function will fail if there is no run stopped, if there are files with unresolved
+/// conflicts, or if any of the commits left could not be applied.
+pub fn sequencer_continue(repo: &Repository) -> Result<SequencerOutcome> {
+    let mut steps = read_todo(repo)?;
+
+    let mut applied = Vec::new();
+    let stopped = repo.sequencer_path().join(STOPPED_FILE_NAME);
+    if stopped.is_file() {
+        if repo.cherry_pick_head_path().is_file() || repo.revert_head_path().is_file() {
+            let message = std::fs::read_to_string(repo.merge_msg_path())
+                .context("could not read MERGE_MSG")?;
+            let message = clean_message(&message);
+            if message.is_empty() {
+                bail!("aborting commit due to empty commit message")
+            }
+            applied.push(commit(repo, &message)?);
+        }
+
+        std::fs::remove_file(&stopped).context("could not remove sequencer stop")?;
+        if !steps.is_empty() {
+            steps.remove(0);
+        }
+    }
+    run(repo, steps, applied)
+}
+
+/// Skips the commit the cherry-pick or revert stopped at, discarding the changes in the index
+/// and the working tree, and applies the rest of the commits.
+///
+/// # Errors
+///
+/// This function will fail if there is no run stopped, or if any of the commits left could not
+/// be applied.
+pub fn sequencer_skip(repo: &Repository) -> Result<SequencerOutcome> {
+    let mut steps = read_todo(repo)?;
+
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to go back to")?;
+    reset_to_commit(repo, ResetMode::Hard, head).context("could not reset to last commit")?;
+    clear_merge_state(repo)?;
+    let stopped = repo.sequencer_path().join(STOPPED_FILE_NAME);
+    if stopped.is_file() {
+        std::fs::remove_file(&stopped).context("could not remove sequencer stop")?;
+    }
+
+    if !steps.is_empty() {
+        steps.remove(0);
+    }
+    run(repo, steps, Vec::new())
+}
+
+/// Stops the cherry-pick or revert in progress, going back to the commit HEAD pointed to before
+/// it started and discarding the commits it created along with the changes in the index and the
+/// working tree.
+///
+/// # Errors
+///
+/// This function will fail if there is no run in progress, or if the index or the working tree
+/// could not be reset.
+pub fn sequencer_abort(repo: &Repository) -> Result<()> {
+    let head_path = repo.sequencer_path().join(HEAD_FILE_NAME);
+    if !head_path.is_file() {
+        bail!("there is no cherry-pick or revert in progress")
+    }
+    let head = std::fs::read_to_string(&head_path).context("could not read sequencer head")?;
+    let head = Hash::from_str(head.trim()).context("sequencer head is not a valid hash")?;
+
+    reset_to_commit(repo, ResetMode::Hard, head).context("could not reset to original commit")?;
+    clear_merge_state(repo)?;
+    remove_sequencer(repo)
+}
+
+/// Starts a run applying the commits the revisions point to with `action`.
+fn start(repo: &Repository, revisions: &[String], action: Action) -> Result<SequencerOutcome> {
+    let in_progress = read_operation(repo)
+        .context("could not read operation in progress")?
+        .is_some();
+    if in_progress || repo.sequencer_path().exists() {
+        bail!("there is an operation in progress, conclude or abort it first")
+    }
+    // every commit is made from the whole index, so staged changes would end up in them
+    if has_staged_changes(repo)? {
+        bail!("cannot apply commits: you have staged changes, commit them first")
+    }
+
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to apply the changes to yet")?;
+
+    let mut steps = Vec::with_capacity(revisions.len());
+    for r in revisions {
+        let entry = LogEntry::read(repo, revision::resolve_commit(repo, r)?)?;
+        if entry.parents.len() > 1 {
+            bail!("commit {} is a merge, which can not be applied", entry.hash)
+        }
+        steps.push(Step {
+            action,
+            subject: entry.subject().to_string(),
+            commit: entry.hash,
+        });
+    }
+
+    let sequencer = repo.sequencer_path();
+    std::fs::create_dir_all(&sequencer).context("could not create sequencer folder")?;
+    std::fs::write(sequencer.join(HEAD_FILE_NAME), format!("{}\n", head))
+        .context("could not write sequencer head")?;
+
+    let outcome = run(repo, steps, Vec::new());
+    // if HEAD did not move, there is nothing to continue or abort, so the run is forgotten
+    if outcome.is_err()
+        && fs::get_last_commit_hash(repo).context("could not get last commit hash")? == Some(head)
+    {
+        remove_sequencer(repo)?;
+    }
+    outcome
+}
+
+/// Applies the steps in order, saving the ones left before every one so the run can be
+/// continued if it stops or fails, with `CHERRY_PICK_HEAD` or `REVERT_HEAD` pointing to the
+/// commit that stopped it because of conflicts. The sequencer folder is removed once all of them
+/// are applied.
+fn run(repo: &Repository, steps: Vec<Step>, mut applied: Vec<Hash>) -> Result<SequencerOutcome> {
+    for (i, step) in steps.iter().enumerate() {
+        write_todo(repo, &steps[i..])?;
+        let (command, acting) = step.action.command_names();
+        match apply_step(repo, step, command, acting)? {
+            StepResult::Committed(hash) => applied.push(hash),
+            StepResult::Empty => {}
+            StepResult::Conflicts(paths) => {
+                let head_file = match step.action {
+                    Action::Pick => Constants::CHERRY_PICK_HEAD_FILE_NAME,
+                    Action::Revert => Constants::REVERT_HEAD_FILE_NAME,
+                };
+                fs::write_ref(repo, head_file, &step.commit)
+                    .context(format!("could not write {}", head_file))?;
+                std::fs::write(repo.sequencer_path().join(STOPPED_FILE_NAME), "")
+                    .context("could not mark the sequencer as stopped")?;
+                return Ok(SequencerOutcome::Conflicts {
+                    applied,
+                    commit: step.commit.clone(),
+                    paths,
+                });
+            }
+        }
+    }
+
+    remove_sequencer(repo)?;
+    Ok(SequencerOutcome::Done(applied))
+}
+
+/// Applies a single step on top of HEAD, committing the result if there were no conflicts and it
+/// changed anything.
+///
+/// The changes are merged taking the parent of the commit as the base for a pick, and the commit
+/// itself for a revert. On conflicts, `MERGE_MSG` has the message of the commit that will
+/// conclude it.
+///
+/// `command` is the name of the command applying the step and `acting` what applying it is
+/// called, which the errors about local changes mention. The move of HEAD is recorded in the
+/// reflog under `command` too.
+///
+/// # Errors
+///
+/// This function will fail if the commit is a merge, if there are local changes to files the
+/// commit changes, or if any of the objects, refs, the index or the working tree files could not
+/// be read or written.
+pub(super) fn apply_step(
+    repo: &Repository,
+    step: &Step,
+    command: &str,
+    acting: &str,
+) -> Result<StepResult> {
+    let entry = LogEntry::read(repo, step.commit.clone())?;
+    let parent = match entry.parents.as_ref() {
+        [] => None,
+        [parent] => Some(parent.clone()),
+        _ => bail!("commit {} is a merge, which can not be applied", entry.hash),
+    };
+    let head = fs::get_last_commit_hash(repo)
+        .context("could not get last commit hash")?
+        .context("there are no commits to apply the changes to yet")?;
+
+    let commit_files = read_tree_files(repo, entry.hash.clone())?;
+    let parent_files = match parent {
+        Some(parent) => read_tree_files(repo, parent)?,
+        None => TreeFiles::new(),
+    };
+    let description = format!("{}... {}", entry.short_hash(), entry.subject());
+    let commit_label = format!("{} ({})", entry.short_hash(), entry.subject());
+    let parent_label = format!("parent of {}", commit_label);
+
+    let (base, theirs, labels) = match step.action {
+        Action::Pick => (parent_files, commit_files, (&parent_label, &commit_label)),
+        Action::Revert => (commit_files, parent_files, (&commit_label, &parent_label)),
+    };
+    let labels = MergeLabels {
+        base: labels.0,
+        ours: Constants::HEAD_FILE_NAME,
+        theirs: labels.1,
+    };
+    let config = Config::load(repo).context("could not read config")?;
+    let options = MergeOptions::from_config(&config)?;
+
+    let tree_merge = merge_trees(
+        repo,
+        &base,
+        &read_tree_files(repo, head.clone())?,
+        &theirs,
+        &labels,
+        &options,
+    )?;
+    let conflicts = apply_merge(repo, tree_merge, command, acting)
+        .context(format!("could not apply {}", description))?;
+
+    let (message, author) = match step.action {
+        Action::Pick => (entry.message.to_string(), Some(entry.author)),
+        Action::Revert => (
+            format!(
+                "Revert \"{}\"\n\nThis reverts commit {}.\n",
+                entry.subject(),
+                entry.hash
+            ),
+            None,
+        ),
+    };
+
+    if !conflicts.is_empty() {
+        write_merge_msg(repo, &message, &conflicts)?;
+        return Ok(StepResult::Conflicts(conflicts));
+    }
+
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let tree = write_tree(repo, &index)?;
+    if tree == LogEntry::read(repo, head.clone())?.tree {
+        return Ok(StepResult::Empty);
+    }
+    let commit = write_commit(repo, tree, vec![head], &message, author)?;
+    let subject = message.lines().next().unwrap_or_default();
+    let log_message = format!("{}: {}", command, subject);
+    move_head(repo, &commit, &log_message).context("could not update current branch")?;
+
+    Ok(StepResult::Committed(commit))
+}
+
+/// Returns the commit HEAD pointed to before the run in progress started and the commits left
+/// to apply, or nothing if there is no run in progress.
+pub(super) fn state_commits(repo: &Repository) -> Result<Vec<Hash>> {
+    let head_path = repo.sequencer_path().join(HEAD_FILE_NAME);
+    if !head_path.is_file() {
+        return Ok(Vec::new());
+    }
+    let head = std::fs::read_to_string(&head_path).context("could not read sequencer head")?;
+    let mut commits =
+        vec![Hash::from_str(head.trim()).context("sequencer head is not a valid hash")?];
+    commits.extend(read_todo(repo)?.into_iter().map(|s| s.commit));
+    Ok(commits)
+}
+
+/// Returns the steps left in the todo file.
+///
+/// # Errors
+///
+/// This function will fail if there is no run in progress, or if the file has invalid lines.
+fn read_todo(repo: &Repository) -> Result<Vec<Step>> {
+    let path = repo.sequencer_path().join(TODO_FILE_NAME);
+    if !path.is_file() {
+        bail!("there is no cherry-pick or revert in progress")
+    }
+
+    let content = std::fs::read_to_string(&path).context("could not read sequencer todo")?;
+    let mut steps = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(3, ' ');
+        let (Some(action), Some(commit)) = (parts.next(), parts.next()) else {
+            bail!("invalid line in sequencer todo: {}", line)
+        };
+        steps.push(Step {
+            action: action.parse()?,
+            commit: revision::resolve_commit(repo, commit)?,
+            subject: parts.next().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(steps)
+}
+
+fn write_todo(repo: &Repository, steps: &[Step]) -> Result<()> {
+    let content: String = steps
+        .iter()
+        .map(|s| format!("{} {} {}\n", s.action, s.commit, s.subject))
+        .collect();
+    std::fs::write(repo.sequencer_path().join(TODO_FILE_NAME), content)
+        .context("could not write sequencer todo")
+}
+
+fn remove_sequencer(repo: &Repository) -> Result<()> {
+    let sequencer = repo.sequencer_path();
+    if sequencer.exists() {
+        std::fs::remove_dir_all(&sequencer).context("could not remove sequencer folder")?;
+    }
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use time::UtcOffset;
+
+    use super::*;
+    use crate::commands::test_utils::*;
+    use crate::commands::{checkout, create_branch};
+    use crate::object::commit::{CommitUser, CommitUserKind};
+
+    fn original_author() -> CommitUser {
+        CommitUser {
+            kind: CommitUserKind::Author,
+            identifier: "Original <original@example.com>".into(),
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_000_000_000),
+            timezone: UtcOffset::UTC,
+        }
+    }
+
+    fn head(repo: &Repository) -> Hash {
+        fs::get_last_commit_hash(repo).unwrap().unwrap()
+    }
+
+    /// Commits `f` on the current branch and then, on a new branch called `side`, a commit
+    /// changing `f` to `side_f` followed by one adding `g`. Goes back to the current branch with
+    /// `f` changed to `ours_f`, returning the commits of `side`.
+    fn side_commits(repo: &Repository, side_f: &str, ours_f: &str) -> (Hash, Hash) {
+        commit_files(repo, &[("f", "1\n2\n3\n")], "base");
+        let branch = fs::get_current_branch_name(repo).unwrap().unwrap();
+        create_branch(repo, "side", None).unwrap();
+        checkout(repo, "side").unwrap();
+        let first = commit_files_as(repo, &[("f", side_f)], "change f", original_author());
+        let second = commit_files_as(repo, &[("g", "g\n")], "add g", original_author());
+        checkout(repo, &branch).unwrap();
+        commit_files(repo, &[("f", ours_f)], "ours");
+        (first, second)
+    }
+
+    fn revisions(commits: &[&Hash]) -> Vec<String> {
+        commits.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_cherry_pick_commits() {
+        let repo = create_repository("cherry-pick");
+        let (first, second) = side_commits(&repo, "1\n2\nside\n", "ours\n2\n3\n");
+
+        let SequencerOutcome::Done(applied) =
+            cherry_pick(&repo, &revisions(&[&first, &second])).unwrap()
+        else {
+            panic!("the commits should apply cleanly")
+        };
+        assert_eq!(applied.len(), 2);
+        assert_eq!(head(&repo), applied[1]);
+        for (new, old) in applied.iter().zip([first, second]) {
+            let new = LogEntry::read(&repo, new.clone()).unwrap();
+            let old = LogEntry::read(&repo, old).unwrap();
+            assert_eq!(new.message, old.message);
+            assert_eq!(new.author.identifier, old.author.identifier);
+            assert_eq!(new.author.timestamp, old.author.timestamp);
+        }
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("ours\n2\nside\n"));
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("g\n"));
+        assert!(!repo.sequencer_path().exists());
+
+        // the changes are already there, so there is nothing to commit
+        assert_eq!(
+            cherry_pick(&repo, &revisions(&[&applied[1]])).unwrap(),
+            SequencerOutcome::Done(Vec::new())
+        );
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_revert_commits() {
+        let repo = create_repository("revert");
+        commit_files(&repo, &[("f", "1\n")], "base");
+        let first = commit_files(&repo, &[("f", "2\n")], "change f");
+        let second = commit_files(&repo, &[("g", "g\n")], "add g");
+
+        let SequencerOutcome::Done(applied) =
+            revert(&repo, &revisions(&[&second, &first])).unwrap()
+        else {
+            panic!("the commits should revert cleanly")
+        };
+        assert_eq!(applied.len(), 2);
+        let message = LogEntry::read(&repo, applied[0].clone()).unwrap().message;
+        assert_eq!(
+            message.as_ref(),
+            format!("Revert \"add g\"\n\nThis reverts commit {}.\n", second)
+        );
+        let message = LogEntry::read(&repo, applied[1].clone()).unwrap().message;
+        assert_eq!(
+            message.as_ref(),
+            format!("Revert \"change f\"\n\nThis reverts commit {}.\n", first)
+        );
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("1\n"));
+        assert_eq!(read_file(&repo, "g"), None);
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_cherry_pick_continue() {
+        let repo = create_repository("cherry-pick-continue");
+        let (first, second) = side_commits(&repo, "1\nside\n3\n", "1\nours\n3\n");
+
+        let outcome = cherry_pick(&repo, &revisions(&[&first, &second])).unwrap();
+        assert_eq!(
+            outcome,
+            SequencerOutcome::Conflicts {
+                applied: Vec::new(),
+                commit: first.clone(),
+                paths: vec![PathBuf::from("f")],
+            }
+        );
+        assert_eq!(
+            fs::read_ref(&repo, Constants::CHERRY_PICK_HEAD_FILE_NAME).unwrap(),
+            Some(first.clone())
+        );
+        let todo = read_todo(&repo).unwrap();
+        assert_eq!(
+            todo.iter().map(|s| s.commit.clone()).collect::<Vec<Hash>>(),
+            vec![first.clone(), second]
+        );
+
+        assert!(sequencer_continue(&repo).is_err());
+        write_file(&repo, "f", "1\nboth\n3\n");
+        add_files(&repo, &["f"]);
+        let SequencerOutcome::Done(applied) = sequencer_continue(&repo).unwrap() else {
+            panic!("the rest of the commits should apply cleanly")
+        };
+        assert_eq!(applied.len(), 2);
+        let resolved = LogEntry::read(&repo, applied[0].clone()).unwrap();
+        assert_eq!(resolved.subject(), "change f");
+        assert_eq!(resolved.author.identifier, original_author().identifier);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("1\nboth\n3\n"));
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("g\n"));
+        assert!(!repo.sequencer_path().exists());
+        assert!(!repo.cherry_pick_head_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_cherry_pick_skip() {
+        let repo = create_repository("cherry-pick-skip");
+        let (first, second) = side_commits(&repo, "1\nside\n3\n", "1\nours\n3\n");
+        let ours = head(&repo);
+
+        cherry_pick(&repo, &revisions(&[&first, &second])).unwrap();
+        let SequencerOutcome::Done(applied) = sequencer_skip(&repo).unwrap() else {
+            panic!("the rest of the commits should apply cleanly")
+        };
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            LogEntry::read(&repo, applied[0].clone())
+                .unwrap()
+                .parents
+                .to_vec(),
+            vec![ours]
+        );
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("1\nours\n3\n"));
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("g\n"));
+        assert!(!repo.sequencer_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_cherry_pick_abort() {
+        let repo = create_repository("cherry-pick-abort");
+        let (first, second) = side_commits(&repo, "1\n2\n3\nside\n", "ours\n2\n3\n");
+        // the first commit applies, then the second one conflicts
+        let ours = commit_files(&repo, &[("g", "ours\n")], "add g too");
+
+        assert!(sequencer_abort(&repo).is_err());
+        let SequencerOutcome::Conflicts { applied, .. } =
+            cherry_pick(&repo, &revisions(&[&first, &second])).unwrap()
+        else {
+            panic!("adding g should conflict")
+        };
+        assert_eq!(applied.len(), 1);
+
+        sequencer_abort(&repo).unwrap();
+        assert_eq!(head(&repo), ours);
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("ours\n2\n3\n"));
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("ours\n"));
+        assert!(
+            !fs::index::read_index_file(&repo)
+                .unwrap()
+                .has_unmerged_entries()
+        );
+        assert!(!repo.sequencer_path().exists());
+        assert!(!repo.cherry_pick_head_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_failed_start_leaves_no_state() {
+        let repo = create_repository("cherry-pick-failed-start");
+        let (first, _) = side_commits(&repo, "1\n2\nside\n", "ours\n2\n3\n");
+
+        // local changes the commit would overwrite
+        write_file(&repo, "f", "local\n");
+        assert!(cherry_pick(&repo, &revisions(&[&first])).is_err());
+        assert!(!repo.sequencer_path().exists());
+        assert_eq!(read_file(&repo, "f").as_deref(), Some("local\n"));
+
+        // staged changes, which would end up in the new commit
+        add_files(&repo, &["f"]);
+        assert!(cherry_pick(&repo, &revisions(&[&first])).is_err());
+        assert!(!repo.sequencer_path().exists());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_continue_after_failed_step() {
+        let repo = create_repository("cherry-pick-failed-step");
+        let (first, second) = side_commits(&repo, "1\n2\nside\n", "ours\n2\n3\n");
+
+        // the first commit applies, then adding g would overwrite the untracked file
+        write_file(&repo, "g", "local\n");
+        assert!(cherry_pick(&repo, &revisions(&[&first, &second])).is_err());
+        let picked = head(&repo);
+        assert_eq!(
+            LogEntry::read(&repo, picked.clone()).unwrap().subject(),
+            "change f"
+        );
+        assert!(!repo.cherry_pick_head_path().exists());
+
+        // nothing was concluded, so the step is still there to apply
+        assert!(sequencer_continue(&repo).is_err());
+        assert_eq!(head(&repo), picked);
+        assert_eq!(read_todo(&repo).unwrap()[0].commit, second);
+
+        std::fs::remove_file(repo.working_tree().join("g")).unwrap();
+        let SequencerOutcome::Done(applied) = sequencer_continue(&repo).unwrap() else {
+            panic!("the commit should apply once g is removed")
+        };
+        assert_eq!(applied.len(), 1);
+        let added = LogEntry::read(&repo, applied[0].clone()).unwrap();
+        assert_eq!(added.subject(), "add g");
+        assert_eq!(added.parents.to_vec(), vec![picked]);
+        assert_eq!(read_file(&repo, "g").as_deref(), Some("g\n"));
+        assert!(!repo.sequencer_path().exists());
+
+        remove_repository(&repo);
+    }
+}
@@ -1,7 +1,9 @@
-mod format;
-mod read;
+pub(crate) mod read;
 
 #[allow(clippy::module_inception)]
 mod status;
 
-pub use status::status;
+pub use status::{
+    Conflict, FileWithStatus, StageStatus, Status, StatusReport, UpstreamStatus, get_statuses,
+    status,
+};
@@ -1,28 +1,28 @@
-use std::path::PathBuf;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
-use anyhow::{bail, Result, Context};
+use anyhow::{Context, Result, bail};
 
-use crate::Constants;
-use crate::utils;
 use crate::fs;
-use crate::object::{self, Object};
-use crate::object::tree::TreeEntry;
-use crate::index::IndexEntryCache;
 use crate::hashing::Hash;
+use crate::index::IndexEntryCache;
+use crate::object::tree::TreeEntry;
+use crate::object::{self, Object};
+use crate::repository::Repository;
+use crate::utils;
 
 use super::status::FileData;
 
-pub fn read_working_tree_data() -> Result<Vec<FileData>> {
-    let root_dir = Constants::working_tree_root_path();
-    let working_tree = fs::path::get_all_files_bufered(&root_dir)
-        .context("could not get files in working tree")?;
+pub fn read_working_tree_data(repo: &Repository) -> Result<Vec<FileData>> {
+    let root_dir = repo.working_tree();
+    let working_tree =
+        fs::path::get_all_files_bufered(repo).context("could not get files in working tree")?;
 
     let working_tree_data = working_tree
         .into_iter()
         .map(|file| FileData {
             // important to convert the paths to relative paths
-            path: utils::path::relative_path(&file.path, &root_dir).unwrap_or(file.path),
+            path: utils::path::relative_path(&file.path, root_dir).unwrap_or(file.path),
             reader: file.reader,
             cache: file.cache,
         })
@@ -31,7 +31,6 @@ pub fn read_working_tree_data() -> Result<Vec<FileData>> {
     Ok(working_tree_data)
 }
 
-
 pub type CommitData = HashMap<PathBuf, Hash>;
 /// Returns a Map with the hash of every file in the previous commit mapped to its path.
 ///
@@ -42,11 +41,11 @@ pub type CommitData = HashMap<PathBuf, Hash>;
 /// # Errors
 ///
 /// This function can fail if the existence of a previous commit could not be verified.
-pub fn read_commit_data() -> Result<Option<CommitData>> {
+pub fn read_commit_data(repo: &Repository) -> Result<Option<CommitData>> {
     // checking if there is a previous commit, otherwise we just leave the data related to the
     // commit
     let previous_commit_hash =
-        fs::get_last_commit_hash().context("could not get last commit hash")?;
+        fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
 
     if previous_commit_hash.is_none() {
         log::info!("no previous commit found");
@@ -55,20 +54,9 @@ pub fn read_commit_data() -> Result<Option<CommitData>> {
 
     let mut commit_data = HashMap::new();
 
-    let commit = fs::object::read_object(previous_commit_hash.expect("should never be None"))
-        .context("could not read last commit")?;
-
-    let tree_obj: Object = if let Object::Commit { tree, .. } = commit {
-        fs::object::read_object(tree).context("could not read commit tree")?
-    } else {
-        bail!("expected tree")
-    };
-
-    let all_entries: Vec<TreeEntry> = if let Object::Tree { entries } = tree_obj {
-        object::tree::get_all_tree_entries(entries).context("could not get all tree entries")?
-    } else {
-        bail!("expected commit")
-    };
+    let all_entries =
+        read_commit_entries(repo, previous_commit_hash.expect("should never be None"))
+            .context("could not read last commit entries")?;
 
     for e in all_entries {
         commit_data.insert(e.path, e.hash);
@@ -77,6 +65,27 @@ pub fn read_commit_data() -> Result<Option<CommitData>> {
     Ok(Some(commit_data))
 }
 
+/// Returns every (non-tree) entry in the tree of the commit with the given hash, with their paths
+/// relative to the root tree.
+///
+/// # Errors
+///
+/// This function can fail if the commit or any of its trees could not be read.
+pub fn read_commit_entries(repo: &Repository, commit_hash: Hash) -> Result<Vec<TreeEntry>> {
+    let commit = fs::object::read_object(repo, commit_hash).context("could not read commit")?;
+
+    let tree_obj: Object = if let Object::Commit { tree, .. } = commit {
+        fs::object::read_object(repo, tree).context("could not read commit tree")?
+    } else {
+        bail!("expected commit")
+    };
+
+    if let Object::Tree { entries } = tree_obj {
+        object::tree::get_all_tree_entries(repo, entries).context("could not get all tree entries")
+    } else {
+        bail!("expected tree")
+    }
+}
 
 pub type IndexData = HashMap<PathBuf, (Hash, IndexEntryCache)>;
 /// Returns a Map with the hash and cache of every file in the current index mapped to its path.
@@ -84,10 +93,10 @@ pub type IndexData = HashMap<PathBuf, (Hash, IndexEntryCache)>;
 /// # Errors
 ///
 /// This function can fail if the index file could not be read.
-pub fn read_index_data() -> Result<IndexData> {
+pub fn read_index_data(repo: &Repository) -> Result<IndexData> {
     let mut index_data = IndexData::default();
 
-    let index = fs::index::read_index_file().context("could not read index file")?;
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
 
     let mut hash: Hash;
     let mut cache: IndexEntryCache;
@@ -102,4 +111,3 @@ pub fn read_index_data() -> Result<IndexData> {
 
     Ok(index_data)
 }
-
@@ -1,23 +1,120 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
+use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 
-use super::format::format_status;
 use super::read::*;
 
+use crate::Constants;
 use crate::byteable::Byteable;
+use crate::commands::FileVersion;
 use crate::error::WarnUnwrap;
+use crate::fs;
 use crate::hashing::Hash;
-use crate::index::IndexEntryCache;
+use crate::index::{FileStage, IndexEntryCache};
 use crate::object::Object;
+use crate::object::commit::ahead_behind;
+use crate::repository::{Operation, Repository, read_operation};
+use crate::revision;
+use crate::utils::nums::as_octal;
+
+/// The state of the repository shown by `status`.
+#[derive(Debug)]
+pub struct StatusReport {
+    /// Name of the current branch, `None` if HEAD is detached
+    pub branch: Option<String>,
+    /// The last commit, `None` if the current branch has no commits yet
+    pub head: Option<Hash>,
+    /// The branch the current one tracks, if it has one configured
+    pub upstream: Option<UpstreamStatus>,
+    /// The merge, rebase, cherry-pick or revert waiting to be continued
+    pub operation: Option<Operation>,
+    /// Files that are not unchanged
+    pub files: Vec<FileWithStatus>,
+}
+
+/// How the current branch compares to the branch it tracks.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UpstreamStatus {
+    /// Short name of the upstream, like `origin/main`
+    pub name: String,
+    /// Number of commits only the current branch has and number of commits only the upstream
+    /// has, `None` if the upstream does not exist anymore
+    pub ahead_behind: Option<(usize, usize)>,
+}
 
+#[derive(Debug)]
 pub struct FileWithStatus {
     pub path: PathBuf,
     pub status: Status,
     pub stage_status: StageStatus,
+    /// The file in the last commit. For moves staged for commit, this is the file at the previous
+    /// path.
+    pub head: Option<FileVersion>,
+    /// The file in the index
+    pub index: Option<FileVersion>,
+    /// Mode of the file in the working tree, in its octal representation
+    pub worktree_mode: Option<u32>,
+}
+
+impl FileWithStatus {
+    fn new(path: PathBuf, status: Status, stage_status: StageStatus) -> Self {
+        Self {
+            path,
+            status,
+            stage_status,
+            head: None,
+            index: None,
+            worktree_mode: None,
+        }
+    }
+
+    /// Returns the two letter code `git status --short` shows for this file:
+    /// - The first one is the change in the index respective to the last commit.
+    /// - The second one is the change in the working tree respective to the index.
+    ///
+    /// Each of them is `A` (added), `D` (deleted), `M` (modified), `R` (moved) or a space if there
+    /// are no changes. Untracked files are `??`.
+    ///
+    /// Files with conflicts use both letters to show which sides changed them instead: `U` if it
+    /// was modified, `A` if it was added and `D` if it was deleted. Conflicts where both sides
+    /// modified the file are `UU`.
+    pub fn short_code(&self) -> [char; 2] {
+        if self.stage_status == StageStatus::Untracked {
+            return ['?', '?'];
+        }
+        if let Status::Unmerged(conflict) = &self.status {
+            return match (&conflict.base, &conflict.ours, &conflict.theirs) {
+                (Some(_), Some(_), Some(_)) => ['U', 'U'],
+                (None, Some(_), Some(_)) => ['A', 'A'],
+                (Some(_), None, Some(_)) => ['D', 'U'],
+                (Some(_), Some(_), None) => ['U', 'D'],
+                (None, Some(_), None) => ['A', 'U'],
+                (None, None, Some(_)) => ['U', 'A'],
+                _ => ['D', 'D'],
+            };
+        }
+        let moved = matches!(self.status, Status::Moved { .. });
+
+        let index = match (&self.head, &self.index) {
+            (Some(_), Some(_)) if moved && self.stage_status == StageStatus::Commit => 'R',
+            (None, Some(_)) => 'A',
+            (Some(_), None) => 'D',
+            (Some(head), Some(index)) if head != index => 'M',
+            _ => ' ',
+        };
+        let working_tree = match self.worktree_mode {
+            None if self.index.is_some() => 'D',
+            _ if moved && self.stage_status == StageStatus::NotCommit => 'R',
+            _ if self.stage_status == StageStatus::NotCommit => 'M',
+            _ => ' ',
+        };
+
+        [index, working_tree]
+    }
 }
 
 pub struct FileData {
@@ -31,8 +128,24 @@ pub enum Status {
     New,
     Modified,
     Deleted,
-    Moved { previous: PathBuf },
+    Moved {
+        previous: PathBuf,
+    },
     Unchanged,
+    /// The file has conflicts from a merge that were not resolved yet
+    Unmerged(Conflict),
+}
+
+/// The versions of a file with merge conflicts, taken from the stages of its index entries. A
+/// missing version means the file did not exist on that side.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Conflict {
+    /// The file in the common ancestor
+    pub base: Option<FileVersion>,
+    /// The file in the current branch
+    pub ours: Option<FileVersion>,
+    /// The file in the branch being merged
+    pub theirs: Option<FileVersion>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -42,7 +155,7 @@ pub enum StageStatus {
     Untracked,
 }
 
-/// Returns a string with the status of the repository. It lists:
+/// Returns the status of the repository. It lists:
 /// - The changes respective to the last commit.
 /// - The changes respective to the working tree.
 ///
@@ -51,18 +164,135 @@ pub enum StageStatus {
 /// This function can fail if:
 /// - The index file couldn't be read.
 /// - Could not get object data from a file in the working tree.
-pub fn status() -> Result<String> {
-    let commit_data_opt = read_commit_data().context("could not get commit data")?;
-    let no_commits = commit_data_opt.is_none();
-    let commit_data = commit_data_opt.unwrap_or_default();
+pub fn status(repo: &Repository) -> Result<StatusReport> {
+    let branch = fs::get_current_branch_name(repo).context("could not get current branch name")?;
+    let head = fs::get_last_commit_hash(repo).context("could not get last commit hash")?;
+    let upstream = match (&branch, &head) {
+        (Some(branch), Some(head)) => upstream_status(repo, branch, head.clone())
+            .context("could not compare the branch with its upstream")?,
+        _ => None,
+    };
+    let operation = read_operation(repo).context("could not read operation in progress")?;
+
+    let files = get_statuses(repo)
+        .context("could not get file statuses")?
+        .into_iter()
+        .filter(|fws| fws.status != Status::Unchanged || fws.short_code() != [' ', ' '])
+        .collect();
+
+    Ok(StatusReport {
+        branch,
+        head,
+        upstream,
+        operation,
+        files,
+    })
+}
+
+/// Returns the upstream of `branch` and how many commits each of them has that the other one does
+/// not, or `None` if the branch does not track any other.
+fn upstream_status(repo: &Repository, branch: &str, head: Hash) -> Result<Option<UpstreamStatus>> {
+    let Some(upstream) = revision::upstream_ref_name(repo, branch)? else {
+        return Ok(None);
+    };
+
+    let ahead_behind = match fs::read_ref(repo, &upstream)? {
+        Some(upstream_head) => Some(ahead_behind(repo, head, upstream_head)?),
+        None => None,
+    };
+
+    let name = [Constants::HEADS_FOLDER_NAME, Constants::REMOTES_FOLDER_NAME]
+        .iter()
+        .find_map(|folder| {
+            upstream.strip_prefix(&format!("{}/{}/", Constants::REFS_FOLDER_NAME, folder))
+        })
+        .unwrap_or(&upstream)
+        .to_string();
 
-    let index_data = read_index_data().context("could not get index data")?;
+    Ok(Some(UpstreamStatus { name, ahead_behind }))
+}
+
+/// Returns the status of every file in the working tree, index and last commit, including the
+/// unchanged ones.
+///
+/// # Errors
+///
+/// This function can fail if the commit, index or working tree data could not be read.
+pub fn get_statuses(repo: &Repository) -> Result<Vec<FileWithStatus>> {
+    let head_versions: HashMap<PathBuf, FileVersion> =
+        match fs::get_last_commit_hash(repo).context("could not get last commit hash")? {
+            Some(hash) => read_commit_entries(repo, hash)
+                .context("could not get commit data")?
+                .into_iter()
+                .map(|e| {
+                    let version = FileVersion {
+                        mode: e.mode,
+                        hash: e.hash,
+                    };
+                    (e.path, version)
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+    let mut commit_data: CommitData = head_versions
+        .iter()
+        .map(|(path, version)| (path.clone(), version.hash.clone()))
+        .collect();
+
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    let mut index_versions: HashMap<PathBuf, FileVersion> = HashMap::new();
+    let mut conflicts: HashMap<PathBuf, Conflict> = HashMap::new();
+    for ie in index.entries() {
+        let version = FileVersion {
+            mode: as_octal(ie.mode),
+            hash: ie.object_hash(),
+        };
+        let path = ie.path().to_owned();
+        match ie.get_stage() {
+            FileStage::Normal => {
+                index_versions.insert(path, version);
+            }
+            FileStage::Base => conflicts.entry(path).or_default().base = Some(version),
+            FileStage::Ours => conflicts.entry(path).or_default().ours = Some(version),
+            FileStage::Theirs => conflicts.entry(path).or_default().theirs = Some(version),
+        }
+    }
+    // files with conflicts are reported as they are, without comparing them with anything
+    let mut index_data = read_index_data(repo).context("could not get index data")?;
+    for path in conflicts.keys() {
+        commit_data.remove(path);
+        index_data.remove(path);
+    }
 
-    let working_tree_data = read_working_tree_data().context("could not get working tree data")?;
+    let working_tree_data: Vec<FileData> = read_working_tree_data(repo)
+        .context("could not get working tree data")?
+        .into_iter()
+        .filter(|fd| !conflicts.contains_key(&fd.path))
+        .collect();
 
-    let file_statuses = determine_statuses(commit_data, index_data, working_tree_data);
+    let mut file_statuses = determine_statuses(commit_data, index_data, working_tree_data);
+    for (path, conflict) in conflicts {
+        file_statuses.push(FileWithStatus::new(
+            path,
+            Status::Unmerged(conflict),
+            StageStatus::NotCommit,
+        ));
+    }
+
+    let root = repo.working_tree();
+    for fws in file_statuses.iter_mut() {
+        let head_path = match (&fws.status, &fws.stage_status) {
+            (Status::Moved { previous }, StageStatus::Commit) => previous,
+            _ => &fws.path,
+        };
+        fws.head = head_versions.get(head_path).cloned();
+        fws.index = index_versions.get(&fws.path).cloned();
+        fws.worktree_mode = std::fs::symlink_metadata(root.join(&fws.path))
+            .ok()
+            .map(|m| as_octal(m.mode()));
+    }
 
-    Ok(format_status(file_statuses, no_commits))
+    Ok(file_statuses)
 }
 
 #[allow(unused_assignments)]
@@ -178,11 +408,7 @@ fn determine_statuses(
         };
 
         // Adding file statuses that are not new, moved or deleted
-        file_statuses.push(FileWithStatus {
-            path: file_path,
-            status,
-            stage_status,
-        })
+        file_statuses.push(FileWithStatus::new(file_path, status, stage_status))
     }
 
     // Processing deleted or moved files
@@ -198,16 +424,12 @@ fn determine_statuses(
 
         file_statuses.push(match moved_file_data {
             // New file has the same hash as a deleted file, so the deleted file has just been moved.
-            Some((new_path, _)) => FileWithStatus {
-                path: new_path,
-                status: Status::Moved { previous: path },
-                stage_status: StageStatus::NotCommit,
-            },
-            None => FileWithStatus {
-                path,
-                status: Status::Deleted,
-                stage_status: StageStatus::NotCommit,
-            },
+            Some((new_path, _)) => FileWithStatus::new(
+                new_path,
+                Status::Moved { previous: path },
+                StageStatus::NotCommit,
+            ),
+            None => FileWithStatus::new(path, Status::Deleted, StageStatus::NotCommit),
         });
     }
 
@@ -218,26 +440,18 @@ fn determine_statuses(
 
         file_statuses.push(match moved_file_data {
             // New file has the same hash as a deleted file, so the deleted file has just been moved.
-            Some((new_path, _)) => FileWithStatus {
-                path: new_path,
-                status: Status::Moved { previous: path },
-                stage_status: StageStatus::Commit,
-            },
-            None => FileWithStatus {
-                path,
-                status: Status::Deleted,
-                stage_status: StageStatus::Commit,
-            },
+            Some((new_path, _)) => FileWithStatus::new(
+                new_path,
+                Status::Moved { previous: path },
+                StageStatus::Commit,
+            ),
+            None => FileWithStatus::new(path, Status::Deleted, StageStatus::Commit),
         });
     }
 
     // Finally, we add the new files.
     for (path, stage_status) in possibly_moved_files.into_values() {
-        file_statuses.push(FileWithStatus {
-            path,
-            status: Status::New,
-            stage_status,
-        });
+        file_statuses.push(FileWithStatus::new(path, Status::New, stage_status));
     }
 
     file_statuses
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result, bail};
+
+use crate::config::Config;
+use crate::hashing::Hash;
+use crate::object::commit::{CommitUser, CommitUserKind};
+use crate::object::{Object, ObjectKind};
+use crate::repository::Repository;
+use crate::{fs, revision};
+
+/// Returns the name of every tag, sorted.
+pub fn list_tags(repo: &Repository) -> Result<Vec<String>> {
+    fs::get_all_tag_names(repo).context("could not get tag names")
+}
+
+/// Creates a tag called `name` pointing to `commit`, or to the last commit if it is not provided.
+/// With a `message`, the tag points to a new tag object that points to the commit.
+///
+/// # Returns
+///
+/// The hash of the tagged commit.
+///
+/// # Errors
+///
+/// This function will fail if the name is not valid, the tag already exists or the commit could
+/// not be resolved.
+pub fn create_tag(
+    repo: &Repository,
+    name: &str,
+    commit: Option<&str>,
+    message: Option<&str>,
+) -> Result<Hash> {
+    check_tag_name(name)?;
+    if fs::read_tag(repo, name)
+        .context("could not read tag")?
+        .is_some()
+    {
+        bail!("tag '{}' already exists", name)
+    }
+
+    let commit = match commit {
+        Some(reference) => revision::resolve_commit(repo, reference)?,
+        None => fs::get_last_commit_hash(repo)
+            .context("could not get last commit hash")?
+            .context("not a valid object name: there are no commits yet")?,
+    };
+
+    let target = match message {
+        Some(message) => {
+            // git uses the committer identity for taggers
+            let config = Config::load(repo).context("could not read config")?;
+            let mut tagger = CommitUser::from_identity(CommitUserKind::Committer, &config)?;
+            tagger.kind = CommitUserKind::Tagger;
+
+            let tag = Object::Tag {
+                object: commit.clone(),
+                kind: ObjectKind::Commit,
+                tag: name.into(),
+                tagger: Some(tagger),
+                message: format!("{}\n", message.trim_end()).into(),
+            };
+            fs::object::write_object(repo, &tag).context("could not write tag object")?
+        }
+        None => commit.clone(),
+    };
+
+    fs::write_tag(repo, name, &target).context("could not write tag")?;
+
+    Ok(commit)
+}
+
+/// Deletes the tag called `name`, returning the hash it pointed to.
+///
+/// # Errors
+///
+/// This function will fail if the name is not valid or the tag does not exist.
+pub fn delete_tag(repo: &Repository, name: &str) -> Result<Hash> {
+    check_tag_name(name)?;
+    let hash = fs::read_tag(repo, name)
+        .context("could not read tag")?
+        .context(format!("tag '{}' not found", name))?;
+
+    fs::delete_tag(repo, name).context("could not delete tag")?;
+
+    Ok(hash)
+}
+
+/// Fails if `name` is not a valid tag name.
+fn check_tag_name(name: &str) -> Result<()> {
+    if !fs::is_valid_ref_name(name) {
+        bail!("'{}' is not a valid tag name", name)
+    }
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::{commit_files, create_repository, remove_repository};
+
+    #[test]
+    pub fn test_create_and_delete_tags() {
+        let repo = create_repository("tags");
+        let head = commit_files(&repo, &[("file", "content\n")], "first");
+
+        assert_eq!(create_tag(&repo, "v1", None, None).unwrap(), head);
+        assert_eq!(create_tag(&repo, "v2", None, Some("second")).unwrap(), head);
+        assert!(create_tag(&repo, "v1", None, None).is_err());
+        assert_eq!(list_tags(&repo).unwrap(), vec!["v1", "v2"]);
+
+        let annotated = fs::read_tag(&repo, "v2").unwrap().unwrap();
+        assert_ne!(annotated, head);
+        assert_eq!(delete_tag(&repo, "v2").unwrap(), annotated);
+        assert_eq!(delete_tag(&repo, "v1").unwrap(), head);
+        assert!(delete_tag(&repo, "v1").is_err());
+        assert!(list_tags(&repo).unwrap().is_empty());
+
+        remove_repository(&repo);
+    }
+
+    #[test]
+    pub fn test_tag_names_outside_of_tags() {
+        let repo = create_repository("tag-names");
+        let head = commit_files(&repo, &[("file", "content\n")], "first");
+        fs::write_ref(&repo, "ORIG_HEAD", &head).unwrap();
+
+        assert!(delete_tag(&repo, "../../ORIG_HEAD").is_err());
+        assert!(create_tag(&repo, "../../ORIG_HEAD", None, None).is_err());
+        assert_eq!(fs::read_ref(&repo, "ORIG_HEAD").unwrap(), Some(head));
+
+        remove_repository(&repo);
+    }
+}
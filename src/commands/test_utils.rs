@@ -0,0 +1,101 @@
+//! Repositories in temporary directories for the tests of the commands.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::config::ConfigFile;
+use crate::fs;
+use crate::hashing::Hash;
+use crate::object::commit::CommitUser;
+use crate::repository::Repository;
+
+use super::commit::{write_commit, write_tree};
+use super::{add, commit, init};
+
+/// Values written to the config of every test repository, so the identity and the style of the
+/// conflicts do not depend on the global config.
+const TEST_CONFIG: &[(&str, &str)] = &[
+    ("user.name", "Test"),
+    ("user.email", "test@example.com"),
+    ("merge.conflictStyle", "merge"),
+];
+
+/// Creates a repository with no commits in a temporary directory.
+pub fn create_repository(name: &str) -> Repository {
+    let root = std::env::temp_dir().join(format!("commands-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let repo = Repository::in_dir(&root.canonicalize().unwrap());
+    init(&repo).unwrap();
+    for (name, value) in TEST_CONFIG {
+        set_config_value(&repo, name, value);
+    }
+    repo
+}
+
+/// Sets `name` to `value` in the config of the repository.
+pub fn set_config_value(repo: &Repository, name: &str, value: &str) {
+    let content = std::fs::read_to_string(repo.config_path()).unwrap();
+    let mut config = ConfigFile::parse(&content).unwrap();
+    config.set(name, value).unwrap();
+    std::fs::write(repo.config_path(), config.to_string()).unwrap();
+}
+
+pub fn remove_repository(repo: &Repository) {
+    std::fs::remove_dir_all(repo.working_tree()).unwrap();
+}
+
+pub fn write_file(repo: &Repository, path: &str, content: &str) {
+    let path = repo.working_tree().join(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    std::fs::write(path, content).unwrap();
+}
+
+/// Returns the contents of the file in the working tree, or `None` if it does not exist.
+pub fn read_file(repo: &Repository, path: &str) -> Option<String> {
+    std::fs::read_to_string(repo.working_tree().join(path)).ok()
+}
+
+pub fn add_files(repo: &Repository, paths: &[&str]) {
+    let paths: Vec<OsString> = paths
+        .iter()
+        .map(|p| repo.working_tree().join(Path::new(p)).into_os_string())
+        .collect();
+    add(repo, &paths).unwrap();
+}
+
+/// Writes and adds every file, then commits them with `message`.
+pub fn commit_files(repo: &Repository, files: &[(&str, &str)], message: &str) -> Hash {
+    for (path, content) in files {
+        write_file(repo, path, content);
+    }
+    let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+    add_files(repo, &paths);
+    commit(repo, message).unwrap()
+}
+
+/// Like `commit_files`, with `author` instead of the identity in the config.
+pub fn commit_files_as(
+    repo: &Repository,
+    files: &[(&str, &str)],
+    message: &str,
+    author: CommitUser,
+) -> Hash {
+    for (path, content) in files {
+        write_file(repo, path, content);
+    }
+    let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+    add_files(repo, &paths);
+
+    let index = fs::index::read_index_file(repo).unwrap();
+    let tree = write_tree(repo, &index).unwrap();
+    let parents = fs::get_last_commit_hash(repo)
+        .unwrap()
+        .into_iter()
+        .collect();
+    let commit = write_commit(repo, tree, parents, message, Some(author)).unwrap();
+    fs::update_head(repo, &commit).unwrap();
+    commit
+}
@@ -0,0 +1,387 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use super::ConfigFile;
+use super::file::split_name;
+use crate::repository::Repository;
+
+/// Name of the key whose values are paths of other config files to read.
+const INCLUDE_PATH: &str = "include.path";
+/// Limit of nested includes, to stop include cycles.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// The groups of config files, from the least to the most specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// `/etc/gitconfig`, or `$GIT_CONFIG_SYSTEM`
+    System,
+    /// `$XDG_CONFIG_HOME/git/config` and `~/.gitconfig`, or `$GIT_CONFIG_GLOBAL`
+    Global,
+    /// The `config` file of the repository
+    Local,
+}
+
+/// A single value read from a config file.
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    /// Full name, with the section and key lowercased
+    pub name: String,
+    /// `None` for keys without `=`, which are implicitly `true`
+    pub value: Option<String>,
+}
+
+/// Configuration values read from git config files, stored as `section.key` or
+/// `section.subsection.key` names.
+///
+/// Files are read from the least to the most specific one (system, global, then repository), so
+/// values read later override the ones read before.
+#[derive(Debug, Default)]
+pub struct Config {
+    entries: Vec<ConfigEntry>,
+}
+
+impl Config {
+    /// Reads the config files of every scope, with the local one being the config of `repo`,
+    /// skipping the ones that do not exist.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if a config file exists but could not be read or parsed.
+    pub fn load(repo: &Repository) -> Result<Self> {
+        Config::load_scopes(
+            &[ConfigScope::System, ConfigScope::Global, ConfigScope::Local],
+            Some(repo),
+        )
+    }
+
+    /// Reads only the config files of the given scopes. The local scope is skipped when there is
+    /// no repository.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if a config file exists but could not be read or parsed.
+    pub fn load_scopes(scopes: &[ConfigScope], repo: Option<&Repository>) -> Result<Self> {
+        let mut config = Config::default();
+        for scope in scopes {
+            for path in Config::scope_paths(*scope, repo) {
+                if path.is_file() {
+                    config.read_file(&path, 0)?;
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the paths of the files read for `scope`, in the order they are read. The local
+    /// scope has no files without a repository.
+    pub fn scope_paths(scope: ConfigScope, repo: Option<&Repository>) -> Vec<PathBuf> {
+        match scope {
+            ConfigScope::System => {
+                if env::var_os("GIT_CONFIG_NOSYSTEM").is_some() {
+                    return Vec::new();
+                }
+                match env::var_os("GIT_CONFIG_SYSTEM") {
+                    Some(path) => vec![PathBuf::from(path)],
+                    None => vec![PathBuf::from("/etc/gitconfig")],
+                }
+            }
+            ConfigScope::Global => {
+                if let Some(path) = env::var_os("GIT_CONFIG_GLOBAL") {
+                    return vec![PathBuf::from(path)];
+                }
+                let home = env::var_os("HOME").map(PathBuf::from);
+                let xdg = match env::var_os("XDG_CONFIG_HOME") {
+                    Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+                    _ => home.as_ref().map(|h| h.join(".config")),
+                };
+
+                let mut paths = Vec::new();
+                if let Some(dir) = xdg {
+                    paths.push(dir.join("git").join("config"));
+                }
+                if let Some(home) = home {
+                    paths.push(home.join(".gitconfig"));
+                }
+                paths
+            }
+            ConfigScope::Local => repo.map(|r| r.config_path()).into_iter().collect(),
+        }
+    }
+
+    /// Returns the file edited when changing values of `scope`.
+    ///
+    /// For the global scope it is `~/.gitconfig`, unless only the XDG file exists.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the scope has no file, like the system scope when
+    /// `GIT_CONFIG_NOSYSTEM` is set, the global scope without a home directory or the local scope
+    /// without a repository.
+    pub fn write_path(scope: ConfigScope, repo: Option<&Repository>) -> Result<PathBuf> {
+        let mut paths = Config::scope_paths(scope, repo);
+        if scope == ConfigScope::Global
+            && let [xdg, home] = paths.as_slice()
+            && xdg.is_file()
+            && !home.is_file()
+        {
+            return Ok(xdg.clone());
+        }
+        paths
+            .pop()
+            .context("there is no config file for this scope")
+    }
+
+    /// Returns the last value set for `name`, where section and key names are case-insensitive.
+    /// Keys without a value are returned as `true`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_entry(name)
+            .map(|e| e.value.as_deref().unwrap_or("true"))
+    }
+
+    /// Returns every value set for `name`, in the order they were read.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        let name = normalize_name(name);
+        self.entries
+            .iter()
+            .filter(|e| e.name == name)
+            .map(|e| e.value.as_deref().unwrap_or("true"))
+            .collect()
+    }
+
+    /// Returns the last value of `name` as a boolean.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the value is not a valid boolean.
+    pub fn get_bool(&self, name: &str) -> Result<Option<bool>> {
+        let Some(entry) = self.get_entry(name) else {
+            return Ok(None);
+        };
+        match &entry.value {
+            None => Ok(Some(true)),
+            Some(value) => parse_bool(value)
+                .map(Some)
+                .context(format!("bad boolean config value for '{}'", name)),
+        }
+    }
+
+    /// Returns the last value of `name` as an integer, which can have a `k`, `m` or `g` suffix.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the value is not a valid integer.
+    pub fn get_int(&self, name: &str) -> Result<Option<i64>> {
+        self.get(name)
+            .map(parse_int)
+            .transpose()
+            .context(format!("bad numeric config value for '{}'", name))
+    }
+
+    /// Returns the last value of `name` as a path, replacing a leading `~/` with the home
+    /// directory.
+    pub fn get_path(&self, name: &str) -> Option<PathBuf> {
+        self.get(name).map(expand_home)
+    }
+
+    /// Returns every value, in the order they were read.
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+
+    fn get_entry(&self, name: &str) -> Option<&ConfigEntry> {
+        let name = normalize_name(name);
+        self.entries.iter().rev().find(|e| e.name == name)
+    }
+
+    /// Adds the values of the file at `path`, followed by the ones of every file it includes
+    /// right where they are included.
+    fn read_file(&mut self, path: &Path, depth: usize) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            bail!("exceeded maximum include depth reading {:?}", path)
+        }
+
+        let content = std::fs::read_to_string(path)
+            .context(format!("could not read config file {:?}", path))?;
+        let file = ConfigFile::parse(&content)
+            .context(format!("could not parse config file {:?}", path))?;
+
+        for (name, value) in file.entries() {
+            let include = match value {
+                Some(value) if name == INCLUDE_PATH => Some(resolve_include(path, value)),
+                _ => None,
+            };
+            self.entries.push(ConfigEntry {
+                name,
+                value: value.map(String::from),
+            });
+
+            // missing included files are ignored
+            if let Some(include) = include
+                && include.is_file()
+            {
+                self.read_file(&include, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    /// Parses the contents of a single config file as a local one, without following its
+    /// includes.
+    fn from_str(s: &str) -> Result<Self> {
+        let file = ConfigFile::parse(s)?;
+        let entries = file
+            .entries()
+            .map(|(name, value)| ConfigEntry {
+                name,
+                value: value.map(String::from),
+            })
+            .collect();
+        Ok(Config { entries })
+    }
+}
+
+/// Resolves an included path, which can start with `~/` or be relative to the directory of the
+/// file including it.
+fn resolve_include(including: &Path, value: &str) -> PathBuf {
+    let path = expand_home(value);
+    if path.is_absolute() {
+        return path;
+    }
+    including
+        .parent()
+        .map_or(path.clone(), |dir| dir.join(&path))
+}
+
+/// Replaces a leading `~/` in `value` with the home directory.
+fn expand_home(value: &str) -> PathBuf {
+    if let Some(rest) = value.strip_prefix("~/")
+        && let Some(home) = env::var_os("HOME")
+    {
+        return PathBuf::from(home).join(rest);
+    }
+    PathBuf::from(value)
+}
+
+/// Parses a boolean the way git does: `true`, `yes`, `on` and `1` are true, while `false`, `no`,
+/// `off`, `0` and the empty string are false. Words are case-insensitive.
+///
+/// # Errors
+///
+/// This function will fail if the value is none of the above.
+pub fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        _ => bail!("'{}' is not a boolean", value),
+    }
+}
+
+/// Parses an integer which can be followed by `k`, `m` or `g` (case-insensitive) to multiply it
+/// by 1024, 1024² or 1024³.
+///
+/// # Errors
+///
+/// This function will fail if the value is not a number or it overflows.
+pub fn parse_int(value: &str) -> Result<i64> {
+    let value = value.trim();
+    let (number, factor) = match value.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => (&value[..value.len() - 1], 1 << 10),
+        Some('m') => (&value[..value.len() - 1], 1 << 20),
+        Some('g') => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    let number: i64 = number
+        .parse()
+        .context(format!("'{}' is not a number", value))?;
+    number
+        .checked_mul(factor)
+        .context(format!("'{}' is out of range", value))
+}
+
+/// Lowercases the section and the key of a name, keeping the case of the subsection.
+fn normalize_name(name: &str) -> String {
+    match split_name(name) {
+        Ok((section, key)) => format!("{}.{}", section, key),
+        // invalid names can not match any entry
+        Err(_) => name.to_string(),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_and_get() {
+        let config = Config::from_str(
+            "# comment\n[user]\n\tname = John Doe\n\tEmail = \"john@example.com\"\n\
+             [branch \"Main\"]\n\tremote = origin\n[core]\n\tbare\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.get("user.name"), Some("John Doe"));
+        assert_eq!(config.get("USER.email"), Some("john@example.com"));
+        assert_eq!(config.get("branch.Main.remote"), Some("origin"));
+        assert_eq!(config.get("branch.main.remote"), None);
+        assert_eq!(config.get("core.bare"), Some("true"));
+        assert_eq!(config.get("user.missing"), None);
+    }
+
+    #[test]
+    pub fn test_multiple_values() {
+        let config = Config::from_str("[remote \"o\"]\nfetch = a\nfetch = b\n").unwrap();
+        assert_eq!(config.get("remote.o.fetch"), Some("b"));
+        assert_eq!(config.get_all("remote.o.fetch"), vec!["a", "b"]);
+    }
+
+    #[test]
+    pub fn test_typed_values() {
+        let config = Config::from_str(
+            "[core]\n\tbare\n\tfilemode = Off\n\tempty =\n\tsize = 2k\n\tbig = 3G\n\tbad = maybe\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.get_bool("core.bare").unwrap(), Some(true));
+        assert_eq!(config.get_bool("core.filemode").unwrap(), Some(false));
+        assert_eq!(config.get_bool("core.empty").unwrap(), Some(false));
+        assert_eq!(config.get_bool("core.missing").unwrap(), None);
+        assert!(config.get_bool("core.bad").is_err());
+
+        assert_eq!(config.get_int("core.size").unwrap(), Some(2048));
+        assert_eq!(config.get_int("core.big").unwrap(), Some(3 << 30));
+        assert!(config.get_int("core.bad").is_err());
+    }
+
+    #[test]
+    pub fn test_includes() {
+        let dir = env::temp_dir().join(format!("config-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(
+            dir.join("main"),
+            "[user]\nname = Before\n[include]\npath = nested/included\n[core]\nbare = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("nested").join("included"),
+            "[user]\nname = Included\n[core]\nbare = false\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.read_file(&dir.join("main"), 0).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // included values are placed where the include is
+        assert_eq!(config.get("user.name"), Some("Included"));
+        assert_eq!(config.get_bool("core.bare").unwrap(), Some(true));
+    }
+}
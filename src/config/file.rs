@@ -0,0 +1,572 @@
+use std::fmt::Display;
+
+use anyhow::{Context, Result, bail};
+
+/// A single config file, keeping every line as it was read so it can be edited without losing
+/// its comments or layout.
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug)]
+enum Line {
+    /// A `[section]` or `[section "subsection"]` header, `name` is normalized as `section` or
+    /// `section.subsection`.
+    Section { raw: String, name: String },
+    /// A `key = value` line, which might span several physical lines. `value` is `None` for keys
+    /// without `=`, which are implicitly `true`.
+    Entry {
+        raw: String,
+        section: String,
+        key: String,
+        value: Option<String>,
+    },
+    /// Empty lines and comments.
+    Other { raw: String },
+}
+
+impl ConfigFile {
+    /// Parses the contents of a config file.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if there is a malformed section header or key, a key outside of a
+    /// section, an unclosed quote or an invalid escape sequence.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut lines = Vec::new();
+        let mut section: Option<String> = None;
+
+        let mut physical = content.split_inclusive('\n').enumerate().peekable();
+        while let Some((number, line)) = physical.next() {
+            let context = || format!("invalid config line {}", number + 1);
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                lines.push(Line::Other {
+                    raw: line.to_string(),
+                });
+                continue;
+            }
+
+            if trimmed.starts_with('[') {
+                let name = parse_section_header(trimmed).with_context(context)?;
+                section = Some(name.clone());
+                lines.push(Line::Section {
+                    raw: line.to_string(),
+                    name,
+                });
+                continue;
+            }
+
+            let Some(current) = &section else {
+                bail!("key outside of a section on line {}", number + 1)
+            };
+
+            // values can continue on the next line if it ends with a backslash
+            let mut raw = line.to_string();
+            while ends_with_continuation(&raw) {
+                match physical.next() {
+                    Some((_, next)) => raw.push_str(next),
+                    None => break,
+                }
+            }
+
+            let (key, value) = parse_entry(&raw).with_context(context)?;
+            lines.push(Line::Entry {
+                raw,
+                section: current.clone(),
+                key,
+                value,
+            });
+        }
+
+        Ok(ConfigFile { lines })
+    }
+
+    /// Returns the full name (`section.key` or `section.subsection.key`) and value of every entry,
+    /// in the order they appear.
+    pub fn entries(&self) -> impl Iterator<Item = (String, Option<&str>)> + '_ {
+        self.lines.iter().filter_map(|l| match l {
+            Line::Entry {
+                section,
+                key,
+                value,
+                ..
+            } => Some((format!("{}.{}", section, key), value.as_deref())),
+            _ => None,
+        })
+    }
+
+    /// Sets `name` to `value`, replacing its current value. If the key does not exist, it is
+    /// added at the end of its section, creating the section if needed.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if `name` is not valid or the key has more than one value.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let (section, key) = split_name(name)?;
+
+        let positions = self.entry_positions(&section, &key);
+        match positions.as_slice() {
+            [] => self.insert_entry(&section, &key, name, value),
+            [position] => {
+                self.lines[*position] = Line::Entry {
+                    raw: format_entry(name, value),
+                    section,
+                    key,
+                    value: Some(value.to_string()),
+                };
+            }
+            _ => bail!("'{}' has multiple values, it can not be set", name),
+        }
+
+        Ok(())
+    }
+
+    /// Adds a new value to `name`, keeping the ones it already had.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if `name` is not valid.
+    pub fn add(&mut self, name: &str, value: &str) -> Result<()> {
+        let (section, key) = split_name(name)?;
+        self.insert_entry(&section, &key, name, value);
+        Ok(())
+    }
+
+    /// Removes the value of `name`. Sections left empty are removed too.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the key did not exist.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if `name` is not valid or the key has more than one value.
+    pub fn unset(&mut self, name: &str) -> Result<bool> {
+        let (section, key) = split_name(name)?;
+
+        let positions = self.entry_positions(&section, &key);
+        let position = match positions.as_slice() {
+            [] => return Ok(false),
+            [position] => *position,
+            _ => bail!("'{}' has multiple values, it can not be unset", name),
+        };
+        self.lines.remove(position);
+
+        // removing the section header if nothing but blank lines is left under it
+        let header = self.lines[..position]
+            .iter()
+            .rposition(|l| matches!(l, Line::Section { .. }));
+        if let Some(header) = header {
+            let end = self.lines[header + 1..]
+                .iter()
+                .position(|l| matches!(l, Line::Section { .. }))
+                .map_or(self.lines.len(), |p| header + 1 + p);
+            let empty = self.lines[header + 1..end]
+                .iter()
+                .all(|l| matches!(l, Line::Other { raw } if raw.trim().is_empty()));
+            if empty {
+                self.lines.drain(header..end);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn entry_positions(&self, section: &str, key: &str) -> Vec<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| matches!(l, Line::Entry { section: s, key: k, .. } if s == section && k == key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Inserts a new entry after the last line of the last `section` header, or appends a new
+    /// section if it does not exist.
+    fn insert_entry(&mut self, section: &str, key: &str, name: &str, value: &str) {
+        let entry = Line::Entry {
+            raw: format_entry(name, value),
+            section: section.to_string(),
+            key: key.to_string(),
+            value: Some(value.to_string()),
+        };
+
+        let header = self
+            .lines
+            .iter()
+            .rposition(|l| matches!(l, Line::Section { name, .. } if name == section));
+        let Some(header) = header else {
+            if let Some(last) = self.lines.last_mut()
+                && !last.raw().ends_with('\n')
+            {
+                last.raw_mut().push('\n');
+            }
+            self.lines.push(Line::Section {
+                raw: format_section_header(name),
+                name: section.to_string(),
+            });
+            self.lines.push(entry);
+            return;
+        };
+
+        // after the last entry of the section, so trailing comments and blank lines stay below
+        let mut position = header + 1;
+        for (i, line) in self.lines.iter().enumerate().skip(header + 1) {
+            match line {
+                Line::Section { .. } => break,
+                Line::Entry { .. } => position = i + 1,
+                Line::Other { .. } => (),
+            }
+        }
+        if let Some(previous) = self.lines.get_mut(position - 1)
+            && !previous.raw().ends_with('\n')
+        {
+            previous.raw_mut().push('\n');
+        }
+        self.lines.insert(position, entry);
+    }
+}
+
+impl Line {
+    fn raw(&self) -> &str {
+        match self {
+            Line::Section { raw, .. } | Line::Entry { raw, .. } | Line::Other { raw } => raw,
+        }
+    }
+
+    fn raw_mut(&mut self) -> &mut String {
+        match self {
+            Line::Section { raw, .. } | Line::Entry { raw, .. } | Line::Other { raw } => raw,
+        }
+    }
+}
+
+impl Display for ConfigFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in self.lines.iter() {
+            f.write_str(line.raw())?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a full name into its normalized section (`section` or `section.subsection`) and key.
+/// Section and key names are case-insensitive, subsections are not.
+///
+/// # Errors
+///
+/// This function will fail if the name does not have a section and a key, or any of them has
+/// invalid characters.
+pub fn split_name(name: &str) -> Result<(String, String)> {
+    let (section, rest) = name
+        .split_once('.')
+        .context(format!("key '{}' does not contain a section", name))?;
+    let (subsection, key) = match rest.rsplit_once('.') {
+        Some((subsection, key)) => (Some(subsection), key),
+        None => (None, rest),
+    };
+
+    let valid_section = !section.is_empty()
+        && section
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid_section || !valid_key || subsection.is_some_and(|s| s.contains('\n')) {
+        bail!("invalid key: {}", name)
+    }
+
+    let section = match subsection {
+        Some(subsection) => format!("{}.{}", section.to_lowercase(), subsection),
+        None => section.to_lowercase(),
+    };
+    Ok((section, key.to_lowercase()))
+}
+
+/// Parses a section header line, returning its normalized name.
+fn parse_section_header(line: &str) -> Result<String> {
+    let content = line
+        .strip_prefix('[')
+        .context("section header does not start with [")?;
+
+    let name_end = content
+        .find([' ', '\t', ']', '"'])
+        .context("unclosed section header")?;
+    let name = &content[..name_end];
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    {
+        bail!("invalid section name '{}'", name)
+    }
+
+    let rest = content[name_end..].trim_start();
+    let (normalized, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+        // [section "subsection"], only \" and \\ are escaped
+        let mut subsection = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => subsection.push(c),
+                    None => bail!("unclosed subsection"),
+                },
+                Some((i, '"')) => break i,
+                Some((_, c)) => subsection.push(c),
+                None => bail!("unclosed subsection"),
+            }
+        };
+        (
+            format!("{}.{}", name.to_lowercase(), subsection),
+            &quoted[end + 1..],
+        )
+    } else {
+        // the deprecated [section.subsection] syntax, where the subsection is lowercased
+        (name.to_lowercase(), rest)
+    };
+
+    let rest = rest
+        .strip_prefix(']')
+        .context("section header is not closed")?
+        .trim();
+    if !rest.is_empty() && !rest.starts_with('#') && !rest.starts_with(';') {
+        bail!("unexpected content after section header")
+    }
+
+    Ok(normalized)
+}
+
+/// Parses a (possibly multiline) entry, returning its lowercased key and its value.
+fn parse_entry(raw: &str) -> Result<(String, Option<String>)> {
+    let line = raw.trim_start();
+    let key_end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(line.len());
+    let key = &line[..key_end];
+    if !key.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        bail!("invalid key name")
+    }
+
+    let rest = line[key_end..].trim_start_matches([' ', '\t']);
+    let value = match rest.strip_prefix('=') {
+        Some(value) => Some(parse_value(value)?),
+        None if rest.trim().is_empty() || rest.starts_with(['#', ';']) => None,
+        None => bail!("expected '=' after key '{}'", key),
+    };
+
+    Ok((key.to_lowercase(), value))
+}
+
+/// Parses a value the way git does: whitespace outside of quotes is turned into spaces (and
+/// removed at the ends), `#` and `;` start a comment and backslashes escape `"`, `\`, `n`,
+/// `t`, `b` or a new line.
+fn parse_value(raw: &str) -> Result<String> {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut pending_spaces = 0;
+
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' | '\r' if !quoted => break,
+            '\n' => bail!("unclosed quote"),
+            c if c.is_whitespace() && !quoted => {
+                if !value.is_empty() {
+                    pending_spaces += 1;
+                }
+                continue;
+            }
+            '#' | ';' if !quoted => break,
+            _ => (),
+        }
+
+        for _ in 0..pending_spaces {
+            value.push(' ');
+        }
+        pending_spaces = 0;
+
+        match c {
+            '\\' => match chars.next() {
+                Some('\n') => (),
+                Some('\r') if chars.next() == Some('\n') => (),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('b') => {
+                    value.pop();
+                }
+                Some(c @ ('"' | '\\')) => value.push(c),
+                _ => bail!("invalid escape sequence"),
+            },
+            '"' => quoted = !quoted,
+            c => value.push(c),
+        }
+    }
+
+    if quoted {
+        bail!("unclosed quote")
+    }
+    Ok(value)
+}
+
+/// Returns `true` if the line ends with a backslash that is not escaped.
+fn ends_with_continuation(raw: &str) -> bool {
+    let line = raw.trim_end_matches(['\n', '\r']);
+    let backslashes = line.chars().rev().take_while(|c| *c == '\\').count();
+    backslashes % 2 == 1
+}
+
+/// Formats an entry line, quoting and escaping the value when needed.
+fn format_entry(name: &str, value: &str) -> String {
+    let key = name.rsplit('.').next().unwrap_or(name);
+
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+
+    let needs_quotes = value.starts_with(' ')
+        || value.ends_with(' ')
+        || value.contains(['#', ';'])
+        || value.contains("  ");
+    if needs_quotes {
+        format!("\t{} = \"{}\"\n", key, escaped)
+    } else {
+        format!("\t{} = {}\n", key, escaped)
+    }
+}
+
+/// Formats the header of the section of `name`.
+fn format_section_header(name: &str) -> String {
+    let (section, rest) = name.split_once('.').unwrap_or((name, ""));
+    match rest.rsplit_once('.') {
+        Some((subsection, _)) => format!(
+            "[{} \"{}\"]\n",
+            section,
+            subsection.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => format!("[{}]\n", section),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CONFIG: &str = "# global comment\n\
+                               [core]\n\
+                               \tbare = false ; inline comment\n\
+                               \tfilemode\n\
+                               \n\
+                               [remote \"Origin\"]\n\
+                               \turl = \"https://example.com/a;b\"\n\
+                               \tfetch = +refs/heads/*:refs/remotes/origin/*\n\
+                               \tfetch = +refs/tags/*:refs/tags/*\n\
+                               [Section.Legacy]\n\
+                               \tKey = multi \\\n  line\n";
+
+    fn entries(file: &ConfigFile) -> Vec<(String, Option<String>)> {
+        file.entries()
+            .map(|(n, v)| (n, v.map(String::from)))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_parse_entries() {
+        let file = ConfigFile::parse(TEST_CONFIG).unwrap();
+        let expected = [
+            ("core.bare", Some("false")),
+            ("core.filemode", None),
+            ("remote.Origin.url", Some("https://example.com/a;b")),
+            (
+                "remote.Origin.fetch",
+                Some("+refs/heads/*:refs/remotes/origin/*"),
+            ),
+            ("remote.Origin.fetch", Some("+refs/tags/*:refs/tags/*")),
+            ("section.legacy.key", Some("multi   line")),
+        ];
+        let expected: Vec<(String, Option<String>)> = expected
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.map(String::from)))
+            .collect();
+        assert_eq!(entries(&file), expected);
+    }
+
+    #[test]
+    pub fn test_parse_values() {
+        assert_eq!(parse_value(" a \t b \t").unwrap(), "a   b");
+        assert_eq!(parse_value("\" a  b \"").unwrap(), " a  b ");
+        assert_eq!(parse_value("a\\tb\\\\c\\\"").unwrap(), "a\tb\\c\"");
+        assert_eq!(parse_value("value # comment").unwrap(), "value");
+        assert!(parse_value("\"unclosed").is_err());
+        assert!(parse_value("bad \\x escape").is_err());
+    }
+
+    #[test]
+    pub fn test_parse_errors() {
+        assert!(ConfigFile::parse("key = outside").is_err());
+        assert!(ConfigFile::parse("[core\n").is_err());
+        assert!(ConfigFile::parse("[core]\n1key = x\n").is_err());
+        assert!(ConfigFile::parse("[core]\nkey value\n").is_err());
+    }
+
+    #[test]
+    pub fn test_set_preserves_layout() {
+        let mut file = ConfigFile::parse(TEST_CONFIG).unwrap();
+        file.set("core.bare", "true").unwrap();
+        file.set("core.editor", "vim").unwrap();
+        file.set("user.name", "John Doe").unwrap();
+        file.set("remote.Origin.url", "with # hash").unwrap();
+
+        let expected = TEST_CONFIG
+            .replace("\tbare = false ; inline comment\n", "\tbare = true\n")
+            .replace("\tfilemode\n", "\tfilemode\n\teditor = vim\n")
+            .replace(
+                "\turl = \"https://example.com/a;b\"\n",
+                "\turl = \"with # hash\"\n",
+            )
+            + "[user]\n\tname = John Doe\n";
+        assert_eq!(file.to_string(), expected);
+
+        // multi-valued keys can not be set
+        assert!(file.set("remote.Origin.fetch", "x").is_err());
+    }
+
+    #[test]
+    pub fn test_add_and_unset() {
+        let mut file = ConfigFile::parse("[core]\n\tbare = false\n").unwrap();
+        file.add("core.bare", "true").unwrap();
+        assert!(file.unset("core.bare").is_err());
+
+        let mut file = ConfigFile::parse("[a]\n\tx = 1\n[b]\n\ty = 2\n").unwrap();
+        assert!(file.unset("a.x").unwrap());
+        assert!(!file.unset("a.x").unwrap());
+        assert_eq!(file.to_string(), "[b]\n\ty = 2\n");
+    }
+
+    #[test]
+    pub fn test_split_name() {
+        assert_eq!(
+            split_name("Core.Bare").unwrap(),
+            ("core".to_string(), "bare".to_string())
+        );
+        assert_eq!(
+            split_name("branch.Feature.x.Remote").unwrap(),
+            ("branch.Feature.x".to_string(), "remote".to_string())
+        );
+        assert!(split_name("nosection").is_err());
+        assert!(split_name("core.1key").is_err());
+        assert!(split_name("co re.key").is_err());
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod config;
+mod file;
+
+pub use config::{Config, ConfigEntry, ConfigScope, parse_bool, parse_int};
+pub use file::{ConfigFile, split_name};
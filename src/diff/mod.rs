@@ -0,0 +1,5 @@
+mod myers;
+mod unified;
+
+pub use myers::{Edit, diff};
+pub use unified::{format_hunks, hunks, split_lines};
@@ -0,0 +1,181 @@
+/// A single operation needed to turn a sequence into another one. The indices point to the
+/// position of the element in the old and new sequences, respectively.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Edit {
+    /// The element is in both sequences.
+    Equal { old: usize, new: usize },
+    /// The element was only in the old sequence.
+    Delete { old: usize },
+    /// The element was only in the new sequence.
+    Insert { new: usize },
+}
+
+/// Returns the shortest edit script that turns `old` into `new`, using Myers' diff algorithm.
+///
+/// The edits are returned in order, so going through them rebuilds both sequences: `Equal` and
+/// `Delete` edits build `old`, while `Equal` and `Insert` edits build `new`.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+
+    // `v[k]` stores the furthest `x` reached on diagonal `k` (where `k = x - y`). Every round
+    // stores the state it started with, that way the path can be followed backwards at the end.
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        // Only diagonals between -(d + 1) and d + 1 can be read by this round
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1] // moving down (insertion)
+            } else {
+                v[index - 1] + 1 // moving right (deletion)
+            };
+            let mut y = x - k;
+
+            // following the diagonal as long as the elements are equal
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::with_capacity(max as usize);
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        // values in the stored state are offset by d + 1
+        let get = |k: isize| v[(k + d + 1) as usize];
+
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = get(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal {
+                old: x as usize,
+                new: y as usize,
+            });
+        }
+
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Insert {
+                    new: previous_y as usize,
+                });
+            } else {
+                edits.push(Edit::Delete {
+                    old: previous_x as usize,
+                });
+            }
+        }
+
+        x = previous_x;
+        y = previous_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rebuilds both sequences from the edits, checking they match the original ones.
+    fn assert_rebuilds(old: &[char], new: &[char], edits: &[Edit]) {
+        let mut rebuilt_old = Vec::new();
+        let mut rebuilt_new = Vec::new();
+        for e in edits {
+            match *e {
+                Edit::Equal { old: o, new: n } => {
+                    assert_eq!(old[o], new[n]);
+                    rebuilt_old.push(old[o]);
+                    rebuilt_new.push(new[n]);
+                }
+                Edit::Delete { old: o } => rebuilt_old.push(old[o]),
+                Edit::Insert { new: n } => rebuilt_new.push(new[n]),
+            }
+        }
+        assert_eq!(old, rebuilt_old.as_slice());
+        assert_eq!(new, rebuilt_new.as_slice());
+    }
+
+    fn count_changes(edits: &[Edit]) -> usize {
+        edits
+            .iter()
+            .filter(|e| !matches!(e, Edit::Equal { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_diff_empty() {
+        assert!(diff::<char>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_diff_equal() {
+        let a: Vec<char> = "abc".chars().collect();
+        let edits = diff(&a, &a);
+        assert_eq!(count_changes(&edits), 0);
+        assert_rebuilds(&a, &a, &edits);
+    }
+
+    #[test]
+    fn test_diff_only_insertions_and_deletions() {
+        let a: Vec<char> = "abc".chars().collect();
+        let edits = diff(&[], &a);
+        assert_eq!(edits.len(), 3);
+        assert_rebuilds(&[], &a, &edits);
+
+        let edits = diff(&a, &[]);
+        assert_eq!(edits.len(), 3);
+        assert_rebuilds(&a, &[], &edits);
+    }
+
+    #[test]
+    fn test_diff_shortest_script() {
+        // classic example from Myers' paper, the shortest edit script has 5 changes
+        let a: Vec<char> = "ABCABBA".chars().collect();
+        let b: Vec<char> = "CBABAC".chars().collect();
+        let edits = diff(&a, &b);
+        assert_eq!(count_changes(&edits), 5);
+        assert_rebuilds(&a, &b, &edits);
+    }
+
+    #[test]
+    fn test_diff_deletions_come_first() {
+        let a: Vec<char> = "axc".chars().collect();
+        let b: Vec<char> = "ayc".chars().collect();
+        let edits = diff(&a, &b);
+        assert_eq!(
+            edits,
+            vec![
+                Edit::Equal { old: 0, new: 0 },
+                Edit::Delete { old: 1 },
+                Edit::Insert { new: 1 },
+                Edit::Equal { old: 2, new: 2 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use super::Edit;
+
+/// A group of changes that are close to each other, along with the unchanged lines around them.
+///
+/// Starting positions are 0-based indices into the old and new sequences.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub edits: Vec<Edit>,
+}
+
+impl Hunk {
+    /// Returns the `@@ -a,b +c,d @@` line that starts this hunk in the unified format.
+    pub fn header(&self) -> String {
+        fn range(start: usize, count: usize) -> String {
+            match count {
+                // an empty range points to the line before it
+                0 => format!("{},0", start),
+                1 => format!("{}", start + 1),
+                _ => format!("{},{}", start + 1, count),
+            }
+        }
+
+        format!(
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_count),
+            range(self.new_start, self.new_count)
+        )
+    }
+}
+
+/// Splits `data` into lines, keeping the line terminators so a missing new line at the end of
+/// the data counts as a change.
+pub fn split_lines(data: &[u8]) -> Vec<&[u8]> {
+    data.split_inclusive(|b| *b == b'\n').collect()
+}
+
+/// Groups the changes in `edits` into hunks, keeping up to `context` unchanged lines around every
+/// change. Changes separated by at most `2 * context` unchanged lines end up in the same hunk.
+pub fn hunks(edits: &[Edit], context: usize) -> Vec<Hunk> {
+    // number of old and new lines before every edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old, mut new) = (0, 0);
+    for e in edits {
+        positions.push((old, new));
+        match e {
+            Edit::Equal { .. } => {
+                old += 1;
+                new += 1;
+            }
+            Edit::Delete { .. } => old += 1,
+            Edit::Insert { .. } => new += 1,
+        }
+    }
+    positions.push((old, new));
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal { .. }))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let start = i.saturating_sub(context);
+        let end = (i + 1 + context).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let (old_start, new_start) = positions[start];
+            let (old_end, new_end) = positions[end];
+            Hunk {
+                old_start,
+                old_count: old_end - old_start,
+                new_start,
+                new_count: new_end - new_start,
+                edits: edits[start..end].to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the hunks in the unified diff format, using `old_lines` and `new_lines` as the
+/// sequences the edits point to.
+pub fn format_hunks(old_lines: &[&[u8]], new_lines: &[&[u8]], hunks: &[Hunk]) -> String {
+    fn push_line(s: &mut String, prefix: char, line: &[u8]) {
+        s.push(prefix);
+        s.push_str(&String::from_utf8_lossy(line));
+        if !line.ends_with(b"\n") {
+            s.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+
+    let mut s = String::new();
+    for h in hunks {
+        s.push_str(&h.header());
+        s.push('\n');
+        for e in h.edits.iter() {
+            match *e {
+                Edit::Equal { old, .. } => push_line(&mut s, ' ', old_lines[old]),
+                Edit::Delete { old } => push_line(&mut s, '-', old_lines[old]),
+                Edit::Insert { new } => push_line(&mut s, '+', new_lines[new]),
+            }
+        }
+    }
+    s
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff;
+
+    fn unified(old: &str, new: &str, context: usize) -> String {
+        let old_lines = split_lines(old.as_bytes());
+        let new_lines = split_lines(new.as_bytes());
+        let edits = diff(&old_lines, &new_lines);
+        format_hunks(&old_lines, &new_lines, &hunks(&edits, context))
+    }
+
+    #[test]
+    fn test_no_changes() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", 3), "");
+    }
+
+    #[test]
+    fn test_single_change_with_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified(old, new, 3),
+            "@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+        assert_eq!(unified(old, new, 0), "@@ -5 +5 @@\n-5\n+five\n");
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "one\n2\n3\n4\n5\n6\n7\n8\nnine\n";
+        assert_eq!(
+            unified(old, new, 1),
+            "@@ -1,2 +1,2 @@\n-1\n+one\n 2\n@@ -8,2 +8,2 @@\n 8\n-9\n+nine\n"
+        );
+        // with enough context both changes end up in the same hunk
+        assert_eq!(unified(old, new, 4).matches("@@ -").count(), 1);
+    }
+
+    #[test]
+    fn test_new_and_deleted_files() {
+        assert_eq!(unified("", "a\nb\n", 3), "@@ -0,0 +1,2 @@\n+a\n+b\n");
+        assert_eq!(unified("a\n", "", 3), "@@ -1 +0,0 @@\n-a\n");
+    }
+
+    #[test]
+    fn test_missing_new_line() {
+        assert_eq!(
+            unified("a\n", "a", 3),
+            "@@ -1 +1 @@\n-a\n+a\n\\ No newline at end of file\n"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
 pub trait WarnUnwrap<T, E> {
+    #[allow(unused)]
     fn warn(self) -> Self;
     fn warn_unwrap_or_default(self) -> T where T: Default;
     #[allow(unused)]
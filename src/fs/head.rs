@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::hashing::Hash;
+use crate::repository::Repository;
+
+use super::{read_ref, write_ref};
+
+/// What the HEAD file holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    /// HEAD follows the ref with this name, like `refs/heads/main`, which might not exist yet if
+    /// the branch has no commits
+    Symbolic(String),
+    /// HEAD holds this commit directly, without following any branch
+    Detached(Hash),
+}
+
+impl Head {
+    /// Returns `ref: refs/heads/<name>`, the HEAD of a checkout of the branch called `name`.
+    pub fn branch(name: &str) -> Self {
+        Head::Symbolic(format!(
+            "{}/{}/{}",
+            Constants::REFS_FOLDER_NAME,
+            Constants::HEADS_FOLDER_NAME,
+            name
+        ))
+    }
+
+    /// Returns the name of the branch HEAD follows, or `None` if it is detached or follows a ref
+    /// that is not a branch.
+    pub fn branch_name(&self) -> Option<&str> {
+        let Head::Symbolic(name) = self else {
+            return None;
+        };
+        name.strip_prefix(Constants::REFS_FOLDER_NAME)?
+            .strip_prefix('/')?
+            .strip_prefix(Constants::HEADS_FOLDER_NAME)?
+            .strip_prefix('/')
+    }
+}
+
+/// Reads the HEAD file, which either starts with `ref: ` followed by the name of a ref or holds
+/// the hash of a commit.
+///
+/// # Errors
+///
+/// This function will fail if the HEAD file could not be read or holds neither of them.
+pub fn read_head(repo: &Repository) -> Result<Head> {
+    let content =
+        std::fs::read_to_string(repo.head_path()).context("could not read from HEAD file")?;
+    let content = content.trim_end(); // Important to remove ending newlines
+
+    if let Some(name) = content.strip_prefix(Constants::HEAD_CONTENT_HEADER) {
+        return Ok(Head::Symbolic(name.to_string()));
+    }
+    match Hash::from_str(content) {
+        Ok(hash) => Ok(Head::Detached(hash)),
+        Err(_) => bail!("HEAD file is neither a ref nor a commit hash: {}", content),
+    }
+}
+
+/// Overwrites the HEAD file with `head`.
+///
+/// # Errors
+///
+/// This function will fail if the HEAD file could not be written.
+pub fn write_head(repo: &Repository, head: &Head) -> Result<()> {
+    let content = match head {
+        Head::Symbolic(name) => format!("{}{}\n", Constants::HEAD_CONTENT_HEADER, name),
+        Head::Detached(hash) => format!("{}\n", hash),
+    };
+    std::fs::write(repo.head_path(), content).context("could not write to HEAD file")
+}
+
+/// Returns the hash of the last commit on the current branch. More specifically, the hash inside
+/// the file HEAD points to, or the one inside HEAD itself if it is detached.
+///
+/// # Returns
+///
+/// This function returns a Result of an option of a Hash. The result might be `Err` if it was not
+/// possible to read from the file or get the path HEAD pointed to, while the Option inside might be
+/// `None` if there were no commits yet.
+pub fn get_last_commit_hash(repo: &Repository) -> Result<Option<Hash>> {
+    match read_head(repo)? {
+        Head::Symbolic(name) => read_ref(repo, &name).context("could not read current branch"),
+        Head::Detached(hash) => Ok(Some(hash)),
+    }
+}
+
+/// Returns the name of the branch HEAD points to, or `None` if it is detached.
+///
+/// # Errors
+///
+/// This function will fail if it could not read from the HEAD file.
+pub fn get_current_branch_name(repo: &Repository) -> Result<Option<String>> {
+    Ok(read_head(repo)?.branch_name().map(String::from))
+}
+
+/// Makes HEAD point to the branch called `name`.
+///
+/// # Errors
+///
+/// This function will fail if the HEAD file could not be written.
+pub fn set_current_branch(repo: &Repository, name: &str) -> Result<()> {
+    write_head(repo, &Head::branch(name))
+}
+
+/// Makes HEAD hold `hash` directly, so new commits do not move any branch.
+///
+/// # Errors
+///
+/// This function will fail if the HEAD file could not be written.
+pub fn detach_head(repo: &Repository, hash: &Hash) -> Result<()> {
+    write_head(repo, &Head::Detached(hash.clone()))
+}
+
+/// Moves HEAD to `hash`: the branch it points to is updated, or HEAD itself if it is detached.
+///
+/// # Errors
+///
+/// This function will fail if the HEAD file or the branch could not be read or written.
+pub fn update_head(repo: &Repository, hash: &Hash) -> Result<()> {
+    match read_head(repo)? {
+        Head::Symbolic(name) => write_ref(repo, &name, hash),
+        Head::Detached(_) => detach_head(repo, hash),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_branch_name() {
+        assert_eq!(Head::branch("feature/x").branch_name(), Some("feature/x"));
+        assert_eq!(Head::Symbolic("refs/tags/v1".into()).branch_name(), None);
+
+        let hash = Hash::from_str("980a72fb0cd5a4985c44cba8a407e79db7e83e32").unwrap();
+        assert_eq!(Head::Detached(hash).branch_name(), None);
+    }
+}
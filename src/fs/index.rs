@@ -2,12 +2,12 @@ use std::fs;
 
 use anyhow::{Context, Result};
 
-use crate::Constants;
 use crate::byteable::Byteable;
 use crate::index::Index;
+use crate::repository::Repository;
 
-pub fn read_index_file() -> Result<Index> {
-    let index_path = Constants::index_path();
+pub fn read_index_file(repo: &Repository) -> Result<Index> {
+    let index_path = repo.index_path();
 
     // returning empty index entry
     if !fs::exists(&index_path).context("could not check index file existance")? {
@@ -22,12 +22,12 @@ pub fn read_index_file() -> Result<Index> {
     Ok(index)
 }
 
-pub fn write_index_file(index: Index) -> Result<()> {
+pub fn write_index_file(repo: &Repository, index: Index) -> Result<()> {
     let data = index
         .as_bytes()
         .context("could not encode index when trying to write to index file")?;
 
-    fs::write(Constants::index_path(), data).context("could not write data to index file")?;
+    fs::write(repo.index_path(), data).context("could not write data to index file")?;
 
     Ok(())
 }
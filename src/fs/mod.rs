@@ -1,7 +1,11 @@
+mod head;
 pub mod index;
 pub mod object;
+pub mod pack;
 pub mod path;
 mod repo;
+pub mod working_tree;
 
+pub use head::*;
 pub use path::*;
 pub use repo::*;
@@ -2,13 +2,16 @@ use std::ffi::OsStr;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
 
 use anyhow::{Context, Result};
 
 use crate::byteable::Byteable;
 use crate::hashing::Hash;
-use crate::object::Object;
-use crate::{Constants, utils};
+use crate::object::{NULL_BYTE, Object, ObjectKind};
+use crate::repository::Repository;
+use crate::utils;
 
 /// Given an object, gets it's serialized representation and hash, and writes it to the object
 /// directory.
@@ -19,11 +22,11 @@ use crate::{Constants, utils};
 /// # Errors
 ///
 /// This function will fail if the object could not be encoded or the data could not be written.
-pub fn write_object(object: &Object) -> Result<Hash> {
+pub fn write_object(repo: &Repository, object: &Object) -> Result<Hash> {
     let bytes = object.as_bytes().context("could not encode object")?;
     let hash = Hash::compute(&bytes);
 
-    write_to_object_dir(&bytes, &hash).context("could not write to object directory")?;
+    write_to_object_dir(repo, &bytes, &hash).context("could not write to object directory")?;
 
     Ok(hash)
 }
@@ -44,11 +47,11 @@ pub fn get_object_hash_and_filename(hash_str: &str) -> (&str, &str) {
 /// # Errors
 ///
 /// This function can fail if there was not possible to create and write to the file.
-pub fn write_to_object_dir(bytes: &[u8], hash: &Hash) -> Result<()> {
+pub fn write_to_object_dir(repo: &Repository, bytes: &[u8], hash: &Hash) -> Result<()> {
     let hash_str = hash.to_string();
     let (file_dir, file_name) = get_object_hash_and_filename(&hash_str);
 
-    let folder_path = Constants::objects_path().join(OsStr::new(file_dir));
+    let folder_path = repo.objects_path().join(OsStr::new(file_dir));
     let file_path = folder_path.join(OsStr::new(file_name));
 
     // avoiding writing to an already existing file
@@ -78,24 +81,149 @@ pub fn write_to_object_dir(bytes: &[u8], hash: &Hash) -> Result<()> {
 }
 
 /// Looks for the file inside the objects directory that has the given hash and converts it to an
-/// object.
+/// object. If there is no loose object with that hash, the packs are searched instead.
 ///
 /// # Errors
 ///
 /// This function can fail if:
-/// - The hash did not correspond to any object file.
+/// - The hash did not correspond to any object file or packed object.
 /// - The file could not be read.
 /// - The file data could not be parsed as an object.
-pub fn read_object(hash: Hash) -> Result<Object> {
-    let hash_str = hash.to_string();
-    let (file_dir, file_name) = get_object_hash_and_filename(&hash_str);
+pub fn read_object(repo: &Repository, hash: Hash) -> Result<Object> {
+    let (kind, data) = read_raw_object(repo, &hash)?;
+
+    // rebuilding the header, so packed objects are parsed the same way as loose objects
+    let mut bytes = format!("{} {}\0", kind, data.len()).into_bytes();
+    bytes.extend_from_slice(&data);
+
+    Object::from_bytes(&bytes).context("could not create object from file bytes")
+}
 
-    let path = Constants::objects_path().join(file_dir).join(file_name);
+/// Returns the kind and the data (without header) of the object with the given hash, reading it
+/// either from its loose file or from a pack, without parsing it.
+///
+/// # Errors
+///
+/// This function can fail if the object does not exist or its file could not be read.
+pub fn read_raw_object(repo: &Repository, hash: &Hash) -> Result<(ObjectKind, Rc<[u8]>)> {
+    let path = get_loose_object_path(repo, hash);
+
+    if !fs::exists(&path).context("could not check object file existance")? {
+        return super::pack::read_packed_object(repo, hash)
+            .context("could not read packed object")?
+            .context(format!("object {} not found", hash));
+    }
 
     let bytes = fs::read(path).context("could not read file")?;
     let decompressed = utils::zlib::decompress(&bytes).context("could not decompress bytes")?;
 
-    Object::from_bytes(&decompressed).context("could not create object from file bytes")
+    let header_end = decompressed
+        .iter()
+        .position(|b| *b == NULL_BYTE)
+        .context("object file does not have a header")?;
+    let header = std::str::from_utf8(&decompressed[..header_end])
+        .context("object header is not valid utf-8")?;
+    let (kind, _) = header
+        .split_once(' ')
+        .context("object header does not have a size")?;
+
+    Ok((
+        ObjectKind::from_str(kind)?,
+        decompressed[header_end + 1..].into(),
+    ))
+}
+
+/// Returns the path of the loose file of the object with the given hash, which might not exist.
+pub fn get_loose_object_path(repo: &Repository, hash: &Hash) -> PathBuf {
+    let hash_str = hash.to_string();
+    let (file_dir, file_name) = get_object_hash_and_filename(&hash_str);
+
+    repo.objects_path().join(file_dir).join(file_name)
+}
+
+/// Returns the hash of every loose object in the objects directory.
+///
+/// # Errors
+///
+/// This function will fail if the objects directory could not be read.
+pub fn get_all_loose_object_hashes(repo: &Repository) -> Result<Vec<Hash>> {
+    let mut hashes = Vec::new();
+    for dir in fs::read_dir(repo.objects_path()).context("could not read objects dir")? {
+        let dir = dir.context("could not get dir entry")?;
+        let dir_name = dir.file_name().to_string_lossy().to_string();
+        // only the directories named after the first byte of a hash have objects (not pack/info)
+        if dir_name.len() != 2 || !dir.path().is_dir() {
+            continue;
+        }
+
+        for file in fs::read_dir(dir.path()).context(format!("could not read {:?}", dir_name))? {
+            let file_name = file.context("could not get dir entry")?.file_name();
+            if let Ok(hash) =
+                Hash::from_str(&format!("{}{}", dir_name, file_name.to_string_lossy()))
+            {
+                hashes.push(hash);
+            }
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// Returns the hash of every object, loose or packed, whose hexadecimal representation starts
+/// with `prefix`, sorted and without duplicates.
+///
+/// # Errors
+///
+/// This function will fail if the objects directory or any of the packs could not be read.
+pub fn find_objects_by_prefix(repo: &Repository, prefix: &str) -> Result<Vec<Hash>> {
+    let prefix = prefix.to_ascii_lowercase();
+    let mut hashes = Vec::new();
+
+    // loose objects are stored in a directory named after the first two characters of the hash
+    if prefix.len() >= 2 {
+        let (dir_name, file_prefix) = get_object_hash_and_filename(&prefix);
+        let dir = repo.objects_path().join(dir_name);
+        if dir.is_dir() {
+            for file in fs::read_dir(&dir).context(format!("could not read {:?}", dir))? {
+                let file_name = file.context("could not get dir entry")?.file_name();
+                let file_name = file_name.to_string_lossy();
+                if file_name.starts_with(file_prefix)
+                    && let Ok(hash) = Hash::from_str(&format!("{}{}", dir_name, file_name))
+                {
+                    hashes.push(hash);
+                }
+            }
+        }
+    }
+
+    for pack in super::pack::get_all_packs(repo).context("could not open packs")? {
+        hashes.extend(
+            pack.index()
+                .entries()
+                .map(|(hash, _)| hash)
+                .filter(|hash| hash.to_string().starts_with(&prefix)),
+        );
+    }
+
+    hashes.sort_by_key(|h| h.to_string());
+    hashes.dedup();
+    Ok(hashes)
+}
+
+/// Removes the loose file of the object with the given hash, and its directory if it was left
+/// empty.
+///
+/// # Errors
+///
+/// This function will fail if the file could not be removed.
+pub fn remove_loose_object(repo: &Repository, hash: &Hash) -> Result<()> {
+    let path = get_loose_object_path(repo, hash);
+    fs::remove_file(&path).context(format!("could not remove object {}", hash))?;
+    if let Some(dir) = path.parent() {
+        // fails if the directory is not empty, which is fine
+        let _ = fs::remove_dir(dir);
+    }
+    Ok(())
 }
 
 /// Reads all the given paths, reading the file and converting it to a `BufBlob` object, which
@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+
+use crate::Constants;
+use crate::byteable::Byteable;
+use crate::hashing::Hash;
+use crate::object::ObjectKind;
+use crate::pack::{Pack, PackIndex};
+use crate::repository::Repository;
+
+/// Returns the path of the index of every pack in the pack directory, sorted.
+///
+/// # Errors
+///
+/// This function will fail if the pack directory could not be read.
+pub fn get_all_pack_index_paths(repo: &Repository) -> Result<Vec<PathBuf>> {
+    let packs_path = repo.packs_path();
+    if !std::fs::exists(&packs_path).context("could not check pack directory existance")? {
+        return Ok(Vec::new());
+    }
+
+    let mut index_paths: Vec<PathBuf> = std::fs::read_dir(&packs_path)
+        .context("could not read pack directory")?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e == Constants::PACK_INDEX_EXTENSION)
+        })
+        .collect();
+    index_paths.sort();
+
+    Ok(index_paths)
+}
+
+/// Returns every pack in the pack directory, opening the ones that have not been opened yet.
+///
+/// # Errors
+///
+/// This function will fail if the pack directory could not be read or any pack is not valid.
+pub fn get_all_packs(repo: &Repository) -> Result<Vec<Rc<Pack>>> {
+    let index_paths = get_all_pack_index_paths(repo)?;
+
+    let mut open = repo.open_packs().borrow_mut();
+    let mut packs = Vec::with_capacity(index_paths.len());
+    for path in index_paths {
+        if let Some(pack) = open.get(&path) {
+            packs.push(Rc::clone(pack));
+            continue;
+        }
+        let pack = Rc::new(Pack::open(&path).context(format!("could not open pack {:?}", path))?);
+        open.insert(path, Rc::clone(&pack));
+        packs.push(pack);
+    }
+    Ok(packs)
+}
+
+/// Looks for the object with the given hash in every pack, returning its kind and data.
+///
+/// # Returns
+///
+/// `None` if the object is not in any pack.
+///
+/// # Errors
+///
+/// This function will fail if the packs could not be opened or the object could not be decoded.
+pub fn read_packed_object(
+    repo: &Repository,
+    hash: &Hash,
+) -> Result<Option<(ObjectKind, Rc<[u8]>)>> {
+    for pack in get_all_packs(repo)? {
+        if let Some(object) = pack.read(hash)? {
+            return Ok(Some(object));
+        }
+    }
+    Ok(None)
+}
+
+/// Writes a pack and its index to the pack directory, naming them after the pack checksum.
+///
+/// # Returns
+///
+/// The path of the written index.
+///
+/// # Errors
+///
+/// This function will fail if the index could not be encoded or any of the files written.
+pub fn write_pack(repo: &Repository, data: &[u8], index: &PackIndex) -> Result<PathBuf> {
+    let packs_path = repo.packs_path();
+    std::fs::create_dir_all(&packs_path).context("could not create pack directory")?;
+
+    let name = format!("pack-{}", index.pack_checksum());
+    let pack_path = packs_path
+        .join(&name)
+        .with_extension(Constants::PACK_EXTENSION);
+    let index_path = packs_path
+        .join(&name)
+        .with_extension(Constants::PACK_INDEX_EXTENSION);
+
+    // the pack goes first, so there is never an index pointing to a missing pack
+    std::fs::write(&pack_path, data).context("could not write pack")?;
+    std::fs::write(
+        &index_path,
+        index.as_bytes().context("could not encode pack index")?,
+    )
+    .context("could not write pack index")?;
+
+    Ok(index_path)
+}
+
+/// Removes the pack with the index at `index_path`, along with its index.
+///
+/// # Errors
+///
+/// This function will fail if any of the files could not be removed.
+pub fn remove_pack(repo: &Repository, index_path: &Path) -> Result<()> {
+    std::fs::remove_file(index_path).context("could not remove pack index")?;
+    std::fs::remove_file(index_path.with_extension(Constants::PACK_EXTENSION))
+        .context("could not remove pack")?;
+    repo.open_packs().borrow_mut().remove(index_path);
+    Ok(())
+}
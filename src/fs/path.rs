@@ -6,8 +6,10 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 
 use crate::error::WarnUnwrap;
-use crate::gitignore;
+use crate::Constants;
+use crate::gitignore::Gitignore;
 use crate::index::IndexEntryCache;
+use crate::repository::Repository;
 
 /// Struct that represents a file which content is buffered.
 ///
@@ -56,7 +58,8 @@ pub fn read_bufered(files: Vec<PathBuf>) -> Result<Vec<BuferedFile>> {
     Ok(bufered)
 }
 
-/// Returns all the files inside `root` as a `BuferedFile`, entering subdirectories recursively.
+/// Returns all the files inside the working tree of `repo` as a `BuferedFile`, entering
+/// subdirectories recursively.
 ///
 /// The paths returned by this function are all absolute.
 ///
@@ -66,43 +69,69 @@ pub fn read_bufered(files: Vec<PathBuf>) -> Result<Vec<BuferedFile>> {
 ///
 /// This function will fail if a file could not be opened through the `std::fs::File::open`
 /// function.
-pub fn get_all_files_bufered(root: &Path) -> Result<Vec<BuferedFile>> {
-    let all_files = get_all_paths(root).context(format!("could not get paths in {:?}", root))?;
+pub fn get_all_files_bufered(repo: &Repository) -> Result<Vec<BuferedFile>> {
+    let all_files =
+        get_all_paths(repo).context(format!("could not get paths in {:?}", repo.working_tree()))?;
     read_bufered(all_files).context("could not create bufered files")
 }
 
 
-/// Returns the path of all the files inside of `root`, entering subdirectories recursively.
+/// Returns the path of all the files inside the working tree of `repo`, entering subdirectories
+/// recursively.
 ///
 /// The paths returned by this function are absolute.
 ///
-/// This function will ignore files present in .gitignore.
+/// This function will ignore the files ignored by the `.gitignore` files, `.git/info/exclude` and
+/// `core.excludesFile`, along with the `.git` directory. Ignored directories are not entered.
 ///
 /// # Errors
 ///
-/// This function will fail if it could not read the files in the working tree.
-pub fn get_all_paths(root: &Path) -> Result<Vec<PathBuf>> {
+/// This function will fail if it could not read the files in the working tree or the ignore files.
+pub fn get_all_paths(repo: &Repository) -> Result<Vec<PathBuf>> {
+    let mut gitignore = Gitignore::load(repo).context("could not get ignored files")?;
     let mut paths = Vec::new();
-    let ignored = gitignore::read_gitignore(root).context("could not get ignored files")?;
-
-    let root_dir = std::fs::read_dir(root).context("could not get root directories")?;
+    let root = repo.working_tree();
+    collect_paths(root, Path::new(""), &mut gitignore, &mut paths)?;
+    Ok(paths)
+}
 
-    let mut path: PathBuf;
-    for direntry in root_dir {
-        path = direntry.context("could not get dir entry")?.path();
+/// Adds the files inside of `dir` (relative to `root`) that are not ignored to `paths`, reading
+/// the `.gitignore` file of every directory it enters.
+fn collect_paths(
+    root: &Path,
+    dir: &Path,
+    gitignore: &mut Gitignore,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    gitignore
+        .read_dir_file(dir)
+        .context(format!("could not read ignore file in {:?}", dir))?;
+
+    let entries = std::fs::read_dir(root.join(dir)).context("could not get root directories")?;
+    for entry in entries {
+        let entry = entry.context("could not get dir entry")?;
+        if entry.file_name() == Constants::REPOSITORY_FOLDER_NAME {
+            continue;
+        }
 
-        if ignored.contains(&path) {
+        let relative = dir.join(entry.file_name());
+        // symlinks are stored as files, even if they point to a directory
+        let is_dir = entry
+            .file_type()
+            .context(format!("could not get file type of {:?}", relative))?
+            .is_dir();
+        if gitignore.is_ignored(&relative, is_dir) {
             continue;
         }
 
-        if path.is_dir() {
-            paths.extend(get_all_paths(&path)?);
+        if is_dir {
+            collect_paths(root, &relative, gitignore, paths)?;
         } else {
-            paths.push(path);
+            paths.push(root.join(relative));
         }
     }
 
-    Ok(paths)
+    Ok(())
 }
 
 /// Returns all the paths of the files and subdirectories inside of `dir`.
@@ -129,7 +158,7 @@ pub fn read_all_dir_paths(path: &Path) -> Result<Vec<PathBuf>> {
 ///
 /// # Example
 ///
-/// ```rust
+/// ```ignore
 /// let paths = vec!["dir", "file.txt"].map(PathBuf::from).collect();
 ///
 /// let expanded = get_all_files_from_list(paths).unwrap();
@@ -156,25 +185,13 @@ pub fn expand_dirs_from_list(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
     Ok(expanded)
 }
 
-/// Returns the files in `path` that are not inside a .gitignore file in the same directory.
-///
-/// # Errors
-///
-/// This function can fail if it couldn't get the files inside `path` or could not filter from the
-/// gitignore.
-#[allow(unused)]
-pub fn read_not_ignored_paths(path: &Path) -> Result<Vec<PathBuf>> {
-    let all_paths = read_all_dir_paths(path).context("could not read root directory entries")?;
-    crate::gitignore::not_in_gitignore(path, all_paths)
-}
-
 // Tests
 
 #[cfg(test)]
 mod tests {
     use std::{env, path::PathBuf};
 
-    use crate::utils::path::{format_path, relative_path};
+    use crate::utils::path::{clean_relative_path, format_path, relative_path};
 
     #[test]
     pub fn relative_path_test() {
@@ -206,4 +223,24 @@ mod tests {
 
         assert_eq!(objective, format_path(&path))
     }
+
+    #[test]
+    pub fn clean_relative_path_test() {
+        let base = PathBuf::from("/home/josgtg/repo");
+
+        assert_eq!(
+            Some(PathBuf::from("src/main.rs")),
+            clean_relative_path(&PathBuf::from("./src/../src/main.rs"), &base)
+        );
+        assert_eq!(
+            Some(PathBuf::from("src")),
+            clean_relative_path(&PathBuf::from("/home/josgtg/repo/src/"), &base)
+        );
+        assert_eq!(
+            Some(PathBuf::new()),
+            clean_relative_path(&PathBuf::from("."), &base)
+        );
+        assert!(clean_relative_path(&PathBuf::from("/home/josgtg/other"), &base).is_none());
+        assert!(clean_relative_path(&PathBuf::from("../other"), &base).is_none());
+    }
 }
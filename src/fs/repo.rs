@@ -1,66 +1,390 @@
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
 use crate::Constants;
-use crate::hashing::Hash;
+use crate::hashing::{HASH_STR_LEN, Hash};
+use crate::object::commit::CommitUser;
+use crate::repository::Repository;
+use crate::utils::path::{format_path, relative_path};
 
-/// Reads the path stored inside the HEAD file.
-//
+/// Symbolic refs pointing to other symbolic refs are followed up to this depth, like git does.
+const MAX_SYMBOLIC_REF_DEPTH: usize = 5;
+
+/// Start of the HEAD reflog messages written when switching branches, which are followed by
+/// `{from} to {to}`.
+pub const CHECKOUT_LOG_PREFIX: &str = "checkout: moving from ";
+
+/// Characters that can not appear anywhere in a ref name.
+const FORBIDDEN_REF_CHARACTERS: &[char] = &[' ', '~', '^', ':', '?', '*', '[', '\\'];
+
+/// Returns the hash the ref called `name` points to, following symbolic refs, or `None` if it
+/// does not exist. The name is relative to the repository folder, like `HEAD` or
+/// `refs/heads/main`.
+///
+/// # Errors
+///
+/// This function will fail if any of the ref files could not be read, if they do not contain a
+/// valid hash or if symbolic refs are nested too deeply.
+pub fn read_ref(repo: &Repository, name: &str) -> Result<Option<Hash>> {
+    let mut name = name.to_string();
+    for _ in 0..MAX_SYMBOLIC_REF_DEPTH {
+        let path = repo.git_dir().join(&name);
+        if path.is_file() {
+            let content = std::fs::read_to_string(&path)
+                .context(format!("could not read ref file {:?}", path))?;
+            if let Some(target) = content
+                .trim_end()
+                .strip_prefix(Constants::HEAD_CONTENT_HEADER)
+            {
+                name = target.to_string();
+                continue;
+            }
+        }
+        return read_ref_file(repo, &path).context(format!("could not read ref {}", name));
+    }
+    bail!("symbolic refs nested too deeply at {}", name)
+}
+
+/// Reads the hash stored in the ref file at `path`. If the file does not exist, the ref is
+/// looked up in the packed-refs file instead, returning `None` if it is not there either.
+///
 /// # Errors
 ///
-/// This function will fail if the HEAD file could not be opened or read from.
-pub fn get_current_branch_path() -> Result<PathBuf> {
-    let bytes = std::fs::read(Constants::head_path()).context("could not read from HEAD file")?;
-    let path_str = String::from_utf8_lossy(&bytes);
+/// This function will fail if the file could not be read or did not contain a valid hash.
+fn read_ref_file(repo: &Repository, path: &Path) -> Result<Option<Hash>> {
+    if !path.is_file() {
+        let Some(name) = relative_path(path, repo.git_dir()) else {
+            return Ok(None);
+        };
+        let name = format_path(&name).to_string_lossy().to_string();
+        return Ok(read_packed_refs(repo)?.into_iter().find(|(n, _)| *n == name).map(|(_, h)| h));
+    }
 
-    let stripped_path_str = path_str
-        .trim_end() // Important to remove ending newlines
-        .strip_prefix(Constants::HEAD_CONTENT_HEADER)
-        .context("HEAD file had an incorrect header")?;
+    let bytes = std::fs::read(path).context("could not read ref file")?;
+    let str = std::str::from_utf8(&bytes).context("could not read ref file as a string")?;
 
-    let relative_path = PathBuf::from(stripped_path_str);
+    let hash = Hash::from_str(str.trim()).context("could not create a hash from the data read")?;
 
-    Ok(Constants::repository_path().join(relative_path))
+    Ok(Some(hash))
 }
 
-/// Returns the hash of the last commit on the current branch. More specifically, the hash inside
-/// the file HEAD points to.
+/// Returns the name and hash of every ref in the packed-refs file, which is where git moves loose
+/// refs when it packs a repository.
 ///
-/// # Returns
+/// # Errors
 ///
-/// This function returns a Result of an option of a Hash. The result might be `Err` if it was not
-/// possible to read from the file or get the path HEAD pointed to, while the Option inside might be
-/// `None` if there were no commits yet.
-pub fn get_last_commit_hash() -> Result<Option<Hash>> {
-    let path = get_current_branch_path().context("could not get current branch path")?;
+/// This function will fail if the file exists but could not be read or has an invalid line.
+fn read_packed_refs(repo: &Repository) -> Result<Vec<(String, Hash)>> {
+    let path = repo.packed_refs_path();
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path).context("could not read packed-refs file")?;
 
-    if !path.exists() {
-        return Ok(None);
+    let mut refs = Vec::new();
+    for line in content.lines() {
+        // comments hold the file traits and `^` lines the commits peeled tags point to
+        if line.starts_with('#') || line.starts_with('^') || line.is_empty() {
+            continue;
+        }
+        let (hash, name) = line
+            .split_once(' ')
+            .context(format!("invalid packed-refs line: {}", line))?;
+        let hash = Hash::from_str(hash).context(format!("invalid hash in packed ref {}", name))?;
+        refs.push((name.to_string(), hash));
     }
 
-    let bytes = std::fs::read(path).context("could not read current branch")?;
-    let str = std::str::from_utf8(&bytes).context("could not read current branch as a string")?;
+    Ok(refs)
+}
 
-    let hash = Hash::from_str(str.trim()).context("could not create a hash from the data read")?;
+/// Removes the ref called `name` from the packed-refs file, if it is there.
+fn remove_packed_ref(repo: &Repository, name: &str) -> Result<()> {
+    let path = repo.packed_refs_path();
+    if !path.is_file() {
+        return Ok(());
+    }
 
-    Ok(Some(hash))
+    let content = std::fs::read_to_string(&path).context("could not read packed-refs file")?;
+
+    let mut kept = String::with_capacity(content.len());
+    let mut removing = false;
+    for line in content.lines() {
+        if line.starts_with('^') {
+            // peeled lines belong to the ref right before them
+            if !removing {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            continue;
+        }
+        removing = line.split_once(' ').is_some_and(|(_, n)| n == name);
+        if !removing {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+
+    std::fs::write(path, kept).context("could not write packed-refs file")
+}
+
+/// Returns the path of the file that stores the commit the branch called `name` points to.
+///
+/// This function does not check if the branch exists.
+pub fn get_branch_path(repo: &Repository, name: &str) -> PathBuf {
+    repo.heads_path().join(name)
+}
+
+/// Returns the hash of the commit the branch called `name` points to, or `None` if the branch
+/// does not exist.
+///
+/// # Errors
+///
+/// This function will fail if the branch file could not be read or did not contain a valid hash.
+pub fn read_branch(repo: &Repository, name: &str) -> Result<Option<Hash>> {
+    read_ref_file(repo, &get_branch_path(repo, name))
+        .context(format!("could not read branch {}", name))
+}
+
+/// Makes the branch called `name` point to `hash`, creating it if it did not exist.
+///
+/// # Errors
+///
+/// This function will fail if the branch file or its parent directories could not be written.
+pub fn write_branch(repo: &Repository, name: &str, hash: &Hash) -> Result<()> {
+    write_ref_file(&get_branch_path(repo, name), hash)
+        .context(format!("could not write branch {}", name))
 }
 
-/// Returns the name of the branch HEAD points to.
+/// Makes the ref called `name` point to `hash`, creating it if it did not exist. The name is
+/// relative to the repository folder, like `ORIG_HEAD` or `refs/heads/main`, and symbolic refs
+/// are not followed.
 ///
 /// # Errors
 ///
-/// This function will fail if it could not read from the HEAD file.
-pub fn get_current_branch_name() -> Result<String> {
-    let path_head_points_to = get_current_branch_path().context("could not read branch path")?;
-
-    Ok(path_head_points_to
-        .components()
-        .last()
-        .context("path in HEAD was empty?")?
-        .as_os_str()
-        .to_string_lossy()
-        .to_string())
+/// This function will fail if the ref file or its parent directories could not be written.
+pub fn write_ref(repo: &Repository, name: &str, hash: &Hash) -> Result<()> {
+    write_ref_file(&repo.git_dir().join(name), hash)
+        .context(format!("could not write ref {}", name))
+}
+
+/// Writes `hash` to the ref file at `path`, creating its parent directories.
+fn write_ref_file(path: &Path, hash: &Hash) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("could not create ref directories")?;
+    }
+    std::fs::write(path, format!("{}\n", hash)).context("could not write ref file")
+}
+
+/// Removes the branch called `name` (both its file and its packed entry), along with the
+/// directories under refs/heads that were left empty because of it.
+///
+/// # Errors
+///
+/// This function will fail if the branch file could not be removed.
+pub fn delete_branch(repo: &Repository, name: &str) -> Result<()> {
+    delete_ref(repo, Constants::HEADS_FOLDER_NAME, name)
+        .context(format!("could not remove branch {}", name))
+}
+
+/// Removes the ref called `name` inside of refs/`folder` (both its file and its packed entry),
+/// along with the directories that were left empty because of it.
+fn delete_ref(repo: &Repository, folder: &str, name: &str) -> Result<()> {
+    let base = repo.refs_path().join(folder);
+    let path = base.join(name);
+    let full_name = format!("{}/{}/{}", Constants::REFS_FOLDER_NAME, folder, name);
+    remove_packed_ref(repo, &full_name).context("could not remove packed ref")?;
+    if !path.is_file() {
+        return Ok(());
+    }
+    std::fs::remove_file(&path).context(format!("could not remove {:?}", path))?;
+
+    let mut parent = path.parent();
+    while let Some(dir) = parent {
+        if dir == base || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+
+    Ok(())
+}
+
+/// Returns the names of every branch in the repository, sorted alphabetically.
+///
+/// # Errors
+///
+/// This function will fail if the refs/heads directory could not be read.
+pub fn get_all_branch_names(repo: &Repository) -> Result<Vec<String>> {
+    get_all_ref_names(repo, Constants::HEADS_FOLDER_NAME)
+}
+
+/// Returns the names of every ref inside of refs/`folder`, both loose and packed, sorted
+/// alphabetically.
+fn get_all_ref_names(repo: &Repository, folder: &str) -> Result<Vec<String>> {
+    fn collect(dir: &Path, base: &Path, names: &mut Vec<String>) -> Result<()> {
+        for entry in std::fs::read_dir(dir).context(format!("could not read {:?}", dir))? {
+            let path = entry.context("could not get dir entry")?.path();
+            if path.is_dir() {
+                collect(&path, base, names)?;
+            } else if let Some(name) = relative_path(&path, base) {
+                names.push(format_path(&name).to_string_lossy().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    let base = repo.refs_path().join(folder);
+    let mut names = Vec::new();
+    if base.is_dir() {
+        collect(&base, &base, &mut names)?;
+    }
+
+    let packed_prefix = format!("{}/{}/", Constants::REFS_FOLDER_NAME, folder);
+    for (name, _) in read_packed_refs(repo).context("could not read packed refs")? {
+        if let Some(name) = name.strip_prefix(&packed_prefix) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    Ok(names)
+}
+
+/// Returns the hash of the object the tag called `name` points to (a tag object for annotated
+/// tags), or `None` if the tag does not exist.
+///
+/// # Errors
+///
+/// This function will fail if the tag file could not be read or did not contain a valid hash.
+pub fn read_tag(repo: &Repository, name: &str) -> Result<Option<Hash>> {
+    read_ref_file(repo, &repo.tags_path().join(name))
+        .context(format!("could not read tag {}", name))
+}
+
+/// Makes the tag called `name` point to `hash`, creating it if it did not exist.
+///
+/// # Errors
+///
+/// This function will fail if the tag file or its parent directories could not be written.
+pub fn write_tag(repo: &Repository, name: &str, hash: &Hash) -> Result<()> {
+    write_ref_file(&repo.tags_path().join(name), hash)
+        .context(format!("could not write tag {}", name))
+}
+
+/// Removes the tag called `name`, both its file and its packed entry.
+///
+/// # Errors
+///
+/// This function will fail if the tag file could not be removed.
+pub fn delete_tag(repo: &Repository, name: &str) -> Result<()> {
+    delete_ref(repo, Constants::TAGS_FOLDER_NAME, name)
+        .context(format!("could not remove tag {}", name))
+}
+
+/// Returns the names of every tag in the repository, sorted alphabetically.
+///
+/// # Errors
+///
+/// This function will fail if the refs/tags directory could not be read.
+pub fn get_all_tag_names(repo: &Repository) -> Result<Vec<String>> {
+    get_all_ref_names(repo, Constants::TAGS_FOLDER_NAME)
+}
+
+/// Checks if `name` is a valid branch or tag name, following a simplified version of git's rules.
+pub fn is_valid_ref_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && !name.ends_with('.')
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && name != "@"
+        && !name.contains(FORBIDDEN_REF_CHARACTERS)
+        && !name.chars().any(|c| c.is_ascii_control())
+        && !name.split('/').any(|c| c.starts_with('.'))
+}
+
+/// Appends an entry to the HEAD reflog, which records how HEAD moved from `old` (`None` if there
+/// were no commits) to `new` and why.
+///
+/// # Errors
+///
+/// This function will fail if the reflog could not be written or the timestamp of `user` is
+/// before the unix epoch.
+pub fn append_head_log(
+    repo: &Repository,
+    old: Option<&Hash>,
+    new: &Hash,
+    user: &CommitUser,
+    message: &str,
+) -> Result<()> {
+    let path = repo.head_log_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("could not create logs directory")?;
+    }
+
+    // the header line has the same identity, date and timezone a reflog entry needs
+    let header = user.to_header_line()?;
+    let (_, identity) = header
+        .trim_end()
+        .split_once(' ')
+        .context("invalid identity header")?;
+    let old = old.map_or("0".repeat(HASH_STR_LEN), Hash::to_string);
+    let entry = format!("{} {} {}\t{}\n", old, new, identity, message);
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context("could not open HEAD reflog")?;
+    file.write_all(entry.as_bytes())
+        .context("could not write to HEAD reflog")
+}
+
+/// Returns the message of every entry of the HEAD reflog, from the oldest to the newest.
+///
+/// # Errors
+///
+/// This function will fail if the reflog exists but could not be read.
+pub fn read_head_log_messages(repo: &Repository) -> Result<Vec<String>> {
+    let path = repo.head_log_path();
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path).context("could not read HEAD reflog")?;
+    Ok(content
+        .lines()
+        .filter_map(|l| l.split_once('\t'))
+        .map(|(_, message)| message.to_string())
+        .collect())
+}
+
+/// Returns every commit the HEAD reflog records HEAD pointing to, before and after each move.
+///
+/// # Errors
+///
+/// This function will fail if the reflog exists but could not be read or has invalid hashes.
+pub fn read_head_log_hashes(repo: &Repository) -> Result<Vec<Hash>> {
+    let path = repo.head_log_path();
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path).context("could not read HEAD reflog")?;
+    let no_commit = "0".repeat(HASH_STR_LEN);
+
+    let mut hashes = Vec::new();
+    for line in content.lines() {
+        for hash in line.split(' ').take(2).filter(|h| *h != no_commit) {
+            hashes.push(Hash::from_str(hash).context("invalid hash in HEAD reflog")?);
+        }
+    }
+    Ok(hashes)
 }
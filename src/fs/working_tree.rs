@@ -0,0 +1,99 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::object::Object;
+use crate::repository::Repository;
+
+/// Mode (in its octal representation) of an executable file in a tree.
+pub const EXECUTABLE_MODE: u32 = 100755;
+/// Mode (in its octal representation) of a symbolic link in a tree.
+pub const SYMLINK_MODE: u32 = 120000;
+
+/// Reads the blob object with the given hash and writes its data to `path` (relative to the
+/// working tree root), creating any missing parent directories.
+///
+/// `mode` is expected to be in its octal representation, as it is stored in tree entries.
+///
+/// # Errors
+///
+/// This function will fail if the object could not be read, was not a blob, or if the file could
+/// not be written.
+pub fn write_blob(repo: &Repository, path: &Path, mode: u32, hash: Hash) -> Result<()> {
+    let data = match super::object::read_object(repo, hash).context("could not read blob object")? {
+        Object::Blob { data } => data,
+        _ => bail!("expected {:?} to point to a blob object", path),
+    };
+
+    write_file(repo, path, mode, &data)
+}
+
+/// Writes `data` to `path` (relative to the working tree root) as a file with the given mode,
+/// creating any missing parent directories.
+///
+/// `mode` is expected to be in its octal representation, as it is stored in tree entries.
+///
+/// # Errors
+///
+/// This function will fail if the file could not be written.
+pub fn write_file(repo: &Repository, path: &Path, mode: u32, data: &[u8]) -> Result<()> {
+    let full_path = repo.working_tree().join(path);
+    if let Some(parent) = full_path.parent() {
+        std::fs::create_dir_all(parent).context(format!("could not create {:?}", parent))?;
+    }
+
+    // Removing whatever was there before, this avoids writing through an old symlink
+    if full_path.symlink_metadata().is_ok() {
+        std::fs::remove_file(&full_path).context(format!("could not replace {:?}", full_path))?;
+    }
+
+    if mode == SYMLINK_MODE {
+        let target = String::from_utf8_lossy(data).to_string();
+        std::os::unix::fs::symlink(target, &full_path)
+            .context(format!("could not create symlink {:?}", full_path))?;
+        return Ok(());
+    }
+
+    std::fs::write(&full_path, data).context(format!("could not write {:?}", full_path))?;
+
+    let permissions = std::fs::Permissions::from_mode(if mode == EXECUTABLE_MODE {
+        0o755
+    } else {
+        0o644
+    });
+    std::fs::set_permissions(&full_path, permissions)
+        .context(format!("could not set permissions of {:?}", full_path))?;
+
+    Ok(())
+}
+
+/// Removes the file at `path` (relative to the working tree root), also removing the parent
+/// directories that were left empty because of it.
+///
+/// Files that do not exist are silently ignored.
+///
+/// # Errors
+///
+/// This function will fail if the file could not be removed.
+pub fn remove_file(repo: &Repository, path: &Path) -> Result<()> {
+    let root = repo.working_tree();
+    let full_path = root.join(path);
+
+    if full_path.symlink_metadata().is_err() {
+        return Ok(());
+    }
+    std::fs::remove_file(&full_path).context(format!("could not remove {:?}", full_path))?;
+
+    // `remove_dir` fails on directories that are not empty, so we stop there
+    let mut parent = full_path.parent();
+    while let Some(dir) = parent {
+        if dir == root || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::Constants;
+use crate::config::Config;
+use crate::repository::Repository;
+use crate::utils::path::relative_path;
+
+use super::Pattern;
+
+/// Struct intended to be used for any operations related to ignored files.
+///
+/// Patterns are read, from the lowest to the highest priority, from `core.excludesFile`,
+/// `.git/info/exclude` and the `.gitignore` files of every directory, where deeper files have
+/// priority over the ones above them. The last pattern matching a path decides if it is ignored.
+///
+/// Paths are always relative to the working tree root.
+#[derive(Debug, Default)]
+pub struct Gitignore {
+    root: PathBuf,
+    patterns: Vec<Pattern>,
+    /// Directories whose `.gitignore` file was already read
+    read_dirs: HashSet<PathBuf>,
+}
+
+impl Gitignore {
+    /// Reads the global excludes file, `.git/info/exclude` and the `.gitignore` file at the root
+    /// of the working tree of `repo`. The `.gitignore` files of subdirectories are read with
+    /// `read_dir_file` as they are needed.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the config or any of the files could not be read.
+    pub fn load(repo: &Repository) -> Result<Self> {
+        let mut gitignore = Gitignore {
+            root: repo.working_tree().to_path_buf(),
+            ..Default::default()
+        };
+
+        let config = Config::load(repo).context("could not read config")?;
+        if let Some(path) = config
+            .get_path("core.excludesFile")
+            .or_else(default_excludes_path)
+        {
+            gitignore.read_file(&path, Path::new(""))?;
+        }
+        gitignore.read_file(&repo.exclude_path(), Path::new(""))?;
+        gitignore.read_dir_file(Path::new(""))?;
+
+        Ok(gitignore)
+    }
+
+    /// Reads the `.gitignore` file inside of `dir`, if there is one and it was not read already.
+    ///
+    /// Files have to be read from the top directory down, so deeper patterns take priority.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the file exists but could not be read.
+    pub fn read_dir_file(&mut self, dir: &Path) -> Result<()> {
+        if !self.read_dirs.insert(dir.to_path_buf()) {
+            return Ok(());
+        }
+        let path = self.root.join(dir).join(Constants::GITIGNORE_FILE_NAME);
+        self.read_file(&path, dir)
+    }
+
+    /// Reads the `.gitignore` files of every directory containing `path`, from the top down.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if any of the files exists but could not be read.
+    pub fn read_parent_files(&mut self, path: &Path) -> Result<()> {
+        let mut dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+        dirs.reverse();
+        for dir in dirs {
+            self.read_dir_file(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the patterns in `content`, read from `source`, which apply to the paths inside of
+    /// `base`.
+    pub fn add_patterns(&mut self, content: &str, base: &Path, source: &Path) {
+        self.patterns.extend(
+            content
+                .lines()
+                .enumerate()
+                .filter_map(|(i, l)| Pattern::parse(l, base, source, i + 1)),
+        );
+    }
+
+    /// Checks if `path` is ignored, either by itself or because one of its parent directories is.
+    /// Once a directory is ignored, nothing inside of it can be included again.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matching_pattern(path, is_dir)
+            .is_some_and(|p| !p.negated)
+    }
+
+    /// Returns the pattern that decides if `path` is ignored, which is the one ignoring its
+    /// closest ignored parent directory or, if there is none, the last pattern matching the path.
+    pub fn matching_pattern(&self, path: &Path, is_dir: bool) -> Option<&Pattern> {
+        let mut parents: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect();
+        parents.reverse();
+        for parent in parents {
+            if let Some(pattern) = self.last_match(parent, true)
+                && !pattern.negated
+            {
+                return Some(pattern);
+            }
+        }
+        self.last_match(path, is_dir)
+    }
+
+    fn last_match(&self, path: &Path, is_dir: bool) -> Option<&Pattern> {
+        self.patterns.iter().rev().find(|p| p.matches(path, is_dir))
+    }
+
+    /// Adds the patterns of the file at `path`, doing nothing if it does not exist.
+    fn read_file(&mut self, path: &Path, base: &Path) -> Result<()> {
+        if !path.is_file() {
+            return Ok(());
+        }
+        let content = std::fs::read(path).context(format!("could not read {:?}", path))?;
+        let source = relative_path(path, &self.root).unwrap_or(path.to_path_buf());
+        self.add_patterns(&String::from_utf8_lossy(&content), base, &source);
+        Ok(())
+    }
+}
+
+/// Returns the path of the global excludes file used when `core.excludesFile` is not set:
+/// `$XDG_CONFIG_HOME/git/ignore` or `~/.config/git/ignore`.
+fn default_excludes_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("git").join("ignore"))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_ignored(gitignore: &Gitignore, path: &str) -> bool {
+        let is_dir = path.ends_with('/');
+        gitignore.is_ignored(Path::new(path.trim_end_matches('/')), is_dir)
+    }
+
+    #[test]
+    pub fn test_negation_and_priority() {
+        let mut gitignore = Gitignore::default();
+        gitignore.add_patterns("*.log\n!keep.log\n", Path::new(""), Path::new(".gitignore"));
+        gitignore.add_patterns(
+            "keep.log\n!other.log\n",
+            Path::new("sub"),
+            Path::new("sub/.gitignore"),
+        );
+
+        assert!(is_ignored(&gitignore, "error.log"));
+        assert!(!is_ignored(&gitignore, "keep.log"));
+        assert!(!is_ignored(&gitignore, "main.rs"));
+        // the deeper file takes priority inside its directory
+        assert!(is_ignored(&gitignore, "sub/keep.log"));
+        assert!(!is_ignored(&gitignore, "sub/other.log"));
+        assert!(is_ignored(&gitignore, "sub/error.log"));
+    }
+
+    #[test]
+    pub fn test_ignored_directories() {
+        let mut gitignore = Gitignore::default();
+        gitignore.add_patterns(
+            "target/\n**/build\n!target/keep\n",
+            Path::new(""),
+            Path::new(".gitignore"),
+        );
+
+        assert!(is_ignored(&gitignore, "target/"));
+        assert!(!is_ignored(&gitignore, "target"));
+        assert!(is_ignored(&gitignore, "a/b/build/"));
+        assert!(is_ignored(&gitignore, "build"));
+        // files inside ignored directories can not be included again
+        assert!(is_ignored(&gitignore, "target/keep"));
+        assert!(is_ignored(&gitignore, "build/out/file.o"));
+
+        let pattern = gitignore
+            .matching_pattern(Path::new("target/keep"), false)
+            .unwrap();
+        assert_eq!((pattern.text.as_str(), pattern.line_number), ("target/", 1));
+    }
+}
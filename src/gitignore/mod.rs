@@ -0,0 +1,8 @@
+#[allow(clippy::module_inception)]
+mod gitignore;
+mod pattern;
+mod wildmatch;
+
+pub use gitignore::Gitignore;
+pub use pattern::Pattern;
+pub use wildmatch::wildmatch;
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use super::wildmatch;
+
+/// A single rule of an ignore file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// File the pattern was read from, relative to the working tree root if it is inside of it
+    pub source: PathBuf,
+    /// Line of `source` the pattern was read from, starting at 1
+    pub line_number: usize,
+    /// The pattern as it was written, without trailing spaces
+    pub text: String,
+    /// Glob matched against paths, without the `!` prefix, the leading `/` or the trailing `/`
+    glob: String,
+    /// Directory of the file the pattern was read from, relative to the working tree root.
+    /// Patterns only apply to paths inside it.
+    base: PathBuf,
+    /// Re-includes paths ignored by previous patterns (`!pattern`)
+    pub negated: bool,
+    /// Only matches directories (`pattern/`)
+    dir_only: bool,
+    /// Matched against the whole path relative to `base` instead of only the file name, for
+    /// patterns with a `/` anywhere but at the end
+    anchored: bool,
+}
+
+impl Pattern {
+    /// Parses a line of an ignore file, returning `None` if it is empty or a comment.
+    pub fn parse(line: &str, base: &Path, source: &Path, line_number: usize) -> Option<Self> {
+        let line = trim_trailing_spaces(line.trim_end_matches(['\n', '\r']));
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        // a leading `\` escapes `#` and `!`, the rest of escapes are handled when matching
+        let (negated, mut glob) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        let dir_only = glob.ends_with('/') && !glob.ends_with("\\/");
+        if dir_only {
+            glob = &glob[..glob.len() - 1];
+        }
+        let anchored = glob.contains('/');
+        let glob = glob.strip_prefix('/').unwrap_or(glob);
+        if glob.is_empty() {
+            return None;
+        }
+
+        Some(Pattern {
+            source: source.to_path_buf(),
+            line_number,
+            text: line.to_string(),
+            glob: glob.to_string(),
+            base: base.to_path_buf(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Checks if `path` (relative to the working tree root) matches this pattern.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+
+        let text = if self.anchored {
+            relative.as_os_str()
+        } else {
+            match relative.file_name() {
+                Some(name) => name,
+                None => return false,
+            }
+        };
+        wildmatch(self.glob.as_bytes(), text.as_encoded_bytes())
+    }
+}
+
+/// Removes the spaces at the end of `line`, unless they are escaped with a backslash.
+fn trim_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') {
+        let backslashes = line[..end - 1]
+            .bytes()
+            .rev()
+            .take_while(|b| *b == b'\\')
+            .count();
+        if backslashes % 2 == 1 {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(line: &str) -> Pattern {
+        Pattern::parse(line, Path::new(""), Path::new(".gitignore"), 1).unwrap()
+    }
+
+    #[test]
+    pub fn test_parse() {
+        assert!(Pattern::parse("", Path::new(""), Path::new(""), 1).is_none());
+        assert!(Pattern::parse("# comment", Path::new(""), Path::new(""), 1).is_none());
+        assert!(Pattern::parse("   ", Path::new(""), Path::new(""), 1).is_none());
+
+        let p = pattern("!/build/  ");
+        assert!(p.negated && p.dir_only && p.anchored);
+        assert_eq!(p.glob, "build");
+        assert_eq!(p.text, "!/build/");
+
+        let p = pattern("\\#not-a-comment\\ ");
+        assert!(!p.negated && !p.dir_only && !p.anchored);
+        assert_eq!(p.glob, "\\#not-a-comment\\ ");
+    }
+
+    #[test]
+    pub fn test_unanchored_matches_any_depth() {
+        let p = pattern("*.log");
+        assert!(p.matches(Path::new("a.log"), false));
+        assert!(p.matches(Path::new("dir/sub/a.log"), false));
+        assert!(!p.matches(Path::new("a.log.txt"), false));
+    }
+
+    #[test]
+    pub fn test_anchored_and_dir_only() {
+        let p = pattern("/target");
+        assert!(p.matches(Path::new("target"), true));
+        assert!(!p.matches(Path::new("sub/target"), true));
+
+        let p = pattern("doc/*.txt");
+        assert!(p.matches(Path::new("doc/a.txt"), false));
+        assert!(!p.matches(Path::new("doc/sub/a.txt"), false));
+
+        let p = pattern("build/");
+        assert!(p.matches(Path::new("sub/build"), true));
+        assert!(!p.matches(Path::new("sub/build"), false));
+    }
+
+    #[test]
+    pub fn test_base_directory() {
+        let p = Pattern::parse("/out", Path::new("sub"), Path::new("sub/.gitignore"), 1).unwrap();
+        assert!(p.matches(Path::new("sub/out"), false));
+        assert!(!p.matches(Path::new("out"), false));
+        assert!(!p.matches(Path::new("other/sub/out"), false));
+    }
+}
@@ -0,0 +1,220 @@
+/// Matches `text` against a glob `pattern` the way git matches paths:
+/// - `*` matches anything but `/`, and `?` matches any single character but `/`.
+/// - `**` between slashes (or at the start or end of the pattern) also matches across
+///   directories: `**/a` matches `a` in any directory, `a/**` matches everything inside `a` and
+///   `a/**/b` matches `a/b`, `a/x/b`, `a/x/y/b` and so on.
+/// - `[...]` matches one character in a set, which can have ranges (`a-z`), classes
+///   (`[:digit:]`) and be negated with `!` or `^`.
+/// - `\` matches the character after it literally.
+pub fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    let mut p = 0;
+    let mut t = 0;
+
+    while p < pattern.len() {
+        match pattern[p] {
+            b'\\' if p + 1 < pattern.len() => {
+                if text.get(t) != Some(&pattern[p + 1]) {
+                    return false;
+                }
+                p += 2;
+                t += 1;
+            }
+            b'?' => {
+                if text.get(t).is_none_or(|c| *c == b'/') {
+                    return false;
+                }
+                p += 1;
+                t += 1;
+            }
+            b'*' => return match_star(pattern, p, &text[t..]),
+            b'[' => {
+                let Some(&c) = text.get(t).filter(|c| **c != b'/') else {
+                    return false;
+                };
+                match match_class(&pattern[p + 1..], c) {
+                    Some((true, len)) => {
+                        p += 1 + len;
+                        t += 1;
+                    }
+                    // an unclosed bracket can not match anything
+                    _ => return false,
+                }
+            }
+            c => {
+                if text.get(t) != Some(&c) {
+                    return false;
+                }
+                p += 1;
+                t += 1;
+            }
+        }
+    }
+
+    t == text.len()
+}
+
+/// Matches the `*` or `**` starting at `pattern[start]` along with the rest of the pattern.
+fn match_star(pattern: &[u8], start: usize, text: &[u8]) -> bool {
+    let mut p = start;
+    while pattern.get(p) == Some(&b'*') {
+        p += 1;
+    }
+    let rest = &pattern[p..];
+
+    let double = p - start >= 2;
+    let after_slash = start == 0 || pattern[start - 1] == b'/';
+    if double && after_slash {
+        match rest.first() {
+            // everything that is left, including other directories
+            None => return true,
+            // zero or more directories
+            Some(b'/') => {
+                let rest = &rest[1..];
+                if wildmatch(rest, text) {
+                    return true;
+                }
+                return text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == b'/' && wildmatch(rest, &text[i + 1..]));
+            }
+            // a `**` next to other characters works like a single `*`
+            Some(_) => (),
+        }
+    }
+
+    for i in 0..=text.len() {
+        if wildmatch(rest, &text[i..]) {
+            return true;
+        }
+        if text.get(i) == Some(&b'/') {
+            break;
+        }
+    }
+    false
+}
+
+/// Checks if `c` is in the bracket expression at the start of `class` (right after the `[`).
+///
+/// # Returns
+///
+/// Whether `c` matches along with the length of the expression including the closing `]`, or
+/// `None` if it is never closed.
+fn match_class(class: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negated = matches!(class.first(), Some(b'!' | b'^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let mut current = *class.get(i)?;
+        if current == b']' && !first {
+            break;
+        }
+        first = false;
+
+        if current == b'[' && class.get(i + 1) == Some(&b':') {
+            let name_end = class[i + 2..].windows(2).position(|w| w == b":]")?;
+            let name = &class[i + 2..i + 2 + name_end];
+            matched |= match_named_class(name, c)?;
+            i += name_end + 4;
+            continue;
+        }
+
+        if current == b'\\' {
+            i += 1;
+            current = *class.get(i)?;
+        }
+
+        if class.get(i + 1) == Some(&b'-') && class.get(i + 2).is_some_and(|e| *e != b']') {
+            let mut end = class[i + 2];
+            i += 2;
+            if end == b'\\' {
+                i += 1;
+                end = *class.get(i)?;
+            }
+            matched |= (current..=end).contains(&c);
+        } else {
+            matched |= current == c;
+        }
+        i += 1;
+    }
+
+    Some((matched != negated, i + 1))
+}
+
+/// Checks if `c` belongs to the character class called `name`, or returns `None` if there is no
+/// class with that name.
+fn match_named_class(name: &[u8], c: u8) -> Option<bool> {
+    let matched = match name {
+        b"alnum" => c.is_ascii_alphanumeric(),
+        b"alpha" => c.is_ascii_alphabetic(),
+        b"blank" => c == b' ' || c == b'\t',
+        b"cntrl" => c.is_ascii_control(),
+        b"digit" => c.is_ascii_digit(),
+        b"graph" => c.is_ascii_graphic(),
+        b"lower" => c.is_ascii_lowercase(),
+        b"print" => c.is_ascii_graphic() || c == b' ',
+        b"punct" => c.is_ascii_punctuation(),
+        b"space" => c.is_ascii_whitespace(),
+        b"upper" => c.is_ascii_uppercase(),
+        b"xdigit" => c.is_ascii_hexdigit(),
+        _ => return None,
+    };
+    Some(matched)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        wildmatch(pattern.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    pub fn test_simple_globs() {
+        assert!(matches("*.log", "error.log"));
+        assert!(!matches("*.log", "error.txt"));
+        assert!(!matches("*.log", "logs/error.log"));
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(!matches("file?.txt", "file10.txt"));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(matches("\\*literal", "*literal"));
+        assert!(!matches("\\*literal", "xliteral"));
+    }
+
+    #[test]
+    pub fn test_double_star() {
+        assert!(matches("**/build", "build"));
+        assert!(matches("**/build", "a/b/build"));
+        assert!(matches("logs/**", "logs/a"));
+        assert!(matches("logs/**", "logs/a/b"));
+        assert!(!matches("logs/**", "logs"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/xb"));
+        // not between slashes, it works like a single star
+        assert!(matches("a**b", "aXb"));
+        assert!(!matches("a**b", "a/b"));
+    }
+
+    #[test]
+    pub fn test_bracket_expressions() {
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(!matches("[abc].txt", "d.txt"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(!matches("[^a-c]x", "ax"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[[:digit:]][[:upper:]]", "1A"));
+        assert!(!matches("[[:digit:]]", "a"));
+        assert!(!matches("[a/]", "/"));
+        assert!(!matches("[unclosed", "u"));
+    }
+}
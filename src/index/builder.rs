@@ -18,9 +18,9 @@ impl IndexBuilder {
     }
 
     pub fn build(mut self) -> Index {
-        self.index
-            .entries
-            .sort_by(|e1, e2| Path::cmp(e1.path(), e2.path()));
+        self.index.entries.sort_by(|e1, e2| {
+            Path::cmp(e1.path(), e2.path()).then_with(|| e1.get_stage().cmp(&e2.get_stage()))
+        });
         self.index.entries_number = self.index.entries.len() as u32;
         self.index
     }
@@ -33,9 +33,21 @@ impl IndexBuilder {
     pub fn add_index_entry(&mut self, entry: IndexEntry) {
         self.index.entries.push(entry)
     }
+
+    /// Removes every entry for `path`, including the ones of each stage if it has conflicts.
+    ///
+    /// # Returns
+    ///
+    /// The entry of the normal stage, if there was one.
     pub fn remove_index_entry_by_path(&mut self, path: &Path) -> Option<IndexEntry> {
-        let position = self.index.entries.iter().position(|ie| ie.path() == path);
-        Some(self.index.entries.swap_remove(position?))
+        let mut normal = None;
+        while let Some(position) = self.index.entries.iter().position(|ie| ie.path() == path) {
+            let entry = self.index.entries.swap_remove(position);
+            if !entry.is_unmerged() {
+                normal = Some(entry);
+            }
+        }
+        normal
     }
 
     #[allow(unused)]
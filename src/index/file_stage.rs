@@ -1,17 +1,18 @@
 use anyhow::anyhow;
 
-/// Represents a file stage, mainly related to a merge.
+/// Represents a file stage, mainly related to a merge. Files with conflicts have an entry for
+/// each of the versions that exist instead of a normal one.
 #[repr(u16)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FileStage {
     /// File is tracked and staged normally.
     Normal = 0,
+    /// Common ancestor version during a merge.
+    Base = 1,
     /// Version from the current branch.
-    Ours = 1,
+    Ours = 2,
     /// Version from the branch being merged in.
-    Theirs = 2,
-    /// Common ancestor version during a merge.
-    Base = 3,
+    Theirs = 3,
 }
 
 impl TryFrom<u16> for FileStage {
@@ -20,9 +21,9 @@ impl TryFrom<u16> for FileStage {
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
             0 => Ok(Self::Normal),
-            1 => Ok(Self::Ours),
-            2 => Ok(Self::Theirs),
-            3 => Ok(Self::Base),
+            1 => Ok(Self::Base),
+            2 => Ok(Self::Ours),
+            3 => Ok(Self::Theirs),
             _ => Err(anyhow!(
                 "value passed ({}) does not correspond to a file stage",
                 value
@@ -26,9 +26,14 @@ impl Index {
     }
 
     /// Returns an iterator over the entries of this index.
-    pub fn entries(&self) -> Iter<IndexEntry> {
+    pub fn entries(&self) -> Iter<'_, IndexEntry> {
         self.entries.iter()
     }
+
+    /// Returns `true` if any file has conflicts from a merge that were not resolved yet.
+    pub fn has_unmerged_entries(&self) -> bool {
+        self.entries.iter().any(|ie| ie.is_unmerged())
+    }
 }
 
 impl Byteable for Index {
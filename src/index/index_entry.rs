@@ -13,8 +13,6 @@ use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use crate::byteable::Byteable;
 use crate::error::WarnUnwrap;
 use crate::hashing::Hash;
-use crate::utils::path::relative_path;
-use crate::Constants;
 
 use super::FileStage;
 
@@ -91,6 +89,7 @@ impl IndexEntry {
     }
 
     /// Returns the length (in bytes) of this index entry.
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         // 62 fixed bytes, variable path length and null byte
         let len = 62 + self.path_len() + 1;
@@ -107,68 +106,87 @@ impl IndexEntry {
         self.path
     }
 
-    /// Tries to build an index entry from the file at `path` and the hash of the blob object for said file.
+    /// Builds an index entry for an object that is not necessarily present in the working tree,
+    /// leaving its cache data empty.
+    ///
+    /// `mode` is expected to be the actual file mode, not its octal representation.
+    pub fn new(mode: u32, path: PathBuf, object_hash: Hash) -> Self {
+        IndexEntry {
+            mode,
+            cache_data: IndexEntryCache::default(),
+            object_hash,
+            flags: IndexEntry::default_flags(path.as_os_str().len()),
+            path,
+        }
+    }
+
+    /// Tries to build an index entry from the file at `path`, relative to the working tree at
+    /// `root`, and the hash of the blob object for said file.
     ///
     /// # Errors
     ///
     /// This function will fail if:
     /// - The file in the provided path could not be opened.
     /// - It wasn't able to get the metadata of the file.
-    pub fn try_from_file(file_path: &Path, object_hash: Hash) -> Result<Self> {
+    pub fn try_from_file(root: &Path, path: &Path, object_hash: Hash) -> Result<Self> {
         let file =
-            File::open(file_path).context("failed to open file when encoding index entry")?;
+            File::open(root.join(path)).context("failed to open file when encoding index entry")?;
         let metadata = file
             .metadata()
             .context("could not get file metadata when encoding index entry")?;
+        let path = path.to_path_buf();
         Ok(IndexEntry {
             mode: metadata.mode(),
             cache_data: IndexEntryCache::try_from_metadata(metadata)
                 .context("could not get cache data from metadata")
                 .warn_unwrap_or_default(),
             object_hash,
-            flags: IndexEntry::default_flags(file_path.as_os_str().len()),
-            path: relative_path(file_path, &Constants::working_tree_root_path())
-                .unwrap_or(file_path.into()),
+            flags: IndexEntry::default_flags(path.as_os_str().len()),
+            path,
         })
     }
 
-    const ASSUME_VALID_FLAG_POSITION: u16 = 0b1101_1111_1111_1111;
-    const STAGE_POSITION: u16 = 0b0011_1111_1111_1111;
+    const ASSUME_VALID_FLAG: u16 = 0b1000_0000_0000_0000;
+    const STAGE_FLAGS: u16 = 0b0011_0000_0000_0000;
+    const STAGE_SHIFT: u16 = 12;
     const PATH_LEN_FLAG_POSITION: u16 = 0x0FFF;
     const MAX_PATH_LEN: u16 = 0x0FFF;
 
-    /// Returns a 16 bit integer where the first 12 bytes store the length of a path, maxed at
-    /// 0xFFF. The next three bytes store:
-    /// - 13: assume valid
-    /// - 14: extended
-    /// - 15-16: stage
-    ///
-    /// The last bit is not used.
+    /// Returns a 16 bit integer where the lowest 12 bits store the length of a path, maxed at
+    /// 0xFFF. The next bits store, from lowest to highest:
+    /// - 13-14: stage
+    /// - 15: extended
+    /// - 16: assume valid
     fn default_flags(path_len: usize) -> u16 {
         path_len.min(IndexEntry::MAX_PATH_LEN as usize) as u16
     }
 
-    /// Returns the 13th bit of the flags.
+    /// Returns the 16th bit of the flags.
     pub fn is_assumed_valid(&self) -> bool {
-        self.flags & !IndexEntry::ASSUME_VALID_FLAG_POSITION != 0
+        self.flags & IndexEntry::ASSUME_VALID_FLAG != 0
     }
     pub fn set_assumed_valid(&mut self, value: bool) {
         self.flags = match value {
-            true => self.flags | IndexEntry::ASSUME_VALID_FLAG_POSITION,
-            false => self.flags & IndexEntry::ASSUME_VALID_FLAG_POSITION,
+            true => self.flags | IndexEntry::ASSUME_VALID_FLAG,
+            false => self.flags & !IndexEntry::ASSUME_VALID_FLAG,
         }
     }
 
-    /// Returns the 15th to 16th bit of the flags.
+    /// Returns the 13th to 14th bit of the flags.
     pub fn get_stage(&self) -> FileStage {
-        FileStage::try_from(self.flags & !IndexEntry::STAGE_POSITION)
+        FileStage::try_from((self.flags & IndexEntry::STAGE_FLAGS) >> IndexEntry::STAGE_SHIFT)
             .context("index entry did not have a valid stage")
             .unwrap()
     }
     pub fn set_stage(&mut self, stage: FileStage) {
         let stage_u16 = stage as u16;
-        self.flags &= IndexEntry::STAGE_POSITION;
-        self.flags |= stage_u16;
+        self.flags &= !IndexEntry::STAGE_FLAGS;
+        self.flags |= stage_u16 << IndexEntry::STAGE_SHIFT;
+    }
+
+    /// Returns `true` if this entry is one of the versions of a file with merge conflicts.
+    pub fn is_unmerged(&self) -> bool {
+        self.get_stage() != FileStage::Normal
     }
 
     /// Returns the first 12 bytes of the flags.
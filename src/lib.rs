@@ -0,0 +1,53 @@
+//! A reimplementation of git that reads and writes repositories in the same format as git does.
+//!
+//! Every operation goes through a [`Repository`], which knows where the working tree and the
+//! repository folder are. The functions in [`commands`] perform the same operations as the
+//! command line tool, but return structured results instead of printing them, while the other
+//! modules give access to the objects, the index, the refs and the rest of the repository files.
+//!
+//! ```no_run
+//! use git_clone::{Repository, commands};
+//!
+//! let repo = Repository::discover(std::path::Path::new(".")).expect("not in a repository");
+//!
+//! let report = commands::status(&repo)?;
+//! if let Some(branch) = &report.branch {
+//!     println!("on branch {}", branch);
+//! }
+//! for file in report.files {
+//!     println!("{:?}: {:?}", file.path, file.status);
+//! }
+//!
+//! for entry in commands::log(&repo, None, Some(5), &[])? {
+//!     println!("{} {}", entry.hash, entry.subject());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#![allow(clippy::uninlined_format_args)]
+
+pub mod byteable;
+pub mod commands;
+pub mod config;
+pub mod diff;
+pub mod fs;
+pub mod gitignore;
+pub mod hashing;
+pub mod index;
+pub mod merge;
+pub mod object;
+pub mod pack;
+pub mod repository;
+pub mod revision;
+
+mod constants;
+mod error;
+mod utils;
+
+pub use constants::*;
+
+pub use hashing::Hash;
+pub use index::Index;
+pub use object::Object;
+pub use object::tree::TreeBuilder;
+pub use repository::Repository;
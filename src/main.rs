@@ -1,18 +1,10 @@
 #![allow(clippy::uninlined_format_args)]
 
 mod args;
-mod byteable;
-mod commands;
-mod constants;
-mod error;
-mod fs;
-mod gitignore;
-mod hashing;
-mod index;
-mod object;
-mod utils;
+mod cli;
 
-pub use constants::*;
+/// Exit status of the commands that fail, the same one git uses for fatal errors.
+const FATAL_EXIT_STATUS: i32 = 128;
 
 fn main() {
     let _ = dotenvy::dotenv();
@@ -21,8 +13,14 @@ fn main() {
     use clap::Parser;
     let args = args::Args::parse_from(wild::args());
 
-    match commands::execute_command(&args.command) {
+    match cli::execute_command(&args.command) {
         Ok(message) => print!("{}", message),
-        Err(error) => eprintln!("{:?}", error),
+        Err(error) => match error.downcast_ref::<cli::ExitStatus>() {
+            Some(status) => std::process::exit(status.0),
+            None => {
+                eprintln!("{:?}", error);
+                std::process::exit(FATAL_EXIT_STATUS)
+            }
+        },
     }
 }
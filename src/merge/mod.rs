@@ -0,0 +1,5 @@
+mod options;
+mod three_way;
+
+pub use options::{ConflictStyle, Favor, MergeOptions};
+pub use three_way::{FileMerge, MergeLabels, merge_blobs, merge_lines};
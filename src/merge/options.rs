@@ -0,0 +1,67 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+
+use crate::config::Config;
+
+const MERGE_STR: &str = "merge";
+const DIFF3_STR: &str = "diff3";
+const ZDIFF3_STR: &str = "zdiff3";
+
+/// How conflicts are written between the conflict markers, as set by `merge.conflictStyle`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStyle {
+    /// Only our and their versions, leaving the lines both sides agree on out of the markers
+    #[default]
+    Merge,
+    /// Our, the base and their versions of the whole changed region
+    Diff3,
+    /// Like `Diff3`, but the lines at the start and end that are the same in our and their
+    /// versions are moved out of the markers
+    Zdiff3,
+}
+
+impl FromStr for ConflictStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            MERGE_STR => ConflictStyle::Merge,
+            DIFF3_STR => ConflictStyle::Diff3,
+            ZDIFF3_STR => ConflictStyle::Zdiff3,
+            _ => bail!("unknown conflict style: {}", s),
+        })
+    }
+}
+
+/// Which side resolves the conflicts instead of writing conflict markers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Favor {
+    Ours,
+    Theirs,
+    /// Both sides, ours first
+    Union,
+}
+
+/// Settings of a three-way merge of files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    pub style: ConflictStyle,
+    /// Resolves conflicts with one side (or both) instead of leaving them marked
+    pub favor: Option<Favor>,
+}
+
+impl MergeOptions {
+    /// Returns the options set in the config, which only decides the conflict style.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if `merge.conflictStyle` is not a known style.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let style = match config.get("merge.conflictStyle") {
+            Some(style) => style.parse()?,
+            None => ConflictStyle::default(),
+        };
+        Ok(MergeOptions { style, favor: None })
+    }
+}
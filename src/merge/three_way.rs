@@ -0,0 +1,429 @@
+use anyhow::{Result, bail};
+
+use crate::diff::{Edit, diff, split_lines};
+use crate::object::Object;
+
+use super::{ConflictStyle, Favor, MergeOptions};
+
+/// Length of the conflict markers.
+const MARKER_SIZE: usize = 7;
+/// Conflicts of the `merge` style separated by at most this many lines are joined into one.
+const MAX_CONFLICT_GAP: usize = 3;
+
+/// Names of the versions of a file, shown next to the conflict markers.
+#[derive(Debug, Clone, Copy)]
+pub struct MergeLabels<'a> {
+    pub base: &'a str,
+    pub ours: &'a str,
+    pub theirs: &'a str,
+}
+
+/// The result of merging the contents of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMerge {
+    /// The merged contents, with conflict markers around the regions both sides changed
+    pub content: Vec<u8>,
+    /// Number of conflicts marked in the contents
+    pub conflicts: usize,
+}
+
+impl FileMerge {
+    /// Returns whether every change could be merged without conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+/// A range of lines of the base replaced by a range of lines of one of the sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Change {
+    base_start: usize,
+    base_end: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Merges the data of three blobs, `ours` and `theirs` coming from `base` (see `merge_lines`).
+/// A missing base merges the contents as if both sides added the file.
+///
+/// # Errors
+///
+/// This function will fail if any of the objects is not a blob.
+pub fn merge_blobs(
+    base: Option<&Object>,
+    ours: &Object,
+    theirs: &Object,
+    labels: &MergeLabels,
+    options: &MergeOptions,
+) -> Result<FileMerge> {
+    fn data(object: &Object) -> Result<&[u8]> {
+        match object {
+            Object::Blob { data } => Ok(data),
+            _ => bail!("can not merge a {} object, only blobs", object.kind()),
+        }
+    }
+
+    let base = match base {
+        Some(base) => data(base)?,
+        None => &[],
+    };
+    Ok(merge_lines(
+        base,
+        data(ours)?,
+        data(theirs)?,
+        labels,
+        options,
+    ))
+}
+
+/// Merges the lines changed from `base` to `ours` with the ones changed from `base` to `theirs`.
+///
+/// Regions changed by only one side take that side's lines, and regions both sides changed in
+/// the same way take them once. Regions changed differently on both sides, including changes
+/// right next to each other, are conflicts: they are written between conflict markers in the
+/// style of the options, unless the options favor one of the sides.
+pub fn merge_lines(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    labels: &MergeLabels,
+    options: &MergeOptions,
+) -> FileMerge {
+    let base = split_lines(base);
+    let ours = split_lines(ours);
+    let theirs = split_lines(theirs);
+    let ours_changes = changes(&diff(&base, &ours));
+    let theirs_changes = changes(&diff(&base, &theirs));
+
+    let mut output = Output {
+        labels,
+        options,
+        content: Vec::new(),
+        conflicts: 0,
+    };
+    let (mut i, mut j) = (0, 0);
+    let mut position = 0;
+    loop {
+        let start = match (ours_changes.get(i), theirs_changes.get(j)) {
+            (None, None) => break,
+            (Some(c), None) | (None, Some(c)) => c.base_start,
+            (Some(a), Some(b)) => a.base_start.min(b.base_start),
+        };
+
+        // taking every change of both sides that overlaps or touches the region
+        let (first_i, first_j) = (i, j);
+        let mut end = start;
+        loop {
+            if let Some(c) = ours_changes.get(i)
+                && c.base_start <= end
+            {
+                end = end.max(c.base_end);
+                i += 1;
+            } else if let Some(c) = theirs_changes.get(j)
+                && c.base_start <= end
+            {
+                end = end.max(c.base_end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        output.push(&base[position..start]);
+        let base_region = &base[start..end];
+        let ours_region = side_region(&ours, &ours_changes[first_i..i], start, end);
+        let theirs_region = side_region(&theirs, &theirs_changes[first_j..j], start, end);
+        match (ours_region, theirs_region) {
+            (Some(region), None) | (None, Some(region)) => output.push(region),
+            (Some(o), Some(t)) if o == t => output.push(o),
+            (Some(o), Some(t)) => output.conflict(base_region, o, t),
+            (None, None) => unreachable!("every region has at least one change"),
+        }
+        position = end;
+    }
+    output.push(&base[position..]);
+
+    FileMerge {
+        content: output.content,
+        conflicts: output.conflicts,
+    }
+}
+
+/// Returns the ranges of lines that `edits` replace, in both sequences.
+fn changes(edits: &[Edit]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    let mut in_change = false;
+    let (mut old, mut new) = (0, 0);
+    for e in edits {
+        if let Edit::Equal { .. } = e {
+            old += 1;
+            new += 1;
+            in_change = false;
+            continue;
+        }
+
+        if !in_change {
+            changes.push(Change {
+                base_start: old,
+                base_end: old,
+                start: new,
+                end: new,
+            });
+            in_change = true;
+        }
+        match e {
+            Edit::Delete { .. } => old += 1,
+            Edit::Insert { .. } => new += 1,
+            Edit::Equal { .. } => unreachable!(),
+        }
+        if let Some(change) = changes.last_mut() {
+            change.base_end = old;
+            change.end = new;
+        }
+    }
+    changes
+}
+
+/// Returns the lines of a side that replace the base lines `start..end`, or `None` if the side
+/// did not change any of them. `changes` are the changes of the side inside the region.
+fn side_region<'a, 'b>(
+    lines: &'b [&'a [u8]],
+    changes: &[Change],
+    start: usize,
+    end: usize,
+) -> Option<&'b [&'a [u8]]> {
+    let (first, last) = (changes.first()?, changes.last()?);
+    // outside of its changes the side has the same lines as the base
+    let range = first.start - (first.base_start - start)..last.end + (end - last.base_end);
+    Some(&lines[range])
+}
+
+/// The contents of a merge while it is being written.
+struct Output<'o> {
+    labels: &'o MergeLabels<'o>,
+    options: &'o MergeOptions,
+    content: Vec<u8>,
+    conflicts: usize,
+}
+
+impl Output<'_> {
+    fn push(&mut self, lines: &[&[u8]]) {
+        for line in lines {
+            self.content.extend_from_slice(line);
+        }
+    }
+
+    /// Pushes lines that are followed by more, adding the new line the last one could be missing.
+    fn push_terminated(&mut self, lines: &[&[u8]]) {
+        self.push(lines);
+        if !lines.is_empty() && !self.content.ends_with(b"\n") {
+            self.content.push(b'\n');
+        }
+    }
+
+    /// Writes a region both sides changed differently in the style of the options.
+    fn conflict(&mut self, base: &[&[u8]], ours: &[&[u8]], theirs: &[&[u8]]) {
+        match self.options.style {
+            ConflictStyle::Diff3 => self.resolve(Some(base), ours, theirs),
+            ConflictStyle::Zdiff3 => {
+                let prefix = ours.iter().zip(theirs).take_while(|(o, t)| o == t).count();
+                let suffix = ours[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(theirs[prefix..].iter().rev())
+                    .take_while(|(o, t)| o == t)
+                    .count();
+                self.push(&ours[..prefix]);
+                self.resolve(
+                    Some(base),
+                    &ours[prefix..ours.len() - suffix],
+                    &theirs[prefix..theirs.len() - suffix],
+                );
+                self.push(&ours[ours.len() - suffix..]);
+            }
+            ConflictStyle::Merge => self.refined_conflict(ours, theirs),
+        }
+    }
+
+    /// Writes the conflict between `ours` and `theirs` as several smaller ones, keeping the
+    /// lines both have in common out of them. Conflicts that end up close to each other are
+    /// written as one to keep them readable.
+    fn refined_conflict(&mut self, ours: &[&[u8]], theirs: &[&[u8]]) {
+        let mut regions: Vec<Change> = Vec::new();
+        for change in changes(&diff(ours, theirs)) {
+            match regions.last_mut() {
+                Some(last) if change.base_start - last.base_end <= MAX_CONFLICT_GAP => {
+                    last.base_end = change.base_end;
+                    last.end = change.end;
+                }
+                _ => regions.push(change),
+            }
+        }
+
+        let mut position = 0;
+        for region in regions {
+            self.push(&ours[position..region.base_start]);
+            self.resolve(
+                None,
+                &ours[region.base_start..region.base_end],
+                &theirs[region.start..region.end],
+            );
+            position = region.base_end;
+        }
+        self.push(&ours[position..]);
+    }
+
+    /// Resolves a conflict with the side the options favor, or writes it between markers.
+    fn resolve(&mut self, base: Option<&[&[u8]]>, ours: &[&[u8]], theirs: &[&[u8]]) {
+        match self.options.favor {
+            Some(Favor::Ours) => return self.push(ours),
+            Some(Favor::Theirs) => return self.push(theirs),
+            Some(Favor::Union) => {
+                if theirs.is_empty() {
+                    self.push(ours);
+                } else {
+                    self.push_terminated(ours);
+                }
+                return self.push(theirs);
+            }
+            None => {}
+        }
+
+        self.conflicts += 1;
+        self.marker('<', self.labels.ours);
+        self.push_terminated(ours);
+        if let Some(base) = base {
+            self.marker('|', self.labels.base);
+            self.push_terminated(base);
+        }
+        self.marker('=', "");
+        self.push_terminated(theirs);
+        self.marker('>', self.labels.theirs);
+    }
+
+    fn marker(&mut self, character: char, label: &str) {
+        let mut line = character.to_string().repeat(MARKER_SIZE);
+        if !label.is_empty() {
+            line.push(' ');
+            line.push_str(label);
+        }
+        line.push('\n');
+        self.content.extend_from_slice(line.as_bytes());
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: MergeLabels = MergeLabels {
+        base: "base",
+        ours: "ours",
+        theirs: "theirs",
+    };
+    const BASE: &str = "a\nb\nc\nd\ne\nf\ng\n";
+
+    fn merge(ours: &str, theirs: &str, style: ConflictStyle, favor: Option<Favor>) -> FileMerge {
+        let options = MergeOptions { style, favor };
+        merge_lines(
+            BASE.as_bytes(),
+            ours.as_bytes(),
+            theirs.as_bytes(),
+            &LABELS,
+            &options,
+        )
+    }
+
+    fn content(merge: &FileMerge) -> &str {
+        std::str::from_utf8(&merge.content).unwrap()
+    }
+
+    #[test]
+    pub fn test_merge_separate_changes() {
+        let merged = merge(
+            "a\nB\nc\nd\ne\nf\ng\n",
+            "a\nb\nc\nd\ne\nF\ng\nh\n",
+            ConflictStyle::Merge,
+            None,
+        );
+        assert!(merged.is_clean());
+        assert_eq!(content(&merged), "a\nB\nc\nd\ne\nF\ng\nh\n");
+
+        let same = "a\nc\nd\ne\nf\ng\nh\n";
+        let merged = merge(same, same, ConflictStyle::Merge, None);
+        assert!(merged.is_clean());
+        assert_eq!(content(&merged), same);
+    }
+
+    #[test]
+    pub fn test_merge_conflict_styles() {
+        let ours = "a\nb\nX\nQ\nd\ne\nf\ng\n";
+        let theirs = "a\nb\nX\nR\nd\ne\nf\nG\n";
+
+        let merged = merge(ours, theirs, ConflictStyle::Merge, None);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            content(&merged),
+            "a\nb\nX\n<<<<<<< ours\nQ\n=======\nR\n>>>>>>> theirs\nd\ne\nf\nG\n"
+        );
+
+        let merged = merge(ours, theirs, ConflictStyle::Diff3, None);
+        assert_eq!(
+            content(&merged),
+            "a\nb\n<<<<<<< ours\nX\nQ\n||||||| base\nc\n=======\nX\nR\n>>>>>>> theirs\nd\ne\nf\nG\n"
+        );
+
+        let merged = merge(ours, theirs, ConflictStyle::Zdiff3, None);
+        assert_eq!(
+            content(&merged),
+            "a\nb\nX\n<<<<<<< ours\nQ\n||||||| base\nc\n=======\nR\n>>>>>>> theirs\nd\ne\nf\nG\n"
+        );
+    }
+
+    #[test]
+    pub fn test_merge_favor() {
+        let ours = "a\nb\nX\nd\ne\nf\ng\n";
+        let theirs = "a\nb\nZ\nd\ne\nf\ng\n";
+
+        let merged = merge(ours, theirs, ConflictStyle::Merge, Some(Favor::Ours));
+        assert!(merged.is_clean());
+        assert_eq!(content(&merged), ours);
+
+        let merged = merge(ours, theirs, ConflictStyle::Diff3, Some(Favor::Theirs));
+        assert_eq!(content(&merged), theirs);
+
+        let merged = merge(ours, theirs, ConflictStyle::Merge, Some(Favor::Union));
+        assert_eq!(content(&merged), "a\nb\nX\nZ\nd\ne\nf\ng\n");
+    }
+
+    #[test]
+    pub fn test_merge_missing_new_line() {
+        let merged = merge(
+            "a\nb\nc\nd\ne\nf\nX",
+            "a\nb\nc\nd\ne\nf\nZ",
+            ConflictStyle::Merge,
+            None,
+        );
+        assert_eq!(
+            content(&merged),
+            "a\nb\nc\nd\ne\nf\n<<<<<<< ours\nX\n=======\nZ\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    pub fn test_merge_blobs() {
+        let blob = |data: &str| Object::Blob {
+            data: data.as_bytes().into(),
+        };
+        let options = MergeOptions::default();
+        let merged = merge_blobs(None, &blob("a\n"), &blob("a\n"), &LABELS, &options).unwrap();
+        assert_eq!(content(&merged), "a\n");
+
+        let tree = Object::Tree {
+            entries: Vec::new(),
+        };
+        assert!(merge_blobs(None, &tree, &blob("a\n"), &LABELS, &options).is_err());
+    }
+}
@@ -1,10 +1,8 @@
 use std::io::Cursor;
 use std::rc::Rc;
 use std::str::{FromStr, Split};
-use std::time::{Duration, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
-use time::UtcOffset;
 
 use crate::hashing::Hash;
 use crate::object::Object;
@@ -51,21 +49,6 @@ fn format_data(
     commiter: &CommitUser,
     message: &str,
 ) -> Result<String> {
-    fn format_commituser(user: &CommitUser) -> Result<String> {
-        Ok(format!(
-            "{} {} {} {}\n",
-            user.kind,
-            user.identifier,
-            user.timestamp
-                .duration_since(UNIX_EPOCH)
-                .context("timestamp was invalid")?
-                .as_secs(),
-            user.timezone
-                .format(TIMEZONE_FORMAT)
-                .expect("timezone formatting should never fail"),
-        ))
-    }
-
     let mut s = String::new();
 
     // tree {hash}
@@ -77,8 +60,8 @@ fn format_data(
     }
 
     // author {identifier} {timestamp} {timezone}
-    s.push_str(&format_commituser(author)?);
-    s.push_str(&format_commituser(commiter)?);
+    s.push_str(&author.to_header_line()?);
+    s.push_str(&commiter.to_header_line()?);
 
     s.push_str(&format!("\n{}\n", message));
 
@@ -166,48 +149,15 @@ pub fn from_bytes(bytes: &[u8]) -> Result<Object> {
             .context(format!("expected {}, got nothing", AUTHOR_STR))?;
     }
 
-    // {userkind} {identifier} {timestamp} {timezone}
-    /// This function expects `userkind` to not be present in `splitted` (have already been
-    /// consumed).
-    fn commituser_from_splitted(splitted: Split<char>, kind: CommitUserKind) -> Result<CommitUser> {
-        let commuser_vec: Vec<&str> = splitted.collect();
-        let word_amount = commuser_vec.len();
-
-        // reading every word but the last two since the identifier can have an arbitrary number of
-        // words but the last two are always the timestamp and timezone.
-        let identifier = commuser_vec[..word_amount - 2].join(" ");
-        if identifier.is_empty() {
-            bail!("expected identifier when reading author")
-        }
-
-        let timestamp_str = *commuser_vec
-            .get(word_amount - 2)
-            .context("expected timestamp when reading author")?;
-        let timestamp_u64 = timestamp_str
-            .parse::<u64>()
-            .context("could not parse timestamp to a number when reading author")?;
-
-        let timezone = *commuser_vec
-            .get(word_amount - 1)
-            .context("expected timezone when reading author")?;
-
-        Ok(CommitUser {
-            kind,
-            identifier,
-            timestamp: UNIX_EPOCH
-                .checked_add(Duration::from_secs(timestamp_u64))
-                .context("author timestamp was invalid")?,
-            timezone: UtcOffset::parse(timezone, TIMEZONE_FORMAT)
-                .context("author timezone was invalid")?,
-        })
-    }
-
     // reading author, `next` is at the first word of the line after the last parent (if there was
     // one or more)
     if next != AUTHOR_STR {
         bail!("expected {}", AUTHOR_STR)
     }
-    let author = commituser_from_splitted(splitted, CommitUserKind::Author)?;
+    let author = CommitUser::from_header_value(
+        &splitted.collect::<Vec<&str>>().join(" "),
+        CommitUserKind::Author,
+    )?;
 
     // consuming first word, `splitted` does not have to contain the first word
     splitted = lines
@@ -219,7 +169,10 @@ pub fn from_bytes(bytes: &[u8]) -> Result<Object> {
     if splitted.next() != Some(COMMITTER_STR) {
         bail!("expected {}", COMMITTER_STR)
     }
-    let committer = commituser_from_splitted(splitted, CommitUserKind::Committer)?;
+    let committer = CommitUser::from_header_value(
+        &splitted.collect::<Vec<&str>>().join(" "),
+        CommitUserKind::Committer,
+    )?;
 
     lines.next(); // skipping empty newline
 
@@ -250,6 +203,7 @@ pub fn display(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Config;
     use crate::hashing::Hash;
     use std::time::{Duration, UNIX_EPOCH};
     use time::UtcOffset;
@@ -312,6 +266,84 @@ mod tests {
         assert_eq!(CommitUserKind::Committer.to_string(), "committer");
     }
 
+    #[test]
+    fn test_commit_user_name_and_email() {
+        let user = create_test_user(
+            CommitUserKind::Author,
+            TEST_AUTHOR_NAME,
+            TEST_AUTHOR_EMAIL,
+            TEST_TIMESTAMP_AUTHOR,
+            TEST_TIMEZONE_OFFSET,
+        );
+        assert_eq!(user.name(), TEST_AUTHOR_NAME);
+        assert_eq!(user.email(), TEST_AUTHOR_EMAIL);
+    }
+
+    #[test]
+    fn test_commit_user_format_date() {
+        let user = create_test_user(
+            CommitUserKind::Author,
+            TEST_AUTHOR_NAME,
+            TEST_AUTHOR_EMAIL,
+            TEST_TIMESTAMP_AUTHOR,
+            TEST_TIMEZONE_OFFSET,
+        );
+        assert_eq!(user.format_date(), "Fri Dec 31 19:00:00 2021 -0500");
+    }
+
+    #[test]
+    fn test_commit_user_from_identity() {
+        let config = Config::from_str(
+            "[user]\nname = Config User\nemail = config@example.com\n[committer]\nname = Other\n",
+        )
+        .unwrap();
+
+        let user =
+            CommitUser::from_identity_with(CommitUserKind::Author, &config, |_| None).unwrap();
+        assert_eq!(user.identifier, "Config User <config@example.com>");
+        let user =
+            CommitUser::from_identity_with(CommitUserKind::Committer, &config, |_| None).unwrap();
+        assert_eq!(user.identifier, "Other <config@example.com>");
+
+        // environment variables take precedence over the config
+        let env = |name: &str| match name {
+            "GIT_AUTHOR_NAME" => Some(TEST_AUTHOR_NAME.to_string()),
+            "GIT_AUTHOR_DATE" => Some(format!("@{} -0500", TEST_TIMESTAMP_AUTHOR)),
+            _ => None,
+        };
+        let user = CommitUser::from_identity_with(CommitUserKind::Author, &config, env).unwrap();
+        assert_eq!(user.identifier, "John Doe <config@example.com>");
+        assert_eq!(user.format_date(), "Fri Dec 31 19:00:00 2021 -0500");
+    }
+
+    #[test]
+    fn test_commit_user_from_identity_unset() {
+        let config = Config::from_str("[user]\nname = Only Name\n").unwrap();
+        assert!(CommitUser::from_identity_with(CommitUserKind::Author, &config, |_| None).is_err());
+
+        let env = |name: &str| (name == "GIT_COMMITTER_EMAIL").then(|| "  ".to_string());
+        assert!(CommitUser::from_identity_with(CommitUserKind::Committer, &config, env).is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let expected = (
+            UNIX_EPOCH + Duration::from_secs(1112904793),
+            UtcOffset::from_hms(2, 0, 0).unwrap(),
+        );
+        for date in [
+            "1112904793 +0200",
+            "@1112904793 +0200",
+            "Thu, 07 Apr 2005 22:13:13 +0200",
+            "2005-04-07T22:13:13+02:00",
+            "2005-04-07 22:13:13 +0200",
+        ] {
+            assert_eq!(parse_date(date).unwrap(), expected, "{}", date);
+        }
+        assert_eq!(parse_date("0").unwrap(), (UNIX_EPOCH, UtcOffset::UTC));
+        assert!(parse_date("yesterday").is_err());
+    }
+
     // Tests for as_bytes
 
     #[test]
@@ -345,7 +377,6 @@ mod tests {
         let commit_str = std::str::from_utf8(&bytes)
             .unwrap()
             .split('\0')
-            .into_iter()
             .skip(1)
             .collect::<String>();
         let mut commit_lines = commit_str.lines();
@@ -406,7 +437,6 @@ mod tests {
         let commit_str = std::str::from_utf8(&bytes)
             .unwrap()
             .split('\0')
-            .into_iter()
             .skip(1)
             .collect::<String>();
         let mut commit_lines = commit_str.lines();
@@ -467,7 +497,7 @@ mod tests {
         } = result.unwrap()
         {
             assert_eq!(tree.to_string(), TEST_TREE_HASH);
-            assert_eq!(parents.get(0).unwrap().to_string(), TEST_PARENT_HASH);
+            assert_eq!(parents.first().unwrap().to_string(), TEST_PARENT_HASH);
             assert_eq!(
                 author.identifier,
                 format!("{} <{}>", TEST_AUTHOR_NAME, TEST_AUTHOR_EMAIL)
@@ -566,7 +596,7 @@ mod tests {
         } = parsed
         {
             assert_eq!(tree.to_string(), TEST_TREE_HASH);
-            assert_eq!(parents.get(0).unwrap().to_string(), TEST_PARENT_HASH);
+            assert_eq!(parents.first().unwrap().to_string(), TEST_PARENT_HASH);
             assert_eq!(
                 parsed_author.identifier,
                 format!("{} <{}>", TEST_AUTHOR_NAME, TEST_AUTHOR_EMAIL)
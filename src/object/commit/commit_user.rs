@@ -1,12 +1,26 @@
+use std::env;
 use std::fmt::Display;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Result, bail};
-use time::UtcOffset;
+use anyhow::{Context, Result, bail};
+use time::format_description::BorrowedFormatItem;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::macros::format_description;
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::config::Config;
 
 use super::*;
 
+/// Format used by git for ISO 8601 dates, which is not strictly ISO 8601.
+const ISO_DATE_FORMAT: &[BorrowedFormatItem] = format_description!(
+    "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]"
+);
+const DATE_FORMAT: &[BorrowedFormatItem] = format_description!(
+    "[weekday repr:short] [month repr:short] [day padding:none] [hour]:[minute]:[second] [year] [offset_hour sign:mandatory][offset_minute]"
+);
+
 #[derive(Debug)]
 pub struct CommitUser {
     pub kind: CommitUserKind,
@@ -17,21 +31,195 @@ pub struct CommitUser {
 }
 
 impl CommitUser {
-    /// Just for debugging for now
-    pub fn default(kind: CommitUserKind) -> Self {
-        CommitUser {
+    /// Builds the author or committer of a new commit, taking every value from the first place
+    /// that has it:
+    /// - Name: `GIT_AUTHOR_NAME`/`GIT_COMMITTER_NAME`, `author.name`/`committer.name` and
+    ///   `user.name`.
+    /// - Email: `GIT_AUTHOR_EMAIL`/`GIT_COMMITTER_EMAIL`, `author.email`/`committer.email` and
+    ///   `user.email`.
+    /// - Date: `GIT_AUTHOR_DATE`/`GIT_COMMITTER_DATE`, or the current time.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the name or the email are not set anywhere, or if the date in
+    /// the environment could not be parsed.
+    pub fn from_identity(kind: CommitUserKind, config: &Config) -> Result<Self> {
+        CommitUser::from_identity_with(kind, config, |name| env::var(name).ok())
+    }
+
+    /// Same as `from_identity`, reading environment variables through `var`.
+    pub(super) fn from_identity_with(
+        kind: CommitUserKind,
+        config: &Config,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let env_prefix = format!("GIT_{}", kind.to_string().to_uppercase());
+        let lookup = |field: &str| {
+            var(&format!("{}_{}", env_prefix, field.to_uppercase()))
+                .or_else(|| config.get(&format!("{}.{}", kind, field)).map(String::from))
+                .or_else(|| config.get(&format!("user.{}", field)).map(String::from))
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        let (Some(name), Some(email)) = (lookup("name"), lookup("email")) else {
+            bail!(
+                "{} identity unknown, please tell me who you are by running:\n\n  \
+                 git config --global user.name \"Your Name\"\n  \
+                 git config --global user.email \"you@example.com\"\n\n\
+                 or by setting the {}_NAME and {}_EMAIL environment variables",
+                kind,
+                env_prefix,
+                env_prefix
+            )
+        };
+
+        let (timestamp, timezone) = match var(&format!("{}_DATE", env_prefix)) {
+            Some(date) => {
+                parse_date(&date).context(format!("invalid date in {}_DATE", env_prefix))?
+            }
+            None => (
+                SystemTime::now(),
+                UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+            ),
+        };
+
+        Ok(CommitUser {
             kind,
-            identifier: "Josué Torres <josue.ger.torres.gar@gmail.com>".to_owned(),
-            timestamp: SystemTime::now(),
-            timezone: UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+            identifier: format!("{} <{}>", name.trim(), email.trim()),
+            timestamp,
+            timezone,
+        })
+    }
+}
+
+/// Parses a date in one of the formats git accepts in its environment variables:
+/// - Raw: `1112904793 +0200`, optionally with a leading `@` and without timezone.
+/// - RFC 2822: `Thu, 07 Apr 2005 22:13:13 +0200`.
+/// - ISO 8601: `2005-04-07T22:13:13+02:00` or `2005-04-07 22:13:13 +0200`.
+pub(super) fn parse_date(date: &str) -> Result<(SystemTime, UtcOffset)> {
+    let date = date.trim();
+
+    let (seconds, zone) = date.split_once(' ').unwrap_or((date, "+0000"));
+    let seconds = seconds.strip_prefix('@').unwrap_or(seconds);
+    if let Ok(seconds) = seconds.parse::<u64>() {
+        let timezone = UtcOffset::parse(zone, TIMEZONE_FORMAT).context("invalid timezone")?;
+        return Ok((UNIX_EPOCH + Duration::from_secs(seconds), timezone));
+    }
+
+    let parsed = OffsetDateTime::parse(date, &Rfc2822)
+        .or_else(|_| OffsetDateTime::parse(date, &Rfc3339))
+        .or_else(|_| OffsetDateTime::parse(date, ISO_DATE_FORMAT))
+        .context(format!("could not parse date '{}'", date))?;
+
+    let seconds =
+        u64::try_from(parsed.unix_timestamp()).context("dates before 1970 are not supported")?;
+    Ok((UNIX_EPOCH + Duration::from_secs(seconds), parsed.offset()))
+}
+
+impl CommitUser {
+    /// Formats this user as a line of a commit or tag header:
+    ///
+    /// `{kind} {identifier} {timestamp} {timezone}\n`
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the timestamp is before the unix epoch.
+    pub fn to_header_line(&self) -> Result<String> {
+        Ok(format!(
+            "{} {} {} {}\n",
+            self.kind,
+            self.identifier,
+            self.timestamp
+                .duration_since(UNIX_EPOCH)
+                .context("timestamp was invalid")?
+                .as_secs(),
+            self.timezone
+                .format(TIMEZONE_FORMAT)
+                .expect("timezone formatting should never fail"),
+        ))
+    }
+
+    /// Parses what follows the kind in a commit or tag header line:
+    ///
+    /// `{identifier} {timestamp} {timezone}`
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if any of the parts is missing or invalid.
+    pub fn from_header_value(value: &str, kind: CommitUserKind) -> Result<Self> {
+        // the identifier can have any number of words, but the last two are always the timestamp
+        // and timezone
+        let mut words = value.rsplitn(3, ' ');
+        let timezone = words
+            .next()
+            .context(format!("expected timezone when reading {}", kind))?;
+        let timestamp_str = words
+            .next()
+            .context(format!("expected timestamp when reading {}", kind))?;
+        let identifier = words.next().unwrap_or_default().to_string();
+        if identifier.is_empty() {
+            bail!("expected identifier when reading {}", kind)
+        }
+
+        let timestamp = timestamp_str.parse::<u64>().context(format!(
+            "could not parse timestamp to a number when reading {}",
+            kind
+        ))?;
+
+        Ok(CommitUser {
+            identifier,
+            timestamp: UNIX_EPOCH
+                .checked_add(Duration::from_secs(timestamp))
+                .context(format!("{} timestamp was invalid", kind))?,
+            timezone: UtcOffset::parse(timezone, TIMEZONE_FORMAT)
+                .context(format!("{} timezone was invalid", kind))?,
+            kind,
+        })
+    }
+
+    /// Returns the name part of the identifier (everything before the email).
+    pub fn name(&self) -> &str {
+        match self.identifier.find('<') {
+            Some(position) => self.identifier[..position].trim_end(),
+            None => &self.identifier,
+        }
+    }
+
+    /// Returns the email inside the angle brackets of the identifier, or an empty string if there
+    /// is none.
+    pub fn email(&self) -> &str {
+        let start = match self.identifier.find('<') {
+            Some(position) => position + 1,
+            None => return "",
+        };
+        match self.identifier[start..].find('>') {
+            Some(len) => &self.identifier[start..start + len],
+            None => &self.identifier[start..],
         }
     }
+
+    /// Returns the timestamp of this user in its timezone, formatted the way git shows dates by
+    /// default, for example: `Thu Jan 1 00:00:00 1970 +0000`.
+    pub fn format_date(&self) -> String {
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        OffsetDateTime::from_unix_timestamp(seconds)
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+            .to_offset(self.timezone)
+            .format(DATE_FORMAT)
+            .expect("date formatting should never fail")
+    }
 }
 
 #[derive(Debug)]
 pub enum CommitUserKind {
     Author,
     Committer,
+    Tagger,
 }
 
 impl FromStr for CommitUserKind {
@@ -41,6 +229,7 @@ impl FromStr for CommitUserKind {
         match s {
             AUTHOR_STR => Ok(CommitUserKind::Author),
             COMMITTER_STR => Ok(CommitUserKind::Committer),
+            TAGGER_STR => Ok(CommitUserKind::Tagger),
             _ => bail!("invalid commit user kind: {}", s),
         }
     }
@@ -51,6 +240,7 @@ impl Display for CommitUserKind {
         f.write_str(match self {
             CommitUserKind::Author => AUTHOR_STR,
             CommitUserKind::Committer => COMMITTER_STR,
+            CommitUserKind::Tagger => TAGGER_STR,
         })
     }
 }
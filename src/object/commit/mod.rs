@@ -1,9 +1,11 @@
 #[allow(clippy::module_inception)]
 mod commit;
 mod commit_user;
+mod utils;
 
 pub use commit::*;
 pub use commit_user::*;
+pub use utils::*;
 
 use time::format_description::BorrowedFormatItem;
 use time::macros::format_description;
@@ -12,5 +14,6 @@ pub const TREE_STR: &str = "tree";
 pub const PARENT_STR: &str = "parent";
 pub const AUTHOR_STR: &str = "author";
 pub const COMMITTER_STR: &str = "committer";
+pub const TAGGER_STR: &str = "tagger";
 const TIMEZONE_FORMAT: &[BorrowedFormatItem] =
     format_description!("[offset_hour sign:mandatory][offset_minute]");
@@ -0,0 +1,301 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{Context, Result, bail};
+
+use crate::fs;
+use crate::hashing::Hash;
+use crate::object::Object;
+use crate::repository::Repository;
+
+/// Reads the commit with the given hash and returns its parents.
+///
+/// # Errors
+///
+/// This function will fail if the object could not be read or was not a commit.
+pub fn read_parents(repo: &Repository, hash: Hash) -> Result<Vec<Hash>> {
+    match fs::object::read_object(repo, hash.clone()).context("could not read commit")? {
+        Object::Commit { parents, .. } => Ok(parents.to_vec()),
+        _ => bail!("{} is not a commit", hash),
+    }
+}
+
+/// Returns `true` if the commit `ancestor` can be reached by following the parents of
+/// `descendant`. A commit is considered an ancestor of itself.
+///
+/// # Errors
+///
+/// This function will fail if any of the commits in the history could not be read.
+pub fn is_ancestor(repo: &Repository, ancestor: &Hash, descendant: Hash) -> Result<bool> {
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut queue = VecDeque::from([descendant]);
+
+    while let Some(hash) = queue.pop_front() {
+        if &hash == ancestor {
+            return Ok(true);
+        }
+        if !visited.insert(hash.clone()) {
+            continue;
+        }
+        queue.extend(read_parents(repo, hash).context("could not read commit parents")?);
+    }
+
+    Ok(false)
+}
+
+/// Returns every commit reachable from `start` (including it) that is not in `stop`, following
+/// parents but never going past a commit in `stop`.
+fn reachable(repo: &Repository, start: Hash, stop: &HashSet<Hash>) -> Result<HashSet<Hash>> {
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(hash) = queue.pop_front() {
+        if stop.contains(&hash) || !visited.insert(hash.clone()) {
+            continue;
+        }
+        queue.extend(read_parents(repo, hash).context("could not read commit parents")?);
+    }
+
+    Ok(visited)
+}
+
+/// Returns the best common ancestors of the commits `a` and `b`: the commits reachable from both
+/// of them that are not an ancestor of another common one. There is usually a single one, but
+/// criss-cross merges can have more, and unrelated histories have none.
+///
+/// # Errors
+///
+/// This function will fail if any of the commits in the history could not be read.
+pub fn merge_bases(repo: &Repository, a: Hash, b: Hash) -> Result<Vec<Hash>> {
+    merge_bases_many(repo, a, vec![b])
+}
+
+/// Returns the best common ancestors of the commit `one` and a hypothetical commit merging all of
+/// `others`, which are the best common ancestors of `one` with any of them (see `merge_bases`).
+///
+/// # Errors
+///
+/// This function will fail if any of the commits in the history could not be read.
+pub fn merge_bases_many(repo: &Repository, one: Hash, others: Vec<Hash>) -> Result<Vec<Hash>> {
+    let from_one = reachable(repo, one, &HashSet::new())?;
+
+    // walking from the others, only keeping the first common commits found on every path
+    let mut common: Vec<Hash> = Vec::new();
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut queue = VecDeque::from(others);
+    while let Some(hash) = queue.pop_front() {
+        if !visited.insert(hash.clone()) {
+            continue;
+        }
+        if from_one.contains(&hash) {
+            common.push(hash);
+            continue;
+        }
+        queue.extend(read_parents(repo, hash).context("could not read commit parents")?);
+    }
+
+    // a common commit might still be an ancestor of another one reached through a different path
+    let mut below_common: HashSet<Hash> = HashSet::new();
+    for hash in common.iter() {
+        for parent in read_parents(repo, hash.clone()).context("could not read commit parents")? {
+            below_common.extend(reachable(repo, parent, &below_common)?);
+        }
+    }
+
+    Ok(common
+        .into_iter()
+        .filter(|hash| !below_common.contains(hash))
+        .collect())
+}
+
+/// Returns the commits reachable from `head` that are not reachable from `upstream`, ordered so
+/// every commit comes after its parents, like the commits a branch would replay on a rebase.
+///
+/// # Errors
+///
+/// This function will fail if any of the commits in the history could not be read.
+pub fn commits_between(repo: &Repository, upstream: Hash, head: Hash) -> Result<Vec<Hash>> {
+    let excluded = reachable(repo, upstream, &HashSet::new())?;
+    let included = reachable(repo, head.clone(), &excluded)?;
+
+    // depth-first, pushing every commit once all of its parents were pushed
+    let mut ordered = Vec::with_capacity(included.len());
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut stack = vec![(head, false)];
+    while let Some((hash, parents_done)) = stack.pop() {
+        if parents_done {
+            ordered.push(hash);
+            continue;
+        }
+        if !included.contains(&hash) || !visited.insert(hash.clone()) {
+            continue;
+        }
+        let parents = read_parents(repo, hash.clone()).context("could not read commit parents")?;
+        stack.push((hash, true));
+        // reversed so the first parent is walked first
+        stack.extend(parents.into_iter().rev().map(|p| (p, false)));
+    }
+
+    Ok(ordered)
+}
+
+/// Returns how many commits `local` has that `upstream` does not, and the other way around.
+///
+/// Only the history after the merge bases of both commits is walked, since everything reachable
+/// from them is shared.
+///
+/// # Errors
+///
+/// This function will fail if any of the commits in the history could not be read.
+pub fn ahead_behind(repo: &Repository, local: Hash, upstream: Hash) -> Result<(usize, usize)> {
+    let mut shared: HashSet<Hash> = HashSet::new();
+    for base in merge_bases(repo, local.clone(), upstream.clone())? {
+        shared.extend(reachable(repo, base, &shared)?);
+    }
+
+    let ahead = reachable(repo, local, &shared)?.len();
+    let behind = reachable(repo, upstream, &shared)?.len();
+    Ok((ahead, behind))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::time::UNIX_EPOCH;
+
+    use time::UtcOffset;
+
+    use super::*;
+    use crate::object::commit::{CommitUser, CommitUserKind};
+
+    fn create_repository(name: &str) -> Repository {
+        let root =
+            std::env::temp_dir().join(format!("commit-utils-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".git").join("objects")).unwrap();
+        Repository::in_dir(&root)
+    }
+
+    /// Writes a commit with an empty tree and returns its hash. Every commit needs a different
+    /// `message` to get a different hash.
+    fn write_commit(repo: &Repository, parents: &[&Hash], message: &str) -> Hash {
+        let user = |kind| CommitUser {
+            kind,
+            identifier: "A <a@example.com>".into(),
+            timestamp: UNIX_EPOCH,
+            timezone: UtcOffset::UTC,
+        };
+        let tree = fs::object::write_object(repo, &Object::Tree { entries: vec![] }).unwrap();
+        let commit = Object::Commit {
+            tree,
+            parents: parents
+                .iter()
+                .map(|h| (*h).clone())
+                .collect::<Vec<Hash>>()
+                .into(),
+            author: user(CommitUserKind::Author),
+            committer: user(CommitUserKind::Committer),
+            message: Rc::from(message),
+        };
+        fs::object::write_object(repo, &commit).unwrap()
+    }
+
+    #[test]
+    pub fn test_merge_bases_and_ahead_behind() {
+        let repo = create_repository("ahead-behind");
+        let root = write_commit(&repo, &[], "root");
+        let local = write_commit(&repo, &[&root], "local 1");
+        let local = write_commit(&repo, &[&local], "local 2");
+        let upstream = write_commit(&repo, &[&root], "upstream");
+        let merge = write_commit(&repo, &[&local, &upstream], "merge");
+
+        assert_eq!(
+            merge_bases(&repo, local.clone(), upstream.clone()).unwrap(),
+            vec![root.clone()]
+        );
+        assert_eq!(
+            ahead_behind(&repo, local.clone(), upstream.clone()).unwrap(),
+            (2, 1)
+        );
+
+        assert_eq!(
+            merge_bases(&repo, merge.clone(), upstream.clone()).unwrap(),
+            vec![upstream.clone()]
+        );
+        assert_eq!(
+            ahead_behind(&repo, merge.clone(), upstream).unwrap(),
+            (3, 0)
+        );
+        assert_eq!(ahead_behind(&repo, local, merge).unwrap(), (0, 2));
+
+        std::fs::remove_dir_all(repo.working_tree()).unwrap();
+    }
+
+    #[test]
+    pub fn test_merge_bases_criss_cross() {
+        let repo = create_repository("criss-cross");
+        let root = write_commit(&repo, &[], "root");
+        let a = write_commit(&repo, &[&root], "a");
+        let b = write_commit(&repo, &[&root], "b");
+        let a_merge = write_commit(&repo, &[&a, &b], "a merge");
+        let b_merge = write_commit(&repo, &[&b, &a], "b merge");
+
+        let mut bases = merge_bases(&repo, a_merge, b_merge).unwrap();
+        bases.sort_by_key(|h| h.to_string());
+        let mut expected = vec![a, b];
+        expected.sort_by_key(|h| h.to_string());
+        assert_eq!(bases, expected);
+
+        let unrelated = write_commit(&repo, &[], "unrelated");
+        assert!(merge_bases(&repo, root, unrelated).unwrap().is_empty());
+
+        std::fs::remove_dir_all(repo.working_tree()).unwrap();
+    }
+
+    #[test]
+    pub fn test_commits_between() {
+        let repo = create_repository("between");
+        let root = write_commit(&repo, &[], "root");
+        let upstream = write_commit(&repo, &[&root], "upstream");
+        let a = write_commit(&repo, &[&root], "a");
+        let b = write_commit(&repo, &[&a], "b");
+        let side = write_commit(&repo, &[&upstream], "side");
+        let merge = write_commit(&repo, &[&b, &side], "merge");
+
+        assert_eq!(
+            commits_between(&repo, upstream.clone(), b.clone()).unwrap(),
+            vec![a.clone(), b.clone()]
+        );
+        assert_eq!(
+            commits_between(&repo, upstream.clone(), merge.clone()).unwrap(),
+            vec![a, b, side, merge]
+        );
+        assert!(
+            commits_between(&repo, root.clone(), root)
+                .unwrap()
+                .is_empty()
+        );
+
+        std::fs::remove_dir_all(repo.working_tree()).unwrap();
+    }
+
+    #[test]
+    pub fn test_merge_bases_many() {
+        let repo = create_repository("many");
+        let root = write_commit(&repo, &[], "root");
+        let x = write_commit(&repo, &[&root], "x");
+        let a = write_commit(&repo, &[&x], "a");
+        let b = write_commit(&repo, &[&root], "b");
+        let c = write_commit(&repo, &[&x], "c");
+
+        assert_eq!(
+            merge_bases(&repo, a.clone(), b.clone()).unwrap(),
+            vec![root]
+        );
+        // the base with `c` is better than the one with `b`
+        assert_eq!(merge_bases_many(&repo, a, vec![b, c]).unwrap(), vec![x]);
+
+        std::fs::remove_dir_all(repo.working_tree()).unwrap();
+    }
+}
@@ -1,11 +1,14 @@
 #[allow(clippy::module_inception)]
 mod object;
+mod object_kind;
 
 pub mod blob;
 pub mod commit;
+pub mod tag;
 pub mod tree;
 
 pub use object::Object;
+pub use object_kind::ObjectKind;
 
 pub const SPACE_BYTE: u8 = b' ';
 pub const NULL_BYTE: u8 = b'\0';
@@ -6,15 +6,16 @@ use crate::byteable::Byteable;
 use crate::hashing::Hash;
 use crate::utils::cursor::EasyRead;
 
+use super::ObjectKind;
 use super::commit::CommitUser;
 use super::tree::TreeEntry;
 
 use super::commit;
 use super::tree;
-use super::{NULL_BYTE, SPACE_BYTE, blob};
+use super::{NULL_BYTE, SPACE_BYTE, blob, tag};
 
-/// Represents the different type of objects there can be: Blobs, Commits and Trees, with methods
-/// for byte encoding and decoding.
+/// Represents the different type of objects there can be: Blobs, Commits, Trees and annotated
+/// Tags, with methods for byte encoding and decoding.
 #[derive(Debug)]
 pub enum Object {
     Blob {
@@ -30,12 +31,20 @@ pub enum Object {
         committer: CommitUser,
         message: Rc<str>,
     },
+    Tag {
+        object: Hash,
+        kind: ObjectKind,
+        tag: Rc<str>,
+        tagger: Option<CommitUser>,
+        message: Rc<str>,
+    },
 }
 
 impl Object {
     pub const BLOB_STRING: &str = "blob";
     pub const TREE_STRING: &str = "tree";
     pub const COMMIT_STRING: &str = "commit";
+    pub const TAG_STRING: &str = "tag";
 
     /// Turns this object into bytes and calls `Hash::new` from said bytes.
     ///
@@ -51,6 +60,16 @@ impl Object {
         ))
     }
 
+    /// Returns the type of this object.
+    pub fn kind(&self) -> ObjectKind {
+        match self {
+            Object::Blob { .. } => ObjectKind::Blob,
+            Object::Tree { .. } => ObjectKind::Tree,
+            Object::Commit { .. } => ObjectKind::Commit,
+            Object::Tag { .. } => ObjectKind::Tag,
+        }
+    }
+
     /// Tries to read an object header file from a sequence of bytes, returning the type of
     /// object if it had a valid header.
     fn read_header(bytes: &[u8]) -> Result<String> {
@@ -84,6 +103,13 @@ impl std::fmt::Display for Object {
                 committer,
                 message,
             } => commit::display(tree, parents, author, committer, message),
+            Object::Tag {
+                object,
+                kind,
+                tag,
+                tagger,
+                message,
+            } => tag::display(object, *kind, tag, tagger.as_ref(), message),
         })
     }
 }
@@ -100,6 +126,13 @@ impl Byteable for Object {
                 committer: commiter,
                 message,
             } => commit::as_bytes(tree, parents, author, commiter, message),
+            Object::Tag {
+                object,
+                kind,
+                tag,
+                tagger,
+                message,
+            } => tag::as_bytes(object, *kind, tag, tagger.as_ref(), message),
         }
     }
 
@@ -120,6 +153,7 @@ impl Byteable for Object {
             Object::BLOB_STRING => blob::from_bytes(bytes),
             Object::TREE_STRING => tree::from_bytes(bytes),
             Object::COMMIT_STRING => commit::from_bytes(bytes),
+            Object::TAG_STRING => tag::from_bytes(bytes),
             _ => bail!("object did not have a valid type, got: {}", kind),
         }
     }
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+
+use super::Object;
+
+/// The type of an object, as written in the header of loose objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    /// Returns the name used for this kind of object in the header of loose objects.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Commit => Object::COMMIT_STRING,
+            ObjectKind::Tree => Object::TREE_STRING,
+            ObjectKind::Blob => Object::BLOB_STRING,
+            ObjectKind::Tag => Object::TAG_STRING,
+        }
+    }
+}
+
+impl FromStr for ObjectKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            Object::COMMIT_STRING => ObjectKind::Commit,
+            Object::TREE_STRING => ObjectKind::Tree,
+            Object::BLOB_STRING => ObjectKind::Blob,
+            Object::TAG_STRING => ObjectKind::Tag,
+            _ => bail!("invalid object type: {}", s),
+        })
+    }
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::Cursor;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::Hash;
+use crate::utils::cursor::EasyRead;
+
+use super::commit::{CommitUser, CommitUserKind, TAGGER_STR};
+use super::{NULL_BYTE, Object, ObjectKind, SPACE_BYTE};
+
+pub const OBJECT_STR: &str = "object";
+pub const TYPE_STR: &str = "type";
+pub const TAG_STR: &str = "tag";
+
+/// Returns the tag as the bytes of a string with the following format:
+///
+/// object {`object`}
+/// type {`kind`}
+/// tag {`tag`}
+/// tagger {`tagger.identifier`} {`tagger.timestamp`} {`tagger.timezone`}
+///
+/// {`message`}
+///
+/// Unlike commits, the message is stored exactly as it is, so it should usually end with a new
+/// line.
+pub fn as_bytes(
+    object: &Hash,
+    kind: ObjectKind,
+    tag: &str,
+    tagger: Option<&CommitUser>,
+    message: &str,
+) -> Result<Rc<[u8]>> {
+    let tag_str =
+        format_data(object, kind, tag, tagger, message).context("could not format tag")?;
+
+    let final_str = format!("{} {}\0{}", Object::TAG_STRING, tag_str.len(), tag_str);
+
+    Ok(final_str.as_bytes().into())
+}
+
+fn format_data(
+    object: &Hash,
+    kind: ObjectKind,
+    tag: &str,
+    tagger: Option<&CommitUser>,
+    message: &str,
+) -> Result<String> {
+    let mut s = format!(
+        "{} {}\n{} {}\n{} {}\n",
+        OBJECT_STR, object, TYPE_STR, kind, TAG_STR, tag
+    );
+    // very old tags do not have a tagger
+    if let Some(tagger) = tagger {
+        s.push_str(&tagger.to_header_line()?);
+    }
+    s.push('\n');
+    s.push_str(message);
+
+    Ok(s)
+}
+
+/// Parses a sequence of bytes expecting the format of a tag file, returning a Tag object.
+///
+/// # Errors
+///
+/// This function will fail if the bytes do not conform to the expected format, or if any of the
+/// parsing operations fail.
+pub fn from_bytes(bytes: &[u8]) -> Result<Object> {
+    let mut cursor = Cursor::new(bytes);
+
+    let kind = String::from_utf8_lossy(&cursor.read_until_checked(SPACE_BYTE)?).to_string();
+    if kind != Object::TAG_STRING {
+        bail!("file is not a tag")
+    }
+
+    let length_str = String::from_utf8_lossy(&cursor.read_until_checked(NULL_BYTE)?).to_string();
+    let length: usize = length_str.parse().context("length was invalid")?;
+
+    let position = cursor.position() as usize;
+    let remaining = &cursor.into_inner()[position..];
+    if remaining.len() != length {
+        bail!(
+            "length read \"{}\" did not match actual data length \"{}\"",
+            length,
+            remaining.len()
+        )
+    }
+
+    let tag_str =
+        std::str::from_utf8(remaining).context("could not form a string from the given bytes")?;
+    let (header, message) = tag_str.split_once("\n\n").unwrap_or((tag_str, ""));
+
+    let mut object: Option<Hash> = None;
+    let mut object_kind: Option<ObjectKind> = None;
+    let mut tag: Option<&str> = None;
+    let mut tagger: Option<CommitUser> = None;
+    for line in header.lines() {
+        let (key, value) = line
+            .split_once(' ')
+            .context(format!("invalid tag header line: {}", line))?;
+        match key {
+            OBJECT_STR => {
+                object = Some(Hash::from_str(value).context("invalid tagged object hash")?)
+            }
+            TYPE_STR => {
+                object_kind = Some(ObjectKind::from_str(value).context("invalid tagged type")?)
+            }
+            TAG_STR => tag = Some(value),
+            TAGGER_STR => {
+                tagger = Some(CommitUser::from_header_value(
+                    value,
+                    CommitUserKind::Tagger,
+                )?)
+            }
+            // unknown headers are kept by git but not needed here
+            _ => (),
+        }
+    }
+
+    Ok(Object::Tag {
+        object: object.context(format!("expected {} in tag", OBJECT_STR))?,
+        kind: object_kind.context(format!("expected {} in tag", TYPE_STR))?,
+        tag: tag.context(format!("expected {} in tag", TAG_STR))?.into(),
+        tagger,
+        message: message.into(),
+    })
+}
+
+pub fn display(
+    object: &Hash,
+    kind: ObjectKind,
+    tag: &str,
+    tagger: Option<&CommitUser>,
+    message: &str,
+) -> String {
+    format_data(object, kind, tag, tagger, message)
+        .unwrap_or(String::from("tag could not be formatted\n"))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use time::UtcOffset;
+
+    use super::*;
+    use crate::byteable::Byteable;
+
+    const TEST_OBJECT_HASH: &str = "0c9d7797a0643d9f4c6b5b0ab25daa28818e7d7f";
+    const TEST_TAG_BYTES: &[u8] = b"tag 134\0object 0c9d7797a0643d9f4c6b5b0ab25daa28818e7d7f\n\
+                                    type commit\n\
+                                    tag v1.0\n\
+                                    tagger John Doe <john@example.com> 1640995200 -0500\n\
+                                    \n\
+                                    Release 1.0\n";
+
+    fn test_tag() -> Object {
+        Object::Tag {
+            object: Hash::from_str(TEST_OBJECT_HASH).unwrap(),
+            kind: ObjectKind::Commit,
+            tag: "v1.0".into(),
+            tagger: Some(CommitUser {
+                kind: CommitUserKind::Tagger,
+                identifier: "John Doe <john@example.com>".into(),
+                timestamp: UNIX_EPOCH + Duration::from_secs(1640995200),
+                timezone: UtcOffset::from_hms(-5, 0, 0).unwrap(),
+            }),
+            message: "Release 1.0\n".into(),
+        }
+    }
+
+    #[test]
+    pub fn test_as_bytes() {
+        assert_eq!(test_tag().as_bytes().unwrap().as_ref(), TEST_TAG_BYTES);
+    }
+
+    #[test]
+    pub fn test_from_bytes_round_trip() {
+        let object = Object::from_bytes(TEST_TAG_BYTES).unwrap();
+        let Object::Tag {
+            object,
+            kind,
+            tag,
+            tagger,
+            message,
+        } = &object
+        else {
+            panic!("expected a tag, got {:?}", object)
+        };
+        assert_eq!(object.to_string(), TEST_OBJECT_HASH);
+        assert_eq!(*kind, ObjectKind::Commit);
+        assert_eq!(tag.as_ref(), "v1.0");
+        assert_eq!(tagger.as_ref().unwrap().name(), "John Doe");
+        assert_eq!(message.as_ref(), "Release 1.0\n");
+
+        let reencoded = Object::from_bytes(TEST_TAG_BYTES)
+            .unwrap()
+            .as_bytes()
+            .unwrap();
+        assert_eq!(reencoded.as_ref(), TEST_TAG_BYTES);
+    }
+
+    #[test]
+    pub fn test_from_bytes_malformed() {
+        assert!(Object::from_bytes(b"tag 12\0type commit\n").is_err());
+        assert!(Object::from_bytes(b"tag 99\0object 0c9d\n").is_err());
+    }
+}
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
@@ -7,6 +6,7 @@ use anyhow::{Context, Result};
 use crate::fs::object::write_object;
 use crate::hashing::Hash;
 use crate::object::Object;
+use crate::repository::Repository;
 use crate::utils;
 use crate::utils::nums::as_octal;
 
@@ -15,7 +15,7 @@ use super::tree::TreeExt;
 
 const DEFAULT_DIR_MODE: u32 = 0o40000;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TreeBuilder {
     entries: Vec<TreeEntry>,
     /// Every entry on the hashmap represents a subtree, where the path is relative to it's parent
@@ -69,35 +69,28 @@ impl TreeBuilder {
 
     /// Builds the tree and subsequent subtrees, assgining `path` to this tree.
     ///
-    /// If `write` is set, the hash would be obtained by writing the object to the object dir, if
-    /// it's not, then the hash will just be computed from scratch.
-    fn build_as_subtree(mut self, subdir: PathBuf, write: bool) -> Result<TreeExt> {
+    /// If a repository is given, the hash would be obtained by writing the object to its object
+    /// dir, if it's not, then the hash will just be computed from scratch.
+    fn build_as_subtree(mut self, subdir: PathBuf, repo: Option<&Repository>) -> Result<TreeExt> {
         let mut subtrees: Vec<TreeExt> = Vec::new();
         for (p, t) in self.subtrees.into_iter() {
             subtrees.push(
-                t.build_as_subtree(p, write)
+                t.build_as_subtree(p, repo)
                     .context("could not build tree")?,
             );
         }
 
         // Updating undefined hashes
         let mut hash: Hash;
-        let mut mode: u32;
         for subt in subtrees {
-            hash = if write {
-                write_object(&subt.tree).context("could not write subtree")?
-            } else {
-                subt.tree.hash().context("could not hash tree")?
-            };
-            mode = if let Ok(m) = subt.path.metadata() {
-                m.mode()
-            } else {
-                // Mode here should always be a directory's mode anyways so it can be hardcoded
-                DEFAULT_DIR_MODE
+            hash = match repo {
+                Some(repo) => write_object(repo, &subt.tree).context("could not write subtree")?,
+                None => subt.tree.hash().context("could not hash tree")?,
             };
-            // Adding entry for this subtree in the main tree
+            // Adding entry for this subtree in the main tree, git always stores directories with
+            // the same mode, whatever their permissions are
             self.entries.push(TreeEntry {
-                mode: as_octal(mode),
+                mode: as_octal(DEFAULT_DIR_MODE),
                 path: subt.path,
                 hash,
             });
@@ -122,11 +115,11 @@ impl TreeBuilder {
     /// This function can fail if a hash for an entry could not be computed.
     #[allow(unused)]
     pub fn build(self) -> Result<TreeExt> {
-        self.build_as_subtree(PathBuf::new(), false) // Since it's the root one, the path remains empty
+        self.build_as_subtree(PathBuf::new(), None) // Since it's the root one, the path remains empty
     }
 
     /// Gets all the entries from this tree builder, consuming it and building a tree form the
-    /// entries, immediately writing it to the objects directory.
+    /// entries, immediately writing it to the objects directory of `repo`.
     ///
     /// The important thing here is that this avoids computing a hash from scratch for the subtrees
     /// since it is obtained when writing the object file.
@@ -135,11 +128,11 @@ impl TreeBuilder {
     ///
     /// This function can fail if it was not possible to write the object or build it in the first
     /// place.
-    pub fn build_and_write(self) -> Result<Hash> {
+    pub fn build_and_write(self, repo: &Repository) -> Result<Hash> {
         let treext = self
-            .build_as_subtree(PathBuf::new(), true)
+            .build_as_subtree(PathBuf::new(), Some(repo))
             .context("could not write subtrees")?;
-        write_object(&treext.tree)
+        write_object(repo, &treext.tree)
     }
 }
 
@@ -148,7 +141,7 @@ mod tests {
     use super::*;
     use crate::hashing::Hash;
     use crate::object::Object;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     // Constants for test data
     const TEST_MODE_FILE: u32 = 0o100644;
@@ -179,7 +172,7 @@ mod tests {
     ) {
         let expected_mode_octal = as_octal(expected_mode);
         let found = entries.iter().find(|entry| {
-            entry.path == PathBuf::from(expected_path)
+            entry.path == Path::new(expected_path)
                 && entry.mode == expected_mode_octal
                 && entry.hash == *expected_hash
         });
@@ -387,7 +380,7 @@ mod tests {
         builder.add_object(TEST_MODE_FILE, PathBuf::from("src/main.rs"), hash2.clone());
 
         let entries = &builder.subtrees.get(&PathBuf::from("src")).unwrap().entries;
-        assert_entry_exists(&entries, "main.rs", TEST_MODE_FILE, &hash2);
+        assert_entry_exists(entries, "main.rs", TEST_MODE_FILE, &hash2);
 
         let result = builder.build().unwrap();
 
@@ -399,7 +392,7 @@ mod tests {
                 assert_eq!(entries.len(), 2); // README.md + src/ directory entry
                 assert_entry_exists(&entries, "README.md", TEST_MODE_FILE, &hash1);
                 // The src directory entry should be present (added by build_as_subtree)
-                let src_entry = entries.iter().find(|e| e.path == PathBuf::from("src"));
+                let src_entry = entries.iter().find(|e| e.path == Path::new("src"));
                 assert!(src_entry.is_some(), "src directory entry not found");
             }
             _ => panic!("Expected Tree object"),
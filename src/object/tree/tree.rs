@@ -23,12 +23,21 @@ pub struct TreeEntry {
     pub hash: Hash,
 }
 
+impl TreeEntry {
+    /// Returns `true` if this entry points to a subtree, checking its mode instead of the working
+    /// tree, since the directory does not need to exist on disk.
+    pub fn is_tree(&self) -> bool {
+        // The mode is stored in its octal representation, so directories look like `40xxx`
+        (40000..50000).contains(&self.mode)
+    }
+}
+
 impl Display for TreeEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
             "{}\t{}\t{}\t{}",
             self.mode,
-            if self.path.is_dir() {
+            if self.is_tree() {
                 Object::TREE_STRING
             } else {
                 Object::BLOB_STRING
@@ -215,7 +224,7 @@ mod tests {
         let entries = vec![];
         let result = as_bytes(&entries).unwrap();
 
-        let expected = format!("tree 0\0");
+        let expected = "tree 0\0";
         assert_eq!(expected.as_bytes(), result.as_ref());
     }
 
@@ -232,7 +241,7 @@ mod tests {
         assert!(result.starts_with(b"tree "));
 
         // Check that it contains the entry data
-        assert_contains_entry_data(&result, TEST_MODE_FILE, TEST_FILENAME_1, &TEST_HASH_1);
+        assert_contains_entry_data(&result, TEST_MODE_FILE, TEST_FILENAME_1, TEST_HASH_1);
 
         // Check structure: should have "tree {len}\0{data}"
         let null_pos = result.iter().position(|&b| b == NULL_BYTE).unwrap();
@@ -250,9 +259,9 @@ mod tests {
         assert!(result.starts_with(b"tree "));
 
         // Check that all entries are present
-        assert_contains_entry_data(&result, TEST_MODE_FILE, TEST_FILENAME_1, &TEST_HASH_1);
-        assert_contains_entry_data(&result, TEST_MODE_EXECUTABLE, TEST_FILENAME_2, &TEST_HASH_2);
-        assert_contains_entry_data(&result, TEST_MODE_DIR, TEST_FILENAME_3, &TEST_HASH_3);
+        assert_contains_entry_data(&result, TEST_MODE_FILE, TEST_FILENAME_1, TEST_HASH_1);
+        assert_contains_entry_data(&result, TEST_MODE_EXECUTABLE, TEST_FILENAME_2, TEST_HASH_2);
+        assert_contains_entry_data(&result, TEST_MODE_DIR, TEST_FILENAME_3, TEST_HASH_3);
     }
 
     #[test]
@@ -389,7 +398,7 @@ mod tests {
 
     #[test]
     fn test_from_bytes_length_mismatch() {
-        let mut input = Vec::from(b"tree 100\0100644 file.txt\0");
+        let mut input = Vec::from(b"tree 100\x00100644 file.txt\0");
         input.extend(
             // Important to format the hash string as a hex encoded string
             Hash::from_str("1111111111111111111111111111111111111111")
@@ -404,7 +413,7 @@ mod tests {
 
     #[test]
     fn test_from_bytes_missing_space_after_mode() {
-        let input = b"tree 15\0100644filename\0";
+        let input = b"tree 15\x00100644filename\0";
         let result = from_bytes(input);
 
         assert!(result.is_err());
@@ -413,7 +422,7 @@ mod tests {
 
     #[test]
     fn test_from_bytes_missing_null_after_path() {
-        let input = b"tree 15\0100644 filename ";
+        let input = b"tree 15\x00100644 filename ";
         let result = from_bytes(input);
 
         assert!(result.is_err());
@@ -422,7 +431,7 @@ mod tests {
 
     #[test]
     fn test_from_bytes_incomplete_hash() {
-        let input = b"tree 20\0100644 file\0short_hash";
+        let input = b"tree 20\x00100644 file\0short_hash";
         let result = from_bytes(input);
 
         assert!(result.is_err());
@@ -473,6 +482,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_tree() {
+        let entries = create_test_entries();
+        assert!(!entries[0].is_tree());
+        assert!(!entries[1].is_tree());
+        assert!(create_test_entry(40000, TEST_FILENAME_3, TEST_HASH_3).is_tree());
+        assert!(create_test_entry(40755, TEST_FILENAME_3, TEST_HASH_3).is_tree());
+    }
+
     #[test]
     fn test_zero_mode() {
         let entries = vec![create_test_entry(0, "zero_mode_file", TEST_HASH_1)];
@@ -5,6 +5,7 @@ use anyhow::{Context, Result, bail};
 use crate::fs;
 use crate::hashing::Hash;
 use crate::object::Object;
+use crate::repository::Repository;
 
 use super::TreeEntry;
 
@@ -13,12 +14,13 @@ use super::TreeEntry;
 ///
 /// It's important to know that all the paths in the returned tree entry will have their path
 /// relative to the root tree.
-pub fn get_all_tree_entries(entries: Vec<TreeEntry>) -> Result<Vec<TreeEntry>> {
+pub fn get_all_tree_entries(repo: &Repository, entries: Vec<TreeEntry>) -> Result<Vec<TreeEntry>> {
     let mut paths = Vec::with_capacity(entries.len());
     for e in entries {
-        if e.path.is_dir() {
+        if e.is_tree() {
             paths.extend(
-                get_subtree(&e.path, e.hash.clone()).context("could not get subtree paths")?,
+                get_subtree(repo, &e.path, e.hash.clone())
+                    .context("could not get subtree paths")?,
             );
         } else {
             paths.push(e);
@@ -29,8 +31,8 @@ pub fn get_all_tree_entries(entries: Vec<TreeEntry>) -> Result<Vec<TreeEntry>> {
 
 /// Reads the tree with the provided hash and goes trough all it's entries, calling itself
 /// recursively if the subtree has another subtree on it.
-fn get_subtree(path: &Path, hash: Hash) -> Result<Vec<TreeEntry>> {
-    let tree = fs::object::read_object(hash).context("could not read tree")?;
+fn get_subtree(repo: &Repository, path: &Path, hash: Hash) -> Result<Vec<TreeEntry>> {
+    let tree = fs::object::read_object(repo, hash).context("could not read tree")?;
     let entries = match tree {
         Object::Tree { entries } => entries,
         _ => bail!("expected a tree object"),
@@ -41,8 +43,10 @@ fn get_subtree(path: &Path, hash: Hash) -> Result<Vec<TreeEntry>> {
     let mut whole_path: PathBuf;
     for mut e in entries {
         whole_path = path.join(&e.path);
-        if whole_path.is_dir() {
-            paths.extend(get_subtree(&whole_path, e.hash).context("could not get subtree paths")?);
+        if e.is_tree() {
+            paths.extend(
+                get_subtree(repo, &whole_path, e.hash).context("could not get subtree paths")?,
+            );
         } else {
             e.path = whole_path;
             paths.push(e);
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+/// Bit that marks a copy instruction, insert instructions have it unset.
+const COPY_INSTRUCTION_BIT: u8 = 0x80;
+/// Size used by copy instructions that do not encode any size bytes.
+const DEFAULT_COPY_SIZE: usize = 0x10000;
+/// Biggest size a copy instruction can encode, since it only has 3 bytes for it.
+const MAX_COPY_SIZE: usize = 0xff_ffff;
+/// Biggest amount of bytes a single insert instruction can hold.
+const MAX_INSERT_SIZE: usize = 0x7f;
+/// Length of the blocks of the base that are indexed to look for matches.
+const BLOCK_SIZE: usize = 16;
+/// Amount of positions of the same block that are compared, so repetitive data stays fast.
+const MAX_CANDIDATES: usize = 16;
+
+/// Rebuilds an object from its `base` and a git delta.
+///
+/// A delta starts with the sizes of the base and the result, followed by instructions that either
+/// copy a range of the base or insert new bytes.
+///
+/// # Errors
+///
+/// This function will fail if the delta is truncated, if it was not built for a base of this size,
+/// or if any instruction reads outside of the base.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut position = 0;
+
+    let base_size = read_size(delta, &mut position).context("could not read base size")?;
+    if base_size != base.len() {
+        bail!(
+            "delta expected a base of {} bytes, found {} bytes",
+            base_size,
+            base.len()
+        )
+    }
+    let result_size = read_size(delta, &mut position).context("could not read result size")?;
+
+    let mut result = Vec::with_capacity(result_size);
+    while position < delta.len() {
+        let instruction = delta[position];
+        position += 1;
+
+        if instruction & COPY_INSTRUCTION_BIT != 0 {
+            // the lower 4 bits say which offset bytes are present, the next 3 which size bytes are
+            let mut offset = 0;
+            let mut size = 0;
+            for i in 0..7 {
+                if instruction & (1 << i) == 0 {
+                    continue;
+                }
+                let byte = *delta.get(position).context("truncated copy instruction")? as usize;
+                position += 1;
+                if i < 4 {
+                    offset |= byte << (8 * i);
+                } else {
+                    size |= byte << (8 * (i - 4));
+                }
+            }
+            if size == 0 {
+                size = DEFAULT_COPY_SIZE;
+            }
+
+            let copied = base
+                .get(offset..offset + size)
+                .context("copy instruction is outside of the base")?;
+            result.extend_from_slice(copied);
+        } else if instruction != 0 {
+            let size = instruction as usize;
+            let inserted = delta
+                .get(position..position + size)
+                .context("truncated insert instruction")?;
+            result.extend_from_slice(inserted);
+            position += size;
+        } else {
+            bail!("invalid delta instruction 0")
+        }
+    }
+
+    if result.len() != result_size {
+        bail!(
+            "delta produced {} bytes, expected {}",
+            result.len(),
+            result_size
+        )
+    }
+
+    Ok(result)
+}
+
+/// Builds a git delta that turns `base` into `target`.
+///
+/// The base is split into blocks of `BLOCK_SIZE` bytes, and every position of the target is
+/// looked up in them. Matches are extended as much as possible and encoded as copies, while the
+/// bytes that do not match anything are inserted.
+pub fn create_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    write_size(&mut delta, base.len());
+    write_size(&mut delta, target.len());
+
+    let mut blocks: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for offset in (0..base.len().saturating_sub(BLOCK_SIZE - 1)).step_by(BLOCK_SIZE) {
+        blocks
+            .entry(&base[offset..offset + BLOCK_SIZE])
+            .or_default()
+            .push(offset);
+    }
+
+    let mut inserted: Vec<u8> = Vec::new();
+    let mut position = 0;
+    while position < target.len() {
+        let best = target
+            .get(position..position + BLOCK_SIZE)
+            .and_then(|block| blocks.get(block))
+            .and_then(|offsets| {
+                offsets
+                    .iter()
+                    .take(MAX_CANDIDATES)
+                    .map(|&o| (o, common_prefix_len(&base[o..], &target[position..])))
+                    .max_by_key(|(_, len)| *len)
+            });
+
+        match best {
+            Some((offset, len)) => {
+                flush_insert(&mut delta, &mut inserted);
+                write_copy(&mut delta, offset, len);
+                position += len;
+            }
+            None => {
+                inserted.push(target[position]);
+                if inserted.len() == MAX_INSERT_SIZE {
+                    flush_insert(&mut delta, &mut inserted);
+                }
+                position += 1;
+            }
+        }
+    }
+    flush_insert(&mut delta, &mut inserted);
+
+    delta
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Writes an insert instruction with the pending `inserted` bytes, leaving it empty.
+fn flush_insert(delta: &mut Vec<u8>, inserted: &mut Vec<u8>) {
+    if inserted.is_empty() {
+        return;
+    }
+    delta.push(inserted.len() as u8);
+    delta.append(inserted);
+}
+
+/// Writes the copy instructions needed to copy `len` bytes of the base starting at `offset`. Only
+/// the bytes of the offset and size that are not zero are written.
+fn write_copy(delta: &mut Vec<u8>, mut offset: usize, mut len: usize) {
+    while len > 0 {
+        let size = len.min(MAX_COPY_SIZE);
+
+        let instruction_position = delta.len();
+        let mut instruction = COPY_INSTRUCTION_BIT;
+        delta.push(instruction);
+        for i in 0..4 {
+            let byte = (offset >> (8 * i)) as u8;
+            if byte != 0 {
+                instruction |= 1 << i;
+                delta.push(byte);
+            }
+        }
+        for i in 0..3 {
+            let byte = (size >> (8 * i)) as u8;
+            if byte != 0 {
+                instruction |= 1 << (4 + i);
+                delta.push(byte);
+            }
+        }
+        delta[instruction_position] = instruction;
+
+        offset += size;
+        len -= size;
+    }
+}
+
+/// Writes a size in the format read by `read_size`.
+fn write_size(delta: &mut Vec<u8>, mut size: usize) {
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            delta.push(byte);
+            return;
+        }
+        delta.push(byte | 0x80);
+    }
+}
+
+/// Reads a little-endian size where every byte holds 7 bits and the highest bit says whether
+/// another byte follows.
+fn read_size(bytes: &[u8], position: &mut usize) -> Result<usize> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*position).context("truncated size")?;
+        *position += 1;
+        size |= ((byte & 0x7f) as usize)
+            .checked_shl(shift)
+            .context("size is too large")?;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_read_size() {
+        let mut position = 0;
+        assert_eq!(read_size(&[0x05], &mut position).unwrap(), 5);
+        assert_eq!(position, 1);
+
+        position = 0;
+        // 0x91 0x2e -> 0x11 | (0x2e << 7)
+        assert_eq!(read_size(&[0x91, 0x2e], &mut position).unwrap(), 5905);
+        assert_eq!(position, 2);
+
+        position = 0;
+        assert!(read_size(&[0x80], &mut position).is_err());
+
+        // continues past the bits of a usize
+        position = 0;
+        let mut bytes = vec![0xff; 10];
+        bytes.push(0x01);
+        assert!(read_size(&bytes, &mut position).is_err());
+    }
+
+    #[test]
+    pub fn test_apply_delta_copy_and_insert() {
+        let base = b"hello world";
+        let delta = [
+            11,   // base size
+            13,   // result size
+            0x91, // copy, one offset byte and one size byte
+            6,    // offset
+            5,    // size
+            2,    // insert 2 bytes
+            b',', b' ', // inserted bytes
+            0x90, // copy, offset 0 and one size byte
+            5,    // size
+            1,    // insert 1 byte
+            b'!',
+        ];
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"world, hello!");
+    }
+
+    #[test]
+    pub fn test_create_delta_roundtrip() {
+        let base: Vec<u8> = (0..2000)
+            .flat_map(|i: u32| i.to_string().into_bytes())
+            .collect();
+        let mut target = base.clone();
+        target.splice(100..110, b"changed".iter().copied());
+        target.extend_from_slice(b"some more data at the end");
+
+        let delta = create_delta(&base, &target);
+        assert!(delta.len() < target.len() / 10);
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+    }
+
+    #[test]
+    pub fn test_create_delta_without_matches() {
+        let target: Vec<u8> = (0..300).map(|i| (i % 251) as u8).collect();
+        let delta = create_delta(b"short", &target);
+        assert_eq!(apply_delta(b"short", &delta).unwrap(), target);
+
+        let delta = create_delta(&target, b"");
+        assert!(apply_delta(&target, &delta).unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn test_apply_delta_errors() {
+        // wrong base size
+        assert!(apply_delta(b"abc", &[4, 1, 1, b'x']).is_err());
+        // copy outside of the base
+        assert!(apply_delta(b"abc", &[3, 4, 0x90, 4]).is_err());
+        // result size does not match
+        assert!(apply_delta(b"abc", &[3, 5, 0x90, 3]).is_err());
+        // zero instruction
+        assert!(apply_delta(b"abc", &[3, 0, 0]).is_err());
+    }
+}
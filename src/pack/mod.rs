@@ -0,0 +1,11 @@
+#[allow(clippy::module_inception)]
+mod pack;
+
+mod delta;
+mod pack_index;
+mod writer;
+
+pub use delta::{apply_delta, create_delta};
+pub use pack::Pack;
+pub use pack_index::PackIndex;
+pub use writer::{PackObject, build_pack};
@@ -0,0 +1,366 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, bail};
+
+use crate::hashing::{HASH_BYTE_LEN, Hash};
+use crate::object::ObjectKind;
+use crate::utils;
+
+use crate::Constants;
+use crate::byteable::Byteable;
+
+use super::{PackIndex, apply_delta};
+
+/// Magic bytes at the start of every `.pack` file.
+pub(super) const PACK_HEADER: &[u8; 4] = b"PACK";
+const PACK_HEADER_LEN: usize = 12;
+pub(super) const SUPPORTED_PACK_VERSIONS: [u32; 2] = [2, 3];
+
+pub(super) const COMMIT_TYPE: u8 = 1;
+pub(super) const TREE_TYPE: u8 = 2;
+pub(super) const BLOB_TYPE: u8 = 3;
+pub(super) const TAG_TYPE: u8 = 4;
+pub(super) const OFS_DELTA_TYPE: u8 = 6;
+const REF_DELTA_TYPE: u8 = 7;
+
+/// Amount of bytes of delta bases kept in memory before the cache is cleared.
+const BASE_CACHE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Returns the kind of object stored with the given pack type, or `None` if it is a delta.
+fn kind_from_type(object_type: u8) -> Option<ObjectKind> {
+    match object_type {
+        COMMIT_TYPE => Some(ObjectKind::Commit),
+        TREE_TYPE => Some(ObjectKind::Tree),
+        BLOB_TYPE => Some(ObjectKind::Blob),
+        TAG_TYPE => Some(ObjectKind::Tag),
+        _ => None,
+    }
+}
+
+/// A `.pack` file along with its `.idx` file.
+///
+/// Objects stored as deltas are resolved when they are read, and the bases used to resolve them
+/// are cached since a lot of deltas usually share the same bases.
+#[derive(Debug)]
+pub struct Pack {
+    index: PackIndex,
+    data: Rc<[u8]>,
+    base_cache: RefCell<BaseCache>,
+}
+
+#[derive(Debug, Default)]
+struct BaseCache {
+    entries: HashMap<u64, (ObjectKind, Rc<[u8]>)>,
+    size: usize,
+}
+
+impl Pack {
+    /// Reads the pack index at `index_path` and the `.pack` file next to it.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if any of the files could not be read, if they are not valid, or if
+    /// they do not belong to each other.
+    pub fn open(index_path: &Path) -> Result<Self> {
+        let index_bytes = std::fs::read(index_path).context("could not read pack index")?;
+        let index = PackIndex::from_bytes(&index_bytes).context("could not parse pack index")?;
+
+        let pack_path = index_path.with_extension(Constants::PACK_EXTENSION);
+        let data: Rc<[u8]> = std::fs::read(&pack_path)
+            .context(format!("could not read pack {:?}", pack_path))?
+            .into();
+
+        Pack::new(index, data)
+    }
+
+    /// Creates a pack from an already parsed index and the bytes of the `.pack` file.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the pack header is not valid or if the pack does not match the
+    /// index.
+    pub fn new(index: PackIndex, data: Rc<[u8]>) -> Result<Self> {
+        if data.len() < PACK_HEADER_LEN + HASH_BYTE_LEN || &data[..4] != PACK_HEADER {
+            bail!("pack does not have a valid header")
+        }
+        let version = u32::from_be_bytes(data[4..8].try_into()?);
+        if !SUPPORTED_PACK_VERSIONS.contains(&version) {
+            bail!("pack version {} is not supported", version)
+        }
+        let count = u32::from_be_bytes(data[8..12].try_into()?) as usize;
+        if count != index.entries().count() {
+            bail!("pack and its index have a different number of objects")
+        }
+        // the checksum of the pack is stored in both files, comparing them is enough to know the
+        // index belongs to this pack without hashing the whole pack
+        if &data[data.len() - HASH_BYTE_LEN..] != index.pack_checksum().as_ref() {
+            bail!("pack checksum does not match the one in its index")
+        }
+
+        Ok(Pack {
+            index,
+            data,
+            base_cache: RefCell::default(),
+        })
+    }
+
+    pub fn index(&self) -> &PackIndex {
+        &self.index
+    }
+
+    /// Returns the kind and the uncompressed data of the object with the given hash, or `None` if
+    /// the object is not in this pack.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if the object or any of its delta bases could not be decoded.
+    pub fn read(&self, hash: &Hash) -> Result<Option<(ObjectKind, Rc<[u8]>)>> {
+        match self.index.find_offset(hash) {
+            Some(offset) => Ok(Some(
+                self.read_at(offset)
+                    .context(format!("could not read packed object {}", hash))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the object at the given offset, resolving it if it is a delta.
+    fn read_at(&self, offset: u64) -> Result<(ObjectKind, Rc<[u8]>)> {
+        if let Some((kind, data)) = self.base_cache.borrow().entries.get(&offset) {
+            return Ok((*kind, Rc::clone(data)));
+        }
+
+        let mut position = usize::try_from(offset).context("offset is too big")?;
+        let (object_type, size) = self
+            .read_entry_header(&mut position)
+            .context(format!("could not read entry header at offset {}", offset))?;
+
+        if let Some(kind) = kind_from_type(object_type) {
+            return Ok((kind, self.inflate(position, size)?));
+        }
+
+        let base_offset = match object_type {
+            OFS_DELTA_TYPE => {
+                let distance = self.read_offset_distance(&mut position)?;
+                offset
+                    .checked_sub(distance)
+                    .context("delta base offset is outside of the pack")?
+            }
+            REF_DELTA_TYPE => {
+                let base_hash: [u8; HASH_BYTE_LEN] = self
+                    .data
+                    .get(position..position + HASH_BYTE_LEN)
+                    .context("truncated delta base hash")?
+                    .try_into()?;
+                position += HASH_BYTE_LEN;
+                let base_hash = Hash::from(base_hash);
+                self.index
+                    .find_offset(&base_hash)
+                    .context(format!("delta base {} is not in the pack", base_hash))?
+            }
+            _ => bail!("invalid object type {} at offset {}", object_type, offset),
+        };
+
+        let (kind, base) = self.read_at(base_offset).context(format!(
+            "could not read delta base at offset {}",
+            base_offset
+        ))?;
+        self.cache_base(base_offset, kind, Rc::clone(&base));
+
+        let delta = self.inflate(position, size)?;
+        let data = apply_delta(&base, &delta)
+            .context(format!("could not apply delta at offset {}", offset))?;
+
+        Ok((kind, data.into()))
+    }
+
+    /// Reads the type and the size of an entry. The first byte holds the type in bits 4-6 and the
+    /// lowest 4 bits of the size, the rest of the size follows 7 bits per byte.
+    fn read_entry_header(&self, position: &mut usize) -> Result<(u8, usize)> {
+        let mut byte = *self.data.get(*position).context("truncated entry header")?;
+        *position += 1;
+
+        let object_type = (byte >> 4) & 0b111;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = *self.data.get(*position).context("truncated entry size")?;
+            *position += 1;
+            size |= ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .context("entry size is too large")?;
+            shift += 7;
+        }
+
+        Ok((object_type, size))
+    }
+
+    /// Reads the distance to the base of an offset delta. Unlike other sizes it is big-endian, and
+    /// every continuation adds one so the same number can not be encoded in two ways.
+    fn read_offset_distance(&self, position: &mut usize) -> Result<u64> {
+        let mut byte = *self.data.get(*position).context("truncated delta offset")?;
+        *position += 1;
+
+        let mut distance = (byte & 0x7f) as u64;
+        while byte & 0x80 != 0 {
+            byte = *self.data.get(*position).context("truncated delta offset")?;
+            *position += 1;
+            distance = distance
+                .checked_add(1)
+                .and_then(|d| d.checked_mul(1 << 7))
+                .context("delta offset is too large")?
+                | (byte & 0x7f) as u64;
+        }
+
+        Ok(distance)
+    }
+
+    /// Decompresses the zlib stream starting at `position`, checking it has the expected size.
+    fn inflate(&self, position: usize, size: usize) -> Result<Rc<[u8]>> {
+        let compressed = self
+            .data
+            .get(position..)
+            .context("entry data is outside of the pack")?;
+        let data = utils::zlib::decompress(compressed).context("could not decompress entry")?;
+        if data.len() != size {
+            bail!("entry data has {} bytes, expected {}", data.len(), size)
+        }
+        Ok(data)
+    }
+
+    fn cache_base(&self, offset: u64, kind: ObjectKind, data: Rc<[u8]>) {
+        let mut cache = self.base_cache.borrow_mut();
+        if cache.entries.contains_key(&offset) {
+            return;
+        }
+        if cache.size + data.len() > BASE_CACHE_LIMIT {
+            cache.entries.clear();
+            cache.size = 0;
+        }
+        cache.size += data.len();
+        cache.entries.insert(offset, (kind, data));
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Object;
+
+    /// Encodes the header of a pack entry.
+    fn entry_header(object_type: u8, size: usize) -> Vec<u8> {
+        let mut bytes = vec![(object_type << 4) | (size & 0x0f) as u8];
+        let mut rest = size >> 4;
+        while rest > 0 {
+            *bytes.last_mut().unwrap() |= 0x80;
+            bytes.push((rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        bytes
+    }
+
+    fn blob_hash(data: &[u8]) -> Hash {
+        Object::from_bytes_new_blob(data).hash().unwrap()
+    }
+
+    /// Builds a pack with a blob, an offset delta based on it and a reference delta based on the
+    /// offset delta.
+    fn build_test_pack() -> (Pack, [Hash; 3]) {
+        let base = b"hello world";
+        // "hello world" -> "hello there"
+        let ofs_delta = [11, 11, 0x90, 6, 5, b't', b'h', b'e', b'r', b'e'];
+        // "hello there" -> "there"
+        let ref_delta = [11, 5, 0x91, 6, 5];
+
+        let hashes = [
+            blob_hash(base),
+            blob_hash(b"hello there"),
+            blob_hash(b"there"),
+        ];
+
+        let mut data = b"PACK".to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+
+        let base_offset = data.len() as u64;
+        data.extend(entry_header(BLOB_TYPE, base.len()));
+        data.extend_from_slice(&utils::zlib::compress(base).unwrap());
+
+        let ofs_offset = data.len() as u64;
+        data.extend(entry_header(OFS_DELTA_TYPE, ofs_delta.len()));
+        // the distance fits in a single byte
+        data.push((ofs_offset - base_offset) as u8);
+        data.extend_from_slice(&utils::zlib::compress(&ofs_delta).unwrap());
+
+        let ref_offset = data.len() as u64;
+        data.extend(entry_header(REF_DELTA_TYPE, ref_delta.len()));
+        data.extend_from_slice(hashes[1].as_ref());
+        data.extend_from_slice(&utils::zlib::compress(&ref_delta).unwrap());
+
+        let checksum = Hash::compute(&data);
+        data.extend_from_slice(checksum.as_ref());
+
+        let index = PackIndex::new(
+            vec![
+                (hashes[0].clone(), 0, base_offset),
+                (hashes[1].clone(), 0, ofs_offset),
+                (hashes[2].clone(), 0, ref_offset),
+            ],
+            checksum,
+        );
+
+        (Pack::new(index, data.into()).unwrap(), hashes)
+    }
+
+    #[test]
+    pub fn test_read_packed_objects() {
+        let (pack, hashes) = build_test_pack();
+
+        let expected: [&[u8]; 3] = [b"hello world", b"hello there", b"there"];
+        for (hash, data) in hashes.iter().zip(expected) {
+            let (kind, read) = pack.read(hash).unwrap().unwrap();
+            assert_eq!(kind, ObjectKind::Blob);
+            assert_eq!(read.as_ref(), data);
+        }
+
+        assert!(pack.read(&blob_hash(b"missing")).unwrap().is_none());
+    }
+
+    #[test]
+    pub fn test_delta_bases_are_cached() {
+        let (pack, hashes) = build_test_pack();
+
+        pack.read(&hashes[2]).unwrap();
+        // both the blob and the offset delta were used as bases
+        assert_eq!(pack.base_cache.borrow().entries.len(), 2);
+    }
+
+    #[test]
+    pub fn test_malformed_entry_header() {
+        // only the header fields are read, so the data does not need to be a valid pack
+        let pack = |data: &[u8]| Pack {
+            index: PackIndex::new(vec![], Hash::default()),
+            data: data.into(),
+            base_cache: RefCell::default(),
+        };
+
+        // the size continues past the bits of a usize
+        let mut position = 0;
+        let mut data = vec![0xff; 12];
+        data.push(0x01);
+        assert!(pack(&data).read_entry_header(&mut position).is_err());
+
+        position = 0;
+        assert!(pack(&[0x90]).read_entry_header(&mut position).is_err());
+
+        position = 0;
+        let mut data = vec![0xff; 10];
+        data.push(0x7f);
+        assert!(pack(&data).read_offset_distance(&mut position).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::io::{Cursor, Read, Write};
+use std::rc::Rc;
+
+use anyhow::{Context, Result, bail};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::byteable::Byteable;
+use crate::hashing::{HASH_BYTE_LEN, Hash};
+
+/// Magic number at the start of every version 2 pack index, `\377tOc`.
+const PACK_INDEX_HEADER: u32 = 0xff744f63;
+const PACK_INDEX_VERSION: u32 = 2;
+const FANOUT_LEN: usize = 256;
+/// Offsets with this bit set are positions in the table of 64 bit offsets.
+const LARGE_OFFSET_BIT: u32 = 0x8000_0000;
+
+/// A parsed `.idx` file, which maps the hash of every object in a pack to its position in the
+/// `.pack` file.
+#[derive(Debug)]
+pub struct PackIndex {
+    /// Number of objects whose first hash byte is less or equal than the position.
+    fanout: [u32; FANOUT_LEN],
+    /// Sorted hashes of every object in the pack.
+    hashes: Vec<[u8; HASH_BYTE_LEN]>,
+    /// CRC32 of the compressed data of every object, in the same order as `hashes`.
+    crcs: Vec<u32>,
+    /// Offsets of every object, in the same order as `hashes`.
+    offsets: Vec<u64>,
+    /// Checksum of the `.pack` file this index belongs to.
+    pack_checksum: Hash,
+}
+
+impl PackIndex {
+    /// Builds the index of a pack from the hash, CRC32 and offset of every object in it.
+    pub fn new(mut entries: Vec<(Hash, u32, u64)>, pack_checksum: Hash) -> Self {
+        entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+
+        let mut fanout = [0; FANOUT_LEN];
+        for (hash, _, _) in entries.iter() {
+            fanout[hash.as_ref()[0] as usize] += 1;
+        }
+        for i in 1..FANOUT_LEN {
+            fanout[i] += fanout[i - 1];
+        }
+
+        let mut hashes = Vec::with_capacity(entries.len());
+        let mut crcs = Vec::with_capacity(entries.len());
+        let mut offsets = Vec::with_capacity(entries.len());
+        for (hash, crc, offset) in entries {
+            hashes.push(hash.into());
+            crcs.push(crc);
+            offsets.push(offset);
+        }
+
+        PackIndex {
+            fanout,
+            hashes,
+            crcs,
+            offsets,
+            pack_checksum,
+        }
+    }
+
+    /// Returns the offset in the pack of the object with the given hash, if it is in the pack.
+    pub fn find_offset(&self, hash: &Hash) -> Option<u64> {
+        let hash: &[u8] = hash.as_ref();
+        let first = hash[0] as usize;
+        let start = if first == 0 {
+            0
+        } else {
+            self.fanout[first - 1] as usize
+        };
+        let end = self.fanout[first] as usize;
+
+        self.hashes[start..end]
+            .binary_search_by(|h| h.as_slice().cmp(hash))
+            .ok()
+            .map(|i| self.offsets[start + i])
+    }
+
+    /// Returns an iterator over the hash and offset of every object, sorted by hash.
+    pub fn entries(&self) -> impl Iterator<Item = (Hash, u64)> + '_ {
+        self.hashes
+            .iter()
+            .zip(self.offsets.iter())
+            .map(|(h, o)| (Hash::from(*h), *o))
+    }
+
+    pub fn pack_checksum(&self) -> &Hash {
+        &self.pack_checksum
+    }
+}
+
+impl Byteable for PackIndex {
+    /// Encodes this index as a version 2 `.idx` file.
+    fn as_bytes(&self) -> Result<Rc<[u8]>> {
+        let mut cursor = Cursor::new(Vec::new());
+
+        cursor
+            .write_u32::<BigEndian>(PACK_INDEX_HEADER)
+            .context("could not write pack index header")?;
+        cursor
+            .write_u32::<BigEndian>(PACK_INDEX_VERSION)
+            .context("could not write pack index version")?;
+        for f in self.fanout.iter() {
+            cursor
+                .write_u32::<BigEndian>(*f)
+                .context("could not write fanout table")?;
+        }
+        for h in self.hashes.iter() {
+            cursor.write_all(h).context("could not write object hash")?;
+        }
+        for crc in self.crcs.iter() {
+            cursor
+                .write_u32::<BigEndian>(*crc)
+                .context("could not write object crc")?;
+        }
+
+        let mut large_offsets = Vec::new();
+        for offset in self.offsets.iter() {
+            let small = match u32::try_from(*offset) {
+                Ok(o) if o & LARGE_OFFSET_BIT == 0 => o,
+                _ => {
+                    large_offsets.push(*offset);
+                    LARGE_OFFSET_BIT | (large_offsets.len() - 1) as u32
+                }
+            };
+            cursor
+                .write_u32::<BigEndian>(small)
+                .context("could not write object offset")?;
+        }
+        for offset in large_offsets {
+            cursor
+                .write_u64::<BigEndian>(offset)
+                .context("could not write large object offset")?;
+        }
+
+        cursor
+            .write_all(self.pack_checksum.as_ref())
+            .context("could not write pack checksum")?;
+        let checksum = Hash::compute(cursor.get_ref());
+        cursor
+            .write_all(checksum.as_ref())
+            .context("could not write pack index checksum")?;
+
+        Ok(cursor.into_inner().into())
+    }
+
+    /// Parses the bytes of a version 2 `.idx` file.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if:
+    /// - The bytes do not start with the version 2 header.
+    /// - The bytes are truncated or the fanout table is not sorted.
+    /// - The checksum at the end does not match the rest of the data.
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 * HASH_BYTE_LEN {
+            bail!("pack index is too short")
+        }
+        let (content, checksum) = bytes.split_at(bytes.len() - HASH_BYTE_LEN);
+        if Hash::compute(content).as_ref() != checksum {
+            bail!("pack index checksum does not correspond with its data")
+        }
+
+        let mut cursor = Cursor::new(content);
+
+        let header = cursor
+            .read_u32::<BigEndian>()
+            .context("could not read pack index header")?;
+        if header != PACK_INDEX_HEADER {
+            bail!("pack index does not have a valid header, only version 2 is supported")
+        }
+        let version = cursor
+            .read_u32::<BigEndian>()
+            .context("could not read pack index version")?;
+        if version != PACK_INDEX_VERSION {
+            bail!("pack index version {} is not supported", version)
+        }
+
+        let mut fanout = [0; FANOUT_LEN];
+        for f in fanout.iter_mut() {
+            *f = cursor
+                .read_u32::<BigEndian>()
+                .context("could not read fanout table")?;
+        }
+        if fanout.windows(2).any(|w| w[0] > w[1]) {
+            bail!("pack index fanout table is not sorted")
+        }
+
+        let count = fanout[FANOUT_LEN - 1] as usize;
+
+        let mut hashes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut hash = [0; HASH_BYTE_LEN];
+            cursor
+                .read_exact(&mut hash)
+                .context("could not read object hash")?;
+            hashes.push(hash);
+        }
+
+        let mut crcs = Vec::with_capacity(count);
+        for _ in 0..count {
+            crcs.push(
+                cursor
+                    .read_u32::<BigEndian>()
+                    .context("could not read object crc")?,
+            );
+        }
+
+        let mut small_offsets = Vec::with_capacity(count);
+        for _ in 0..count {
+            small_offsets.push(
+                cursor
+                    .read_u32::<BigEndian>()
+                    .context("could not read object offset")?,
+            );
+        }
+
+        // the 64 bit offsets come right after the 32 bit ones, in the order they are referenced
+        let large_offsets_start = cursor.position() as usize;
+        let mut offsets = Vec::with_capacity(count);
+        for offset in small_offsets {
+            if offset & LARGE_OFFSET_BIT == 0 {
+                offsets.push(offset as u64);
+                continue;
+            }
+            let position = large_offsets_start + (offset & !LARGE_OFFSET_BIT) as usize * 8;
+            let large = content
+                .get(position..position + 8)
+                .context("large offset is outside of the pack index")?;
+            offsets.push(u64::from_be_bytes(large.try_into()?));
+        }
+
+        let trailer_start = content.len() - HASH_BYTE_LEN;
+        let mut pack_checksum = [0; HASH_BYTE_LEN];
+        pack_checksum.copy_from_slice(
+            content
+                .get(trailer_start..)
+                .context("could not read pack checksum")?,
+        );
+
+        Ok(PackIndex {
+            fanout,
+            hashes,
+            crcs,
+            offsets,
+            pack_checksum: Hash::from(pack_checksum),
+        })
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Object;
+
+    #[test]
+    pub fn test_index_checksum_mismatch() {
+        let hash = Object::from_bytes_new_blob(b"a").hash().unwrap();
+        let index = PackIndex::new(vec![(hash, 0, 12)], Hash::default());
+        let mut index = index.as_bytes().unwrap().to_vec();
+        let last = index.len() - 1;
+        index[last] ^= 1;
+        assert!(PackIndex::from_bytes(&index).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use flate2::Crc;
+
+use crate::hashing::Hash;
+use crate::object::ObjectKind;
+use crate::utils;
+
+use super::pack::{
+    BLOB_TYPE, COMMIT_TYPE, OFS_DELTA_TYPE, PACK_HEADER, SUPPORTED_PACK_VERSIONS, TAG_TYPE,
+    TREE_TYPE,
+};
+use super::{PackIndex, create_delta};
+
+/// Amount of previous objects every object is compared with to look for a delta base.
+const WINDOW_SIZE: usize = 10;
+/// Longest chain of deltas allowed, so reading an object does not need to resolve too many bases.
+const MAX_DEPTH: usize = 50;
+/// Objects smaller than this are always stored whole, a delta would not save anything.
+const MIN_DELTA_SIZE: usize = 64;
+
+/// An object that will be written to a pack.
+pub struct PackObject {
+    pub hash: Hash,
+    pub kind: ObjectKind,
+    pub data: Rc<[u8]>,
+    /// Path of the object in the tree it was found in, objects with the same name are usually
+    /// good delta bases for each other.
+    pub path: Option<PathBuf>,
+}
+
+/// Builds a version 2 pack with the given objects, returning its bytes along with its index.
+///
+/// Objects are sorted by kind, name and size, and every object is compared with the
+/// `WINDOW_SIZE` objects before it. The smallest delta found is stored as an offset delta if it
+/// saves at least half of the size of the object.
+///
+/// # Errors
+///
+/// This function will fail if any of the objects could not be compressed.
+pub fn build_pack(mut objects: Vec<PackObject>) -> Result<(Rc<[u8]>, PackIndex)> {
+    objects.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then_with(|| {
+                let name = |o: &PackObject| {
+                    o.path
+                        .as_ref()
+                        .and_then(|p| p.file_name().map(|n| n.to_owned()))
+                };
+                name(a).cmp(&name(b))
+            })
+            .then_with(|| b.data.len().cmp(&a.data.len()))
+    });
+
+    // for every object, the position of its base and its delta
+    let mut deltas: Vec<Option<(usize, Vec<u8>)>> = Vec::with_capacity(objects.len());
+    let mut depths: Vec<usize> = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        let mut best: Option<(usize, Vec<u8>)> = None;
+        if object.data.len() >= MIN_DELTA_SIZE {
+            for j in i.saturating_sub(WINDOW_SIZE)..i {
+                let base = &objects[j];
+                if base.kind != object.kind || depths[j] >= MAX_DEPTH {
+                    continue;
+                }
+                let delta = create_delta(&base.data, &object.data);
+                let best_len = best
+                    .as_ref()
+                    .map_or(object.data.len() / 2, |(_, d)| d.len());
+                if delta.len() < best_len {
+                    best = Some((j, delta));
+                }
+            }
+        }
+
+        depths.push(match &best {
+            Some((j, _)) => depths[*j] + 1,
+            None => 0,
+        });
+        deltas.push(best);
+    }
+
+    let mut data = PACK_HEADER.to_vec();
+    data.extend_from_slice(&SUPPORTED_PACK_VERSIONS[0].to_be_bytes());
+    data.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+
+    let mut offsets: Vec<u64> = Vec::with_capacity(objects.len());
+    let mut index_entries = Vec::with_capacity(objects.len());
+    for (object, delta) in objects.into_iter().zip(deltas) {
+        let offset = data.len() as u64;
+
+        let mut entry = Vec::new();
+        match delta {
+            Some((base, delta)) => {
+                write_entry_header(&mut entry, OFS_DELTA_TYPE, delta.len());
+                write_offset_distance(&mut entry, offset - offsets[base]);
+                entry.extend_from_slice(
+                    &utils::zlib::compress(&delta).context("could not compress delta")?,
+                );
+            }
+            None => {
+                write_entry_header(&mut entry, kind_type(object.kind), object.data.len());
+                entry.extend_from_slice(
+                    &utils::zlib::compress(&object.data)
+                        .context(format!("could not compress object {}", object.hash))?,
+                );
+            }
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&entry);
+        data.extend_from_slice(&entry);
+
+        offsets.push(offset);
+        index_entries.push((object.hash, crc.sum(), offset));
+    }
+
+    let checksum = Hash::compute(&data);
+    data.extend_from_slice(checksum.as_ref());
+
+    Ok((data.into(), PackIndex::new(index_entries, checksum)))
+}
+
+fn kind_type(kind: ObjectKind) -> u8 {
+    match kind {
+        ObjectKind::Commit => COMMIT_TYPE,
+        ObjectKind::Tree => TREE_TYPE,
+        ObjectKind::Blob => BLOB_TYPE,
+        ObjectKind::Tag => TAG_TYPE,
+    }
+}
+
+/// Writes the type and size of an entry in the format read by `Pack::read_entry_header`.
+fn write_entry_header(entry: &mut Vec<u8>, object_type: u8, size: usize) {
+    let mut byte = (object_type << 4) | (size & 0x0f) as u8;
+    let mut rest = size >> 4;
+    while rest > 0 {
+        entry.push(byte | 0x80);
+        byte = (rest & 0x7f) as u8;
+        rest >>= 7;
+    }
+    entry.push(byte);
+}
+
+/// Writes the distance to the base of an offset delta in the format read by
+/// `Pack::read_offset_distance`.
+fn write_offset_distance(entry: &mut Vec<u8>, mut distance: u64) {
+    let mut bytes = vec![(distance & 0x7f) as u8];
+    distance >>= 7;
+    while distance > 0 {
+        distance -= 1;
+        bytes.push(0x80 | (distance & 0x7f) as u8);
+        distance >>= 7;
+    }
+    bytes.reverse();
+    entry.extend_from_slice(&bytes);
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byteable::Byteable;
+    use crate::object::Object;
+    use crate::pack::Pack;
+
+    fn blob(data: Vec<u8>, path: &str) -> PackObject {
+        let data: Rc<[u8]> = data.into();
+        PackObject {
+            hash: Object::from_bytes_new_blob(&data).hash().unwrap(),
+            kind: ObjectKind::Blob,
+            data,
+            path: Some(PathBuf::from(path)),
+        }
+    }
+
+    #[test]
+    pub fn test_build_pack_roundtrip() {
+        let base: Vec<u8> = (0..500)
+            .flat_map(|i: u32| format!("line {}\n", i).into_bytes())
+            .collect();
+        let mut versions = Vec::new();
+        for i in 0..5 {
+            let mut data = base.clone();
+            data.extend_from_slice(format!("version {}\n", i).as_bytes());
+            versions.push(blob(data, "file.txt"));
+        }
+        versions.push(blob(b"small".to_vec(), "other.txt"));
+
+        let expected: Vec<(Hash, Rc<[u8]>)> = versions
+            .iter()
+            .map(|o| (o.hash.clone(), Rc::clone(&o.data)))
+            .collect();
+        let total_size: usize = expected.iter().map(|(_, d)| d.len()).sum();
+
+        let (data, index) = build_pack(versions).unwrap();
+        // every version but one should be stored as a small delta
+        assert!(data.len() < total_size / 4);
+
+        // the index has to survive being written and read again
+        let index = PackIndex::from_bytes(&index.as_bytes().unwrap()).unwrap();
+        let pack = Pack::new(index, data).unwrap();
+        for (hash, data) in expected {
+            let (kind, read) = pack.read(&hash).unwrap().unwrap();
+            assert_eq!(kind, ObjectKind::Blob);
+            assert_eq!(read, data);
+        }
+    }
+
+    #[test]
+    pub fn test_offset_distance_encoding() {
+        for distance in [1, 127, 128, 255, 16511, 16512, 1 << 30] {
+            let mut bytes = Vec::new();
+            write_offset_distance(&mut bytes, distance);
+
+            // decoding it the same way packs are read
+            let mut decoded = (bytes[0] & 0x7f) as u64;
+            for b in bytes[1..].iter() {
+                decoded = ((decoded + 1) << 7) | (b & 0x7f) as u64;
+            }
+            assert_eq!(decoded, distance);
+            assert!(bytes[..bytes.len() - 1].iter().all(|b| b & 0x80 != 0));
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+
+use crate::Constants;
+
+use super::Repository;
+
+/// Prefix of the `.git` files used by linked working trees and submodules to point to their
+/// repository folder.
+const GIT_DIR_FILE_PREFIX: &str = "gitdir: ";
+
+/// Finds the repository `cwd` belongs to, reading environment variables through `var`:
+/// - `GIT_DIR` sets the repository folder, in which case the working tree is `cwd` unless
+///   `GIT_WORK_TREE` is set. Nothing is checked, so the repository might not exist.
+/// - Otherwise, `cwd` and its parents are searched for a `.git` folder (or a `.git` file pointing
+///   to one), without entering the directories in `GIT_CEILING_DIRECTORIES` (a colon separated
+///   list) or the ones above them.
+/// - `GIT_WORK_TREE` replaces the working tree of the repository found.
+///
+/// Relative paths in the environment are relative to `cwd`.
+///
+/// # Returns
+///
+/// The repository, or `None` if none was found.
+pub fn discover(cwd: &Path, var: impl Fn(&str) -> Option<OsString>) -> Option<Repository> {
+    let env_path = |name: &str| {
+        var(name)
+            .filter(|v| !v.is_empty())
+            .map(|v| absolute_path(&cwd.join(v)))
+    };
+    let work_tree = env_path("GIT_WORK_TREE");
+
+    if let Some(repository) = env_path("GIT_DIR") {
+        return Some(Repository::new(
+            work_tree.unwrap_or(cwd.to_path_buf()),
+            repository,
+        ));
+    }
+
+    let ceilings: Vec<PathBuf> = var("GIT_CEILING_DIRECTORIES")
+        .map(|v| {
+            env::split_paths(&v)
+                .filter(|p| p.is_absolute())
+                .map(|p| absolute_path(&p))
+                // a ceiling at the current directory does not stop it from being searched
+                .filter(|p| cwd.starts_with(p) && cwd != p)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut dir = cwd;
+    loop {
+        if let Some(repository) = find_repository_folder(dir) {
+            return Some(Repository::new(
+                work_tree.unwrap_or(dir.to_path_buf()),
+                repository,
+            ));
+        }
+
+        let parent = dir.parent()?;
+        if ceilings.iter().any(|c| c.starts_with(parent)) {
+            return None;
+        }
+        dir = parent;
+    }
+}
+
+/// Returns the repository folder of a working tree rooted at `dir`, if it has one.
+fn find_repository_folder(dir: &Path) -> Option<PathBuf> {
+    let path = dir.join(Constants::REPOSITORY_FOLDER_NAME);
+    if path.is_file() {
+        let content = std::fs::read_to_string(&path).ok()?;
+        let target = content.trim_end().strip_prefix(GIT_DIR_FILE_PREFIX)?;
+        return Some(absolute_path(&dir.join(target)));
+    }
+
+    // a folder without HEAD is not a repository, git keeps searching above it
+    path.join(Constants::HEAD_FILE_NAME)
+        .is_file()
+        .then_some(path)
+}
+
+/// Returns the canonical version of `path`, or just a cleaned one if it does not exist.
+fn absolute_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.clean())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Creates `root/.git/HEAD` and `root/a/b`, returning the canonical root.
+    fn create_repository(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("discovery-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("a").join("b")).unwrap();
+        std::fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    /// Returns the working tree and repository folder of the repository found.
+    fn discover_with(cwd: &Path, vars: &[(&str, &Path)]) -> Option<(PathBuf, PathBuf)> {
+        let vars: HashMap<&str, OsString> = vars
+            .iter()
+            .map(|(k, v)| (*k, v.as_os_str().to_os_string()))
+            .collect();
+        discover(cwd, |name| vars.get(name).cloned()).map(|repo| {
+            (
+                repo.working_tree().to_path_buf(),
+                repo.git_dir().to_path_buf(),
+            )
+        })
+    }
+
+    fn in_dir(root: &Path) -> (PathBuf, PathBuf) {
+        (root.to_path_buf(), root.join(".git"))
+    }
+
+    #[test]
+    pub fn test_discover_from_subdirectory() {
+        let root = create_repository("subdir");
+
+        assert_eq!(
+            discover_with(&root.join("a").join("b"), &[]),
+            Some(in_dir(&root))
+        );
+        assert_eq!(discover_with(&root, &[]), Some(in_dir(&root)));
+
+        // a .git file points to the real repository folder
+        std::fs::write(root.join("a").join(".git"), "gitdir: ../.git\n").unwrap();
+        assert_eq!(
+            discover_with(&root.join("a").join("b"), &[]),
+            Some((root.join("a"), root.join(".git")))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn test_discover_ceiling_directories() {
+        let root = create_repository("ceiling");
+        let cwd = root.join("a").join("b");
+
+        assert_eq!(
+            discover_with(&cwd, &[("GIT_CEILING_DIRECTORIES", &root)]),
+            None
+        );
+        assert_eq!(
+            discover_with(&cwd, &[("GIT_CEILING_DIRECTORIES", &root.join("a"))]),
+            None
+        );
+        // the current directory is always searched
+        assert_eq!(
+            discover_with(&root, &[("GIT_CEILING_DIRECTORIES", &root)]),
+            Some(in_dir(&root))
+        );
+        // ceilings that are not above the current directory do not matter
+        assert_eq!(
+            discover_with(&cwd, &[("GIT_CEILING_DIRECTORIES", Path::new("/nowhere"))]),
+            Some(in_dir(&root))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn test_discover_environment_overrides() {
+        let root = create_repository("environment");
+        let cwd = root.join("a");
+
+        assert_eq!(
+            discover_with(&cwd, &[("GIT_DIR", Path::new("../.git"))]),
+            Some((cwd.clone(), root.join(".git")))
+        );
+        assert_eq!(
+            discover_with(
+                &cwd,
+                &[
+                    ("GIT_DIR", &root.join(".git")),
+                    ("GIT_WORK_TREE", Path::new(".."))
+                ]
+            ),
+            Some(in_dir(&root))
+        );
+        assert_eq!(
+            discover_with(&cwd, &[("GIT_WORK_TREE", Path::new("b"))]),
+            Some((cwd.join("b"), root.join(".git")))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+mod discovery;
+mod operation;
+#[allow(clippy::module_inception)]
+mod repository;
+
+pub use discovery::discover;
+pub use operation::{Operation, read_operation};
+pub use repository::Repository;
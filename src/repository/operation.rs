@@ -0,0 +1,90 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use crate::Constants;
+use crate::hashing::Hash;
+
+use super::Repository;
+
+/// An operation that stopped before finishing, usually because of conflicts, and is waiting to be
+/// continued or aborted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    Rebase {
+        /// The branch being rebased, `None` if HEAD was detached when it started
+        branch: Option<String>,
+        /// The commit the changes are being applied onto
+        onto: Option<Hash>,
+        interactive: bool,
+    },
+    /// Cherry-picking the commit
+    CherryPick(Hash),
+    /// Reverting the commit
+    Revert(Hash),
+}
+
+/// Returns the operation in progress in the repository, found from the files git leaves in the
+/// repository folder while it waits for it to be continued, or `None` if there is none.
+///
+/// # Errors
+///
+/// This function will fail if any of the files of the operation could not be read.
+pub fn read_operation(repo: &Repository) -> Result<Option<Operation>> {
+    if repo.merge_head_path().is_file() {
+        return Ok(Some(Operation::Merge));
+    }
+
+    for folder in [repo.rebase_merge_path(), repo.rebase_apply_path()] {
+        if folder.is_dir() {
+            return read_rebase(&folder).map(Some);
+        }
+    }
+
+    if let Some(hash) = read_hash_file(&repo.cherry_pick_head_path())? {
+        return Ok(Some(Operation::CherryPick(hash)));
+    }
+    if let Some(hash) = read_hash_file(&repo.revert_head_path())? {
+        return Ok(Some(Operation::Revert(hash)));
+    }
+
+    Ok(None)
+}
+
+fn read_rebase(folder: &Path) -> Result<Operation> {
+    let heads_prefix = format!(
+        "{}/{}/",
+        Constants::REFS_FOLDER_NAME,
+        Constants::HEADS_FOLDER_NAME
+    );
+    // detached rebases have `detached HEAD` as their head name
+    let branch = std::fs::read_to_string(folder.join(Constants::REBASE_HEAD_NAME_FILE_NAME))
+        .ok()
+        .and_then(|name| {
+            name.trim_end()
+                .strip_prefix(&heads_prefix)
+                .map(String::from)
+        });
+
+    Ok(Operation::Rebase {
+        branch,
+        onto: read_hash_file(&folder.join(Constants::REBASE_ONTO_FILE_NAME))?,
+        interactive: folder
+            .join(Constants::REBASE_INTERACTIVE_FILE_NAME)
+            .exists(),
+    })
+}
+
+/// Reads the hash at the start of the file at `path`, or returns `None` if it does not exist.
+fn read_hash_file(path: &Path) -> Result<Option<Hash>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path).context(format!("could not read {:?}", path))?;
+    let hash = content.split_whitespace().next().unwrap_or_default();
+    Hash::from_str(hash)
+        .map(Some)
+        .context(format!("{:?} does not contain a valid hash", path))
+}
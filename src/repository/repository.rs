@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::Constants;
+use crate::pack::Pack;
+use crate::utils::path::clean_relative_path;
+
+use super::discover;
+
+/// A handle to a repository, which every operation reading or writing its files goes through.
+///
+/// It knows where the working tree and the repository folder are, and keeps the packs of the
+/// object database that have already been opened, so many repositories can be used at once.
+#[derive(Debug)]
+pub struct Repository {
+    working_tree: PathBuf,
+    git_dir: PathBuf,
+    /// Packs that have already been opened, by the path of their index. Packs never change once
+    /// written, so they only need to be read once.
+    packs: RefCell<HashMap<PathBuf, Rc<Pack>>>,
+}
+
+impl Repository {
+    /// Returns a handle to the repository with its working tree at `working_tree` and its
+    /// repository folder at `git_dir`. Nothing is read, so the repository might not exist yet.
+    pub fn new(working_tree: impl Into<PathBuf>, git_dir: impl Into<PathBuf>) -> Self {
+        Repository {
+            working_tree: working_tree.into(),
+            git_dir: git_dir.into(),
+            packs: RefCell::default(),
+        }
+    }
+
+    /// Returns a handle to the repository whose working tree is `dir`, with the repository folder
+    /// right inside of it.
+    pub fn in_dir(dir: &Path) -> Self {
+        Repository::new(dir, dir.join(Constants::REPOSITORY_FOLDER_NAME))
+    }
+
+    /// Finds the repository `dir` belongs to, looking at its parent directories and the git
+    /// environment variables (see `discover`).
+    pub fn discover(dir: &Path) -> Option<Self> {
+        discover(dir, |name| env::var_os(name))
+    }
+
+    /// Checks if the repository folder exists.
+    pub fn exists(&self) -> bool {
+        self.git_dir.is_dir()
+    }
+
+    /// The root folder of the working tree
+    pub fn working_tree(&self) -> &Path {
+        &self.working_tree
+    }
+
+    /// The location of the .git folder
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// Returns `path`, relative to the current directory or absolute, as a path relative to the
+    /// working tree root, or `None` if it is outside of the working tree. An empty path is the
+    /// root itself.
+    pub fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        let absolute = std::path::absolute(path).ok()?;
+        clean_relative_path(&absolute, &self.working_tree)
+    }
+
+    /// Packs of the object database that have already been opened.
+    pub(crate) fn open_packs(&self) -> &RefCell<HashMap<PathBuf, Rc<Pack>>> {
+        &self.packs
+    }
+
+    pub fn objects_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::OBJECTS_FOLDER_NAME)
+    }
+
+    pub fn packs_path(&self) -> PathBuf {
+        self.objects_path().join(Constants::PACK_FOLDER_NAME)
+    }
+
+    pub fn refs_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::REFS_FOLDER_NAME)
+    }
+
+    pub fn heads_path(&self) -> PathBuf {
+        self.refs_path().join(Constants::HEADS_FOLDER_NAME)
+    }
+
+    pub fn tags_path(&self) -> PathBuf {
+        self.refs_path().join(Constants::TAGS_FOLDER_NAME)
+    }
+
+    pub fn head_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::HEAD_FILE_NAME)
+    }
+
+    pub fn packed_refs_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::PACKED_REFS_FILE_NAME)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::CONFIG_FILE_NAME)
+    }
+
+    pub fn exclude_path(&self) -> PathBuf {
+        self.git_dir
+            .join(Constants::INFO_FOLDER_NAME)
+            .join(Constants::EXCLUDE_FILE_NAME)
+    }
+
+    pub fn head_log_path(&self) -> PathBuf {
+        self.git_dir
+            .join(Constants::LOGS_FOLDER_NAME)
+            .join(Constants::HEAD_FILE_NAME)
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::INDEX_NAME)
+    }
+
+    pub fn merge_head_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::MERGE_HEAD_FILE_NAME)
+    }
+
+    pub fn merge_msg_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::MERGE_MSG_FILE_NAME)
+    }
+
+    pub fn cherry_pick_head_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::CHERRY_PICK_HEAD_FILE_NAME)
+    }
+
+    pub fn revert_head_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::REVERT_HEAD_FILE_NAME)
+    }
+
+    pub fn rebase_merge_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::REBASE_MERGE_FOLDER_NAME)
+    }
+
+    pub fn rebase_apply_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::REBASE_APPLY_FOLDER_NAME)
+    }
+
+    pub fn sequencer_path(&self) -> PathBuf {
+        self.git_dir.join(Constants::SEQUENCER_FOLDER_NAME)
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::object::Object;
+
+    /// Creates an empty repository in a temporary directory, returning its canonical root.
+    fn create_repository(name: &str) -> Repository {
+        let root = env::temp_dir().join(format!("repository-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".git").join("objects")).unwrap();
+        Repository::in_dir(&root.canonicalize().unwrap())
+    }
+
+    #[test]
+    pub fn test_repositories_are_independent() {
+        let first = create_repository("first");
+        let second = create_repository("second");
+
+        let blob = Object::from_bytes_new_blob(b"only in the first one\n");
+        let hash = crate::fs::object::write_object(&first, &blob).unwrap();
+
+        assert!(crate::fs::object::read_object(&first, hash.clone()).is_ok());
+        assert!(crate::fs::object::read_object(&second, hash).is_err());
+
+        fs::remove_dir_all(first.working_tree()).unwrap();
+        fs::remove_dir_all(second.working_tree()).unwrap();
+    }
+
+    #[test]
+    pub fn test_relative_path() {
+        let repo = Repository::new("/home/josgtg/repo", "/home/josgtg/repo/.git");
+
+        assert_eq!(
+            repo.relative_path(Path::new("/home/josgtg/repo/src/../src/main.rs")),
+            Some(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(
+            repo.relative_path(Path::new("/home/josgtg/repo")),
+            Some(PathBuf::new())
+        );
+        assert_eq!(repo.relative_path(Path::new("/home/josgtg/other")), None);
+    }
+}
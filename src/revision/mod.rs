@@ -0,0 +1,6 @@
+mod resolve;
+#[allow(clippy::module_inception)]
+mod revision;
+
+pub use resolve::{resolve, resolve_commit, upstream_ref_name};
+pub use revision::{Revision, RevisionBase, RevisionStep};
@@ -0,0 +1,324 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use crate::Constants;
+use crate::config::Config;
+use crate::fs;
+use crate::hashing::{HASH_STR_LEN, Hash};
+use crate::object::{Object, ObjectKind};
+use crate::repository::Repository;
+
+use super::{Revision, RevisionBase, RevisionStep};
+
+/// Abbreviated hashes shorter than this are not looked up, since they would match too many
+/// objects.
+const MIN_ABBREVIATED_HASH_LEN: usize = 4;
+
+/// Returns the hash of the object `revision` points to in `repo`. Revisions start with one of:
+/// - `HEAD` or `@`.
+/// - A ref name, looked up in the same order git does (see `resolve_ref_name`).
+/// - A full hash, or an abbreviated one of at least 4 characters matching a single object.
+/// - `@{-N}`, the branch checked out N checkouts ago.
+/// - `<branch>@{upstream}` (or `@{u}`), the upstream of a branch, or of the current one.
+///
+/// Followed by any number of `~N`, `^N` and `^{type}` suffixes and an optional `:<path>` inside
+/// the resulting tree. A lone `:<path>` is looked up in the index instead.
+///
+/// # Errors
+///
+/// This function will fail if the revision is not valid, if it does not point to any object or
+/// if an abbreviated hash matches more than one object, listing all of them.
+pub fn resolve(repo: &Repository, revision: &str) -> Result<Hash> {
+    let parsed = Revision::from_str(revision)?;
+
+    if parsed.base == RevisionBase::Index {
+        return resolve_index_path(repo, parsed.path.as_deref().unwrap_or_default());
+    }
+
+    let mut hash = resolve_base(repo, &parsed.base)?;
+    for step in parsed.steps {
+        hash = apply_step(repo, hash, step)?;
+    }
+    if let Some(path) = &parsed.path {
+        let tree = peel(repo, hash, Some(ObjectKind::Tree))?;
+        hash = resolve_tree_path(repo, tree, path)
+            .context(format!("path '{}' does not exist in '{}'", path, revision))?;
+    }
+
+    Ok(hash)
+}
+
+/// Same as `resolve`, but peels the object to the commit it points to, so annotated tags can be
+/// used wherever a commit is expected.
+///
+/// # Errors
+///
+/// This function will fail if the revision could not be resolved or does not point to a commit.
+pub fn resolve_commit(repo: &Repository, revision: &str) -> Result<Hash> {
+    let hash = resolve(repo, revision)?;
+    peel(repo, hash, Some(ObjectKind::Commit)).context(format!("'{}' is not a commit", revision))
+}
+
+/// Looks `name` up as a ref, trying in order: `<name>` inside the repository folder (only for
+/// names like `HEAD` or `ORIG_HEAD`), `refs/<name>`, `refs/tags/<name>`, `refs/heads/<name>`,
+/// `refs/remotes/<name>` and `refs/remotes/<name>/HEAD`.
+///
+/// # Returns
+///
+/// The hash the first existing ref points to, or `None` if none of them exist.
+///
+/// # Errors
+///
+/// This function will fail if any of the ref files could not be read.
+pub fn resolve_ref_name(repo: &Repository, name: &str) -> Result<Option<Hash>> {
+    if !fs::is_valid_ref_name(name) {
+        return Ok(None);
+    }
+
+    let mut candidates = Vec::new();
+    if name.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+        candidates.push(name.to_string());
+    }
+    candidates.push(format!("{}/{}", Constants::REFS_FOLDER_NAME, name));
+    for folder in [
+        Constants::TAGS_FOLDER_NAME,
+        Constants::HEADS_FOLDER_NAME,
+        Constants::REMOTES_FOLDER_NAME,
+    ] {
+        candidates.push(format!(
+            "{}/{}/{}",
+            Constants::REFS_FOLDER_NAME,
+            folder,
+            name
+        ));
+    }
+    candidates.push(format!(
+        "{}/{}/{}/{}",
+        Constants::REFS_FOLDER_NAME,
+        Constants::REMOTES_FOLDER_NAME,
+        name,
+        Constants::HEAD_FILE_NAME
+    ));
+
+    for candidate in candidates {
+        if let Some(hash) = fs::read_ref(repo, &candidate)? {
+            return Ok(Some(hash));
+        }
+    }
+    Ok(None)
+}
+
+fn resolve_base(repo: &Repository, base: &RevisionBase) -> Result<Hash> {
+    match base {
+        RevisionBase::Head => fs::read_ref(repo, Constants::HEAD_FILE_NAME)
+            .context("could not read HEAD")?
+            .context("HEAD does not point to any commit yet"),
+        RevisionBase::Name(name) => resolve_name(repo, name),
+        RevisionBase::Upstream(branch) => resolve_upstream(repo, branch.as_deref()),
+        RevisionBase::PreviousBranch(n) => resolve_name(repo, &previous_branch_name(repo, *n)?),
+        RevisionBase::Index => bail!("the index can only be used along with a path"),
+    }
+}
+
+/// Resolves a full hash, a ref name or an abbreviated hash, in that order.
+fn resolve_name(repo: &Repository, name: &str) -> Result<Hash> {
+    if name.len() == HASH_STR_LEN
+        && let Ok(hash) = Hash::from_str(name)
+    {
+        fs::object::read_raw_object(repo, &hash)
+            .context(format!("not a valid object name: '{}'", name))?;
+        return Ok(hash);
+    }
+
+    if let Some(hash) = resolve_ref_name(repo, name)? {
+        return Ok(hash);
+    }
+
+    if name.len() >= MIN_ABBREVIATED_HASH_LEN && name.chars().all(|c| c.is_ascii_hexdigit()) {
+        return resolve_abbreviated_hash(repo, name);
+    }
+
+    bail!("not a valid object name: '{}'", name)
+}
+
+/// Returns the only object whose hash starts with `prefix`.
+fn resolve_abbreviated_hash(repo: &Repository, prefix: &str) -> Result<Hash> {
+    let mut candidates =
+        fs::object::find_objects_by_prefix(repo, prefix).context("could not search objects")?;
+
+    match candidates.len() {
+        0 => bail!("not a valid object name: '{}'", prefix),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            let mut message = format!(
+                "short object ID {} is ambiguous, the candidates are:",
+                prefix
+            );
+            for hash in candidates {
+                let kind = fs::object::read_raw_object(repo, &hash)
+                    .map_or(String::from("unknown"), |(kind, _)| kind.to_string());
+                message.push_str(&format!("\n  {} {}", hash, kind));
+            }
+            bail!(message)
+        }
+    }
+}
+
+/// Returns the commit the upstream of `branch` (or of the current branch) points to, taken from
+/// the `branch.<name>.remote` and `branch.<name>.merge` config values.
+fn resolve_upstream(repo: &Repository, branch: Option<&str>) -> Result<Hash> {
+    let branch = match branch {
+        Some(branch) => {
+            if !fs::is_valid_ref_name(branch) || fs::read_branch(repo, branch)?.is_none() {
+                bail!("no such branch: '{}'", branch)
+            }
+            branch.to_string()
+        }
+        None => fs::get_current_branch_name(repo)?.context("HEAD does not point to a branch")?,
+    };
+
+    let upstream = upstream_ref_name(repo, &branch)?
+        .context(format!("no upstream configured for branch '{}'", branch))?;
+
+    fs::read_ref(repo, &upstream)?.context(format!(
+        "upstream branch '{}' of '{}' does not exist",
+        upstream, branch
+    ))
+}
+
+/// Returns the full name of the ref `branch` tracks, like `refs/remotes/origin/main`, taken from
+/// the `branch.<name>.remote` and `branch.<name>.merge` config values, or `None` if the branch
+/// has no upstream configured. The ref itself might not exist.
+///
+/// # Errors
+///
+/// This function will fail if the config could not be read or if the upstream is not a branch.
+pub fn upstream_ref_name(repo: &Repository, branch: &str) -> Result<Option<String>> {
+    let config = Config::load(repo).context("could not read config")?;
+    let Some(merge) = config.get(&format!("branch.{}.merge", branch)) else {
+        return Ok(None);
+    };
+    // a `.` remote means the upstream is another local branch
+    let remote = config
+        .get(&format!("branch.{}.remote", branch))
+        .unwrap_or(".");
+
+    let heads_prefix = format!(
+        "{}/{}/",
+        Constants::REFS_FOLDER_NAME,
+        Constants::HEADS_FOLDER_NAME
+    );
+    let upstream = match merge.strip_prefix(&heads_prefix) {
+        _ if remote == "." => merge.to_string(),
+        Some(name) => format!(
+            "{}/{}/{}/{}",
+            Constants::REFS_FOLDER_NAME,
+            Constants::REMOTES_FOLDER_NAME,
+            remote,
+            name
+        ),
+        None => bail!("upstream of branch '{}' is not a branch: {}", branch, merge),
+    };
+
+    Ok(Some(upstream))
+}
+
+/// Returns the name of the branch (or the hash, for detached checkouts) that was checked out `n`
+/// checkouts ago, according to the HEAD reflog.
+fn previous_branch_name(repo: &Repository, n: usize) -> Result<String> {
+    let messages = fs::read_head_log_messages(repo).context("could not read HEAD reflog")?;
+    let switches: Vec<&str> = messages
+        .iter()
+        .rev()
+        .filter_map(|m| m.strip_prefix(fs::CHECKOUT_LOG_PREFIX))
+        .filter_map(|m| m.split_once(" to ").map(|(from, _)| from))
+        .collect();
+
+    switches.get(n - 1).map(|s| s.to_string()).context(format!(
+        "'@{{-{}}}' does not exist, only {} checkouts were found in the HEAD reflog",
+        n,
+        switches.len()
+    ))
+}
+
+fn apply_step(repo: &Repository, hash: Hash, step: RevisionStep) -> Result<Hash> {
+    match step {
+        RevisionStep::Ancestor(n) => {
+            let mut hash = peel(repo, hash, Some(ObjectKind::Commit))?;
+            for _ in 0..n {
+                hash = nth_parent(repo, &hash, 1)?;
+            }
+            Ok(hash)
+        }
+        RevisionStep::Parent(0) => peel(repo, hash, Some(ObjectKind::Commit)),
+        RevisionStep::Parent(n) => {
+            nth_parent(repo, &peel(repo, hash, Some(ObjectKind::Commit))?, n)
+        }
+        RevisionStep::Peel(kind) => peel(repo, hash, kind),
+    }
+}
+
+/// Returns the `n`th parent of `commit`, starting at 1.
+fn nth_parent(repo: &Repository, commit: &Hash, n: usize) -> Result<Hash> {
+    let Object::Commit { parents, .. } =
+        fs::object::read_object(repo, commit.clone()).context("could not read commit")?
+    else {
+        bail!("{} is not a commit", commit)
+    };
+
+    parents.get(n - 1).cloned().context(format!(
+        "commit {} does not have a parent number {}",
+        &commit.to_string()[..7],
+        n
+    ))
+}
+
+/// Follows `hash` until reaching an object of type `kind`, going from tags to the object they
+/// point to and from commits to their tree. Without a `kind`, only tags are followed.
+fn peel(repo: &Repository, mut hash: Hash, kind: Option<ObjectKind>) -> Result<Hash> {
+    loop {
+        let object = fs::object::read_object(repo, hash.clone())
+            .context(format!("could not read object {}", hash))?;
+        let current = object.kind();
+        if Some(current) == kind {
+            return Ok(hash);
+        }
+
+        hash = match (object, kind) {
+            (Object::Tag { object, .. }, _) => object,
+            (Object::Commit { tree, .. }, Some(ObjectKind::Tree)) => tree,
+            (_, None) => return Ok(hash),
+            (_, Some(kind)) => bail!("object {} is a {}, not a {}", hash, current, kind),
+        };
+    }
+}
+
+/// Returns the object at `path` inside of `tree`, where an empty path is the tree itself.
+fn resolve_tree_path(repo: &Repository, tree: Hash, path: &str) -> Result<Hash> {
+    let mut hash = tree;
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        let Object::Tree { entries } =
+            fs::object::read_object(repo, hash.clone()).context("could not read tree")?
+        else {
+            bail!("'{}' is not a directory", component)
+        };
+        hash = entries
+            .into_iter()
+            .find(|e| e.path.as_os_str() == component)
+            .map(|e| e.hash)
+            .context(format!("'{}' not found", component))?;
+    }
+    Ok(hash)
+}
+
+/// Returns the object the index has at `path`.
+fn resolve_index_path(repo: &Repository, path: &str) -> Result<Hash> {
+    let index = fs::index::read_index_file(repo).context("could not read index file")?;
+    index
+        .entries()
+        .find(|e| e.path() == Path::new(path))
+        .map(|e| e.object_hash())
+        .context(format!("path '{}' is not in the index", path))
+}
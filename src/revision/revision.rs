@@ -0,0 +1,234 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+
+use crate::object::ObjectKind;
+
+/// A parsed revision, like `main~2`, `v1.0^{tree}` or `HEAD:src/main.rs`, which still has to be
+/// resolved against a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub base: RevisionBase,
+    /// Applied to the base object from left to right
+    pub steps: Vec<RevisionStep>,
+    /// Path inside the tree of the revision (`<rev>:<path>`), or inside the index for `:<path>`
+    pub path: Option<String>,
+}
+
+/// The object a revision starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevisionBase {
+    /// `HEAD` or `@`
+    Head,
+    /// A ref name or a full or abbreviated hash
+    Name(String),
+    /// `<branch>@{upstream}` or `@{u}`, with `None` for the current branch
+    Upstream(Option<String>),
+    /// `@{-N}`, the branch checked out N checkouts ago
+    PreviousBranch(usize),
+    /// The index, only used along with a path (`:<path>`)
+    Index,
+}
+
+/// An operation that moves from an object to another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionStep {
+    /// `~N`, the Nth generation ancestor following first parents
+    Ancestor(usize),
+    /// `^N`, the Nth parent, where `^0` is the commit itself
+    Parent(usize),
+    /// `^{type}`, peels tags (and commits into trees) until reaching an object of that type, or
+    /// any object that is not a tag for `^{}`
+    Peel(Option<ObjectKind>),
+}
+
+impl FromStr for Revision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            bail!("empty revision")
+        }
+
+        if let Some(path) = s.strip_prefix(':') {
+            return Ok(Revision {
+                base: RevisionBase::Index,
+                steps: Vec::new(),
+                path: Some(path.to_string()),
+            });
+        }
+
+        let (revision, path) = match s.split_once(':') {
+            Some((revision, path)) => (revision, Some(path.to_string())),
+            None => (s, None),
+        };
+
+        let (base, suffix) = parse_base(revision)?;
+        let steps = parse_steps(suffix).context(format!("invalid revision '{}'", s))?;
+
+        Ok(Revision { base, steps, path })
+    }
+}
+
+/// Parses the start of `revision` up to the first `~` or `^`, returning the base and the rest of
+/// the string.
+fn parse_base(revision: &str) -> Result<(RevisionBase, &str)> {
+    if let Some(rest) = revision.strip_prefix("@{-") {
+        let (number, suffix) = rest
+            .split_once('}')
+            .context(format!("unclosed '@{{' in '{}'", revision))?;
+        let number = number
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context(format!("invalid previous branch number in '{}'", revision))?;
+        return Ok((RevisionBase::PreviousBranch(number), suffix));
+    }
+
+    let end = revision.find(['~', '^']).unwrap_or(revision.len());
+    let (name, suffix) = revision.split_at(end);
+
+    if let Some(at) = name.find("@{") {
+        let branch = &name[..at];
+        let (inner, rest) = name[at + 2..]
+            .split_once('}')
+            .context(format!("unclosed '@{{' in '{}'", revision))?;
+        if !rest.is_empty() {
+            bail!("invalid revision '{}'", revision)
+        }
+        if !matches!(inner.to_ascii_lowercase().as_str(), "upstream" | "u") {
+            bail!("'@{{{}}}' is not supported in '{}'", inner, revision)
+        }
+        let branch = match branch {
+            "" | "@" | "HEAD" => None,
+            branch => Some(branch.to_string()),
+        };
+        return Ok((RevisionBase::Upstream(branch), suffix));
+    }
+
+    let base = match name {
+        "" => bail!("invalid revision '{}'", revision),
+        "@" | "HEAD" => RevisionBase::Head,
+        name => RevisionBase::Name(name.to_string()),
+    };
+    Ok((base, suffix))
+}
+
+/// Parses a sequence of `~N`, `^N` and `^{type}` suffixes.
+fn parse_steps(mut suffix: &str) -> Result<Vec<RevisionStep>> {
+    let mut steps = Vec::new();
+    while let Some(operator) = suffix.chars().next() {
+        suffix = &suffix[1..];
+
+        if operator == '^' && suffix.starts_with('{') {
+            let (kind, rest) = suffix[1..].split_once('}').context("unclosed '^{'")?;
+            let kind = match kind {
+                "" => None,
+                kind => Some(
+                    ObjectKind::from_str(kind)
+                        .context(format!("'{}' is not an object type", kind))?,
+                ),
+            };
+            steps.push(RevisionStep::Peel(kind));
+            suffix = rest;
+            continue;
+        }
+
+        let digits = suffix
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(suffix.len());
+        let number = match &suffix[..digits] {
+            "" => 1,
+            number => number.parse().context("number is too big")?,
+        };
+        suffix = &suffix[digits..];
+
+        match operator {
+            '~' => steps.push(RevisionStep::Ancestor(number)),
+            '^' => steps.push(RevisionStep::Parent(number)),
+            c => bail!("unexpected '{}'", c),
+        }
+    }
+    Ok(steps)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Revision {
+        Revision::from_str(s).unwrap()
+    }
+
+    #[test]
+    pub fn test_parse_names_and_steps() {
+        assert_eq!(
+            parse("HEAD~3"),
+            Revision {
+                base: RevisionBase::Head,
+                steps: vec![RevisionStep::Ancestor(3)],
+                path: None,
+            }
+        );
+        assert_eq!(
+            parse("main^2~").steps,
+            vec![RevisionStep::Parent(2), RevisionStep::Ancestor(1)]
+        );
+        assert_eq!(
+            parse("feature/x^^0").base,
+            RevisionBase::Name("feature/x".into())
+        );
+        assert_eq!(
+            parse("feature/x^^0").steps,
+            vec![RevisionStep::Parent(1), RevisionStep::Parent(0)]
+        );
+        assert_eq!(parse("@").base, RevisionBase::Head);
+        assert_eq!(parse("1a2b3c").base, RevisionBase::Name("1a2b3c".into()));
+    }
+
+    #[test]
+    pub fn test_parse_peel_and_path() {
+        let revision = parse("v1.0^{commit}^{tree}:src/main.rs");
+        assert_eq!(revision.base, RevisionBase::Name("v1.0".into()));
+        assert_eq!(
+            revision.steps,
+            vec![
+                RevisionStep::Peel(Some(ObjectKind::Commit)),
+                RevisionStep::Peel(Some(ObjectKind::Tree)),
+            ]
+        );
+        assert_eq!(revision.path.as_deref(), Some("src/main.rs"));
+
+        assert_eq!(parse("v1.0^{}").steps, vec![RevisionStep::Peel(None)]);
+        assert_eq!(parse("HEAD:").path.as_deref(), Some(""));
+
+        let index = parse(":README");
+        assert_eq!(index.base, RevisionBase::Index);
+        assert_eq!(index.path.as_deref(), Some("README"));
+    }
+
+    #[test]
+    pub fn test_parse_at_forms() {
+        assert_eq!(parse("@{-1}").base, RevisionBase::PreviousBranch(1));
+        assert_eq!(parse("@{-2}~1").steps, vec![RevisionStep::Ancestor(1)]);
+        assert_eq!(parse("@{u}").base, RevisionBase::Upstream(None));
+        assert_eq!(parse("HEAD@{upstream}").base, RevisionBase::Upstream(None));
+        assert_eq!(
+            parse("main@{upstream}^").base,
+            RevisionBase::Upstream(Some("main".into()))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_invalid() {
+        assert!(Revision::from_str("").is_err());
+        assert!(Revision::from_str("~1").is_err());
+        assert!(Revision::from_str("HEAD^{unknown}").is_err());
+        assert!(Revision::from_str("HEAD^{tree").is_err());
+        assert!(Revision::from_str("main@{1}").is_err());
+        assert!(Revision::from_str("@{-0}").is_err());
+        assert!(Revision::from_str("@{-1").is_err());
+    }
+}
@@ -5,3 +5,8 @@ pub fn as_octal(num: impl Octal) -> u32 {
         .parse()
         .expect("octal conversion should never fail")
 }
+
+/// Does the opposite of `as_octal`, interpreting the decimal digits of `num` as an octal number.
+pub fn from_octal(num: u32) -> u32 {
+    u32::from_str_radix(&num.to_string(), 8).expect("number should only have octal digits")
+}
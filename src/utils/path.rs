@@ -13,7 +13,7 @@ use path_clean::PathClean;
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```ignore
 /// let path = PathBuf::from("dir/subdir/file.txt");
 /// let (root, stripped_path) = strip_root(path);
 ///
@@ -36,7 +36,6 @@ pub fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
 }
 
 /// Returns the path divided by forward slashes.
-#[allow(unused)]
 pub fn format_path(path: &Path) -> OsString {
     let mut formatted = OsString::new();
     let mut prev: &OsStr = OsStr::new("");
@@ -55,6 +54,7 @@ pub fn format_path(path: &Path) -> OsString {
 /// Returns the path without useless characters.
 ///
 /// If the `absolute` flag is set, it will not strip the forward slash from the path.
+#[allow(unused)]
 pub fn clean_path(path: &Path, relative: bool) -> PathBuf {
     let mut cleaned = path.clean();
     if relative && cleaned.starts_with("/") {
@@ -76,3 +76,28 @@ pub fn normalize_path_relative(path: PathBuf, base: &Path) -> Result<PathBuf> {
 
     Ok(relative_path(&canonical, base).unwrap_or(canonical))
 }
+
+/// Returns `path` relative to `base` without touching the filesystem, so it also works for paths
+/// that do not exist (for example, deleted files).
+///
+/// Relative paths are assumed to already be relative to `base`. A path pointing to `base` itself
+/// results in an empty path.
+///
+/// # Errors
+///
+/// This function will return `None` if `path` is absolute and outside of `base`.
+pub fn clean_relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let cleaned = path.clean();
+    let relative = if cleaned.is_absolute() {
+        relative_path(&cleaned, base)?
+    } else if cleaned.starts_with("..") {
+        return None;
+    } else {
+        cleaned
+    };
+
+    if relative == Path::new(".") {
+        return Some(PathBuf::new());
+    }
+    Some(relative)
+}